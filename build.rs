@@ -0,0 +1,15 @@
+fn main() {
+    #[cfg(feature = "header")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        cbindgen::Builder::new()
+            .with_src(format!("{crate_dir}/src/ffi.rs"))
+            .with_config(cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")).unwrap())
+            .generate()
+            .expect("failed to generate the C header")
+            .write_to_file(format!("{out_dir}/rnla.h"));
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
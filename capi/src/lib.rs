@@ -0,0 +1,3 @@
+//! Shared and static C libraries exporting the `rnla_*` functions of
+//! `rnla::ffi`.
+pub use rnla::ffi::*;
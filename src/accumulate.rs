@@ -0,0 +1,220 @@
+//! Dot products, gemv and gemm with a choice of accumulation.
+//!
+//! The accumulation is a policy type parameter: Plain for the ordinary f64
+//! sum, Compensated for the Dot2 algorithm of Ogita, Rump and Oishi, and
+//! DoubleDouble for a normalized double-double sum. The last two track the
+//! rounding error of every product and addition with error-free
+//! transformations, so the result is as accurate as if it were computed in
+//! twice the precision and then rounded; sums with heavy cancellation, such
+//! as residuals of accurate solutions, keep their digits. They cost several
+//! times a plain sum and always run serially.
+use crate::{MatrixView, MatrixViewMut};
+
+/// A running sum of products.
+pub trait Accumulator: Default {
+    /// Add a * b to the sum.
+    fn add_product(&mut self, a: f64, b: f64);
+
+    /// Return the sum as an unevaluated pair hi + lo.
+    fn parts(&self) -> (f64, f64);
+
+    /// Return the sum rounded to f64.
+    fn value(&self) -> f64 {
+        let (hi, lo) = self.parts();
+        hi + lo
+    }
+}
+
+/// The ordinary floating-point sum.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Plain(f64);
+
+impl Accumulator for Plain {
+    #[inline]
+    fn add_product(&mut self, a: f64, b: f64) {
+        self.0 += a * b;
+    }
+
+    fn parts(&self) -> (f64, f64) {
+        (self.0, 0.0)
+    }
+}
+
+/// A sum with its rounding errors collected in a separate term.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Compensated {
+    sum: f64,
+    err: f64,
+}
+
+impl Accumulator for Compensated {
+    #[inline]
+    fn add_product(&mut self, a: f64, b: f64) {
+        let (p, ep) = two_product(a, b);
+        let (s, es) = two_sum(self.sum, p);
+        self.sum = s;
+        self.err += ep + es;
+    }
+
+    fn parts(&self) -> (f64, f64) {
+        (self.sum, self.err)
+    }
+}
+
+/// A double-double sum hi + lo with |lo| at most half an ulp of hi.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DoubleDouble {
+    hi: f64,
+    lo: f64,
+}
+
+impl Accumulator for DoubleDouble {
+    #[inline]
+    fn add_product(&mut self, a: f64, b: f64) {
+        let (p, ep) = two_product(a, b);
+        let (s, es) = two_sum(self.hi, p);
+        (self.hi, self.lo) = fast_two_sum(s, es + ep + self.lo);
+    }
+
+    fn parts(&self) -> (f64, f64) {
+        (self.hi, self.lo)
+    }
+}
+
+/// Return (s, e) with s = fl(a + b) and a + b = s + e exactly.
+#[inline]
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+/// two_sum() for |a| >= |b|.
+#[inline]
+fn fast_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+/// Return (p, e) with p = fl(a b) and a b = p + e exactly, by Dekker's
+/// splitting, which needs no fused multiply-add. Exact unless a or b is
+/// within a factor 2^27 of overflow.
+#[inline]
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    let (ah, al) = split(a);
+    let (bh, bl) = split(b);
+    (p, al * bl - (((p - ah * bh) - al * bh) - ah * bl))
+}
+
+/// Split a into two halves of 26 bits.
+#[inline]
+fn split(a: f64) -> (f64, f64) {
+    let c = 134217729.0 * a; // 2^27 + 1
+    let hi = c - (c - a);
+    (hi, a - hi)
+}
+
+/// Return alpha t + beta y for the accumulated t.
+fn finish<A: Accumulator>(alpha: f64, t: &A, beta: f64, y: f64) -> f64 {
+    let (hi, lo) = t.parts();
+    let mut acc = A::default();
+    acc.add_product(alpha, hi);
+    acc.add_product(alpha, lo);
+    if beta != 0.0 {
+        acc.add_product(beta, y);
+    }
+    acc.value()
+}
+
+/// Return the dot product of x and y accumulated with A.
+pub fn dot_with<A: Accumulator>(x: &[f64], y: &[f64]) -> f64 {
+    assert_eq!(x.len(), y.len());
+    let mut acc = A::default();
+    x.iter().zip(y).for_each(|(a, b)| acc.add_product(*a, *b));
+    acc.value()
+}
+
+/// Compute y = alpha * a * x + beta * y accumulated with A.
+///
+/// beta * y joins the sum, so a residual b - A x is computed as
+/// gemv_with(-1.0, a, x, 1.0, b) with a single rounding per entry.
+pub fn gemv_with<A: Accumulator>(alpha: f64, a: &MatrixView, x: &[f64], beta: f64, y: &mut [f64]) {
+    assert_eq!(a.n, x.len());
+    assert_eq!(a.m, y.len());
+    for (row, yi) in a.data.iter().zip(y.iter_mut()) {
+        let mut acc = A::default();
+        row.iter().zip(x).for_each(|(aij, xj)| acc.add_product(*aij, *xj));
+        *yi = finish(alpha, &acc, beta, *yi);
+    }
+}
+
+/// Compute c = alpha * a * b + beta * c accumulated with A.
+///
+/// A beta of zero overwrites c, as in fma_scale().
+pub fn gemm_with<A: Accumulator>(alpha: f64, a: &MatrixView, b: &MatrixView, beta: f64, c: &mut MatrixViewMut) {
+    assert_eq!(a.m, c.m);
+    assert_eq!(a.n, b.m);
+    assert_eq!(b.n, c.n);
+    for i in 0..c.m {
+        for j in 0..c.n {
+            let mut acc = A::default();
+            for k in 0..a.n {
+                acc.add_product(a.data[i][k], b.data[k][j]);
+            }
+            c.data[i][j] = finish(alpha, &acc, beta, c.data[i][j]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Matrix;
+    use alloc::vec;
+
+    #[test]
+    fn cancellation_keeps_digits() {
+        let x = [1e16, 1.0, -1e16, 1e-3];
+        let y = [1.0; 4];
+        assert_eq!(dot_with::<Plain>(&x, &y), 1e-3);
+        assert_eq!(dot_with::<Compensated>(&x, &y), 1.001);
+        assert_eq!(dot_with::<DoubleDouble>(&x, &y), 1.001);
+        // Products whose rounding errors cancel the leading terms.
+        let a = [1.0 + f64::EPSILON, -1.0];
+        let b = [1.0 - f64::EPSILON, 1.0];
+        assert_eq!(dot_with::<Plain>(&a, &b), 0.0);
+        for v in [dot_with::<Compensated>(&a, &b), dot_with::<DoubleDouble>(&a, &b)] {
+            assert_eq!(v, -f64::EPSILON * f64::EPSILON);
+        }
+        assert_eq!(DoubleDouble::default().parts(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn accurate_residual_and_product() {
+        // Both rows of A x cancel almost completely for x = (1, -1).
+        let a = Matrix::from_vec(2, 2, vec![1.0 + 1e-15, 1.0,
+                                            1e10, 1e10 - 1.0]);
+        let x = [1.0, -1.0];
+        let mut r = [0.0; 2];
+        gemv_with::<DoubleDouble>(1.0, &a.view(), &x, 0.0, &mut r);
+        // The first entry is exactly the stored 1 + 1e-15 minus 1.
+        assert_eq!(r, [1.1102230246251565e-15, 1.0]);
+
+        // The residual b - A x with b added inside the sum.
+        let mut b = [0.5, 0.25];
+        gemv_with::<Compensated>(-1.0, &a.view(), &x, 1.0, &mut b);
+        assert_eq!(b, [0.5 - r[0], -0.75]);
+
+        let c0 = Matrix::rand_seeded(3, 4, 1);
+        let d = Matrix::rand_seeded(4, 2, 2);
+        let mut plain = Matrix::zero(3, 2);
+        crate::matmul(&c0.view(), &d.view(), &mut plain.view_mut());
+        let mut dd = Matrix::from_vec(3, 2, vec![f64::NAN; 6]);
+        gemm_with::<DoubleDouble>(1.0, &c0.view(), &d.view(), 0.0, &mut dd.view_mut());
+        assert!(plain.as_slice().iter().zip(dd.as_slice()).all(|(p, q)| (p - q).abs() < 1e-15));
+        let mut twice = Matrix::from_vec(3, 2, dd.as_slice().to_vec());
+        gemm_with::<Plain>(1.0, &c0.view(), &d.view(), 1.0, &mut twice.view_mut());
+        assert!(twice.as_slice().iter().zip(dd.as_slice()).all(|(t, q)| (t - 2.0 * q).abs() < 1e-14));
+    }
+}
@@ -0,0 +1,368 @@
+//! Smoothed aggregation algebraic multigrid.
+//!
+//! The hierarchy is built from the matrix alone: strongly connected nodes
+//! are grouped into aggregates, the piecewise constant prolongator over the
+//! aggregates is smoothed with one damped Jacobi step, and the coarse
+//! operators are the Galerkin products R A P with R = P^T.
+//!
+//! The coarsest level is factored densely only up to `max_direct`
+//! unknowns. When coarsening stops early, at `max_levels` or because the
+//! aggregation stalls, a larger coarsest level is smoothed with Jacobi
+//! sweeps instead, which keeps the V-cycle a fixed SPD operator.
+use crate::iterative::Preconditioner;
+use crate::lu::{lu, lu_solve};
+use crate::math;
+use crate::operations::axpy;
+use crate::stationary::jacobi_sweep;
+use crate::{Matrix, SparseMatrix};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Multigrid settings, built with `AmgOptions::new().strength(0.25)`.
+#[derive(Clone, Debug)]
+pub struct AmgOptions {
+    /// Strength of connection threshold.
+    strength: f64,
+    /// Jacobi sweeps before and after each coarse correction.
+    sweeps: usize,
+    /// Maximum number of levels, including the finest.
+    max_levels: usize,
+    /// Size at or below which a level is solved directly.
+    coarse_size: usize,
+    /// Largest coarsest level that is factored densely.
+    max_direct: usize,
+}
+
+/// Jacobi sweeps on a coarsest level too large to factor.
+const COARSE_SWEEPS: usize = 20;
+
+impl AmgOptions {
+    /// Return the default options: strength 0.08, one sweep, at most 10
+    /// levels, a direct solve below 50 unknowns and no dense coarsest
+    /// level above 2000 unknowns.
+    pub fn new() -> AmgOptions {
+        AmgOptions {
+            strength: 0.08,
+            sweeps: 1,
+            max_levels: 10,
+            coarse_size: 50,
+            max_direct: 2000,
+        }
+    }
+
+    /// Treat a_ij as a strong connection if |a_ij| >= strength * sqrt(|a_ii a_jj|).
+    pub fn strength(mut self, strength: f64) -> AmgOptions {
+        assert!(strength >= 0.0);
+        self.strength = strength;
+        self
+    }
+
+    /// Set the number of pre- and post-smoothing sweeps.
+    pub fn sweeps(mut self, sweeps: usize) -> AmgOptions {
+        self.sweeps = sweeps;
+        self
+    }
+
+    /// Set the maximum number of levels.
+    pub fn max_levels(mut self, max_levels: usize) -> AmgOptions {
+        assert!(max_levels > 0);
+        self.max_levels = max_levels;
+        self
+    }
+
+    /// Set the size at or below which a level is solved directly.
+    pub fn coarse_size(mut self, coarse_size: usize) -> AmgOptions {
+        self.coarse_size = coarse_size;
+        self
+    }
+
+    /// Set the largest coarsest level that is factored densely; larger
+    /// ones are smoothed with Jacobi sweeps.
+    pub fn max_direct(mut self, max_direct: usize) -> AmgOptions {
+        self.max_direct = max_direct;
+        self
+    }
+}
+
+impl Default for AmgOptions {
+    fn default() -> AmgOptions {
+        AmgOptions::new()
+    }
+}
+
+/// One level of the hierarchy above the coarsest.
+struct Level {
+    a: SparseMatrix,
+    /// Prolongator to this level from the next coarser one, and its
+    /// transpose.
+    p: SparseMatrix,
+    r: SparseMatrix,
+    /// Jacobi weight over the diagonal, omega / a_ii.
+    scaled_inv_diag: Vec<f64>,
+}
+
+/// Solver on the coarsest level.
+enum Coarse {
+    /// LU factors of the operator.
+    Direct { lu: Matrix, piv: Vec<usize> },
+    /// The operator, smoothed with COARSE_SWEEPS Jacobi sweeps from zero.
+    Jacobi { a: SparseMatrix, scaled_inv_diag: Vec<f64> },
+}
+
+/// Smoothed aggregation AMG hierarchy for a sparse SPD matrix.
+///
+/// As a `Preconditioner` it applies one V-cycle, which is symmetric and so
+/// suits cg().
+pub struct Amg {
+    levels: Vec<Level>,
+    coarse: Coarse,
+    coarse_size: usize,
+    sweeps: usize,
+}
+
+impl Amg {
+    /// Build the hierarchy with the default options.
+    pub fn new(a: &SparseMatrix) -> Amg {
+        Amg::with_options(a, &AmgOptions::new())
+    }
+
+    /// Build the hierarchy with the given options.
+    pub fn with_options(a: &SparseMatrix, opts: &AmgOptions) -> Amg {
+        assert_eq!(a.m, a.n);
+        let mut levels = Vec::new();
+        let mut a = a.clone();
+        while levels.len() + 1 < opts.max_levels && a.m > opts.coarse_size {
+            let diag = a.diagonal();
+            assert!(diag.iter().all(|&d| d > 0.0), "AMG needs a positive diagonal");
+            let (agg, count) = aggregate(&a, &diag, opts.strength);
+            if count == a.m {
+                break;
+            }
+            let scaled_inv_diag = jacobi_weights(&a, &diag);
+            let p = smoothed_prolongator(&a, &agg, count, &scaled_inv_diag);
+            let r = p.transpose();
+            let coarse = r.matmul(&a).matmul(&p);
+            levels.push(Level {
+                a,
+                p,
+                r,
+                scaled_inv_diag,
+            });
+            a = coarse;
+        }
+        let coarse_size = a.m;
+        let coarse = if a.m <= opts.max_direct {
+            let mut lu_factors = a.to_dense();
+            let piv = lu(&mut lu_factors).expect("coarsest AMG operator is singular");
+            Coarse::Direct { lu: lu_factors, piv }
+        } else {
+            let diag = a.diagonal();
+            assert!(diag.iter().all(|&d| d > 0.0), "AMG needs a positive diagonal");
+            let scaled_inv_diag = jacobi_weights(&a, &diag);
+            Coarse::Jacobi { a, scaled_inv_diag }
+        };
+        Amg {
+            levels,
+            coarse,
+            coarse_size,
+            sweeps: opts.sweeps,
+        }
+    }
+
+    /// Return the number of levels, including the finest and coarsest.
+    pub fn num_levels(&self) -> usize {
+        self.levels.len() + 1
+    }
+
+    /// Return the number of unknowns on each level, finest first.
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut res: Vec<usize> = self.levels.iter().map(|l| l.a.m).collect();
+        res.push(self.coarse_size);
+        res
+    }
+
+    /// Improve x as a solution of A x = b with one V-cycle.
+    pub fn vcycle(&self, b: &[f64], x: &mut [f64]) {
+        self.cycle(0, b, x);
+    }
+
+    fn cycle(&self, k: usize, b: &[f64], x: &mut [f64]) {
+        let Some(level) = self.levels.get(k) else {
+            match &self.coarse {
+                Coarse::Direct { lu, piv } => {
+                    x.copy_from_slice(b);
+                    lu_solve(lu, piv, x);
+                }
+                Coarse::Jacobi { a, scaled_inv_diag } => {
+                    x.fill(0.0);
+                    for _ in 0..COARSE_SWEEPS {
+                        jacobi_sweep(a, scaled_inv_diag, b, x, 1.0);
+                    }
+                }
+            }
+            return;
+        };
+        for _ in 0..self.sweeps {
+            jacobi_sweep(&level.a, &level.scaled_inv_diag, b, x, 1.0);
+        }
+        let mut r = vec![0.0; b.len()];
+        level.a.matvec(x, &mut r);
+        for (ri, bi) in r.iter_mut().zip(b) {
+            *ri = bi - *ri;
+        }
+        let mut bc = vec![0.0; level.r.m];
+        level.r.matvec(&r, &mut bc);
+        let mut xc = vec![0.0; bc.len()];
+        self.cycle(k + 1, &bc, &mut xc);
+        level.p.matvec(&xc, &mut r);
+        axpy(1.0, &r, x);
+        for _ in 0..self.sweeps {
+            jacobi_sweep(&level.a, &level.scaled_inv_diag, b, x, 1.0);
+        }
+    }
+}
+
+impl Preconditioner for Amg {
+    fn apply(&self, r: &[f64], z: &mut [f64]) {
+        z.fill(0.0);
+        self.vcycle(r, z);
+    }
+}
+
+/// Return the damped Jacobi weights omega / a_ii.
+///
+/// An upper bound on the spectral radius of D^-1 A from the Gershgorin
+/// discs gives the usual weight 4 / (3 rho).
+fn jacobi_weights(a: &SparseMatrix, diag: &[f64]) -> Vec<f64> {
+    let rho = (0..a.m)
+        .map(|i| {
+            let row = a.row_ptr()[i]..a.row_ptr()[i + 1];
+            a.values()[row].iter().map(|v| v.abs()).sum::<f64>() / diag[i]
+        })
+        .fold(0.0, f64::max);
+    let omega = 4.0 / (3.0 * rho);
+    diag.iter().map(|d| omega / d).collect()
+}
+
+/// Group the nodes into aggregates of strongly connected neighbours.
+///
+/// Returns the aggregate of each node and the number of aggregates.
+fn aggregate(a: &SparseMatrix, diag: &[f64], strength: f64) -> (Vec<usize>, usize) {
+    let n = a.m;
+    let strong: Vec<Vec<usize>> = (0..n)
+        .map(|i| {
+            (a.row_ptr()[i]..a.row_ptr()[i + 1])
+                .filter(|&k| {
+                    let j = a.col_idx()[k];
+                    j != i && a.values()[k].abs() >= strength * math::sqrt(diag[i] * diag[j])
+                })
+                .map(|k| a.col_idx()[k])
+                .collect()
+        })
+        .collect();
+    let mut agg = vec![usize::MAX; n];
+    let mut count = 0;
+
+    // A node whose neighbourhood is still free seeds a new aggregate.
+    for i in 0..n {
+        if agg[i] == usize::MAX && strong[i].iter().all(|&j| agg[j] == usize::MAX) {
+            agg[i] = count;
+            for &j in &strong[i] {
+                agg[j] = count;
+            }
+            count += 1;
+        }
+    }
+    // Remaining nodes join a neighbouring aggregate from the first pass.
+    let first = agg.clone();
+    for i in 0..n {
+        if agg[i] == usize::MAX {
+            if let Some(&j) = strong[i].iter().find(|&&j| first[j] != usize::MAX) {
+                agg[i] = first[j];
+            }
+        }
+    }
+    // Anything left forms aggregates with its free neighbours.
+    for i in 0..n {
+        if agg[i] == usize::MAX {
+            agg[i] = count;
+            for &j in &strong[i] {
+                if agg[j] == usize::MAX {
+                    agg[j] = count;
+                }
+            }
+            count += 1;
+        }
+    }
+    (agg, count)
+}
+
+/// Return P = (I - omega D^-1 A) T, where T is the tentative prolongator
+/// with one normalized constant vector per aggregate.
+fn smoothed_prolongator(a: &SparseMatrix, agg: &[usize], count: usize, scaled_inv_diag: &[f64]) -> SparseMatrix {
+    let mut sizes = vec![0usize; count];
+    for &g in agg {
+        sizes[g] += 1;
+    }
+    let t: Vec<(usize, usize, f64)> = agg
+        .iter()
+        .enumerate()
+        .map(|(i, &g)| (i, g, 1.0 / math::sqrt(sizes[g] as f64)))
+        .collect();
+    let t = SparseMatrix::from_triplets(a.m, count, &t);
+    let mut s = a.clone();
+    for i in 0..s.m {
+        for k in s.row_ptr()[i]..s.row_ptr()[i + 1] {
+            let j = s.col_idx()[k];
+            let value = -scaled_inv_diag[i] * s.values()[k] + if i == j { 1.0 } else { 0.0 };
+            s.values_mut()[k] = value;
+        }
+    }
+    s.matmul(&t)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iterative::{cg, Identity};
+    use crate::solver::tolerance;
+
+    #[test]
+    fn amg_hierarchy_coarsens() {
+        let amg = Amg::new(&SparseMatrix::laplacian(&[40, 40]));
+        let sizes = amg.sizes();
+        assert!(amg.num_levels() >= 3);
+        assert!(sizes.windows(2).all(|w| w[1] * 3 < w[0]));
+        assert!(*sizes.last().unwrap() <= 50);
+
+        // A direct solve when the matrix is already small.
+        let small = Amg::with_options(&SparseMatrix::laplacian(&[5, 5]), &AmgOptions::new().coarse_size(25));
+        assert_eq!(small.num_levels(), 1);
+        let mut x = vec![0.0; 25];
+        small.vcycle(&[1.0; 25], &mut x);
+        let mut ax = vec![0.0; 25];
+        SparseMatrix::laplacian(&[5, 5]).matvec(&x, &mut ax);
+        assert!(ax.iter().all(|v| (v - 1.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn amg_preconditioned_cg() {
+        let a = SparseMatrix::laplacian(&[64, 64]);
+        let b: Vec<f64> = (0..a.m).map(|i| ((i * 7 % 13) as f64) - 6.0).collect();
+        let mut x = vec![0.0; a.m];
+        let plain = cg(&a, &b, &mut x, &Identity, tolerance(1e-8, 2000), None);
+        let mut x = vec![0.0; a.m];
+        let res = cg(&a, &b, &mut x, &Amg::new(&a), tolerance(1e-8, 2000), None);
+        assert!(plain.converged && res.converged);
+        assert!(res.iterations * 5 < plain.iterations);
+
+        // Stopped after two levels, the coarsest level is too large to
+        // factor and is smoothed instead; the V-cycle still helps cg.
+        let opts = AmgOptions::new().max_levels(2).max_direct(500);
+        let shallow = Amg::with_options(&a, &opts);
+        assert!(shallow.sizes()[1] > 500);
+        let mut x = vec![0.0; a.m];
+        let res = cg(&a, &b, &mut x, &shallow, tolerance(1e-8, 2000), None);
+        assert!(res.converged && res.iterations * 2 < plain.iterations);
+    }
+}
@@ -0,0 +1,267 @@
+//! Assembly of sparse matrices from element contributions.
+//!
+//! Finite element matrices are sums of small dense element matrices
+//! scattered into the rows and columns of the element's unknowns. Each
+//! thread adds its elements to its own AssemblyBuffer, which needs no
+//! locks or atomics, and Assembler::finish() merges the buffers, summing
+//! the contributions to the same entry, into one CSR matrix.
+//!
+//! apply_dirichlet() then imposes essential boundary conditions on the
+//! assembled system, changing the matrix and right-hand side together.
+use crate::{Matrix, MatrixIndex, SparseMatrix};
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Builds an m x n sparse matrix from buffers of contributions.
+#[derive(Clone, Copy, Debug)]
+pub struct Assembler {
+    m: usize,
+    n: usize,
+}
+
+/// Contributions collected by one thread.
+#[derive(Clone, Debug)]
+pub struct AssemblyBuffer {
+    m: usize,
+    n: usize,
+    triplets: Vec<(usize, usize, f64)>,
+}
+
+impl Assembler {
+    /// Start assembling an m x n matrix.
+    pub fn new(m: usize, n: usize) -> Assembler {
+        Assembler { m, n }
+    }
+
+    /// Return an empty buffer; use one per thread.
+    pub fn buffer(&self) -> AssemblyBuffer {
+        AssemblyBuffer { m: self.m, n: self.n, triplets: Vec::new() }
+    }
+
+    /// Return the sum of the contributions in the buffers.
+    pub fn finish(&self, buffers: impl IntoIterator<Item = AssemblyBuffer>) -> SparseMatrix {
+        let mut triplets = Vec::new();
+        for b in buffers {
+            assert_eq!((b.m, b.n), (self.m, self.n));
+            if triplets.is_empty() {
+                triplets = b.triplets;
+            } else {
+                triplets.extend(b.triplets);
+            }
+        }
+        SparseMatrix::from_triplets(self.m, self.n, &triplets)
+    }
+
+    /// Assemble the contributions of all elements in parallel, calling
+    /// `add` with each element and the buffer of the thread handling it.
+    #[cfg(feature = "parallel")]
+    pub fn assemble_par<E: Sync>(&self, elements: &[E], add: impl Fn(&E, &mut AssemblyBuffer) + Sync) -> SparseMatrix {
+        let buffers: Vec<AssemblyBuffer> = crate::parallel::run(|| {
+            elements
+                .par_iter()
+                .fold(|| self.buffer(), |mut buf, e| {
+                    add(e, &mut buf);
+                    buf
+                })
+                .collect()
+        });
+        self.finish(buffers)
+    }
+}
+
+impl AssemblyBuffer {
+    /// Add v to entry (i, j).
+    pub fn add(&mut self, i: usize, j: usize, v: f64) {
+        assert!(i < self.m && j < self.n);
+        self.triplets.push((i, j, v));
+    }
+
+    /// Add the element matrix ke to the rows and columns `dofs`.
+    pub fn add_element(&mut self, dofs: &[usize], ke: &Matrix) {
+        self.add_block(dofs, dofs, ke);
+    }
+
+    /// Add block to the rows `rows` and columns `cols`, as for mixed
+    /// problems with different unknowns for rows and columns.
+    pub fn add_block(&mut self, rows: &[usize], cols: &[usize], block: &Matrix) {
+        assert_eq!((block.m, block.n), (rows.len(), cols.len()));
+        for (a, &i) in rows.iter().enumerate() {
+            for (b, &j) in cols.iter().enumerate() {
+                self.add(i, j, block.get(a, b));
+            }
+        }
+    }
+
+    /// Return the number of contributions added.
+    pub fn len(&self) -> usize {
+        self.triplets.len()
+    }
+
+    /// Return whether nothing was added.
+    pub fn is_empty(&self) -> bool {
+        self.triplets.is_empty()
+    }
+}
+
+/// How apply_dirichlet() imposes the boundary values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoundaryMethod {
+    /// Zero the rows and columns of the constrained unknowns except the
+    /// diagonal, moving the known values to the right-hand side. Keeps A
+    /// symmetric and the constrained unknowns exact.
+    Elimination,
+    /// Add the penalty to the diagonal of the constrained rows and the
+    /// penalty times the value to their right-hand side. Leaves the other
+    /// entries alone but imposes the values only to O(1 / penalty).
+    Penalty(f64),
+}
+
+/// Impose u[dofs[k]] = values[k] on the system A u = b in place.
+///
+/// Every constrained row needs a nonzero diagonal entry. Eliminated
+/// entries are set to zero but stay stored, so the pattern, and anything
+/// computed from it, is unchanged.
+pub fn apply_dirichlet(a: &mut SparseMatrix, b: &mut [f64], dofs: &[usize], values: &[f64], method: BoundaryMethod) {
+    assert_eq!(a.m, a.n);
+    assert_eq!(b.len(), a.m);
+    assert_eq!(dofs.len(), values.len());
+    let mut fixed: Vec<Option<f64>> = vec![None; a.n];
+    for (&i, &g) in dofs.iter().zip(values) {
+        fixed[i] = Some(g);
+    }
+    let row_ptr = a.row_ptr().to_vec();
+    let col_idx = a.col_idx().to_vec();
+    let vals = a.values_mut();
+    for i in 0..b.len() {
+        let row = row_ptr[i]..row_ptr[i + 1];
+        let diag = row.clone().find(|&k| col_idx[k] == i);
+        match (fixed[i], method) {
+            (Some(g), BoundaryMethod::Elimination) => {
+                let d = diag.map(|k| vals[k]).filter(|&d| d != 0.0).expect("constrained row without a diagonal");
+                row.for_each(|k| vals[k] = 0.0);
+                vals[diag.unwrap()] = d;
+                b[i] = d * g;
+            }
+            (Some(g), BoundaryMethod::Penalty(p)) => {
+                vals[diag.expect("constrained row without a diagonal")] += p;
+                b[i] += p * g;
+            }
+            (None, BoundaryMethod::Elimination) => {
+                for k in row {
+                    if let Some(g) = fixed[col_idx[k]] {
+                        b[i] -= vals[k] * g;
+                        vals[k] = 0.0;
+                    }
+                }
+            }
+            (None, BoundaryMethod::Penalty(_)) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Stiffness matrix of a linear element of length h on [0, 1].
+    fn element(h: f64) -> Matrix {
+        Matrix::from_vec(2, 2, vec![1.0 / h, -1.0 / h, -1.0 / h, 1.0 / h])
+    }
+
+    #[test]
+    fn assembles_the_1d_stiffness_matrix() {
+        let elements = 8;
+        let h = 1.0 / elements as f64;
+        let asm = Assembler::new(elements + 1, elements + 1);
+        let mut buf = asm.buffer();
+        for e in 0..elements {
+            buf.add_element(&[e, e + 1], &element(h));
+        }
+        assert_eq!(buf.len(), 4 * elements);
+        let k = asm.finish([buf]);
+        // Tridiagonal (-1, 2, -1) / h with 1 / h in the corners.
+        assert_eq!(k.nnz(), 3 * (elements + 1) - 2);
+        let d = k.to_dense();
+        assert_eq!((d.get(0, 0), d.get(3, 3), d.get(3, 4), d.get(8, 8)), (8.0, 16.0, -8.0, 8.0));
+
+        let mut mixed = asm.buffer();
+        mixed.add_block(&[0], &[2, 5], &Matrix::from_vec(1, 2, vec![1.0, 2.0]));
+        mixed.add(0, 5, 3.0);
+        let b = asm.finish([mixed, asm.buffer()]);
+        assert_eq!((b.nnz(), b.to_dense().get(0, 5)), (2, 5.0));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn per_thread_buffers_give_the_serial_result() {
+        // Linear triangles on a grid of 12 x 12 squares.
+        let (cells, side) = (12, 13);
+        let triangles: Vec<[usize; 3]> = (0..cells * cells).flat_map(|c| {
+            let (i, j) = (c / cells, c % cells);
+            let v = i * side + j;
+            [[v, v + 1, v + side], [v + 1, v + side + 1, v + side]]
+        }).collect();
+        let ke = Matrix::from_vec(3, 3, vec![1.0, -0.5, -0.5, -0.5, 0.5, 0.0, -0.5, 0.0, 0.5]);
+        let asm = Assembler::new(side * side, side * side);
+        let mut serial = asm.buffer();
+        triangles.iter().for_each(|t| serial.add_element(t, &ke));
+        let expected = asm.finish([serial]);
+
+        let buffers: Vec<AssemblyBuffer> = std::thread::scope(|s| {
+            let handles: Vec<_> = triangles.chunks(50).map(|chunk| {
+                let (asm, ke) = (&asm, &ke);
+                s.spawn(move || {
+                    let mut buf = asm.buffer();
+                    chunk.iter().for_each(|t| buf.add_element(t, ke));
+                    buf
+                })
+            }).collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let threaded = asm.finish(buffers);
+        assert_eq!(threaded.row_ptr(), expected.row_ptr());
+        assert!(threaded.values().iter().zip(expected.values()).all(|(a, b)| (a - b).abs() < 1e-14));
+        #[cfg(feature = "parallel")]
+        {
+            let par = asm.assemble_par(&triangles, |t, buf| buf.add_element(t, &ke));
+            assert_eq!(par.col_idx(), expected.col_idx());
+        }
+    }
+
+    #[test]
+    fn dirichlet_conditions_solve_poisson() {
+        use crate::lu::{lu, lu_solve};
+        // -u'' = 1 on [0, 1] with u(0) = 1, u(1) = 2, for which linear
+        // elements are exact at the nodes.
+        let elements = 10;
+        let h = 1.0 / elements as f64;
+        let asm = Assembler::new(elements + 1, elements + 1);
+        let mut buf = asm.buffer();
+        let mut f = vec![0.0; elements + 1];
+        for e in 0..elements {
+            buf.add_element(&[e, e + 1], &element(h));
+            f[e] += h / 2.0;
+            f[e + 1] += h / 2.0;
+        }
+        let exact: Vec<f64> = (0..=elements).map(|i| {
+            let x = i as f64 * h;
+            x * (1.0 - x) / 2.0 + 1.0 + x
+        }).collect();
+        for (method, tol) in [(BoundaryMethod::Elimination, 1e-12), (BoundaryMethod::Penalty(1e10), 1e-8)] {
+            let mut k = asm.finish([buf.clone()]);
+            let mut b = f.clone();
+            apply_dirichlet(&mut k, &mut b, &[0, elements], &[1.0, 2.0], method);
+            let mut d = k.to_dense();
+            if method == BoundaryMethod::Elimination {
+                assert_eq!(k.nnz(), 3 * (elements + 1) - 2);
+                assert!((0..=elements).all(|i| (0..=elements).all(|j| d.get(i, j) == d.get(j, i))));
+                assert_eq!((d.get(0, 1), d.get(1, 0), d.get(0, 0)), (0.0, 0.0, 10.0));
+            }
+            let piv = lu(&mut d).unwrap();
+            lu_solve(&d, &piv, &mut b);
+            assert!(b.iter().zip(&exact).all(|(u, e)| (u - e).abs() < tol), "{method:?}");
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Reverse-mode automatic differentiation over matrix operations.
+//!
+//! A Tape records each operation on whole matrices with its result. Calling
+//! grad() on a scalar (1 x 1) result then walks the tape backwards once and
+//! returns the gradient with respect to every recorded matrix, so the cost
+//! is a small multiple of evaluating the expression. Vectors are n x 1
+//! matrices.
+use crate::factor::Factor;
+use crate::lu::LuFactor;
+use crate::{matmul, matmul_acc, Matrix, MatrixIndex};
+use alloc::vec::Vec;
+
+/// Handle to a matrix recorded on a Tape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Var(usize);
+
+enum Op {
+    Leaf,
+    Add(Var, Var),
+    Sub(Var, Var),
+    Scale(Var, f64),
+    MatMul(Var, Var),
+    /// X = A^-1 B, keeping the factorization of A for the backward solve.
+    Solve(Var, Var, LuFactor),
+    SumSquares(Var),
+}
+
+/// Record of matrix operations for reverse-mode differentiation.
+#[derive(Default)]
+pub struct Tape {
+    ops: Vec<Op>,
+    values: Vec<Matrix>,
+}
+
+impl Tape {
+    /// Return an empty tape.
+    pub fn new() -> Tape {
+        Tape::default()
+    }
+
+    fn push(&mut self, op: Op, value: Matrix) -> Var {
+        self.ops.push(op);
+        self.values.push(value);
+        Var(self.values.len() - 1)
+    }
+
+    /// Record an input.
+    pub fn var(&mut self, value: Matrix) -> Var {
+        self.push(Op::Leaf, value)
+    }
+
+    /// Return the value of v.
+    pub fn value(&self, v: Var) -> &Matrix {
+        &self.values[v.0]
+    }
+
+    /// Return a + b.
+    pub fn add(&mut self, a: Var, b: Var) -> Var {
+        let c = combine(self.value(a), self.value(b), 1.0);
+        self.push(Op::Add(a, b), c)
+    }
+
+    /// Return a - b.
+    pub fn sub(&mut self, a: Var, b: Var) -> Var {
+        let c = combine(self.value(a), self.value(b), -1.0);
+        self.push(Op::Sub(a, b), c)
+    }
+
+    /// Return alpha a.
+    pub fn scale(&mut self, a: Var, alpha: f64) -> Var {
+        let mut c = self.value(a).clone();
+        c.as_mut_slice().iter_mut().for_each(|v| *v *= alpha);
+        self.push(Op::Scale(a, alpha), c)
+    }
+
+    /// Return the product a b.
+    pub fn matmul(&mut self, a: Var, b: Var) -> Var {
+        let (x, y) = (self.value(a), self.value(b));
+        let mut c = Matrix::zero(x.m, y.n);
+        matmul(&x.view(), &y.view(), &mut c.view_mut());
+        self.push(Op::MatMul(a, b), c)
+    }
+
+    /// Return A^-1 B for square A, or None if A is singular.
+    pub fn solve(&mut self, a: Var, b: Var) -> Option<Var> {
+        let lu = LuFactor::new(self.value(a))?;
+        let x = solve_columns(&lu, self.value(b), false);
+        Some(self.push(Op::Solve(a, b, lu), x))
+    }
+
+    /// Return the 1 x 1 squared Frobenius norm of a.
+    pub fn sum_squares(&mut self, a: Var) -> Var {
+        let s = self.value(a).as_slice().iter().map(|v| v * v).sum();
+        self.push(Op::SumSquares(a), Matrix::from_vec(1, 1, [s].to_vec()))
+    }
+
+    /// Return the gradients of the 1 x 1 result f with respect to every
+    /// matrix recorded before it.
+    pub fn grad(&self, f: Var) -> Gradients {
+        assert_eq!((self.value(f).m, self.value(f).n), (1, 1), "gradient of a non-scalar");
+        let mut adj: Vec<Matrix> = self.values[..=f.0].iter().map(|v| Matrix::zero(v.m, v.n)).collect();
+        adj[f.0].set(0, 0, 1.0);
+        for k in (0..=f.0).rev() {
+            let g = core::mem::replace(&mut adj[k], Matrix::zero(0, 0));
+            match &self.ops[k] {
+                Op::Leaf => {}
+                Op::Add(a, b) => {
+                    accumulate(&mut adj[a.0], &g, 1.0);
+                    accumulate(&mut adj[b.0], &g, 1.0);
+                }
+                Op::Sub(a, b) => {
+                    accumulate(&mut adj[a.0], &g, 1.0);
+                    accumulate(&mut adj[b.0], &g, -1.0);
+                }
+                Op::Scale(a, alpha) => accumulate(&mut adj[a.0], &g, *alpha),
+                Op::MatMul(a, b) => {
+                    // dA = G B^T, dB = A^T G.
+                    let bt = self.value(*b).transpose();
+                    matmul_acc(&g.view(), &bt.view(), &mut adj[a.0].view_mut());
+                    let at = self.value(*a).transpose();
+                    matmul_acc(&at.view(), &g.view(), &mut adj[b.0].view_mut());
+                }
+                Op::Solve(a, b, lu) => {
+                    // With H = A^-T G, dB = H and dA = -H X^T.
+                    let h = solve_columns(lu, &g, true);
+                    let xt = self.values[k].transpose();
+                    let mut ha = Matrix::zero(h.m, xt.n);
+                    matmul(&h.view(), &xt.view(), &mut ha.view_mut());
+                    accumulate(&mut adj[a.0], &ha, -1.0);
+                    accumulate(&mut adj[b.0], &h, 1.0);
+                }
+                Op::SumSquares(a) => {
+                    let s = g.get(0, 0);
+                    accumulate(&mut adj[a.0], self.value(*a), 2.0 * s);
+                }
+            }
+            adj[k] = g;
+        }
+        Gradients { adj }
+    }
+}
+
+/// Gradients of a scalar from Tape::grad().
+pub struct Gradients {
+    adj: Vec<Matrix>,
+}
+
+impl Gradients {
+    /// Return the gradient with respect to v, shaped like v.
+    pub fn wrt(&self, v: Var) -> &Matrix {
+        &self.adj[v.0]
+    }
+}
+
+/// Return a + alpha b.
+fn combine(a: &Matrix, b: &Matrix, alpha: f64) -> Matrix {
+    let mut c = a.clone();
+    accumulate(&mut c, b, alpha);
+    c
+}
+
+/// Replace a with a + alpha b.
+fn accumulate(a: &mut Matrix, b: &Matrix, alpha: f64) {
+    assert_eq!((a.m, a.n), (b.m, b.n));
+    for i in 0..a.m {
+        for j in 0..a.n {
+            a.set(i, j, a.get(i, j) + alpha * b.get(i, j));
+        }
+    }
+}
+
+/// Return A^-1 B, or A^-T B if `transpose` is set.
+fn solve_columns(lu: &LuFactor, b: &Matrix, transpose: bool) -> Matrix {
+    assert_eq!(b.m, lu.dim());
+    let mut x = Matrix::zero(b.m, b.n);
+    let mut col = Vec::with_capacity(b.m);
+    for j in 0..b.n {
+        col.clear();
+        col.extend((0..b.m).map(|i| b.get(i, j)));
+        if transpose {
+            lu.solve_transpose_in_place(&mut col);
+        } else {
+            lu.solve_in_place(&mut col);
+        }
+        (0..b.m).for_each(|i| x.set(i, j, col[i]));
+    }
+    x
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// f(A, B) = ||2 A^-1 B - B + A B||^2, through every operation.
+    fn eval(a: &Matrix, b: &Matrix) -> (Tape, Var, Var, Var) {
+        let mut tape = Tape::new();
+        let av = tape.var(a.clone());
+        let bv = tape.var(b.clone());
+        let x = tape.solve(av, bv).unwrap();
+        let x2 = tape.scale(x, 2.0);
+        let r = tape.sub(x2, bv);
+        let ab = tape.matmul(av, bv);
+        let s = tape.add(r, ab);
+        let f = tape.sum_squares(s);
+        (tape, av, bv, f)
+    }
+
+    #[test]
+    fn gradients_match_finite_differences() {
+        let mut a = Matrix::rand_seeded(3, 3, 1);
+        (0..3).for_each(|i| a.set(i, i, a.get(i, i) + 2.0));
+        let b = Matrix::rand_seeded(3, 2, 2);
+        let (tape, av, bv, f) = eval(&a, &b);
+        let grad = tape.grad(f);
+        let h = 1e-6;
+        for (v, m) in [(av, &a), (bv, &b)] {
+            for i in 0..m.m {
+                for j in 0..m.n {
+                    let mut plus = m.clone();
+                    plus.set(i, j, m.get(i, j) + h);
+                    let mut minus = m.clone();
+                    minus.set(i, j, m.get(i, j) - h);
+                    let (fp, fm) = if v == av {
+                        let (t1, _, _, f1) = eval(&plus, &b);
+                        let (t2, _, _, f2) = eval(&minus, &b);
+                        (t1.value(f1).get(0, 0), t2.value(f2).get(0, 0))
+                    } else {
+                        let (t1, _, _, f1) = eval(&a, &plus);
+                        let (t2, _, _, f2) = eval(&a, &minus);
+                        (t1.value(f1).get(0, 0), t2.value(f2).get(0, 0))
+                    };
+                    let fd = (fp - fm) / (2.0 * h);
+                    assert!((grad.wrt(v).get(i, j) - fd).abs() < 1e-6 * fd.abs().max(1.0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn least_squares_gradient() {
+        // d/dA ||A x - b||^2 = 2 (A x - b) x^T.
+        let mut tape = Tape::new();
+        let a = tape.var(Matrix::rand_seeded(4, 3, 3));
+        let x = tape.var(Matrix::rand_seeded(3, 1, 4));
+        let b = tape.var(Matrix::rand_seeded(4, 1, 5));
+        let ax = tape.matmul(a, x);
+        let r = tape.sub(ax, b);
+        let f = tape.sum_squares(r);
+        let grad = tape.grad(f);
+        let (rv, xv) = (tape.value(r), tape.value(x));
+        for i in 0..4 {
+            for j in 0..3 {
+                assert!((grad.wrt(a).get(i, j) - 2.0 * rv.get(i, 0) * xv.get(j, 0)).abs() < 1e-14);
+            }
+            assert!((grad.wrt(b).get(i, 0) + 2.0 * rv.get(i, 0)).abs() < 1e-14);
+        }
+        // Intermediate results have gradients too.
+        assert!((grad.wrt(r).get(2, 0) - 2.0 * rv.get(2, 0)).abs() < 1e-14);
+
+        let mut tape = Tape::new();
+        let z = tape.var(Matrix::zero(2, 2));
+        let b = tape.var(Matrix::rand_seeded(2, 1, 6));
+        assert!(tape.solve(z, b).is_none());
+    }
+}
@@ -0,0 +1,383 @@
+//! Banded matrices and their LU and QR factorizations.
+//!
+//! A band matrix with kl subdiagonals and ku superdiagonals is stored by
+//! rows, kl + ku + 1 entries per row. Partial pivoting within the band
+//! widens U to kl + ku superdiagonals, as does QR by Givens rotations, so
+//! both factorizations work in rows of width 2 kl + ku + 1 and cost
+//! O(n kl (kl + ku)) instead of O(n^3).
+use crate::factor::Factor;
+use crate::givens::Givens;
+use crate::iterative::LinearOperator;
+use crate::{Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// An m x n matrix that is zero outside kl subdiagonals and ku
+/// superdiagonals.
+pub struct BandMatrix {
+    pub m: usize,
+    pub n: usize,
+    pub kl: usize,
+    pub ku: usize,
+    /// Row i holds columns i - kl ..= i + ku.
+    data: Vec<f64>,
+}
+
+impl BandMatrix {
+    /// Return the zero band matrix.
+    pub fn zero(m: usize, n: usize, kl: usize, ku: usize) -> BandMatrix {
+        BandMatrix { m, n, kl, ku, data: vec![0.0; m * (kl + ku + 1)] }
+    }
+
+    /// Return the band of a; entries outside it are ignored.
+    pub fn from_dense(a: &Matrix, kl: usize, ku: usize) -> BandMatrix {
+        let mut b = BandMatrix::zero(a.m, a.n, kl, ku);
+        for i in 0..a.m {
+            for j in b.cols(i) {
+                b.set(i, j, a.get(i, j));
+            }
+        }
+        b
+    }
+
+    /// Return the matrix as a dense one.
+    pub fn to_dense(&self) -> Matrix {
+        let mut a = Matrix::zero(self.m, self.n);
+        for i in 0..self.m {
+            for j in self.cols(i) {
+                a.set(i, j, self.get(i, j));
+            }
+        }
+        a
+    }
+
+    /// Return the columns of the band in row i.
+    pub fn cols(&self, i: usize) -> core::ops::Range<usize> {
+        i.saturating_sub(self.kl).min(self.n)..(i + self.ku + 1).min(self.n)
+    }
+
+    /// Return entry (i, j), which is zero outside the band.
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        assert!(i < self.m && j < self.n);
+        if j + self.kl < i || j > i + self.ku {
+            return 0.0;
+        }
+        self.data[i * (self.kl + self.ku + 1) + j + self.kl - i]
+    }
+
+    /// Set entry (i, j), which must be inside the band.
+    pub fn set(&mut self, i: usize, j: usize, value: f64) {
+        assert!(i < self.m && j < self.n);
+        assert!(j + self.kl >= i && j <= i + self.ku, "entry outside the band");
+        self.data[i * (self.kl + self.ku + 1) + j + self.kl - i] = value;
+    }
+
+    /// Compute y = A x.
+    pub fn matvec(&self, x: &[f64], y: &mut [f64]) {
+        assert_eq!((x.len(), y.len()), (self.n, self.m));
+        for (i, yi) in y.iter_mut().enumerate() {
+            *yi = self.cols(i).map(|j| self.get(i, j) * x[j]).sum();
+        }
+    }
+
+    /// Return the 1-norm, the largest column sum of absolute values.
+    pub fn norm1(&self) -> f64 {
+        let mut sums = vec![0.0; self.n];
+        for i in 0..self.m {
+            for j in self.cols(i) {
+                sums[j] += self.get(i, j).abs();
+            }
+        }
+        sums.into_iter().fold(0.0, f64::max)
+    }
+}
+
+impl LinearOperator for BandMatrix {
+    fn nrows(&self) -> usize {
+        self.m
+    }
+
+    fn ncols(&self) -> usize {
+        self.n
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        self.matvec(x, y);
+    }
+}
+
+/// Working storage for a factorization: rows of width 2 kl + ku + 1, row i
+/// holding columns i - kl ..= i + kl + ku.
+struct Work {
+    kl: usize,
+    width: usize,
+    data: Vec<f64>,
+}
+
+impl Work {
+    fn new(a: &BandMatrix) -> Work {
+        let width = 2 * a.kl + a.ku + 1;
+        let mut w = Work { kl: a.kl, width, data: vec![0.0; a.m * width] };
+        for i in 0..a.m {
+            for j in a.cols(i) {
+                *w.at(i, j) = a.get(i, j);
+            }
+        }
+        w
+    }
+
+    #[inline]
+    fn get(&self, i: usize, j: usize) -> f64 {
+        self.data[i * self.width + j + self.kl - i]
+    }
+
+    #[inline]
+    fn at(&mut self, i: usize, j: usize) -> &mut f64 {
+        &mut self.data[i * self.width + j + self.kl - i]
+    }
+}
+
+/// LU factorization of a square band matrix with partial pivoting, kept
+/// for repeated solves.
+///
+/// Pivots are chosen from the kl rows below the diagonal, so U has
+/// kl + ku superdiagonals. As in LAPACK's gbtrf the multipliers of each
+/// step are not permuted by later swaps; solves apply the swaps and the
+/// eliminations interleaved.
+pub struct BandLu {
+    n: usize,
+    kl: usize,
+    ku: usize,
+    f: Work,
+    piv: Vec<usize>,
+    norm1: f64,
+}
+
+impl BandLu {
+    /// Factor a, or return None if it is singular.
+    pub fn new(a: &BandMatrix) -> Option<BandLu> {
+        assert_eq!(a.m, a.n);
+        let (n, kl, ku) = (a.n, a.kl, a.ku);
+        let mut f = Work::new(a);
+        let mut piv = vec![0; n];
+        for k in 0..n {
+            let last = (k + kl).min(n - 1);
+            let end = (k + kl + ku + 1).min(n);
+            let mut p = k;
+            for i in k + 1..=last {
+                if f.get(i, k).abs() > f.get(p, k).abs() {
+                    p = i;
+                }
+            }
+            piv[k] = p;
+            if p != k {
+                for j in k..end {
+                    let t = f.get(k, j);
+                    *f.at(k, j) = f.get(p, j);
+                    *f.at(p, j) = t;
+                }
+            }
+
+            let pivot = f.get(k, k);
+            if pivot == 0.0 {
+                return None;
+            }
+            for i in k + 1..=last {
+                let l = f.get(i, k) / pivot;
+                *f.at(i, k) = l;
+                for j in k + 1..end {
+                    *f.at(i, j) -= l * f.get(k, j);
+                }
+            }
+        }
+        Some(BandLu { n, kl, ku, f, piv, norm1: a.norm1() })
+    }
+
+    /// Return the pivot indices: row k was swapped with row piv[k] at
+    /// step k.
+    pub fn pivots(&self) -> &[usize] {
+        &self.piv
+    }
+
+    fn upper_end(&self, i: usize) -> usize {
+        (i + self.kl + self.ku + 1).min(self.n)
+    }
+}
+
+impl Factor for BandLu {
+    fn dim(&self) -> usize {
+        self.n
+    }
+
+    fn solve_in_place(&self, b: &mut [f64]) {
+        let (n, f) = (self.n, &self.f);
+        assert_eq!(b.len(), n);
+        for k in 0..n {
+            b.swap(k, self.piv[k]);
+            for i in k + 1..=(k + self.kl).min(n - 1) {
+                b[i] -= f.get(i, k) * b[k];
+            }
+        }
+        for i in (0..n).rev() {
+            let sum: f64 = (i + 1..self.upper_end(i)).map(|j| f.get(i, j) * b[j]).sum();
+            b[i] = (b[i] - sum) / f.get(i, i);
+        }
+    }
+
+    fn solve_transpose_in_place(&self, b: &mut [f64]) {
+        // Solve with U^T, then undo each elimination step and its row swap
+        // in reverse.
+        let (n, f) = (self.n, &self.f);
+        assert_eq!(b.len(), n);
+        for i in 0..n {
+            let sum: f64 = (i.saturating_sub(self.kl + self.ku)..i).map(|k| f.get(k, i) * b[k]).sum();
+            b[i] = (b[i] - sum) / f.get(i, i);
+        }
+        for k in (0..n).rev() {
+            let sum: f64 = (k + 1..=(k + self.kl).min(n - 1)).map(|i| f.get(i, k) * b[i]).sum();
+            b[k] -= sum;
+            b.swap(k, self.piv[k]);
+        }
+    }
+
+    fn det(&self) -> f64 {
+        let swaps = self.piv.iter().enumerate().filter(|(k, &p)| *k != p).count();
+        let det: f64 = (0..self.n).map(|i| self.f.get(i, i)).product();
+        if swaps % 2 == 0 { det } else { -det }
+    }
+
+    fn norm1(&self) -> f64 {
+        self.norm1
+    }
+}
+
+/// QR factorization of an m x n band matrix, m >= n, by Givens rotations,
+/// for least squares problems.
+///
+/// R has kl + ku superdiagonals. Q is kept as the sequence of rotations.
+pub struct BandQr {
+    m: usize,
+    n: usize,
+    kl: usize,
+    ku: usize,
+    r: Work,
+    /// Rotation of rows (k, i) at each step, in order.
+    rotations: Vec<(usize, usize, Givens)>,
+}
+
+impl BandQr {
+    /// Factor a.
+    pub fn new(a: &BandMatrix) -> BandQr {
+        assert!(a.m >= a.n);
+        let (m, n, kl, ku) = (a.m, a.n, a.kl, a.ku);
+        let mut r = Work::new(a);
+        let mut rotations = Vec::new();
+        for k in 0..n {
+            let end = (k + kl + ku + 1).min(n);
+            for i in k + 1..(k + kl + 1).min(m) {
+                if r.get(i, k) == 0.0 {
+                    continue;
+                }
+                let (g, diag) = Givens::new(r.get(k, k), r.get(i, k));
+                *r.at(k, k) = diag;
+                *r.at(i, k) = 0.0;
+                for j in k + 1..end {
+                    let (x, y) = g.rotate(r.get(k, j), r.get(i, j));
+                    *r.at(k, j) = x;
+                    *r.at(i, j) = y;
+                }
+                rotations.push((k, i, g));
+            }
+        }
+        BandQr { m, n, kl, ku, r, rotations }
+    }
+
+    /// Return the least squares solution of min ||A x - b||.
+    pub fn solve(&self, b: &[f64]) -> Vec<f64> {
+        assert_eq!(b.len(), self.m);
+        let mut z = b.to_vec();
+        for (k, i, g) in &self.rotations {
+            g.apply(&mut z, *k, *i);
+        }
+        let n = self.n;
+        for i in (0..n).rev() {
+            let d = self.r.get(i, i);
+            assert!(d != 0.0, "least squares problem is rank deficient");
+            let end = (i + self.kl + self.ku + 1).min(n);
+            let sum: f64 = (i + 1..end).map(|j| self.r.get(i, j) * z[j]).sum();
+            z[i] = (z[i] - sum) / d;
+        }
+        z.truncate(n);
+        z
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lu::LuFactor;
+    use crate::qr::LeastSquares;
+
+    /// Upwind convection-diffusion on n points: -eps u'' + u' with a
+    /// second-order upwind stencil, giving two subdiagonals and one
+    /// superdiagonal.
+    fn convection(n: usize, eps: f64) -> BandMatrix {
+        let h = 1.0 / (n + 1) as f64;
+        let mut a = BandMatrix::zero(n, n, 2, 1);
+        for i in 0..n {
+            let d = eps / (h * h);
+            a.set(i, i, 2.0 * d + 1.5 / h);
+            if i > 0 {
+                a.set(i, i - 1, -d - 2.0 / h);
+            }
+            if i > 1 {
+                a.set(i, i - 2, 0.5 / h);
+            }
+            if i + 1 < n {
+                a.set(i, i + 1, -d);
+            }
+        }
+        a
+    }
+
+    #[test]
+    fn band_lu_matches_dense() {
+        let a = convection(30, 1e-3);
+        let dense = a.to_dense();
+        let f = BandLu::new(&a).unwrap();
+        let g = LuFactor::new(&dense).unwrap();
+        let b: Vec<f64> = (0..30).map(|i| (i as f64 * 0.3).sin()).collect();
+        let tol = 1e-12 * f.cond_est();
+        let (x, y) = (f.solve(&b), g.solve(&b));
+        assert!(x.iter().zip(&y).all(|(p, q)| (p - q).abs() <= tol * q.abs().max(1.0)));
+        let (mut x, mut y) = (b.clone(), b.clone());
+        f.solve_transpose_in_place(&mut x);
+        g.solve_transpose_in_place(&mut y);
+        assert!(x.iter().zip(&y).all(|(p, q)| (p - q).abs() <= tol * q.abs().max(1.0)));
+        assert!((f.det() / g.det() - 1.0).abs() < 1e-10);
+
+        // Pivoting is needed: the leading entry is zero.
+        let mut p = BandMatrix::zero(3, 3, 1, 1);
+        for (i, j, v) in [(0, 1, 1.0), (1, 0, 2.0), (1, 1, 1.0), (1, 2, 1.0), (2, 1, 1.0), (2, 2, 3.0)] {
+            p.set(i, j, v);
+        }
+        let x = BandLu::new(&p).unwrap().solve(&[2.0, 7.0, 11.0]);
+        assert!(x.iter().zip(&[1.0, 2.0, 3.0]).all(|(p, q)| (p - q).abs() < 1e-15));
+        assert!(BandLu::new(&BandMatrix::zero(3, 3, 1, 1)).is_none());
+    }
+
+    #[test]
+    fn band_least_squares() {
+        let (m, n) = (12, 8);
+        let mut a = BandMatrix::zero(m, n, 3, 1);
+        for i in 0..m {
+            for j in a.cols(i) {
+                a.set(i, j, 1.0 + ((i * 7 + j * 3) % 5) as f64);
+            }
+        }
+        let b: Vec<f64> = (0..m).map(|i| i as f64 - 4.0).collect();
+        let x = BandQr::new(&a).solve(&b);
+        let y = LeastSquares::from_rows(&a.to_dense(), &b).solve();
+        assert!(x.iter().zip(&y).all(|(p, q)| (p - q).abs() < 1e-12));
+    }
+}
@@ -0,0 +1,138 @@
+//! Batched operations over many equally sized small matrices.
+//!
+//! Each batch is a contiguous slice of row-major matrices stored one after
+//! the other. With the `parallel` feature the batch is split across threads.
+use crate::lu::{lu_slice, lu_solve_slice};
+use alloc::vec::Vec;
+#[cfg(feature = "parallel")]
+use crate::parallel;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Multiply a single m x k matrix by a k x n matrix.
+#[inline]
+fn matmul_slice(m: usize, k: usize, n: usize, a: &[f64], b: &[f64], c: &mut [f64]) {
+    c.fill(0.0);
+    for i in 0..m {
+        for l in 0..k {
+            let ail = a[i * k + l];
+            for j in 0..n {
+                c[i * n + j] += ail * b[l * n + j];
+            }
+        }
+    }
+}
+
+/// Multiply each m x k matrix in `a` by the matching k x n matrix in `b`.
+pub fn batch_matmul(m: usize, k: usize, n: usize, a: &[f64], b: &[f64], c: &mut [f64]) {
+    assert!(m > 0 && k > 0 && n > 0);
+    assert_eq!(a.len() % (m * k), 0);
+    let count = a.len() / (m * k);
+    assert_eq!(b.len(), count * k * n);
+    assert_eq!(c.len(), count * m * n);
+
+    #[cfg(feature = "parallel")]
+    parallel::run(|| {
+        c.par_chunks_mut(m * n)
+            .zip(a.par_chunks(m * k))
+            .zip(b.par_chunks(k * n))
+            .for_each(|((c, a), b)| matmul_slice(m, k, n, a, b, c))
+    });
+    #[cfg(not(feature = "parallel"))]
+    c.chunks_mut(m * n)
+        .zip(a.chunks(m * k))
+        .zip(b.chunks(k * n))
+        .for_each(|((c, a), b)| matmul_slice(m, k, n, a, b, c));
+}
+
+/// LU factor each n x n matrix in `a` in place, storing pivots in `piv`.
+///
+/// Returns whether each factorization was nonsingular.
+pub fn batch_lu(n: usize, a: &mut [f64], piv: &mut [usize]) -> Vec<bool> {
+    assert!(n > 0);
+    assert_eq!(a.len() % (n * n), 0);
+    let count = a.len() / (n * n);
+    assert_eq!(piv.len(), count * n);
+
+    #[cfg(feature = "parallel")]
+    let res = parallel::run(|| {
+        a.par_chunks_mut(n * n)
+            .zip(piv.par_chunks_mut(n))
+            .map(|(a, piv)| lu_slice(n, n, a, piv))
+            .collect()
+    });
+    #[cfg(not(feature = "parallel"))]
+    let res = a.chunks_mut(n * n)
+        .zip(piv.chunks_mut(n))
+        .map(|(a, piv)| lu_slice(n, n, a, piv))
+        .collect();
+    res
+}
+
+/// Solve each system in place using factorizations from batch_lu().
+pub fn batch_solve(n: usize, lu: &[f64], piv: &[usize], b: &mut [f64]) {
+    assert!(n > 0);
+    assert_eq!(lu.len() % (n * n), 0);
+    let count = lu.len() / (n * n);
+    assert_eq!(piv.len(), count * n);
+    assert_eq!(b.len(), count * n);
+
+    #[cfg(feature = "parallel")]
+    parallel::run(|| {
+        b.par_chunks_mut(n)
+            .zip(lu.par_chunks(n * n))
+            .zip(piv.par_chunks(n))
+            .for_each(|((b, lu), piv)| lu_solve_slice(n, n, lu, piv, b))
+    });
+    #[cfg(not(feature = "parallel"))]
+    b.chunks_mut(n)
+        .zip(lu.chunks(n * n))
+        .zip(piv.chunks(n))
+        .for_each(|((b, lu), piv)| lu_solve_slice(n, n, lu, piv, b));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{approx, matmul, Matrix, MatrixIndex};
+    use alloc::vec;
+
+    #[test]
+    fn batch_matmul_matches_matmul() {
+        let count = 5;
+        let a: Vec<Matrix> = (0..count).map(|k| Matrix::rand_seeded(3, 4, 2 * k as u64)).collect();
+        let b: Vec<Matrix> = (0..count).map(|k| Matrix::rand_seeded(4, 2, 2 * k as u64 + 1)).collect();
+        let a_data: Vec<f64> = a.iter().flat_map(|x| x.as_slice().to_vec()).collect();
+        let b_data: Vec<f64> = b.iter().flat_map(|x| x.as_slice().to_vec()).collect();
+        let mut c_data = vec![0.0; count * 3 * 2];
+
+        batch_matmul(3, 4, 2, &a_data, &b_data, &mut c_data);
+
+        for (idx, (x, y)) in a.iter().zip(&b).enumerate() {
+            let mut z = Matrix::zero(3, 2);
+            matmul(&x.view(), &y.view(), &mut z.view_mut());
+            for i in 0..3 {
+                for j in 0..2 {
+                    assert!(approx(z.get(i, j), c_data[idx * 6 + i * 2 + j]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn batch_lu_solve() {
+        // Two 2 x 2 systems, each with solution [1, 1]. The second is not
+        // diagonally dominant and needs a row swap, which tests pivoting.
+        let mut a = vec![4.0, 1.0,
+                         2.0, 5.0,
+                         1.0, 3.0,
+                         6.0, 2.0];
+        let mut piv = vec![0; 4];
+        let mut b = vec![5.0, 7.0, 4.0, 8.0];
+
+        assert!(batch_lu(2, &mut a, &mut piv).iter().all(|ok| *ok));
+        batch_solve(2, &a, &piv, &mut b);
+
+        assert!(b.iter().all(|x| approx(*x, 1.0)));
+    }
+}
@@ -1,33 +1,856 @@
-/// Benchmark abstraction.
-use std::time::Instant;
+//! Benchmark abstraction.
+#[cfg(all(feature = "perf", target_os = "linux"))]
+pub mod perf;
+use std::fmt;
+pub use std::hint::black_box;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
+/// Benchmark settings, built with `BenchOptions::new().warmup(5).trials(50)`.
+#[derive(Clone, Debug)]
 pub struct BenchOptions {
     /// Number of warmup iterations.
     warmup: usize,
 
     /// Number of trials
     trial_count: usize,
+
+    /// Floating point operations per trial.
+    flops: Option<f64>,
+
+    /// Bytes moved to or from memory per trial.
+    bytes: Option<f64>,
+
+    /// Keep running trials until this much time was measured.
+    time_budget: Option<Duration>,
+
+    /// Keep running trials until the 95% confidence interval of the mean is
+    /// within this fraction of the mean.
+    rel_ci: Option<f64>,
+
+    /// Upper bound on the trials of an adaptive run.
+    max_trials: usize,
+
+    /// Size of the buffer swept before each trial to evict the caches.
+    flush_bytes: Option<usize>,
+}
+
+impl BenchOptions {
+    /// Return the default options: 3 warmup iterations and 10 trials.
+    pub fn new() -> BenchOptions {
+        BenchOptions {
+            warmup: 3,
+            trial_count: 10,
+            flops: None,
+            bytes: None,
+            time_budget: None,
+            rel_ci: None,
+            max_trials: 1_000_000,
+            flush_bytes: None,
+        }
+    }
+
+    /// Set the number of untimed warmup iterations.
+    pub fn warmup(mut self, warmup: usize) -> BenchOptions {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Set the number of timed trials.
+    ///
+    /// In an adaptive run (see time_budget() and rel_ci()) this is the
+    /// minimum number of trials.
+    pub fn trials(mut self, trial_count: usize) -> BenchOptions {
+        assert!(trial_count > 0);
+        self.trial_count = trial_count;
+        self
+    }
+
+    /// Set the floating point operations done by each trial.
+    pub fn flops(mut self, flops: f64) -> BenchOptions {
+        self.flops = Some(flops);
+        self
+    }
+
+    /// Set the estimated bytes moved by each trial.
+    pub fn bytes(mut self, bytes: f64) -> BenchOptions {
+        self.bytes = Some(bytes);
+        self
+    }
+
+    /// Run trials until their total measured time reaches `budget`.
+    pub fn time_budget(mut self, budget: Duration) -> BenchOptions {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Run trials until the 95% confidence interval of the mean time is
+    /// within the fraction `rel_ci` of the mean (e.g. 0.01 for 1%).
+    ///
+    /// Combined with time_budget(), the run stops at whichever comes first.
+    pub fn rel_ci(mut self, rel_ci: f64) -> BenchOptions {
+        assert!(rel_ci > 0.0);
+        self.rel_ci = Some(rel_ci);
+        self
+    }
+
+    /// Set the upper bound on the trials of an adaptive run.
+    pub fn max_trials(mut self, max_trials: usize) -> BenchOptions {
+        self.max_trials = max_trials;
+        self
+    }
+
+    /// Sweep a buffer of `bytes` bytes before each trial (untimed), so the
+    /// trials run with a cold cache.
+    ///
+    /// `bytes` should be a few times the size of the last level cache; see
+    /// DEFAULT_FLUSH_BYTES.
+    pub fn flush_cache(mut self, bytes: usize) -> BenchOptions {
+        self.flush_bytes = Some(bytes);
+        self
+    }
+
+    /// Return true once n trials, with the given sum and sum of squares of
+    /// their times, satisfy the stopping rule.
+    fn done(&self, n: usize, sum: f64, sum_sq: f64) -> bool {
+        if n < self.trial_count {
+            return false;
+        }
+        if self.time_budget.is_none() && self.rel_ci.is_none() || n >= self.max_trials {
+            return true;
+        }
+        if let Some(budget) = self.time_budget {
+            if sum >= budget.as_secs_f64() {
+                return true;
+            }
+        }
+        if let (Some(rel_ci), true) = (self.rel_ci, n >= 2) {
+            let mean = sum / n as f64;
+            let var = ((sum_sq - sum * mean) / (n - 1) as f64).max(0.0);
+            let half_width = 1.96 * (var / n as f64).sqrt();
+            if half_width <= rel_ci * mean {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl Default for BenchOptions {
+    fn default() -> BenchOptions {
+        BenchOptions::new()
+    }
+}
+
+/// Flush size that exceeds the last level cache of most machines.
+pub const DEFAULT_FLUSH_BYTES: usize = 256 << 20;
+
+/// Buffer that is read and written to evict the caches.
+struct Flusher {
+    buf: Vec<u64>,
+}
+
+impl Flusher {
+    fn new(bytes: Option<usize>) -> Flusher {
+        Flusher { buf: vec![0; bytes.unwrap_or(0) / 8] }
+    }
+
+    fn flush(&mut self) {
+        for x in self.buf.iter_mut() {
+            *x = x.wrapping_add(1);
+        }
+        black_box(&mut self.buf);
+    }
+}
+
+/// Timing statistics, in seconds, over the trials of a benchmark.
+#[derive(Clone, Debug)]
+pub struct BenchResult {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub median: f64,
+    /// Sample standard deviation (zero for a single trial).
+    pub std_dev: f64,
+    /// Time of each trial, sorted in increasing order.
+    pub times: Vec<f64>,
+    /// Floating point operations per trial, if known.
+    pub flops: Option<f64>,
+    /// Bytes moved per trial, if known.
+    pub bytes: Option<f64>,
+    /// Readings of each extra Timer, per trial in the order they ran.
+    pub counters: Vec<(String, Vec<f64>)>,
+}
+
+impl BenchResult {
+    /// Compute the statistics of the given trial times.
+    pub fn from_times(mut times: Vec<f64>) -> BenchResult {
+        assert!(!times.is_empty());
+        times.sort_by(f64::total_cmp);
+        let count = times.len() as f64;
+        let mean = times.iter().sum::<f64>() / count;
+        let var = if times.len() > 1 {
+            times.iter().map(|t| (t - mean) * (t - mean)).sum::<f64>() / (count - 1.0)
+        } else {
+            0.0
+        };
+        let mut res = BenchResult {
+            mean,
+            min: times[0],
+            max: times[times.len() - 1],
+            median: 0.0,
+            std_dev: var.sqrt(),
+            times,
+            flops: None,
+            bytes: None,
+            counters: Vec::new(),
+        };
+        res.median = res.percentile(50.0);
+        res
+    }
+
+    /// Return the p-th percentile (0 to 100), interpolating linearly
+    /// between trials.
+    pub fn percentile(&self, p: f64) -> f64 {
+        assert!((0.0..=100.0).contains(&p));
+        let rank = p / 100.0 * (self.times.len() - 1) as f64;
+        let lo = rank.floor() as usize;
+        let hi = rank.ceil() as usize;
+        self.times[lo] + (rank - lo as f64) * (self.times[hi] - self.times[lo])
+    }
+
+    /// Return the mean reading of the named counter.
+    pub fn counter_mean(&self, name: &str) -> Option<f64> {
+        let (_, values) = self.counters.iter().find(|(n, _)| n == name)?;
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+
+    /// Return the rate in GFLOP/s at the median time.
+    pub fn gflops(&self) -> Option<f64> {
+        self.flops.map(|f| f / self.median * 1e-9)
+    }
+
+    /// Return the bandwidth in GB/s at the median time.
+    pub fn gbytes_per_sec(&self) -> Option<f64> {
+        self.bytes.map(|b| b / self.median * 1e-9)
+    }
+}
+
+/// Source of a per-trial measurement, e.g. wall time or a hardware counter.
+pub trait Timer {
+    /// Return the name of the measured quantity.
+    fn name(&self) -> &str;
+
+    /// Start measuring.
+    fn start(&mut self);
+
+    /// Stop and return the amount measured since start().
+    fn stop(&mut self) -> f64;
+}
+
+/// Wall-clock time in seconds.
+#[derive(Clone, Debug)]
+pub struct WallClock {
+    start: Instant,
+}
+
+impl WallClock {
+    pub fn new() -> WallClock {
+        WallClock { start: Instant::now() }
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> WallClock {
+        WallClock::new()
+    }
+}
+
+impl Timer for WallClock {
+    fn name(&self) -> &str {
+        "seconds"
+    }
+
+    fn start(&mut self) {
+        self.start = Instant::now();
+    }
+
+    fn stop(&mut self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+}
+
+/// Benchmark the function and return statistics over the trials.
+///
+/// `startup` builds the input of each trial untimed, followed by the cache
+/// flush if one was requested. The input and the result of `critical_code`
+/// pass through `black_box`, so the optimizer cannot elide the work.
+pub fn bench<S, C, A, R>(opts: BenchOptions, startup: S, critical_code: C) -> BenchResult
+where
+    S: FnMut() -> A,
+    C: FnMut(A) -> R,
+{
+    bench_with_timers(opts, &mut [], startup, critical_code)
 }
 
-/// Benchmark the function and return the average time.
-pub fn bench<S, C, A>(opts: BenchOptions, startup: S, critical_code: C) -> f64
+/// Benchmark like bench(), also recording `timers` around each trial.
+///
+/// The per-trial readings are returned in BenchResult::counters.
+pub fn bench_with_timers<S, C, A, R>(opts: BenchOptions, timers: &mut [&mut dyn Timer],
+                                     mut startup: S, mut critical_code: C) -> BenchResult
 where
-    S: Fn() -> A,
-    C: Fn(A),
+    S: FnMut() -> A,
+    C: FnMut(A) -> R,
 {
-    let mut total_time = 0.0;
-    let mut total_count = 0;
-    for i in 0..opts.warmup + opts.trial_count {
+    let mut flusher = Flusher::new(opts.flush_bytes);
+    let mut wall = WallClock::new();
+    let mut counts = vec![Vec::new(); timers.len()];
+    let mut res = run_trials(&opts, || {
         let args = startup();
+        flusher.flush();
+
+        measure(&mut wall, timers, &mut counts, || critical_code(black_box(args)))
+    });
+    res.counters = timers
+        .iter()
+        .zip(counts)
+        .map(|(t, mut c)| (t.name().to_string(), c.split_off(opts.warmup)))
+        .collect();
+    res
+}
+
+/// Benchmark a kernel that reuses preallocated buffers in `state`.
+///
+/// `reset` runs untimed before each trial, e.g. to restore inputs that an
+/// in-place kernel overwrites. Nothing is reallocated between trials, so
+/// the cache state matches repeated calls in a real application.
+pub fn bench_with_state<T, S, C, R>(opts: BenchOptions, state: &mut T, mut reset: S, mut critical_code: C) -> BenchResult
+where
+    S: FnMut(&mut T),
+    C: FnMut(&mut T) -> R,
+{
+    let mut flusher = Flusher::new(opts.flush_bytes);
+    let mut wall = WallClock::new();
+    run_trials(&opts, || {
+        reset(state);
+        flusher.flush();
+
+        measure(&mut wall, &mut [], &mut [], || critical_code(black_box(&mut *state)))
+    })
+}
+
+/// Run `f` between the timers and return the wall time.
+///
+/// The result of `f` is dropped after the timers stop, so freeing its
+/// output buffers is not timed.
+fn measure<R>(wall: &mut WallClock, timers: &mut [&mut dyn Timer], counts: &mut [Vec<f64>],
+              f: impl FnOnce() -> R) -> f64 {
+    for t in timers.iter_mut() {
+        t.start();
+    }
+    wall.start();
+    let out = black_box(f());
+    let time = wall.stop();
+    for (t, c) in timers.iter_mut().zip(counts).rev() {
+        c.push(t.stop());
+    }
+    drop(out);
+    time
+}
+
+/// Run the warmup and timed trials, where `trial` returns its time.
+fn run_trials(opts: &BenchOptions, mut trial: impl FnMut() -> f64) -> BenchResult {
+    for _ in 0..opts.warmup {
+        trial();
+    }
+    let mut times = Vec::with_capacity(opts.trial_count);
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    while !opts.done(times.len(), sum, sum_sq) {
+        let time = trial();
+        times.push(time);
+        sum += time;
+        sum_sq += time * time;
+    }
+
+    let mut res = BenchResult::from_times(times);
+    res.flops = opts.flops;
+    res.bytes = opts.bytes;
+    res
+}
 
-        let timer = Instant::now();
-        critical_code(args);
-        if i >= opts.warmup {
-            total_time += timer.elapsed().as_secs_f64();
-            total_count += 1;
+/// Results of a parameter sweep, one row per parameter.
+#[derive(Clone, Debug)]
+pub struct Sweep<P> {
+    pub rows: Vec<(P, BenchResult)>,
+}
+
+/// Benchmark the function once for each parameter.
+///
+/// `options` gives the settings (and flop/byte counts) for a parameter, and
+/// `startup` builds the input of `critical_code` from it.
+pub fn bench_sweep<P, O, S, C, A, R>(params: &[P], options: O, mut startup: S, mut critical_code: C) -> Sweep<P>
+where
+    P: Clone,
+    O: Fn(&P) -> BenchOptions,
+    S: FnMut(&P) -> A,
+    C: FnMut(A) -> R,
+{
+    let rows = params
+        .iter()
+        .map(|p| (p.clone(), bench(options(p), || startup(p), &mut critical_code)))
+        .collect();
+    Sweep { rows }
+}
+
+/// Columns written by Sweep::to_csv().
+const CSV_HEADER: &str = "param,trials,mean,median,min,max,std_dev,flops,bytes,times";
+
+/// A parameter whose median time regressed against a baseline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regression<P> {
+    pub param: P,
+    /// Baseline median time in seconds.
+    pub baseline: f64,
+    /// Current median time in seconds.
+    pub current: f64,
+}
+
+impl<P> Sweep<P> {
+    /// Return the parameters whose median time grew by more than the
+    /// fraction `threshold` (e.g. 0.05 for 5%) relative to `baseline`.
+    ///
+    /// Parameters missing from the baseline are skipped.
+    pub fn compare(&self, baseline: &Sweep<P>, threshold: f64) -> Vec<Regression<P>>
+    where
+        P: Clone + PartialEq,
+    {
+        let mut res = Vec::new();
+        for (p, r) in &self.rows {
+            let Some((_, b)) = baseline.rows.iter().find(|(q, _)| q == p) else {
+                continue;
+            };
+            if r.median > b.median * (1.0 + threshold) {
+                res.push(Regression { param: p.clone(), baseline: b.median, current: r.median });
+            }
+        }
+        res
+    }
+
+    /// Write one CSV row per parameter, including every trial time.
+    pub fn to_csv(&self) -> String
+    where
+        P: fmt::Display,
+    {
+        let mut out = format!("{CSV_HEADER}\n");
+        for (p, r) in &self.rows {
+            let times: Vec<String> = r.times.iter().map(|t| t.to_string()).collect();
+            out += &format!("{},{},{},{},{},{},{},{},{},{}\n",
+                            csv_field(&p.to_string()), r.times.len(), r.mean, r.median, r.min, r.max,
+                            r.std_dev, opt_to_string(r.flops), opt_to_string(r.bytes), times.join(";"));
+        }
+        out
+    }
+
+    /// Read the output of to_csv(). Returns None if it is malformed.
+    pub fn from_csv(s: &str) -> Option<Sweep<P>>
+    where
+        P: FromStr,
+    {
+        let mut lines = s.lines();
+        if lines.next()? != CSV_HEADER {
+            return None;
+        }
+        let mut rows = Vec::new();
+        for line in lines.filter(|l| !l.is_empty()) {
+            let fields = split_csv(line)?;
+            if fields.len() != 10 {
+                return None;
+            }
+            let times = fields[9].split(';').map(|t| t.parse().ok()).collect::<Option<Vec<f64>>>()?;
+            if times.is_empty() {
+                return None;
+            }
+            let mut res = BenchResult::from_times(times);
+            res.flops = opt_from_str(&fields[7])?;
+            res.bytes = opt_from_str(&fields[8])?;
+            rows.push((fields[0].parse().ok()?, res));
+        }
+        Some(Sweep { rows })
+    }
+
+    /// Write a JSON array with one object per parameter.
+    pub fn to_json(&self) -> String
+    where
+        P: fmt::Display,
+    {
+        // JSON has no infinities, e.g. for a rate over a zero time.
+        let json = |x: Option<f64>| x.filter(|x| x.is_finite()).map_or("null".to_string(), |x| x.to_string());
+        let rows: Vec<String> = self.rows.iter().map(|(p, r)| {
+            let times: Vec<String> = r.times.iter().map(|t| t.to_string()).collect();
+            format!("{{\"param\": {}, \"mean\": {}, \"median\": {}, \"min\": {}, \"max\": {}, \
+                     \"std_dev\": {}, \"flops\": {}, \"bytes\": {}, \"gflops\": {}, \
+                     \"gbytes_per_sec\": {}, \"times\": [{}]}}",
+                    json_string(&p.to_string()), r.mean, r.median, r.min, r.max, r.std_dev,
+                    json(r.flops), json(r.bytes), json(r.gflops()), json(r.gbytes_per_sec()),
+                    times.join(", "))
+        }).collect();
+        format!("[\n  {}\n]\n", rows.join(",\n  "))
+    }
+}
+
+fn opt_to_string(x: Option<f64>) -> String {
+    x.map_or(String::new(), |x| x.to_string())
+}
+
+fn opt_from_str(s: &str) -> Option<Option<f64>> {
+    if s.is_empty() {
+        Some(None)
+    } else {
+        s.parse().ok().map(Some)
+    }
+}
+
+/// Quote a CSV field if needed.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Split a CSV line into fields, undoing csv_field().
+fn split_csv(line: &str) -> Option<Vec<String>> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut()?.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut()?.push(c),
+        }
+    }
+    if quoted {
+        None
+    } else {
+        Some(fields)
+    }
+}
+
+/// Return s as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out + "\""
+}
+
+impl<P: fmt::Debug> fmt::Display for Sweep<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>16} {:>12} {:>12} {:>12} {:>12} {:>10} {:>10}",
+                 "param", "median (s)", "min (s)", "max (s)", "std dev (s)", "GFLOP/s", "GB/s")?;
+        for (p, r) in &self.rows {
+            let rate = |x: Option<f64>| x.map_or("-".to_string(), |x| format!("{x:.3}"));
+            writeln!(f, "{:>16} {:>12.3e} {:>12.3e} {:>12.3e} {:>12.3e} {:>10} {:>10}",
+                     format!("{p:?}"), r.median, r.min, r.max, r.std_dev,
+                     rate(r.gflops()), rate(r.gbytes_per_sec()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Measured machine peaks for roofline analysis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Machine {
+    pub peak_gflops: f64,
+    pub peak_gbytes_per_sec: f64,
+}
+
+impl Machine {
+    /// Measure the peaks with measure_peak_gflops() and
+    /// measure_peak_bandwidth().
+    pub fn measure() -> Machine {
+        Machine {
+            peak_gflops: measure_peak_gflops(),
+            peak_gbytes_per_sec: measure_peak_bandwidth(),
         }
     }
-    assert_eq!(total_count, opts.trial_count);
 
-    total_time / (opts.trial_count as f64)
+    /// Return the intensity (flop/byte) where the roofline turns from
+    /// memory bound to compute bound.
+    pub fn ridge_point(&self) -> f64 {
+        self.peak_gflops / self.peak_gbytes_per_sec
+    }
+
+    /// Place a benchmark result on the roofline. Returns None without both
+    /// flop and byte counts.
+    pub fn roofline(&self, r: &BenchResult) -> Option<RooflinePoint> {
+        let intensity = r.flops? / r.bytes?;
+        let attainable = self.peak_gflops.min(intensity * self.peak_gbytes_per_sec);
+        let gflops = r.gflops()?;
+        Some(RooflinePoint {
+            intensity,
+            gflops,
+            attainable,
+            memory_bound: intensity < self.ridge_point(),
+        })
+    }
+
+    /// Write a roofline table for a sweep.
+    pub fn roofline_report<P: fmt::Debug>(&self, sweep: &Sweep<P>) -> String {
+        let mut out = format!("peak {:.3} GFLOP/s, {:.3} GB/s, ridge point {:.3} flop/byte\n",
+                              self.peak_gflops, self.peak_gbytes_per_sec, self.ridge_point());
+        out += &format!("{:>16} {:>12} {:>10} {:>12} {:>10} {:>8}\n",
+                        "param", "flop/byte", "GFLOP/s", "attainable", "% of roof", "bound");
+        for (p, r) in &sweep.rows {
+            let Some(pt) = self.roofline(r) else {
+                continue;
+            };
+            out += &format!("{:>16} {:>12.3} {:>10.3} {:>12.3} {:>10.1} {:>8}\n",
+                            format!("{p:?}"), pt.intensity, pt.gflops, pt.attainable,
+                            100.0 * pt.efficiency(), if pt.memory_bound { "memory" } else { "compute" });
+        }
+        out
+    }
+}
+
+/// Position of a kernel on the roofline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RooflinePoint {
+    /// Arithmetic intensity in flop/byte.
+    pub intensity: f64,
+    /// Measured rate.
+    pub gflops: f64,
+    /// Roofline bound at this intensity.
+    pub attainable: f64,
+    /// True left of the ridge point.
+    pub memory_bound: bool,
+}
+
+impl RooflinePoint {
+    /// Return the fraction of the attainable rate that was reached.
+    pub fn efficiency(&self) -> f64 {
+        self.gflops / self.attainable
+    }
+}
+
+/// Measure the peak rate in GFLOP/s of independent multiply-add chains.
+///
+/// This is the peak of auto-vectorized code built with the current target
+/// features, not necessarily the hardware peak.
+pub fn measure_peak_gflops() -> f64 {
+    const CHAINS: usize = 64;
+    const ITERS: usize = 1 << 16;
+    let opts = BenchOptions::new().trials(10).flops((2 * CHAINS * ITERS) as f64);
+    let res = bench(opts, || [1.0f64; CHAINS], |mut acc| {
+        let (a, b) = (black_box(0.999999), black_box(1e-6));
+        for _ in 0..ITERS {
+            for x in acc.iter_mut() {
+                *x = *x * a + b;
+            }
+        }
+        acc
+    });
+    res.flops.unwrap() / res.min * 1e-9
+}
+
+/// Measure the memory bandwidth in GB/s with a STREAM-style update.
+///
+/// This runs a = a + s b, which unlike the STREAM triad moves no hidden
+/// write-allocate traffic, so its byte count is exact.
+pub fn measure_peak_bandwidth() -> f64 {
+    // Two arrays of 64 MiB, far larger than the caches.
+    const N: usize = 1 << 23;
+    let mut arrays = (vec![1.0; N], vec![2.0; N]);
+    let opts = BenchOptions::new().trials(10).bytes((3 * 8 * N) as f64);
+    let res = bench_with_state(opts, &mut arrays, |_| (), |(a, b)| {
+        let s = black_box(1e-3);
+        for i in 0..N {
+            a[i] += s * b[i];
+        }
+    });
+    res.bytes.unwrap() / res.min * 1e-9
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn bench_runs_warmup_and_trials() {
+        let mut calls = 0;
+        let res = bench(BenchOptions::new().warmup(2).trials(5), || (), |_| calls += 1);
+        assert_eq!(calls, 7);
+        assert_eq!(res.times.len(), 5);
+        assert!(res.min <= res.median && res.median <= res.max);
+
+        // Dropping the result is slow here but happens after the clock stops.
+        struct SlowDrop;
+        impl Drop for SlowDrop {
+            fn drop(&mut self) {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        }
+        let res = bench(BenchOptions::new().warmup(0).trials(3), || (), |_| SlowDrop);
+        assert!(res.max < 0.01);
+    }
+
+    #[test]
+    fn bench_with_state_reuses_buffers() {
+        let mut y = vec![0.0; 100];
+        let ptr = y.as_ptr();
+        let mut resets = 0;
+        let res = bench_with_state(BenchOptions::new().warmup(1).trials(3), &mut y,
+                                   |y| { y.fill(1.0); resets += 1; },
+                                   |y| { y.iter_mut().for_each(|v| *v *= 2.0); y.iter().sum::<f64>() });
+        assert_eq!(res.times.len(), 3);
+        assert_eq!(resets, 4);
+        assert_eq!(y.as_ptr(), ptr);
+        assert!(y.iter().all(|v| *v == 2.0));
+    }
+
+    #[test]
+    fn bench_adaptive_stopping() {
+        let opts = BenchOptions::new().warmup(0).trials(3);
+        let done = |opts: BenchOptions, times: &[f64]| {
+            opts.done(times.len(), times.iter().sum(), times.iter().map(|t| t * t).sum())
+        };
+        assert!(!done(opts.clone().rel_ci(0.01), &[1.0, 1.0]));
+        assert!(done(opts.clone().rel_ci(0.01), &[1.0, 1.0, 1.0]));
+        assert!(!done(opts.clone().rel_ci(0.01), &[1.0, 2.0, 3.0]));
+        assert!(done(opts.clone().rel_ci(0.01).max_trials(3), &[1.0, 2.0, 3.0]));
+        assert!(done(opts.clone().time_budget(Duration::from_secs(5)), &[1.0, 2.0, 3.0]));
+        assert!(!done(opts.clone().time_budget(Duration::from_secs(7)), &[1.0, 2.0, 3.0]));
+
+        let budget = Duration::from_millis(20);
+        let res = bench(opts.time_budget(budget), || (), |_| std::thread::sleep(Duration::from_millis(2)));
+        assert!(res.times.len() >= 3 && res.times.len() < 20);
+        assert!(res.times.iter().sum::<f64>() >= budget.as_secs_f64());
+    }
+
+    #[test]
+    fn bench_flush_cache() {
+        let mut flusher = Flusher::new(Some(1 << 12));
+        flusher.flush();
+        flusher.flush();
+        assert_eq!(flusher.buf.len(), 512);
+        assert!(flusher.buf.iter().all(|x| *x == 2));
+        assert!(Flusher::new(None).buf.is_empty());
+
+        let opts = BenchOptions::new().trials(2).flush_cache(1 << 20);
+        assert_eq!(bench(opts, || (), |_| ()).times.len(), 2);
+    }
+
+    #[test]
+    fn roofline_position() {
+        let machine = Machine { peak_gflops: 100.0, peak_gbytes_per_sec: 10.0 };
+        assert_eq!(machine.ridge_point(), 10.0);
+        let r = |flops, bytes| BenchResult { flops: Some(flops), bytes: Some(bytes), ..BenchResult::from_times(vec![1e-9]) };
+
+        // 2 flop/byte: memory bound at 20 GFLOP/s.
+        let pt = machine.roofline(&r(10.0, 5.0)).unwrap();
+        assert!(pt.memory_bound);
+        assert_eq!(pt.attainable, 20.0);
+        assert_eq!(pt.efficiency(), 0.5);
+
+        let pt = machine.roofline(&r(50.0, 1.0)).unwrap();
+        assert!(!pt.memory_bound);
+        assert_eq!(pt.attainable, 100.0);
+        assert!(machine.roofline(&BenchResult::from_times(vec![1.0])).is_none());
+
+        let sweep = Sweep { rows: vec![(1, r(10.0, 5.0)), (2, BenchResult::from_times(vec![1.0]))] };
+        assert_eq!(machine.roofline_report(&sweep).lines().count(), 3);
+    }
+
+    #[test]
+    fn bench_with_counting_timer() {
+        // Counts the work done by the kernel, like a hardware counter would.
+        struct Work<'a>(&'a Cell<usize>, usize);
+        impl Timer for Work<'_> {
+            fn name(&self) -> &str {
+                "work"
+            }
+
+            fn start(&mut self) {
+                self.1 = self.0.get();
+            }
+
+            fn stop(&mut self) -> f64 {
+                (self.0.get() - self.1) as f64
+            }
+        }
+
+        let work = Cell::new(0);
+        let mut timer = Work(&work, 0);
+        let res = bench_with_timers(BenchOptions::new().warmup(2).trials(4), &mut [&mut timer],
+                                    || (), |_| work.set(work.get() + 3));
+        assert_eq!(work.get(), 18);
+        assert_eq!(res.counters, vec![("work".to_string(), vec![3.0; 4])]);
+        assert_eq!(res.counter_mean("work"), Some(3.0));
+        assert_eq!(res.counter_mean("cycles"), None);
+    }
+
+    #[test]
+    fn bench_sweep_sizes() {
+        let sweep = bench_sweep(
+            &[10, 100],
+            |n| BenchOptions::new().warmup(0).trials(2).flops(*n as f64),
+            |n| vec![1.0; *n],
+            |x| assert!(x.iter().sum::<f64>() > 0.0),
+        );
+        assert_eq!(sweep.rows.len(), 2);
+        assert_eq!(sweep.rows[1].0, 100);
+        assert_eq!(sweep.rows[1].1.flops, Some(100.0));
+        assert_eq!(sweep.to_string().lines().count(), 3);
+    }
+
+    #[test]
+    fn sweep_csv_round_trip_and_compare() {
+        let result = |times: Vec<f64>| BenchResult { flops: Some(1e9), ..BenchResult::from_times(times) };
+        let baseline = Sweep {
+            rows: vec![("a,\"b\"".to_string(), result(vec![1.0, 2.0])), ("c".to_string(), result(vec![0.5]))],
+        };
+        let csv = baseline.to_csv();
+        let read = Sweep::<String>::from_csv(&csv).unwrap();
+        assert_eq!(read.rows.len(), 2);
+        assert_eq!(read.rows[0].0, "a,\"b\"");
+        assert_eq!(read.rows[0].1.times, vec![1.0, 2.0]);
+        assert_eq!(read.rows[1].1.flops, Some(1e9));
+        assert!(Sweep::<String>::from_csv("bad").is_none());
+        assert!(baseline.to_json().contains("\"param\": \"a,\\\"b\\\"\""));
+
+        let current = Sweep {
+            rows: vec![("a,\"b\"".to_string(), result(vec![1.6])), ("c".to_string(), result(vec![0.6]))],
+        };
+        let regressions = current.compare(&read, 0.1);
+        assert_eq!(regressions, vec![Regression { param: "c".to_string(), baseline: 0.5, current: 0.6 }]);
+    }
+
+    #[test]
+    fn bench_result_statistics() {
+        let res = BenchResult::from_times(vec![4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(res.mean, 2.5);
+        assert_eq!((res.min, res.max), (1.0, 4.0));
+        assert_eq!(res.median, 2.5);
+        assert_eq!(res.percentile(0.0), 1.0);
+        assert_eq!(res.percentile(100.0 / 3.0), 2.0);
+        assert!((res.std_dev - (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert_eq!(res.gflops(), None);
+
+        let res = BenchResult { flops: Some(5e9), bytes: Some(1e9), ..res };
+        assert_eq!(res.gflops(), Some(2.0));
+        assert_eq!(res.gbytes_per_sec(), Some(0.4));
+    }
 }
@@ -0,0 +1,143 @@
+//! Linux hardware and software counters via perf_event_open(2).
+use super::Timer;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::fd::{AsRawFd, FromRawFd};
+
+/// Counted event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PerfEvent {
+    Cycles,
+    Instructions,
+    CacheReferences,
+    CacheMisses,
+    BranchMisses,
+    PageFaults,
+}
+
+impl PerfEvent {
+    /// Return the perf event type and config.
+    fn code(self) -> (u32, u64) {
+        const HARDWARE: u32 = 0;
+        const SOFTWARE: u32 = 1;
+        match self {
+            PerfEvent::Cycles => (HARDWARE, 0),
+            PerfEvent::Instructions => (HARDWARE, 1),
+            PerfEvent::CacheReferences => (HARDWARE, 2),
+            PerfEvent::CacheMisses => (HARDWARE, 3),
+            PerfEvent::BranchMisses => (HARDWARE, 5),
+            PerfEvent::PageFaults => (SOFTWARE, 2),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PerfEvent::Cycles => "cycles",
+            PerfEvent::Instructions => "instructions",
+            PerfEvent::CacheReferences => "cache-references",
+            PerfEvent::CacheMisses => "cache-misses",
+            PerfEvent::BranchMisses => "branch-misses",
+            PerfEvent::PageFaults => "page-faults",
+        }
+    }
+}
+
+/// Leading fields of struct perf_event_attr (PERF_ATTR_SIZE_VER0).
+#[repr(C)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+const FLAG_DISABLED: u64 = 1 << 0;
+const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+const IOC_ENABLE: libc::c_ulong = 0x2400;
+const IOC_DISABLE: libc::c_ulong = 0x2401;
+const IOC_RESET: libc::c_ulong = 0x2403;
+
+/// Counter for one event in user space of the calling thread.
+#[derive(Debug)]
+pub struct PerfCounter {
+    event: PerfEvent,
+    file: File,
+}
+
+impl PerfCounter {
+    /// Open a counter. This fails if the kernel or the
+    /// `perf_event_paranoid` setting does not allow it.
+    pub fn new(event: PerfEvent) -> io::Result<PerfCounter> {
+        let (type_, config) = event.code();
+        let attr = PerfEventAttr {
+            type_,
+            size: std::mem::size_of::<PerfEventAttr>() as u32,
+            config,
+            sample_period: 0,
+            sample_type: 0,
+            read_format: 0,
+            flags: FLAG_DISABLED | FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV,
+            wakeup_events: 0,
+            bp_type: 0,
+            config1: 0,
+        };
+        // pid 0 and cpu -1: this thread on any CPU.
+        let fd = unsafe { libc::syscall(libc::SYS_perf_event_open, &attr, 0, -1, -1, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let file = unsafe { File::from_raw_fd(fd as libc::c_int) };
+        Ok(PerfCounter { event, file })
+    }
+
+    fn ioctl(&self, request: libc::c_ulong) {
+        unsafe {
+            libc::ioctl(self.file.as_raw_fd(), request as _, 0);
+        }
+    }
+}
+
+impl Timer for PerfCounter {
+    fn name(&self) -> &str {
+        self.event.name()
+    }
+
+    fn start(&mut self) {
+        self.ioctl(IOC_RESET);
+        self.ioctl(IOC_ENABLE);
+    }
+
+    fn stop(&mut self) -> f64 {
+        self.ioctl(IOC_DISABLE);
+        let mut buf = [0; 8];
+        match self.file.read_exact(&mut buf) {
+            Ok(()) => u64::from_ne_bytes(buf) as f64,
+            Err(_) => f64::NAN,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bench::{bench_with_timers, BenchOptions};
+
+    #[test]
+    fn perf_counts_page_faults() {
+        let Ok(mut faults) = PerfCounter::new(PerfEvent::PageFaults) else {
+            return;
+        };
+        // Touching fresh pages faults each one in.
+        let res = bench_with_timers(BenchOptions::new().warmup(0).trials(3), &mut [&mut faults],
+                                    || (), |_| vec![1u8; 1 << 24]);
+        assert!(res.counter_mean("page-faults").unwrap() > 0.0);
+    }
+}
@@ -0,0 +1,113 @@
+//! Benchmark the main kernels across problem sizes.
+//!
+//! Run with `cargo run --release --bin rnla-bench`. Pass `--csv` to print
+//! CSV instead of tables, `--cold` to flush the caches before each trial,
+//! and `--roofline` to measure the machine peaks and place each kernel on
+//! the roofline.
+use rnla::bench::{bench_with_state, BenchOptions, Machine, Sweep, DEFAULT_FLUSH_BYTES};
+use rnla::lu::lu;
+use rnla::operations::{axpy, gemv};
+use rnla::{matmul, Matrix, SparseMatrix};
+use std::time::Duration;
+
+/// Options for one size: at least 3 trials, or 0.2s of measured time.
+fn options(flops: f64, bytes: f64) -> BenchOptions {
+    let opts = BenchOptions::new()
+        .warmup(1)
+        .trials(3)
+        .time_budget(Duration::from_millis(200))
+        .flops(flops)
+        .bytes(bytes);
+    if std::env::args().any(|arg| arg == "--cold") {
+        // Each flush is slow, so bound the trials of the fast kernels.
+        opts.flush_cache(DEFAULT_FLUSH_BYTES).max_trials(10)
+    } else {
+        opts
+    }
+}
+
+fn bench_matmul(sizes: &[usize]) -> Sweep<usize> {
+    let rows = sizes.iter().map(|&n| {
+        let a = Matrix::rand_seeded(n, n, 1);
+        let b = Matrix::rand_seeded(n, n, 2);
+        let mut c = Matrix::zero(n, n);
+        let n3 = (n * n * n) as f64;
+        let res = bench_with_state(options(2.0 * n3, 24.0 * (n * n) as f64), &mut c, |_| (),
+                                   |c| matmul(&a.view(), &b.view(), &mut c.view_mut()));
+        (n, res)
+    }).collect();
+    Sweep { rows }
+}
+
+fn bench_gemv(sizes: &[usize]) -> Sweep<usize> {
+    let rows = sizes.iter().map(|&n| {
+        let a = Matrix::rand_seeded(n, n, 1);
+        let x = Matrix::rand_seeded(n, 1, 2).to_vec();
+        let mut y = vec![0.0; n];
+        let res = bench_with_state(options(2.0 * (n * n) as f64, 8.0 * (n * n + 2 * n) as f64), &mut y, |_| (),
+                                   |y| gemv(1.0, &a.view(), &x, 0.0, y));
+        (n, res)
+    }).collect();
+    Sweep { rows }
+}
+
+fn bench_axpy(sizes: &[usize]) -> Sweep<usize> {
+    let rows = sizes.iter().map(|&n| {
+        let x = Matrix::rand_seeded(n, 1, 1).to_vec();
+        let mut y = vec![0.0; n];
+        let res = bench_with_state(options(2.0 * n as f64, 24.0 * n as f64), &mut y, |_| (),
+                                   |y| axpy(0.5, &x, y));
+        (n, res)
+    }).collect();
+    Sweep { rows }
+}
+
+fn bench_lu(sizes: &[usize]) -> Sweep<usize> {
+    let rows = sizes.iter().map(|&n| {
+        let a = Matrix::rand_seeded(n, n, 1);
+        let mut w = Matrix::zero(n, n);
+        let n3 = (n * n * n) as f64;
+        let res = bench_with_state(options(2.0 / 3.0 * n3, 16.0 * (n * n) as f64), &mut w,
+                                   |w| w.as_mut_slice().copy_from_slice(a.as_slice()), lu);
+        (n, res)
+    }).collect();
+    Sweep { rows }
+}
+
+fn bench_spmv(sizes: &[usize]) -> Sweep<usize> {
+    let rows = sizes.iter().map(|&n| {
+        // About 10 nonzeros per row.
+        let a = SparseMatrix::rand(n, n, (10.0 / n as f64).min(1.0), &mut rnla::rng::Xoshiro256::new(1));
+        let x = Matrix::rand_seeded(n, 1, 2).to_vec();
+        let mut y = vec![0.0; n];
+        let nnz = a.nnz() as f64;
+        let res = bench_with_state(options(2.0 * nnz, 12.0 * nnz + 24.0 * n as f64), &mut y, |_| (),
+                                   |y| a.matvec(&x, y));
+        (n, res)
+    }).collect();
+    Sweep { rows }
+}
+
+fn main() {
+    let csv = std::env::args().any(|arg| arg == "--csv");
+    let machine = std::env::args().any(|arg| arg == "--roofline").then(Machine::measure);
+    let sweeps = [
+        ("matmul", bench_matmul(&[64, 128, 256, 512])),
+        ("lu", bench_lu(&[128, 256, 512, 1024])),
+        ("gemv", bench_gemv(&[256, 1024, 4096])),
+        ("axpy", bench_axpy(&[1 << 10, 1 << 16, 1 << 22])),
+        ("spmv", bench_spmv(&[1 << 10, 1 << 14, 1 << 18])),
+    ];
+    for (name, sweep) in &sweeps {
+        if csv {
+            println!("# {name}");
+            print!("{}", sweep.to_csv());
+        } else {
+            println!("{name}");
+            println!("{sweep}");
+            if let Some(machine) = &machine {
+                println!("{}", machine.roofline_report(sweep));
+            }
+        }
+    }
+}
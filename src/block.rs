@@ -0,0 +1,189 @@
+//! Block elimination for 2 x 2 block systems.
+//!
+//! ```text
+//! [A B] [x]   [f]
+//! [C D] [y] = [g]
+//! ```
+//!
+//! Eliminating x leaves the Schur complement system S y = g - C A^-1 f
+//! with S = D - C A^-1 B, after which x = A^-1 (f - B y). Only A and S are
+//! factored, so the (n + p) x (n + p) block matrix is never formed.
+//!
+//! The Schur complement itself is also what domain decomposition
+//! (interface systems) and statistics (the covariance of one block of a
+//! Gaussian conditioned on the other) need, so it is available both dense
+//! and as an operator that is never formed.
+use crate::factor::Factor;
+use crate::iterative::LinearOperator;
+use crate::lu::LuFactor;
+use crate::operations::gemv;
+use crate::{fma_scale, Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Return S = D - C A^-1 B, given a factorization of the n x n A, n x p B,
+/// p x n C and p x p D.
+///
+/// This costs p solves with A and one p x n by n x p product.
+pub fn schur_complement<F: Factor>(fa: &F, b: &Matrix, c: &Matrix, d: &Matrix) -> Matrix {
+    let (n, p) = (fa.dim(), d.n);
+    assert_eq!((b.m, b.n, c.m, c.n, d.m), (n, p, p, n, p));
+    // Z = A^-1 B, a column at a time.
+    let mut z = Matrix::zero(n, p);
+    let mut col = vec![0.0; n];
+    for j in 0..p {
+        (0..n).for_each(|i| col[i] = b.get(i, j));
+        fa.solve_in_place(&mut col);
+        (0..n).for_each(|i| z.set(i, j, col[i]));
+    }
+    let mut s = d.clone();
+    fma_scale(-1.0, &c.view(), &z.view(), 1.0, &mut s.view_mut());
+    s
+}
+
+/// The Schur complement S = D - C A^-1 B of a general 2 x 2 block system
+/// as an operator, applied with one solve with A and products with B, C
+/// and D. saddle::SchurComplement is the symmetric saddle point case.
+///
+/// Use it when S is too large to form, for example solving the
+/// interface system of a domain decomposition by conjugate gradients.
+pub struct BlockSchurComplement<'a, F> {
+    fa: &'a F,
+    b: &'a Matrix,
+    c: &'a Matrix,
+    d: &'a Matrix,
+}
+
+impl<'a, F: Factor> BlockSchurComplement<'a, F> {
+    /// Return the operator for the given factorization of A and blocks
+    /// B, C and D, with the shapes of schur_complement().
+    pub fn new(fa: &'a F, b: &'a Matrix, c: &'a Matrix, d: &'a Matrix) -> BlockSchurComplement<'a, F> {
+        let (n, p) = (fa.dim(), d.n);
+        assert_eq!((b.m, b.n, c.m, c.n, d.m), (n, p, p, n, p));
+        BlockSchurComplement { fa, b, c, d }
+    }
+}
+
+impl<F: Factor> LinearOperator for BlockSchurComplement<'_, F> {
+    fn nrows(&self) -> usize {
+        self.d.m
+    }
+
+    fn ncols(&self) -> usize {
+        self.d.n
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        let mut t = vec![0.0; self.b.m];
+        gemv(1.0, &self.b.view(), x, 0.0, &mut t);
+        self.fa.solve_in_place(&mut t);
+        gemv(1.0, &self.d.view(), x, 0.0, y);
+        gemv(-1.0, &self.c.view(), &t, 1.0, y);
+    }
+}
+
+/// Solve the block system for n x n A, n x p B, p x n C and p x p D.
+///
+/// Returns (x, y), or None if A or the Schur complement is singular. Block
+/// elimination needs A itself to be nonsingular, which is stronger than
+/// the whole system being nonsingular, and is only as stable as the
+/// factorization of A allows.
+pub fn solve_augmented(a: &Matrix, b: &Matrix, c: &Matrix, d: &Matrix, f: &[f64], g: &[f64]) -> Option<(Vec<f64>, Vec<f64>)> {
+    let (n, p) = (a.n, d.n);
+    assert_eq!((a.m, b.m, b.n, c.m, c.n, d.m), (n, n, p, p, n, p));
+    assert_eq!((f.len(), g.len()), (n, p));
+    let fa = LuFactor::new(a)?;
+    let fs = LuFactor::from_matrix(schur_complement(&fa, b, c, d))?;
+
+    let mut rhs = g.to_vec();
+    gemv(-1.0, &c.view(), &fa.solve(f), 1.0, &mut rhs);
+    let y = fs.solve(&rhs);
+    let mut x = f.to_vec();
+    gemv(-1.0, &b.view(), &y, 1.0, &mut x);
+    fa.solve_in_place(&mut x);
+    Some((x, y))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cholesky::CholFactor;
+
+    #[test]
+    fn augmented_matches_assembled_system() {
+        let (n, p) = (5, 3);
+        let mut a = Matrix::rand_seeded(n, n, 1);
+        (0..n).for_each(|i| a.set(i, i, a.get(i, i) + 2.0));
+        let b = Matrix::rand_seeded(n, p, 2);
+        let c = Matrix::rand_seeded(p, n, 3);
+        let d = Matrix::rand_seeded(p, p, 4);
+        let f: Vec<f64> = (0..n).map(|i| i as f64).collect();
+        let g = vec![1.0, -1.0, 0.5];
+        let (x, y) = solve_augmented(&a, &b, &c, &d, &f, &g).unwrap();
+
+        let mut full = Matrix::zero(n + p, n + p);
+        for i in 0..n + p {
+            for j in 0..n + p {
+                let v = match (i < n, j < n) {
+                    (true, true) => a.get(i, j),
+                    (true, false) => b.get(i, j - n),
+                    (false, true) => c.get(i - n, j),
+                    (false, false) => d.get(i - n, j - n),
+                };
+                full.set(i, j, v);
+            }
+        }
+        let rhs: Vec<f64> = f.iter().chain(&g).copied().collect();
+        let expected = LuFactor::new(&full).unwrap().solve(&rhs);
+        let got: Vec<f64> = x.iter().chain(&y).copied().collect();
+        assert!(got.iter().zip(&expected).all(|(p, q)| (p - q).abs() < 1e-10));
+    }
+
+    #[test]
+    fn saddle_point_system() {
+        // [A B; B^T 0] with A SPD and B of full column rank.
+        let a = Matrix::from_vec(3, 3, vec![4.0, 1.0, 0.0, 1.0, 3.0, 1.0, 0.0, 1.0, 2.0]);
+        let b = Matrix::from_vec(3, 1, vec![1.0, 1.0, 1.0]);
+        let bt = Matrix::from_vec(1, 3, vec![1.0, 1.0, 1.0]);
+        let (x, y) = solve_augmented(&a, &b, &bt, &Matrix::zero(1, 1), &[1.0, 2.0, 3.0], &[0.0]).unwrap();
+        // The constraint holds and the first block row is satisfied.
+        assert!(x.iter().sum::<f64>().abs() < 1e-14);
+        let mut r = vec![1.0, 2.0, 3.0];
+        gemv(-1.0, &a.view(), &x, 1.0, &mut r);
+        assert!(r.iter().all(|ri| (ri - y[0]).abs() < 1e-14));
+
+        // A singular A cannot be eliminated.
+        let z = Matrix::zero(3, 3);
+        assert!(solve_augmented(&z, &b, &bt, &Matrix::from_vec(1, 1, vec![1.0]), &[0.0; 3], &[0.0]).is_none());
+    }
+
+    #[test]
+    fn schur_complement_dense_and_lazy() {
+        // The covariance of y given x for a Gaussian with covariance
+        // [A B; B^T D] is D - B^T A^-1 B.
+        let g = Matrix::rand_seeded(6, 6, 5);
+        let mut cov = Matrix::zero(6, 6);
+        fma_scale(1.0, &g.view(), &g.transpose().view(), 0.0, &mut cov.view_mut());
+        (0..6).for_each(|i| cov.set(i, i, cov.get(i, i) + 1.0));
+        let (a, b, c, d) = (block(&cov, 0, 0, 4, 4), block(&cov, 0, 4, 4, 2), block(&cov, 4, 0, 2, 4), block(&cov, 4, 4, 2, 2));
+        let fa = CholFactor::new(&a).unwrap();
+        let s = schur_complement(&fa, &b, &c, &d);
+
+        // It is the inverse of the trailing block of the inverse.
+        let inv = LuFactor::new(&cov).unwrap().inverse();
+        let t = LuFactor::new(&block(&inv, 4, 4, 2, 2)).unwrap().inverse();
+        assert!((0..2).all(|i| (0..2).all(|j| (s.get(i, j) - t.get(i, j)).abs() < 1e-10)));
+
+        let op = BlockSchurComplement::new(&fa, &b, &c, &d);
+        let x = [0.5, -2.0];
+        let (mut y, mut z) = (vec![0.0; 2], vec![0.0; 2]);
+        op.apply(&x, &mut y);
+        s.apply(&x, &mut z);
+        assert_eq!((op.nrows(), op.ncols()), (2, 2));
+        assert!(y.iter().zip(&z).all(|(p, q)| (p - q).abs() < 1e-12));
+    }
+
+    fn block(a: &Matrix, r: usize, c: usize, m: usize, n: usize) -> Matrix {
+        Matrix::from_vec(m, n, (0..m * n).map(|k| a.get(r + k / n, c + k % n)).collect())
+    }
+}
@@ -0,0 +1,199 @@
+//! Cholesky factorization and rank-1 updates of the factor.
+use crate::factor::{self, Factor};
+use crate::math;
+use crate::Matrix;
+use alloc::vec::Vec;
+
+/// Factor the row-major n x n SPD array a = L L^T in place (leading
+/// dimension `ld`), storing L in the lower triangle. Returns false if a is
+/// not positive definite.
+pub(crate) fn cholesky_slice(n: usize, ld: usize, a: &mut [f64]) -> bool {
+    for j in 0..n {
+        let d = a[j * ld + j] - (0..j).map(|k| a[j * ld + k] * a[j * ld + k]).sum::<f64>();
+        if d <= 0.0 || d.is_nan() {
+            return false;
+        }
+        let d = math::sqrt(d);
+        a[j * ld + j] = d;
+        for i in j + 1..n {
+            let sum: f64 = (0..j).map(|k| a[i * ld + k] * a[j * ld + k]).sum();
+            a[i * ld + j] = (a[i * ld + j] - sum) / d;
+        }
+    }
+    true
+}
+
+/// Solve A Y = T in place for the row-major n x s array t, given the
+/// factor from cholesky_slice().
+pub(crate) fn cholesky_solve_slice(n: usize, ld: usize, l: &[f64], s: usize, t: &mut [f64]) {
+    for c in 0..s {
+        for i in 0..n {
+            let sum: f64 = (0..i).map(|k| l[i * ld + k] * t[k * s + c]).sum();
+            t[i * s + c] = (t[i * s + c] - sum) / l[i * ld + i];
+        }
+        for i in (0..n).rev() {
+            let sum: f64 = (i + 1..n).map(|k| l[k * ld + i] * t[k * s + c]).sum();
+            t[i * s + c] = (t[i * s + c] - sum) / l[i * ld + i];
+        }
+    }
+}
+
+/// Compute the Cholesky factor of an SPD matrix in place.
+///
+/// The lower triangle is overwritten with L and the strict upper triangle
+/// is zeroed. Returns false if the matrix is not positive definite.
+pub fn cholesky(a: &mut Matrix) -> bool {
+    assert_eq!(a.m, a.n);
+    let (n, ld) = (a.n, a.ld());
+    let data = a.as_mut_slice();
+    if !cholesky_slice(n, ld, data) {
+        return false;
+    }
+    for i in 0..n {
+        data[i * ld + i + 1..i * ld + n].fill(0.0);
+    }
+    true
+}
+
+/// Solve Ax = b in place given the factor from cholesky().
+pub fn cholesky_solve(l: &Matrix, b: &mut [f64]) {
+    assert_eq!(l.m, l.n);
+    assert_eq!(b.len(), l.n);
+    cholesky_solve_slice(l.n, l.ld(), l.as_slice(), 1, b);
+}
+
+/// Update the Cholesky factor L of A to that of A + sign x x^T, where sign
+/// is 1.0 for an update and -1.0 for a downdate, in O(n^2).
+///
+/// Step k sets r = sqrt(l_kk^2 + sign x_k^2), c = r / l_kk and
+/// s = x_k / l_kk, then replaces column k of L with (L_k + sign s x) / c
+/// and x with c x - s L_k. Returns false, leaving L unchanged, if a
+/// downdate would make A indefinite.
+pub fn chol_update(l: &mut Matrix, x: &[f64], sign: f64) -> bool {
+    assert!(sign == 1.0 || sign == -1.0, "sign must be 1.0 or -1.0");
+    assert_eq!(l.m, l.n);
+    assert_eq!(x.len(), l.n);
+    let (n, ld) = (l.n, l.ld());
+    let a = l.as_mut_slice();
+    if sign < 0.0 {
+        // A - x x^T is positive definite exactly when ||L^-1 x|| < 1.
+        let mut p: Vec<f64> = x.to_vec();
+        for i in 0..n {
+            p[i] = (p[i] - (0..i).map(|k| a[i * ld + k] * p[k]).sum::<f64>()) / a[i * ld + i];
+        }
+        if p.iter().map(|v| v * v).sum::<f64>() >= 1.0 {
+            return false;
+        }
+    }
+    let mut x = x.to_vec();
+    for k in 0..n {
+        let lkk = a[k * ld + k];
+        let r = math::sqrt(lkk * lkk + sign * x[k] * x[k]);
+        let (c, s) = (r / lkk, x[k] / lkk);
+        a[k * ld + k] = r;
+        for i in k + 1..n {
+            let lik = (a[i * ld + k] + sign * s * x[i]) / c;
+            a[i * ld + k] = lik;
+            x[i] = c * x[i] - s * lik;
+        }
+    }
+    true
+}
+
+/// Cholesky factorization of an SPD matrix, kept for repeated solves.
+pub struct CholFactor {
+    l: Matrix,
+    norm1: f64,
+}
+
+impl CholFactor {
+    /// Factor a copy of `a`, or return None if it is not positive definite.
+    pub fn new(a: &Matrix) -> Option<CholFactor> {
+        CholFactor::from_matrix(a.clone())
+    }
+
+    /// Factor `a` in place, or return None if it is not positive definite.
+    pub fn from_matrix(mut a: Matrix) -> Option<CholFactor> {
+        let norm1 = factor::norm1(&a);
+        cholesky(&mut a).then_some(CholFactor { l: a, norm1 })
+    }
+
+    /// Return the lower triangular factor L.
+    pub fn l(&self) -> &Matrix {
+        &self.l
+    }
+}
+
+impl Factor for CholFactor {
+    fn dim(&self) -> usize {
+        self.l.n
+    }
+
+    fn solve_in_place(&self, b: &mut [f64]) {
+        cholesky_solve(&self.l, b);
+    }
+
+    fn solve_transpose_in_place(&self, b: &mut [f64]) {
+        cholesky_solve(&self.l, b);
+    }
+
+    fn det(&self) -> f64 {
+        (0..self.l.n).map(|i| self.l.as_slice()[i * self.l.ld() + i]).map(|d| d * d).product()
+    }
+
+    fn norm1(&self) -> f64 {
+        self.norm1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Lcg;
+    use crate::MatrixIndex;
+    use alloc::vec;
+
+    #[test]
+    fn cholesky_factor_and_solve() {
+        let a = Matrix::rand_spd(6, &mut Lcg::new(1));
+        let mut l = Matrix::rand_spd(6, &mut Lcg::new(1));
+        assert!(cholesky(&mut l));
+        let x = [1.0, -2.0, 3.0, 0.5, 0.0, 4.0];
+        let mut b: Vec<f64> = (0..6).map(|i| (0..6).map(|j| a.get(i, j) * x[j]).sum()).collect();
+        cholesky_solve(&l, &mut b);
+        assert!(b.iter().zip(&x).all(|(u, v)| (u - v).abs() < 1e-12));
+        assert!(!cholesky(&mut Matrix::from_vec(2, 2, vec![1.0, 2.0, 2.0, 1.0])));
+    }
+
+    #[test]
+    fn rank_one_update_and_downdate() {
+        let n = 5;
+        let a = Matrix::rand_spd(n, &mut Lcg::new(2));
+        let x: Vec<f64> = (0..n).map(|i| 0.3 * i as f64 - 0.5).collect();
+        let mut l = Matrix::rand_spd(n, &mut Lcg::new(2));
+        cholesky(&mut l);
+
+        // Update matches the factor of A + x x^T.
+        assert!(chol_update(&mut l, &x, 1.0));
+        let mut expected = Matrix::rand_spd(n, &mut Lcg::new(2));
+        for i in 0..n {
+            for j in 0..n {
+                expected.set(i, j, a.get(i, j) + x[i] * x[j]);
+            }
+        }
+        cholesky(&mut expected);
+        assert!((0..n * n).all(|k| (l.get(k / n, k % n) - expected.get(k / n, k % n)).abs() < 1e-12));
+
+        // Downdating restores the factor of A.
+        assert!(chol_update(&mut l, &x, -1.0));
+        let mut orig = Matrix::rand_spd(n, &mut Lcg::new(2));
+        cholesky(&mut orig);
+        assert!((0..n * n).all(|k| (l.get(k / n, k % n) - orig.get(k / n, k % n)).abs() < 1e-12));
+
+        // A downdate past positive definiteness is rejected.
+        let before = l.as_slice().to_vec();
+        let big: Vec<f64> = x.iter().map(|v| 100.0 * v).collect();
+        assert!(!chol_update(&mut l, &big, -1.0));
+        assert_eq!(l.as_slice(), &before[..]);
+    }
+}
@@ -0,0 +1,91 @@
+//! Greedy coloring of the adjacency graph of a sparse matrix.
+//!
+//! Rows i and j are adjacent when a_ij or a_ji is nonzero. In a coloring
+//! no two adjacent rows share a color, so the rows of one color do not
+//! read each other's unknowns, and a Gauss-Seidel sweep can update them
+//! all at once (multicolor ordering). Greedy coloring in natural order
+//! uses at most one more color than the largest degree, and finds the
+//! red-black ordering of the 5-point Laplacian.
+use crate::SparseMatrix;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A coloring of the rows of a square sparse matrix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Coloring {
+    colors: Vec<usize>,
+    /// The rows of each color, increasing.
+    classes: Vec<Vec<usize>>,
+}
+
+impl Coloring {
+    /// Color the rows of a in order, each with the smallest color not used
+    /// by an adjacent row.
+    pub fn greedy(a: &SparseMatrix) -> Coloring {
+        assert_eq!(a.m, a.n);
+        let at = a.transpose();
+        let n = a.n;
+        let mut colors = vec![usize::MAX; n];
+        // The last row for which each color was seen among the neighbours.
+        let mut seen: Vec<usize> = Vec::new();
+        let mut classes: Vec<Vec<usize>> = Vec::new();
+        for i in 0..n {
+            for m in [a, &at] {
+                for &j in &m.col_idx()[m.row_ptr()[i]..m.row_ptr()[i + 1]] {
+                    if j != i && colors[j] != usize::MAX {
+                        seen[colors[j]] = i;
+                    }
+                }
+            }
+            let c = (0..seen.len()).find(|&c| seen[c] != i).unwrap_or(seen.len());
+            if c == seen.len() {
+                seen.push(usize::MAX);
+                classes.push(Vec::new());
+            }
+            colors[i] = c;
+            classes[c].push(i);
+        }
+        Coloring { colors, classes }
+    }
+
+    /// Return the number of colors.
+    pub fn num_colors(&self) -> usize {
+        self.classes.len()
+    }
+
+    /// Return the color of each row.
+    pub fn colors(&self) -> &[usize] {
+        &self.colors
+    }
+
+    /// Return the rows of each color.
+    pub fn classes(&self) -> &[Vec<usize>] {
+        &self.classes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Lcg;
+
+    #[test]
+    fn greedy_colorings_are_proper() {
+        let l = Coloring::greedy(&SparseMatrix::laplacian(&[6, 9]));
+        assert_eq!(l.num_colors(), 2);
+        assert_eq!(l.colors()[..3], [0, 1, 0]);
+        assert_eq!(l.classes().iter().map(|c| c.len()).sum::<usize>(), 54);
+        assert_eq!(Coloring::greedy(&SparseMatrix::laplacian(&[3, 3, 3])).num_colors(), 2);
+
+        // An unsymmetric pattern: a_ij != 0 alone must separate i and j.
+        let a = SparseMatrix::rand(60, 60, 0.05, &mut Lcg::new(3));
+        let c = Coloring::greedy(&a);
+        for i in 0..60 {
+            for &j in &a.col_idx()[a.row_ptr()[i]..a.row_ptr()[i + 1]] {
+                assert!(i == j || c.colors()[i] != c.colors()[j]);
+            }
+        }
+        let max_degree = (0..60).map(|i| a.row_ptr()[i + 1] - a.row_ptr()[i]).max().unwrap();
+        assert!(c.num_colors() <= 2 * max_degree + 1);
+    }
+}
@@ -0,0 +1,132 @@
+//! Linear discrete-time systems x_(k+1) = A x_k + B u_k, y_k = C x_k.
+//!
+//! The system is controllable when [B, A B, ..., A^(n-1) B] has full rank n
+//! and observable when [C; C A; ...; C A^(n-1)] does. For stable A, the
+//! Gramians W_c = sum_k A^k B B^T (A^T)^k and W_o = sum_k (A^T)^k C^T C A^k
+//! quantify this: their eigenvalues measure how much input energy reaches
+//! each state direction, and how much output energy each one produces.
+//! They solve discrete Lyapunov (Stein) equations.
+use crate::matfun::product;
+use crate::{Matrix, MatrixIndex};
+
+/// Return the n x nm controllability matrix [B, A B, ..., A^(n-1) B] of
+/// the pair (a, b), with a n x n and b n x m.
+pub fn controllability_matrix(a: &Matrix, b: &Matrix) -> Matrix {
+    assert!(a.m == a.n && b.m == a.n);
+    let (n, m) = (a.n, b.n);
+    let mut k = Matrix::zero(n, n * m);
+    let mut block = b.clone();
+    for p in 0..n {
+        for i in 0..n {
+            (0..m).for_each(|j| k.set(i, p * m + j, block.get(i, j)));
+        }
+        if p + 1 < n {
+            block = product(a, &block);
+        }
+    }
+    k
+}
+
+/// Return the pn x n observability matrix [C; C A; ...; C A^(n-1)] of the
+/// pair (a, c), with c p x n.
+pub fn observability_matrix(a: &Matrix, c: &Matrix) -> Matrix {
+    assert!(a.m == a.n && c.n == a.n);
+    // It is the transpose of the controllability matrix of (A^T, C^T).
+    controllability_matrix(&a.transpose(), &c.transpose()).transpose()
+}
+
+/// Return the solution X of the discrete Lyapunov equation
+/// A X A^T - X + Q = 0, or None if the iteration does not converge because
+/// A has an eigenvalue of modulus 1 or more.
+///
+/// X = sum_k A^k Q (A^T)^k is summed by Smith's doubling, X := X + A X A^T
+/// and A := A^2, which doubles the number of terms each step, so it takes
+/// about log2 of the number of terms needed, each step three products.
+pub fn discrete_lyapunov(a: &Matrix, q: &Matrix) -> Option<Matrix> {
+    assert!(a.m == a.n && (q.m, q.n) == (a.m, a.n));
+    let n = a.n;
+    let mut x = q.clone();
+    let mut power = a.clone();
+    for _ in 0..64 {
+        let term = product(&product(&power, &x), &power.transpose());
+        let size = x.as_slice().iter().fold(0.0, |m: f64, v| m.max(v.abs()));
+        let change = term.as_slice().iter().fold(0.0, |m: f64, v| m.max(v.abs()));
+        if !change.is_finite() {
+            return None;
+        }
+        for t in 0..n * n {
+            x.set(t / n, t % n, x.get(t / n, t % n) + term.get(t / n, t % n));
+        }
+        if change <= f64::EPSILON * size {
+            return Some(x);
+        }
+        power = product(&power, &power);
+    }
+    None
+}
+
+/// Return the controllability Gramian, the solution of
+/// A W A^T - W + B B^T = 0, or None if A is not stable.
+pub fn controllability_gramian(a: &Matrix, b: &Matrix) -> Option<Matrix> {
+    assert_eq!(b.m, a.n);
+    discrete_lyapunov(a, &product(b, &b.transpose()))
+}
+
+/// Return the observability Gramian, the solution of
+/// A^T W A - W + C^T C = 0, or None if A is not stable.
+pub fn observability_gramian(a: &Matrix, c: &Matrix) -> Option<Matrix> {
+    assert_eq!(c.n, a.n);
+    discrete_lyapunov(&a.transpose(), &product(&c.transpose(), c))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::svd::svd;
+    use alloc::vec;
+
+    /// A stable system with two states driven by the input and a third
+    /// one it cannot reach.
+    fn system() -> (Matrix, Matrix, Matrix) {
+        let a = Matrix::from_vec(3, 3, vec![0.5, 0.2, 0.0,
+                                            -0.1, 0.8, 0.0,
+                                            0.0, 0.0, 0.3]);
+        let b = Matrix::from_vec(3, 1, vec![1.0, 0.0, 0.0]);
+        let c = Matrix::from_vec(1, 3, vec![1.0, 0.0, 1.0]);
+        (a, b, c)
+    }
+
+    #[test]
+    fn controllability_and_observability_matrices() {
+        let (a, b, c) = system();
+        let k = controllability_matrix(&a, &b);
+        assert_eq!((k.m, k.n), (3, 3));
+        // The columns are b, A b and A^2 b.
+        assert!((k.get(0, 1) - 0.5).abs() < 1e-15 && (k.get(1, 1) + 0.1).abs() < 1e-15);
+        assert_eq!(svd(&k).rank(1e-12), 2);
+        let o = observability_matrix(&a, &c);
+        assert_eq!((o.m, o.n), (3, 3));
+        assert!((o.get(1, 0) - 0.5).abs() < 1e-15 && (o.get(1, 2) - 0.3).abs() < 1e-15);
+        assert_eq!(svd(&o).rank(1e-12), 3);
+    }
+
+    #[test]
+    fn gramians_solve_lyapunov_equations() {
+        let (a, b, c) = system();
+        let wc = controllability_gramian(&a, &b).unwrap();
+        let residual = |a: &Matrix, w: &Matrix, q: &Matrix| {
+            let awa = product(&product(a, w), &a.transpose());
+            (0..9).map(|t| (awa.get(t / 3, t % 3) - w.get(t / 3, t % 3) + q.get(t / 3, t % 3)).abs()).fold(0.0, f64::max)
+        };
+        assert!(residual(&a, &wc, &product(&b, &b.transpose())) < 1e-14);
+        // The unreachable state gets no energy, so W_c is singular like
+        // the controllability matrix.
+        assert!((0..3).all(|i| wc.get(i, 2) == 0.0));
+        let wo = observability_gramian(&a, &c).unwrap();
+        assert!(residual(&a.transpose(), &wo, &product(&c.transpose(), &c)) < 1e-14);
+        assert!(svd(&wo).s[2] > 1e-3);
+
+        let unstable = Matrix::from_vec(1, 1, vec![1.0]);
+        assert!(discrete_lyapunov(&unstable, &Matrix::from_vec(1, 1, vec![1.0])).is_none());
+    }
+}
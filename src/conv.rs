@@ -0,0 +1,292 @@
+//! 1-D and 2-D convolutions as matrix-free operators.
+//!
+//! A convolution of a signal or a row-major image with a small kernel is a
+//! (block) Toeplitz matrix, which would take n^2 storage. Here it is
+//! applied directly in O(n k), or through FFTs of a padded grid in
+//! O(n log n) for zero and periodic boundaries, which pays off for large
+//! kernels. The transpose is available too, so blurred data can be
+//! deconvolved by least squares without forming the matrix.
+use crate::fft::{fft, ifft};
+use crate::iterative::{LinearOperator, TransposeOperator};
+use crate::{Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// How a convolution extends the signal past its ends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// Zero outside.
+    Zero,
+    /// The signal repeats with its length as period.
+    Periodic,
+    /// Mirror images, repeating the end sample: x1 x0 | x0 x1 ...
+    Reflect,
+    /// The end samples repeat.
+    Replicate,
+}
+
+impl Boundary {
+    /// Return the sample that index i of the extended signal of length n
+    /// refers to, or None for a zero.
+    fn index(self, i: isize, n: usize) -> Option<usize> {
+        let n = n as isize;
+        if (0..n).contains(&i) {
+            return Some(i as usize);
+        }
+        match self {
+            Boundary::Zero => None,
+            Boundary::Periodic => Some(i.rem_euclid(n) as usize),
+            Boundary::Reflect => {
+                let t = i.rem_euclid(2 * n);
+                Some(if t >= n { 2 * n - 1 - t } else { t } as usize)
+            }
+            Boundary::Replicate => Some(i.clamp(0, n - 1) as usize),
+        }
+    }
+}
+
+/// The operator y = h * x on rows x cols row-major images, with output of
+/// the same size: y(i, j) = sum_(a, b) h(a, b) x(i - a + ca, j - b + cb),
+/// centered at (ca, cb) = (kr / 2, kc / 2) for a kr x kc kernel h.
+pub struct Convolution {
+    rows: usize,
+    cols: usize,
+    kernel: Matrix,
+    boundary: Boundary,
+    /// The padded grid size and the transform of the kernel on it.
+    spectrum: Option<(usize, usize, Vec<f64>, Vec<f64>)>,
+}
+
+impl Convolution {
+    /// Return the convolution of signals of length n with the kernel.
+    pub fn new_1d(kernel: &[f64], n: usize, boundary: Boundary) -> Convolution {
+        Convolution::new_2d(&Matrix::from_vec(1, kernel.len(), kernel.to_vec()), 1, n, boundary)
+    }
+
+    /// Return the convolution of rows x cols images with the kernel.
+    pub fn new_2d(kernel: &Matrix, rows: usize, cols: usize, boundary: Boundary) -> Convolution {
+        assert!(kernel.m > 0 && kernel.n > 0 && rows > 0 && cols > 0);
+        let kernel = kernel.clone();
+        Convolution { rows, cols, kernel, boundary, spectrum: None }
+    }
+
+    /// Apply through FFTs instead of directly; only zero and periodic
+    /// boundaries support it.
+    pub fn fft(mut self, fft: bool) -> Convolution {
+        if !fft {
+            self.spectrum = None;
+            return self;
+        }
+        let (kr, kc) = (self.kernel.m, self.kernel.n);
+        let (p, q) = match self.boundary {
+            Boundary::Periodic => (self.rows, self.cols),
+            Boundary::Zero => ((self.rows + kr - 1).next_power_of_two(), (self.cols + kc - 1).next_power_of_two()),
+            _ => panic!("FFT convolution needs zero or periodic boundaries"),
+        };
+        // The kernel wrapped around the grid, so that the circular
+        // convolution has the same centering.
+        let (mut re, mut im) = (vec![0.0; p * q], vec![0.0; p * q]);
+        for a in 0..kr {
+            for b in 0..kc {
+                let i = (a as isize - (kr / 2) as isize).rem_euclid(p as isize) as usize;
+                let j = (b as isize - (kc / 2) as isize).rem_euclid(q as isize) as usize;
+                re[i * q + j] += self.kernel.get(a, b);
+            }
+        }
+        fft2(&mut re, &mut im, p, q, false);
+        self.spectrum = Some((p, q, re, im));
+        self
+    }
+
+    /// Apply x -> h * x, or its transpose, directly: the transpose sends
+    /// every product to the input sample it came from.
+    fn apply_direct(&self, x: &[f64], y: &mut [f64], transpose: bool) {
+        let (kr, kc) = (self.kernel.m, self.kernel.n);
+        let (ca, cb) = ((kr / 2) as isize, (kc / 2) as isize);
+        y.fill(0.0);
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                for a in 0..kr {
+                    let Some(r) = self.boundary.index(i as isize - a as isize + ca, self.rows) else { continue };
+                    for b in 0..kc {
+                        let Some(c) = self.boundary.index(j as isize - b as isize + cb, self.cols) else { continue };
+                        let h = self.kernel.get(a, b);
+                        if transpose {
+                            y[r * self.cols + c] += h * x[i * self.cols + j];
+                        } else {
+                            y[i * self.cols + j] += h * x[r * self.cols + c];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Multiply by the kernel spectrum, or its conjugate for the transpose.
+    fn apply_fft(&self, x: &[f64], y: &mut [f64], transpose: bool) {
+        let (p, q, hr, hi) = self.spectrum.as_ref().unwrap();
+        let (p, q) = (*p, *q);
+        let (mut re, mut im) = (vec![0.0; p * q], vec![0.0; p * q]);
+        for i in 0..self.rows {
+            re[i * q..i * q + self.cols].copy_from_slice(&x[i * self.cols..(i + 1) * self.cols]);
+        }
+        fft2(&mut re, &mut im, p, q, false);
+        let sign = if transpose { -1.0 } else { 1.0 };
+        for t in 0..p * q {
+            let (a, b) = (re[t], im[t]);
+            re[t] = a * hr[t] - sign * b * hi[t];
+            im[t] = b * hr[t] + sign * a * hi[t];
+        }
+        fft2(&mut re, &mut im, p, q, true);
+        for i in 0..self.rows {
+            y[i * self.cols..(i + 1) * self.cols].copy_from_slice(&re[i * q..i * q + self.cols]);
+        }
+    }
+}
+
+/// 2-D transform of a p x q row-major grid, by rows and then by columns.
+fn fft2(re: &mut [f64], im: &mut [f64], p: usize, q: usize, inverse: bool) {
+    let transform = if inverse { ifft } else { fft };
+    for i in 0..p {
+        transform(&mut re[i * q..(i + 1) * q], &mut im[i * q..(i + 1) * q]);
+    }
+    let (mut cr, mut ci) = (vec![0.0; p], vec![0.0; p]);
+    for j in 0..q {
+        (0..p).for_each(|i| (cr[i], ci[i]) = (re[i * q + j], im[i * q + j]));
+        transform(&mut cr, &mut ci);
+        (0..p).for_each(|i| (re[i * q + j], im[i * q + j]) = (cr[i], ci[i]));
+    }
+}
+
+impl LinearOperator for Convolution {
+    fn nrows(&self) -> usize {
+        self.rows * self.cols
+    }
+
+    fn ncols(&self) -> usize {
+        self.rows * self.cols
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        assert_eq!((x.len(), y.len()), (self.ncols(), self.nrows()));
+        if self.spectrum.is_some() {
+            self.apply_fft(x, y, false);
+        } else {
+            self.apply_direct(x, y, false);
+        }
+    }
+}
+
+impl TransposeOperator for Convolution {
+    fn apply_transpose(&self, x: &[f64], y: &mut [f64]) {
+        assert_eq!((x.len(), y.len()), (self.nrows(), self.ncols()));
+        if self.spectrum.is_some() {
+            self.apply_fft(x, y, true);
+        } else {
+            self.apply_direct(x, y, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iterative::{cg, Identity};
+    use crate::solver::tolerance;
+
+    /// Return the columns of the dense matrix of A, or of A^T.
+    fn dense(op: &impl TransposeOperator, transpose: bool) -> Vec<Vec<f64>> {
+        let n = op.ncols();
+        (0..n).map(|j| {
+            let mut e = vec![0.0; n];
+            e[j] = 1.0;
+            let mut col = vec![0.0; n];
+            if transpose {
+                op.apply_transpose(&e, &mut col);
+            } else {
+                op.apply(&e, &mut col);
+            }
+            col
+        }).collect()
+    }
+
+    #[test]
+    fn boundary_modes_and_fft_agree_with_the_definition() {
+        let h = [1.0, -2.0, 0.5, 3.0];
+        let n = 7;
+        let x: Vec<f64> = (0..n).map(|i| (i as f64 + 1.0).sqrt()).collect();
+        let ext = |i: isize, b: Boundary| b.index(i, n).map_or(0.0, |k| x[k]);
+        for b in [Boundary::Zero, Boundary::Periodic, Boundary::Reflect, Boundary::Replicate] {
+            let conv = Convolution::new_1d(&h, n, b);
+            let mut y = vec![0.0; n];
+            conv.apply(&x, &mut y);
+            for i in 0..n {
+                let exact: f64 = (0..4).map(|a| h[a] * ext(i as isize - a as isize + 2, b)).sum();
+                assert!((y[i] - exact).abs() < 1e-14, "{b:?}");
+            }
+            // <A x, z> = <x, A^T z>.
+            let z: Vec<f64> = (0..n).map(|i| (i as f64).cos()).collect();
+            let mut atz = vec![0.0; n];
+            conv.apply_transpose(&z, &mut atz);
+            let lhs: f64 = y.iter().zip(&z).map(|(a, b)| a * b).sum();
+            let rhs: f64 = x.iter().zip(&atz).map(|(a, b)| a * b).sum();
+            assert!((lhs - rhs).abs() < 1e-12, "{b:?}");
+        }
+        assert_eq!(Boundary::Reflect.index(-2, 4), Some(1));
+        assert_eq!(Boundary::Reflect.index(5, 4), Some(2));
+
+        // The FFT path, in 2-D with a kernel wider than the image in
+        // periodic mode.
+        let kernel = Matrix::rand_seeded(3, 6, 4);
+        for b in [Boundary::Zero, Boundary::Periodic] {
+            let direct = Convolution::new_2d(&kernel, 5, 4, b);
+            let fast = Convolution::new_2d(&kernel, 5, 4, b).fft(true);
+            for transpose in [false, true] {
+                let (d, f) = (dense(&direct, transpose), dense(&fast, transpose));
+                assert!(d.iter().flatten().zip(f.iter().flatten()).all(|(p, q)| (p - q).abs() < 1e-13), "{b:?}");
+            }
+        }
+    }
+
+    /// A^T A + lambda I for Tikhonov-regularized deconvolution.
+    struct Regularized<'a> {
+        a: &'a Convolution,
+        lambda: f64,
+    }
+
+    impl LinearOperator for Regularized<'_> {
+        fn nrows(&self) -> usize {
+            self.a.ncols()
+        }
+
+        fn ncols(&self) -> usize {
+            self.a.ncols()
+        }
+
+        fn apply(&self, x: &[f64], y: &mut [f64]) {
+            let mut t = vec![0.0; self.a.nrows()];
+            self.a.apply(x, &mut t);
+            self.a.apply_transpose(&t, y);
+            y.iter_mut().zip(x).for_each(|(y, x)| *y += self.lambda * x);
+        }
+    }
+
+    #[test]
+    fn deconvolution_by_regularized_least_squares() {
+        let (rows, cols) = (16, 12);
+        let kernel = Matrix::from_vec(3, 3, vec![1.0, 2.0, 1.0, 2.0, 4.0, 2.0, 1.0, 2.0, 1.0]);
+        let blur = Convolution::new_2d(&kernel, rows, cols, Boundary::Reflect);
+        let image: Vec<f64> = (0..rows * cols).map(|t| if (t / cols + t % cols) % 5 < 2 { 1.0 } else { 0.0 }).collect();
+        let mut blurred = vec![0.0; rows * cols];
+        blur.apply(&image, &mut blurred);
+
+        let normal = Regularized { a: &blur, lambda: 1e-8 };
+        let mut rhs = vec![0.0; rows * cols];
+        blur.apply_transpose(&blurred, &mut rhs);
+        let mut x = vec![0.0; rows * cols];
+        let res = cg(&normal, &rhs, &mut x, &Identity, tolerance(1e-12, 2000), None);
+        assert!(res.converged, "{:?}", res.final_residual());
+        let err = x.iter().zip(&image).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max);
+        assert!(err < 1e-3, "{err}");
+    }
+}
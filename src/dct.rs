@@ -0,0 +1,133 @@
+//! Discrete cosine and sine transforms, and a fast Poisson solver.
+//!
+//! Both transforms are FFTs of symmetric extensions: DCT-II of the even
+//! extension of length 2n, and DST-I of the odd extension of length
+//! 2(n + 1). The vectors sin(pi (j + 1)(k + 1) / (n + 1)) are the
+//! eigenvectors of the 1-D Dirichlet Laplacian tridiag(-1, 2, -1), so DST-I
+//! along each dimension diagonalizes the grid Laplacian of
+//! SparseMatrix::laplacian() and solves it exactly in O(N log N).
+use crate::fft::{irfft, rfft};
+use crate::math;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+/// Return the DCT-II X_k = sum_j x_j cos(pi k (2j + 1) / (2n)).
+pub fn dct2(x: &[f64]) -> Vec<f64> {
+    let n = x.len();
+    let y: Vec<f64> = x.iter().chain(x.iter().rev()).copied().collect();
+    let (re, im) = rfft(&y);
+    // Y_k = 2 exp(i pi k / (2n)) X_k.
+    (0..n).map(|k| {
+        let (s, c) = math::sin_cos(-PI * k as f64 / (2 * n) as f64);
+        0.5 * (c * re[k] - s * im[k])
+    }).collect()
+}
+
+/// Return the x with dct2(x) = a, a scaled DCT-III.
+pub fn idct2(a: &[f64]) -> Vec<f64> {
+    let n = a.len();
+    let (mut re, mut im) = (vec![0.0; n + 1], vec![0.0; n + 1]);
+    for k in 0..n {
+        let (s, c) = math::sin_cos(PI * k as f64 / (2 * n) as f64);
+        re[k] = 2.0 * c * a[k];
+        im[k] = 2.0 * s * a[k];
+    }
+    let mut y = irfft(&re, &im, 2 * n);
+    y.truncate(n);
+    y
+}
+
+/// Return the DST-I X_k = sum_j x_j sin(pi (j + 1)(k + 1) / (n + 1)).
+///
+/// It is its own inverse up to the factor 2 / (n + 1).
+pub fn dst1(x: &[f64]) -> Vec<f64> {
+    let n = x.len();
+    let mut y = vec![0.0; 2 * (n + 1)];
+    for j in 0..n {
+        y[j + 1] = x[j];
+        y[2 * n + 1 - j] = -x[j];
+    }
+    let (_, im) = rfft(&y);
+    (0..n).map(|k| -0.5 * im[k + 1]).collect()
+}
+
+/// Transform every line of the row-major grid data along each dimension.
+fn along_each_dimension(data: &mut [f64], dims: &[usize], transform: impl Fn(&[f64]) -> Vec<f64>) {
+    let mut stride = 1;
+    for &len in dims.iter().rev() {
+        let block = stride * len;
+        let mut line = vec![0.0; len];
+        for start in (0..data.len()).step_by(block) {
+            for offset in 0..stride {
+                (0..len).for_each(|i| line[i] = data[start + offset + i * stride]);
+                let out = transform(&line);
+                (0..len).for_each(|i| data[start + offset + i * stride] = out[i]);
+            }
+        }
+        stride = block;
+    }
+}
+
+/// Return the solution u of L u = f for the Dirichlet Laplacian
+/// L = SparseMatrix::laplacian(dims), with the same grid numbering.
+///
+/// The eigenvalues of L are the sums over dimensions of
+/// 2 - 2 cos(pi (k + 1) / (len + 1)), all positive, so the solve always
+/// succeeds.
+pub fn poisson_dirichlet(dims: &[usize], f: &[f64]) -> Vec<f64> {
+    let n: usize = dims.iter().product();
+    assert_eq!(f.len(), n);
+    let mut u = f.to_vec();
+    along_each_dimension(&mut u, dims, dst1);
+    let eig: Vec<Vec<f64>> = dims.iter().map(|&len| {
+        (0..len).map(|k| 2.0 - 2.0 * math::sin_cos(PI * (k + 1) as f64 / (len + 1) as f64).1).collect()
+    }).collect();
+    for (idx, v) in u.iter_mut().enumerate() {
+        let (mut rest, mut lambda) = (idx, 0.0);
+        for (d, &len) in dims.iter().enumerate().rev() {
+            lambda += eig[d][rest % len];
+            rest /= len;
+        }
+        *v /= lambda;
+    }
+    along_each_dimension(&mut u, dims, dst1);
+    let scale: f64 = dims.iter().map(|&len| 2.0 / (len + 1) as f64).product();
+    u.iter_mut().for_each(|v| *v *= scale);
+    u
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SparseMatrix;
+
+    #[test]
+    fn transforms_match_their_definitions() {
+        for n in [1, 4, 7, 12] {
+            let x: Vec<f64> = (0..n).map(|j| (j as f64 * 0.9).sin() + 0.3).collect();
+            let c = dct2(&x);
+            let s = dst1(&x);
+            for k in 0..n {
+                let ck: f64 = (0..n).map(|j| x[j] * (PI * (k * (2 * j + 1)) as f64 / (2 * n) as f64).cos()).sum();
+                let sk: f64 = (0..n).map(|j| x[j] * (PI * ((j + 1) * (k + 1)) as f64 / (n + 1) as f64).sin()).sum();
+                assert!((c[k] - ck).abs() < 1e-12 && (s[k] - sk).abs() < 1e-12, "{n}");
+            }
+            assert!(idct2(&c).iter().zip(&x).all(|(a, b)| (a - b).abs() < 1e-13));
+            let twice = dst1(&s);
+            assert!(twice.iter().zip(&x).all(|(a, b)| (a * 2.0 / (n + 1) as f64 - b).abs() < 1e-13));
+        }
+    }
+
+    #[test]
+    fn poisson_solve_is_exact() {
+        for dims in [&[9][..], &[6, 11], &[4, 5, 3]] {
+            let n: usize = dims.iter().product();
+            let f: Vec<f64> = (0..n).map(|i| (i as f64 * 0.61).cos()).collect();
+            let u = poisson_dirichlet(dims, &f);
+            let mut lu = vec![0.0; n];
+            SparseMatrix::laplacian(dims).matvec(&u, &mut lu);
+            assert!(lu.iter().zip(&f).all(|(a, b)| (a - b).abs() < 1e-11), "{dims:?}");
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Deflated conjugate gradients with subspace recycling.
+use crate::cholesky::{cholesky_slice, cholesky_solve_slice};
+use crate::eig::sym_eig;
+use crate::iterative::{gather, gram, orthonormalize, update, LinearOperator, Preconditioner};
+use crate::math;
+use crate::operations::{axpy, dot, gemv, norm2, scale};
+use crate::solver::{Breakdown, Monitor, SolveResult, StoppingCriterion};
+use crate::{Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Deflated preconditioned CG that recycles an approximate invariant
+/// subspace across a sequence of SPD systems.
+///
+/// Each solve projects the recycled subspace W out of the Krylov space, so
+/// the eigenvalues it captures no longer slow convergence. Afterwards W is
+/// replaced by the Ritz vectors of the smallest Ritz values over W and the
+/// first search directions of the solve. This pays off when many slowly
+/// varying systems are solved in turn.
+pub struct DeflatedCg {
+    k: usize,
+    directions: usize,
+    w: Matrix,
+}
+
+impl DeflatedCg {
+    /// Create a solver recycling `k` vectors, refreshed after each solve
+    /// from its first `directions` search directions.
+    pub fn new(k: usize, directions: usize) -> DeflatedCg {
+        DeflatedCg {
+            k,
+            directions,
+            w: Matrix::zero(0, 0),
+        }
+    }
+
+    /// Return the recycled subspace, one vector per column.
+    pub fn subspace(&self) -> &Matrix {
+        &self.w
+    }
+
+    /// Solve Ax = b for SPD A, then update the recycled subspace.
+    ///
+    /// `x` is the initial guess on entry and `m` must be SPD. Each solve
+    /// costs about 2k + `directions` extra products with A to rebuild the
+    /// projection and the subspace.
+    pub fn solve(
+        &mut self,
+        a: &impl LinearOperator,
+        b: &[f64],
+        x: &mut [f64],
+        m: &impl Preconditioner,
+        mut stop: impl StoppingCriterion,
+        mut monitor: Option<Monitor>,
+    ) -> SolveResult {
+        let n = b.len();
+        assert_eq!((a.nrows(), a.ncols()), (n, n));
+        if self.w.m != n {
+            self.w = Matrix::zero(n, 0);
+        }
+        let mut aw = Matrix::zero(n, self.w.n);
+        a.apply_block(&self.w, &mut aw);
+        let mut waw = gram(&self.w, &aw);
+        if !cholesky_slice(self.w.n, self.w.n, &mut waw) {
+            // The operator changed too much for the old subspace.
+            self.w = Matrix::zero(n, 0);
+            aw = Matrix::zero(n, 0);
+        }
+        let k = self.w.n;
+        // mu = (W^T A W)^-1 B^T v for the basis B = W or A W.
+        let coefficients = |basis: &Matrix, v: &[f64]| {
+            let mut mu = transpose_mul(basis, v);
+            cholesky_solve_slice(k, k, &waw, 1, &mut mu);
+            mu
+        };
+
+        let mut r = vec![0.0; n];
+        a.apply(x, &mut r);
+        r.iter_mut().zip(b).for_each(|(ri, bi)| *ri = bi - *ri);
+        if k > 0 {
+            // Make the initial residual orthogonal to W.
+            let mu = coefficients(&self.w, &r);
+            gemv(1.0, &self.w.view(), &mu, 1.0, x);
+            gemv(-1.0, &aw.view(), &mu, 1.0, &mut r);
+        }
+        let mut z = vec![0.0; n];
+        m.apply(&r, &mut z);
+        let mut p = z.clone();
+        if k > 0 {
+            gemv(-1.0, &self.w.view(), &coefficients(&aw, &z), 1.0, &mut p);
+        }
+        let mut ap = vec![0.0; n];
+        let mut rz = dot(&r, &z);
+        let rz0 = rz;
+        let b_norm = norm2(b);
+        let mut res = SolveResult::new(norm2(&r));
+        let mut stored: Vec<Vec<f64>> = Vec::new();
+
+        while res.check(&mut stop, norm2(&r), b_norm, Some((math::sqrt(rz), math::sqrt(rz0)))) {
+            if stored.len() < self.directions {
+                stored.push(p.clone());
+            }
+            a.apply(&p, &mut ap);
+            let pap = dot(&p, &ap);
+            if pap <= 0.0 {
+                res.breakdown = Some(Breakdown::NotPositiveDefinite);
+                break;
+            }
+            let alpha = rz / pap;
+            axpy(alpha, &p, x);
+            axpy(-alpha, &ap, &mut r);
+            if !res.record(norm2(&r), &mut monitor) {
+                break;
+            }
+            m.apply(&r, &mut z);
+            let rz_next = dot(&r, &z);
+            if rz_next < 0.0 {
+                res.breakdown = Some(Breakdown::NotPositiveDefinite);
+                break;
+            }
+            scale(rz_next / rz, &mut p);
+            axpy(1.0, &z, &mut p);
+            if k > 0 {
+                gemv(-1.0, &self.w.view(), &coefficients(&aw, &z), 1.0, &mut p);
+            }
+            rz = rz_next;
+        }
+        if res.breakdown.is_none() {
+            self.recycle(a, &stored);
+        }
+        res
+    }
+
+    /// Replace W by the Ritz vectors of the k smallest Ritz values over W
+    /// and the stored directions.
+    fn recycle(&mut self, a: &impl LinearOperator, stored: &[Vec<f64>]) {
+        let (n, k) = (self.w.m, self.w.n);
+        let mut z = Matrix::zero(n, k + stored.len());
+        for i in 0..n {
+            for j in 0..k {
+                z.set(i, j, self.w.get(i, j));
+            }
+            for (j, p) in stored.iter().enumerate() {
+                z.set(i, k + j, p[i]);
+            }
+        }
+        let r = orthonormalize(&mut z);
+        let kept: Vec<usize> = (0..z.n).filter(|&j| r[j * z.n + j] != 0.0).collect();
+        let q = gather(&z, &kept);
+        let mut aq = Matrix::zero(n, q.n);
+        a.apply_block(&q, &mut aq);
+        let s = q.n;
+        let mut h = gram(&q, &aq);
+        for i in 0..s {
+            for j in 0..i {
+                let value = 0.5 * (h[i * s + j] + h[j * s + i]);
+                h[i * s + j] = value;
+                h[j * s + i] = value;
+            }
+        }
+        // Keep the Ritz vectors of the smallest Ritz values.
+        let f = sym_eig(&Matrix::from_vec(s, s, h));
+        let kept = self.k.min(s);
+        let c: Vec<f64> = (0..s).flat_map(|i| (0..kept).map(move |t| (i, t))).map(|(i, t)| f.vectors.get(i, t)).collect();
+        self.w = Matrix::zero(n, kept);
+        update(&mut self.w, &q, &c);
+    }
+}
+
+/// Return W^T v.
+fn transpose_mul(w: &Matrix, v: &[f64]) -> Vec<f64> {
+    let mut res = vec![0.0; w.n];
+    for i in 0..w.m {
+        for j in 0..w.n {
+            res[j] += w.get(i, j) * v[i];
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iterative::Identity;
+    use crate::solver::tolerance;
+    use crate::SparseMatrix;
+
+    #[test]
+    fn deflation_speeds_up_later_solves() {
+        let base = SparseMatrix::laplacian(&[24, 24]);
+        let mut solver = DeflatedCg::new(8, 30);
+        let mut iterations = Vec::new();
+        for t in 0..5 {
+            // Slowly varying systems A + 0.001 t I.
+            let mut a = base.clone();
+            for i in 0..a.m {
+                for k in a.row_ptr()[i]..a.row_ptr()[i + 1] {
+                    if a.col_idx()[k] == i {
+                        a.values_mut()[k] += 0.001 * t as f64;
+                    }
+                }
+            }
+            let b = Matrix::rand_seeded(a.m, 1, t).to_vec();
+            let mut x = vec![0.0; a.m];
+            let res = solver.solve(&a, &b, &mut x, &Identity, tolerance(1e-8, 1000), None);
+            assert!(res.converged);
+            let mut r = vec![0.0; a.m];
+            a.apply(&x, &mut r);
+            assert!(r.iter().zip(&b).map(|(ri, bi)| (ri - bi) * (ri - bi)).sum::<f64>().sqrt() < 1e-7 * norm2(&b));
+            iterations.push(res.iterations);
+        }
+        assert_eq!(solver.subspace().n, 8);
+        assert!(iterations[4] * 10 < iterations[0] * 7);
+    }
+}
@@ -0,0 +1,468 @@
+//! Distributed-memory block-row matrices and solvers.
+//!
+//! Algorithms are written against the small `Communicator` trait of three
+//! collectives (allreduce, broadcast and allgatherv), which users implement
+//! for their own transport such as MPI. `ThreadComm` runs several ranks as
+//! threads of one process, which is mostly useful for testing.
+use crate::givens::Givens;
+use crate::operations::{axpy, dot, gemv, scale};
+use crate::qr::{qr_r, tsqr_reduce};
+use crate::solver::{Breakdown, Monitor, SolveResult, StoppingCriterion};
+use crate::triangular::solve_upper;
+use crate::{matmul_acc, Matrix, MatrixIndex};
+use std::ops::Range;
+use std::sync::{Arc, Barrier, Mutex};
+
+/// Collective operations needed by the distributed algorithms.
+pub trait Communicator {
+    /// Return the rank of this process.
+    fn rank(&self) -> usize;
+
+    /// Return the number of processes.
+    fn size(&self) -> usize;
+
+    /// Sum `buf` elementwise over all processes, in place.
+    fn allreduce_sum(&self, buf: &mut [f64]);
+
+    /// Copy `buf` from `root` to every other process.
+    fn broadcast(&self, root: usize, buf: &mut [f64]);
+
+    /// Concatenate `local` from every process in rank order into `out`.
+    ///
+    /// `counts[r]` is the length of the piece contributed by rank r.
+    fn allgather(&self, local: &[f64], counts: &[usize], out: &mut [f64]);
+}
+
+/// Return the block of `len` entries owned by `rank`.
+pub fn block_range(len: usize, size: usize, rank: usize) -> Range<usize> {
+    rank * len / size..(rank + 1) * len / size
+}
+
+/// Return the length of every rank's block.
+fn block_counts(len: usize, size: usize) -> Vec<usize> {
+    (0..size).map(|r| block_range(len, size, r).len()).collect()
+}
+
+struct ThreadShared {
+    barrier: Barrier,
+    buf: Mutex<Vec<f64>>,
+}
+
+/// Communicator between threads of a single process.
+pub struct ThreadComm {
+    rank: usize,
+    size: usize,
+    shared: Arc<ThreadShared>,
+}
+
+impl ThreadComm {
+    /// Create one communicator per rank for a group of `size` threads.
+    pub fn group(size: usize) -> Vec<ThreadComm> {
+        let shared = Arc::new(ThreadShared {
+            barrier: Barrier::new(size),
+            buf: Mutex::new(vec![]),
+        });
+        (0..size)
+            .map(|rank| ThreadComm {
+                rank,
+                size,
+                shared: Arc::clone(&shared),
+            })
+            .collect()
+    }
+
+    /// Run `write` on the shared buffer, then `read` once everyone wrote.
+    fn exchange<W, R>(&self, len: usize, write: W, read: R)
+    where
+        W: FnOnce(&mut Vec<f64>),
+        R: FnOnce(&[f64]),
+    {
+        self.shared.barrier.wait();
+        if self.rank == 0 {
+            let mut buf = self.shared.buf.lock().unwrap();
+            buf.clear();
+            buf.resize(len, 0.0);
+        }
+        self.shared.barrier.wait();
+        write(&mut self.shared.buf.lock().unwrap());
+        self.shared.barrier.wait();
+        read(&self.shared.buf.lock().unwrap());
+        self.shared.barrier.wait();
+    }
+}
+
+impl Communicator for ThreadComm {
+    fn rank(&self) -> usize {
+        self.rank
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn allreduce_sum(&self, buf: &mut [f64]) {
+        let local = buf.to_vec();
+        self.exchange(
+            buf.len(),
+            |shared| shared.iter_mut().zip(&local).for_each(|(s, l)| *s += l),
+            |shared| buf.copy_from_slice(shared),
+        );
+    }
+
+    fn broadcast(&self, root: usize, buf: &mut [f64]) {
+        let local = buf.to_vec();
+        let rank = self.rank;
+        self.exchange(
+            buf.len(),
+            |shared| if rank == root { shared.copy_from_slice(&local) },
+            |shared| buf.copy_from_slice(shared),
+        );
+    }
+
+    fn allgather(&self, local: &[f64], counts: &[usize], out: &mut [f64]) {
+        assert_eq!(counts.len(), self.size);
+        assert_eq!(counts[self.rank], local.len());
+        let start: usize = counts[..self.rank].iter().sum();
+        self.exchange(
+            out.len(),
+            |shared| shared[start..start + local.len()].copy_from_slice(local),
+            |shared| out.copy_from_slice(shared),
+        );
+    }
+}
+
+/// Matrix distributed by blocks of contiguous rows.
+pub struct DistMatrix {
+    /// Global number of rows.
+    pub m: usize,
+    /// Global number of columns.
+    pub n: usize,
+    rows: Range<usize>,
+    local: Matrix,
+}
+
+impl DistMatrix {
+    /// Create from the rows this process owns.
+    pub fn from_local<C: Communicator>(comm: &C, m: usize, local: Matrix) -> DistMatrix {
+        let rows = block_range(m, comm.size(), comm.rank());
+        assert_eq!(rows.len(), local.m);
+        DistMatrix {
+            m,
+            n: local.n,
+            rows,
+            local,
+        }
+    }
+
+    /// Take this process's rows of a matrix replicated on every process.
+    pub fn from_global<C: Communicator>(comm: &C, a: &Matrix) -> DistMatrix {
+        let rows = block_range(a.m, comm.size(), comm.rank());
+        let mut local = Matrix::zero(rows.len(), a.n);
+        for (li, i) in rows.clone().enumerate() {
+            for j in 0..a.n {
+                local.set(li, j, a.get(i, j));
+            }
+        }
+        DistMatrix::from_local(comm, a.m, local)
+    }
+
+    /// Return the global indices of the local rows.
+    pub fn rows(&self) -> Range<usize> {
+        self.rows.clone()
+    }
+
+    /// Return the local rows.
+    pub fn local(&self) -> &Matrix {
+        &self.local
+    }
+
+    /// Gather the full matrix on every process.
+    pub fn to_global<C: Communicator>(&self, comm: &C) -> Matrix {
+        let counts: Vec<usize> = block_counts(self.m, comm.size())
+            .iter()
+            .map(|c| c * self.n)
+            .collect();
+        let mut data = vec![0.0; self.m * self.n];
+        let local = self.local.view();
+        let local: Vec<f64> = local.data.iter().flat_map(|row| row.iter().copied()).collect();
+        comm.allgather(&local, &counts, &mut data);
+        Matrix::from_vec(self.m, self.n, data)
+    }
+
+    /// Compute y = A x, where x and y are distributed by block rows.
+    pub fn matvec<C: Communicator>(&self, comm: &C, x: &[f64], y: &mut [f64]) {
+        assert_eq!(x.len(), block_range(self.n, comm.size(), comm.rank()).len());
+        assert_eq!(y.len(), self.rows.len());
+        let mut full = vec![0.0; self.n];
+        comm.allgather(x, &block_counts(self.n, comm.size()), &mut full);
+        gemv(1.0, &self.local.view(), &full, 0.0, y);
+    }
+}
+
+/// Multiply two block-row distributed matrices with a 1-D SUMMA.
+///
+/// At step r, rank r broadcasts its rows of B and every process
+/// accumulates the product of its matching columns of A with them.
+pub fn summa<C: Communicator>(comm: &C, a: &DistMatrix, b: &DistMatrix) -> DistMatrix {
+    assert_eq!(a.n, b.m);
+    let mut c = Matrix::zero(a.local.m, b.n);
+    for root in 0..comm.size() {
+        let ks = block_range(b.m, comm.size(), root);
+        let mut panel = if root == comm.rank() {
+            b.local.as_slice().chunks(b.local.ld())
+                .flat_map(|row| &row[..b.n])
+                .copied()
+                .collect()
+        } else {
+            vec![0.0; ks.len() * b.n]
+        };
+        comm.broadcast(root, &mut panel);
+        let panel = Matrix::from_vec(ks.len(), b.n, panel);
+
+        let mut a_cols = Matrix::zero(a.local.m, ks.len());
+        for i in 0..a.local.m {
+            for (lk, k) in ks.clone().enumerate() {
+                a_cols.set(i, lk, a.local.get(i, k));
+            }
+        }
+        matmul_acc(&a_cols.view(), &panel.view(), &mut c.view_mut());
+    }
+    DistMatrix::from_local(comm, a.m, c)
+}
+
+/// Return the triangular factor R of a tall block-row distributed matrix
+/// by TSQR, on every process.
+///
+/// Each process factors its own rows, then the n x n factors are gathered
+/// and combined in the same binary tree everywhere, so every process ends
+/// up with the same R. Only the small factors are communicated.
+pub fn tsqr<C: Communicator>(comm: &C, a: &DistMatrix) -> Matrix {
+    let n = a.n;
+    let local = qr_r(&a.local).to_vec();
+    let mut all = vec![0.0; comm.size() * n * n];
+    comm.allgather(&local, &vec![n * n; comm.size()], &mut all);
+    let rs = all.chunks(n * n).map(|r| Matrix::from_vec(n, n, r.to_vec())).collect();
+    tsqr_reduce(rs, n)
+}
+
+/// Return the global dot product of two distributed vectors.
+fn dist_dot<C: Communicator>(comm: &C, x: &[f64], y: &[f64]) -> f64 {
+    let mut res = [dot(x, y)];
+    comm.allreduce_sum(&mut res);
+    res[0]
+}
+
+/// Compute r = b - A x and return its global norm.
+fn dist_residual<C: Communicator>(comm: &C, a: &DistMatrix, b: &[f64], x: &[f64], r: &mut [f64]) -> f64 {
+    a.matvec(comm, x, r);
+    scale(-1.0, r);
+    axpy(1.0, b, r);
+    dist_dot(comm, r, r).sqrt()
+}
+
+/// Solve Ax = b for SPD A with conjugate gradients.
+///
+/// `b` and `x` hold the local block rows; `x` is the initial guess on entry.
+/// `stop` is checked before every iteration.
+pub fn cg<C: Communicator>(
+    comm: &C,
+    a: &DistMatrix,
+    b: &[f64],
+    x: &mut [f64],
+    mut stop: impl StoppingCriterion,
+    mut monitor: Option<Monitor>,
+) -> SolveResult {
+    let mut r = b.to_vec();
+    let mut ap = vec![0.0; b.len()];
+    a.matvec(comm, x, &mut ap);
+    axpy(-1.0, &ap, &mut r);
+    let mut p = r.clone();
+    let mut rr = dist_dot(comm, &r, &r);
+    let b_norm = dist_dot(comm, b, b).sqrt();
+    let mut res = SolveResult::new(rr.sqrt());
+
+    while res.check(&mut stop, rr.sqrt(), b_norm, None) {
+        a.matvec(comm, &p, &mut ap);
+        let pap = dist_dot(comm, &p, &ap);
+        if pap <= 0.0 {
+            res.breakdown = Some(Breakdown::NotPositiveDefinite);
+            return res;
+        }
+        let alpha = rr / pap;
+        axpy(alpha, &p, x);
+        axpy(-alpha, &ap, &mut r);
+        let rr_next = dist_dot(comm, &r, &r);
+        if !res.record(rr_next.sqrt(), &mut monitor) {
+            return res;
+        }
+        scale(rr_next / rr, &mut p);
+        axpy(1.0, &r, &mut p);
+        rr = rr_next;
+    }
+    res
+}
+
+/// Solve Ax = b with restarted GMRES.
+///
+/// `b` and `x` hold the local block rows; `x` is the initial guess on entry.
+/// The small Hessenberg least squares problem is replicated on every
+/// process. The recorded residuals are the estimates from the least squares
+/// problem; `stop` sees these during a cycle and the true residual at each
+/// restart, so it may be checked twice at the same iteration.
+pub fn gmres<C: Communicator>(
+    comm: &C,
+    a: &DistMatrix,
+    b: &[f64],
+    x: &mut [f64],
+    restart: usize,
+    mut stop: impl StoppingCriterion,
+    mut monitor: Option<Monitor>,
+) -> SolveResult {
+    assert!(restart > 0);
+    let b_norm = dist_dot(comm, b, b).sqrt();
+    let mut w = vec![0.0; b.len()];
+    let mut r = vec![0.0; b.len()];
+    let mut beta = dist_residual(comm, a, b, x, &mut r);
+    let mut res = SolveResult::new(beta);
+
+    while res.breakdown.is_none() && res.check(&mut stop, beta, b_norm, None) {
+        scale(1.0 / beta, &mut r);
+        let mut v = vec![r.clone()];
+        let mut h = vec![vec![0.0; restart]; restart + 1];
+        let mut rot = vec![Givens::identity(); restart];
+        let mut g = vec![0.0; restart + 1];
+        g[0] = beta;
+
+        let mut k = 0;
+        while k < restart {
+            a.matvec(comm, &v[k], &mut w);
+            for j in 0..=k {
+                h[j][k] = dist_dot(comm, &w, &v[j]);
+                axpy(-h[j][k], &v[j], &mut w);
+            }
+            let wnorm = dist_dot(comm, &w, &w).sqrt();
+            h[k + 1][k] = wnorm;
+
+            for j in 0..k {
+                (h[j][k], h[j + 1][k]) = rot[j].rotate(h[j][k], h[j + 1][k]);
+            }
+            let (rotation, r) = Givens::new(h[k][k], h[k + 1][k]);
+            rot[k] = rotation;
+            h[k][k] = r;
+            h[k + 1][k] = 0.0;
+            (g[k], g[k + 1]) = rot[k].rotate(g[k], 0.0);
+
+            let mut vnext = w.clone();
+            if wnorm > 0.0 {
+                scale(1.0 / wnorm, &mut vnext);
+            }
+            v.push(vnext);
+            k += 1;
+            if !res.record(g[k].abs(), &mut monitor) || !res.check(&mut stop, g[k].abs(), b_norm, None) {
+                break;
+            }
+        }
+
+        let hk = Matrix::from_vec(k, k, h[..k].iter().flat_map(|row| row[..k].iter().copied()).collect());
+        let mut y = g[..k].to_vec();
+        solve_upper(&hk, &mut y);
+        for (vi, yi) in v.iter().zip(&y) {
+            axpy(*yi, vi, x);
+        }
+        beta = dist_residual(comm, a, b, x, &mut r);
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Lcg;
+    use crate::solver::{tolerance, AbsoluteResidual, MaxIterations};
+    use crate::{approx, matmul};
+
+    /// Run `f` on every rank of a thread group.
+    fn run_ranks<F>(size: usize, f: F)
+    where
+        F: Fn(&ThreadComm) + Sync,
+    {
+        let comms = ThreadComm::group(size);
+        std::thread::scope(|s| {
+            for comm in &comms {
+                let f = &f;
+                s.spawn(move || f(comm));
+            }
+        });
+    }
+
+    #[test]
+    fn summa_matches_matmul() {
+        let x = Matrix::rand(7, 5);
+        let y = Matrix::rand(5, 4);
+        let mut z = Matrix::zero(7, 4);
+        matmul(&x.view(), &y.view(), &mut z.view_mut());
+
+        run_ranks(3, |comm| {
+            let a = DistMatrix::from_global(comm, &x);
+            let b = DistMatrix::from_global(comm, &y);
+            let c = summa(comm, &a, &b).to_global(comm);
+            for i in 0..7 {
+                for j in 0..4 {
+                    assert!(approx(c.get(i, j), z.get(i, j)));
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn distributed_tsqr() {
+        let x = Matrix::rand_seeded(50, 4, 3);
+        let expected = qr_r(&x);
+        run_ranks(3, |comm| {
+            let r = tsqr(comm, &DistMatrix::from_global(comm, &x));
+            for i in 0..4 {
+                for j in 0..4 {
+                    assert!((r.get(i, j).abs() - expected.get(i, j).abs()).abs() < 1e-12);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn distributed_cg_gmres() {
+        let n = 10;
+        let global = Matrix::rand_spd(n, &mut Lcg::new(1));
+        run_ranks(3, |comm| {
+            let a = DistMatrix::from_global(comm, &global);
+            let rows = a.rows();
+            // Exact solution is all ones.
+            let mut b = vec![0.0; rows.len()];
+            a.matvec(comm, &vec![1.0; rows.len()], &mut b);
+
+            let mut calls = 0;
+            let mut x = vec![0.0; rows.len()];
+            let res = cg(comm, &a, &b, &mut x, tolerance(1e-12, 100), Some(&mut |_, _| calls += 1));
+            assert!(res.converged && res.breakdown.is_none());
+            assert_eq!(calls, res.iterations);
+            assert_eq!(res.residuals.len(), res.iterations + 1);
+            assert!(x.iter().all(|xi| approx(*xi, 1.0)));
+
+            let mut x = vec![0.0; rows.len()];
+            let res = gmres(comm, &a, &b, &mut x, 4, tolerance(1e-12, 100), None);
+            assert!(res.converged);
+            assert!(res.residuals.windows(2).all(|w| w[1] <= w[0] * (1.0 + 1e-12)));
+            assert!(x.iter().all(|xi| approx(*xi, 1.0)));
+
+            let mut x = vec![0.0; rows.len()];
+            let res = gmres(comm, &a, &b, &mut x, 4, AbsoluteResidual(0.0).or(MaxIterations(3)), None);
+            assert!(!res.converged);
+            assert_eq!(res.iterations, 3);
+
+            // CG detects an indefinite matrix.
+            let neg = DistMatrix::from_global(comm, &Matrix::from_vec(2, 2, vec![-1.0, 0.0, 0.0, -1.0]));
+            let mut x = vec![0.0; neg.rows().len()];
+            let res = cg(comm, &neg, &vec![1.0; neg.rows().len()], &mut x, tolerance(1e-12, 10), None);
+            assert_eq!(res.breakdown, Some(Breakdown::NotPositiveDefinite));
+        });
+    }
+}
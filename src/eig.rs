@@ -0,0 +1,757 @@
+//! Symmetric eigenvalue problems.
+//!
+//! sym_eig() solves small dense problems with the cyclic Jacobi method,
+//! which is slow for large n but accurate and simple. subspace_iteration()
+//! finds the dominant eigenpairs of a large symmetric operator, using
+//! sym_eig() for the Rayleigh-Ritz projections. hermitian_eig() is the
+//! same Jacobi method with complex rotations, for complex matrices held as
+//! real and imaginary parts.
+//!
+//! Generalized problems A x = lambda B x are reduced to the standard form
+//! by a Cholesky factorization of B when A is symmetric and B SPD, as in
+//! modal analysis with stiffness and mass matrices, and otherwise solved
+//! for the eigenvalues by the QZ algorithm.
+use crate::cholesky::CholFactor;
+use crate::factor::Factor;
+use crate::givens::{ComplexGivens, Givens};
+use crate::iterative::LinearOperator;
+use crate::lu::LuFactor;
+use crate::math;
+use crate::orth::{gram_schmidt, GramSchmidt};
+use crate::rng::{Distribution, Rng};
+use crate::triangular::solve_lower;
+use crate::{matmul, Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Eigendecomposition A = V diag(values) V^T of a symmetric matrix.
+pub struct SymEig {
+    /// The eigenvalues, in increasing order.
+    pub values: Vec<f64>,
+    /// The orthonormal eigenvectors, one per column.
+    pub vectors: Matrix,
+}
+
+/// Return the eigendecomposition of a, which must be symmetric; this is
+/// not checked.
+pub fn sym_eig(a: &Matrix) -> SymEig {
+    assert_eq!(a.m, a.n);
+    let n = a.n;
+    let mut d = a.clone();
+    // W accumulates the rotations, so that W A W^T is diagonal.
+    let mut w = Matrix::zero(n, n);
+    (0..n).for_each(|i| w.set(i, i, 1.0));
+    jacobi_sweeps(n, |p, q| {
+        let (app, apq, aqq) = (d.get(p, p), d.get(p, q), d.get(q, q));
+        if negligible(apq.abs(), app, aqq) {
+            return false;
+        }
+        let rot = Givens::jacobi(app, apq, aqq);
+        rot.apply_rows(&mut d, p, q);
+        rot.apply_cols(&mut d, p, q);
+        rot.apply_rows(&mut w, p, q);
+        true
+    });
+
+    let order = ascending(n, |i| d.get(i, i));
+    let mut vectors = Matrix::zero(n, n);
+    for (k, &i) in order.iter().enumerate() {
+        (0..n).for_each(|j| vectors.set(j, k, w.get(i, j)));
+    }
+    SymEig { values: order.iter().map(|&i| d.get(i, i)).collect(), vectors }
+}
+
+/// Run cyclic Jacobi sweeps over the pairs p < q of an n x n matrix until
+/// a sweep rotates nothing. `rotate(p, q)` annihilates entry (p, q) and
+/// returns false if it was already negligible.
+fn jacobi_sweeps(n: usize, mut rotate: impl FnMut(usize, usize) -> bool) {
+    for _ in 0..60 {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in p + 1..n {
+                rotated |= rotate(p, q);
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+}
+
+/// Return whether an off-diagonal entry of magnitude r is negligible next
+/// to the diagonal entries app and aqq.
+fn negligible(r: f64, app: f64, aqq: f64) -> bool {
+    r == 0.0 || r <= f64::EPSILON * math::sqrt((app * aqq).abs())
+}
+
+/// Return the indices 0..n sorted by increasing diag(i).
+fn ascending(n: usize, diag: impl Fn(usize) -> f64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| diag(i).total_cmp(&diag(j)));
+    order
+}
+
+/// Eigendecomposition A = V diag(values) V^H of a Hermitian matrix.
+pub struct HermitianEig {
+    /// The eigenvalues, in increasing order.
+    pub values: Vec<f64>,
+    /// Real parts of the orthonormal eigenvectors, one per column.
+    pub vectors_re: Matrix,
+    /// Imaginary parts of the eigenvectors.
+    pub vectors_im: Matrix,
+}
+
+/// Return the eigendecomposition of the Hermitian matrix re + i im; re
+/// must be symmetric and im skew-symmetric, which is not checked.
+pub fn hermitian_eig(re: &Matrix, im: &Matrix) -> HermitianEig {
+    assert!(re.m == re.n && (im.m, im.n) == (re.m, re.n));
+    let n = re.n;
+    let (mut dr, mut di) = (re.clone(), im.clone());
+    // W accumulates the rotations, so that W A W^H is diagonal.
+    let (mut wr, mut wi) = (Matrix::zero(n, n), Matrix::zero(n, n));
+    (0..n).for_each(|i| wr.set(i, i, 1.0));
+    jacobi_sweeps(n, |p, q| {
+        let (app, apq, aqq) = (dr.get(p, p), (dr.get(p, q), di.get(p, q)), dr.get(q, q));
+        if negligible(math::hypot(apq.0, apq.1), app, aqq) {
+            return false;
+        }
+        let rot = ComplexGivens::jacobi(app, apq, aqq);
+        rot.apply_rows(&mut dr, &mut di, p, q);
+        rot.apply_cols(&mut dr, &mut di, p, q);
+        rot.apply_rows(&mut wr, &mut wi, p, q);
+        true
+    });
+
+    let order = ascending(n, |i| dr.get(i, i));
+    let (mut vectors_re, mut vectors_im) = (Matrix::zero(n, n), Matrix::zero(n, n));
+    for (k, &i) in order.iter().enumerate() {
+        for j in 0..n {
+            vectors_re.set(j, k, wr.get(i, j));
+            vectors_im.set(j, k, -wi.get(i, j));
+        }
+    }
+    HermitianEig { values: order.iter().map(|&i| dr.get(i, i)).collect(), vectors_re, vectors_im }
+}
+
+/// Result of subspace_iteration().
+pub struct SubspaceEig {
+    /// The k eigenvalues of largest magnitude, in decreasing order of
+    /// magnitude.
+    pub values: Vec<f64>,
+    /// n x k orthonormal eigenvectors.
+    pub vectors: Matrix,
+    /// Residual norms ||A v - lambda v||.
+    pub residuals: Vec<f64>,
+    /// Number of iterations run.
+    pub iterations: usize,
+    /// True if every residual fell below tol times the largest |lambda|.
+    pub converged: bool,
+}
+
+/// Return the k eigenpairs of largest magnitude of a symmetric operator by
+/// subspace iteration with Rayleigh-Ritz projection.
+///
+/// The iteration keeps p = max(2k, k + 5) vectors (at most n), so the
+/// error in eigenvalue i shrinks by about |lambda_(p+1) / lambda_i| per
+/// iteration. Each iteration costs one block product with A of p vectors
+/// and O(n p^2).
+pub fn subspace_iteration(a: &impl LinearOperator, k: usize, tol: f64, max_iter: usize, rng: &mut impl Rng) -> SubspaceEig {
+    let n = a.nrows();
+    assert_eq!(a.ncols(), n);
+    assert!(k > 0 && k <= n);
+    let p = (2 * k).max(k + 5).min(n);
+    let mut z = Matrix::rand_dist(n, p, Distribution::Normal, rng);
+    let mut q = Matrix::zero(n, p);
+    let mut theta = Vec::new();
+    let mut residuals = Vec::new();
+    let mut iterations = 0;
+    let mut converged = false;
+    while iterations < max_iter && !converged {
+        iterations += 1;
+        (q, _) = gram_schmidt(&z, GramSchmidt::Classical, 1e-13);
+        a.apply_block(&q, &mut z);
+
+        // Rayleigh-Ritz: H = Q^T A Q = S diag(theta) S^T, then rotate Q
+        // and Z = A Q by S, largest |theta| first.
+        let mut h = Matrix::zero(p, p);
+        matmul(&q.transpose().view(), &z.view(), &mut h.view_mut());
+        let f = sym_eig(&h);
+        let mut order: Vec<usize> = (0..p).collect();
+        order.sort_by(|&i, &j| f.values[j].abs().total_cmp(&f.values[i].abs()));
+        let s = Matrix::from_vec(p, p, (0..p * p).map(|t| f.vectors.get(t / p, order[t % p])).collect());
+        theta = order.iter().map(|&i| f.values[i]).collect();
+        let (mut qs, mut zs) = (Matrix::zero(n, p), Matrix::zero(n, p));
+        matmul(&q.view(), &s.view(), &mut qs.view_mut());
+        matmul(&z.view(), &s.view(), &mut zs.view_mut());
+        (q, z) = (qs, zs);
+
+        residuals = (0..k)
+            .map(|j| {
+                let r2: f64 = (0..n).map(|i| z.get(i, j) - theta[j] * q.get(i, j)).map(|r| r * r).sum();
+                math::sqrt(r2)
+            })
+            .collect();
+        converged = residuals.iter().all(|&r| r <= tol * theta[0].abs());
+    }
+    SubspaceEig {
+        values: theta[..k].to_vec(),
+        vectors: Matrix::from_vec(n, k, (0..n * k).map(|t| q.get(t / k, t % k)).collect()),
+        residuals,
+        iterations,
+        converged,
+    }
+}
+
+/// Return the eigendecomposition of the symmetric-definite pencil
+/// A x = lambda B x, or None if B is not positive definite.
+///
+/// With B = L L^T, the eigenvalues are those of L^-1 A L^-T and the
+/// eigenvectors X = L^-T Y are B-orthonormal, X^T B X = I. The reduction
+/// is accurate when B is well conditioned.
+pub fn sym_eig_generalized(a: &Matrix, b: &Matrix) -> Option<SymEig> {
+    assert_eq!((a.m, a.n), (b.m, b.n));
+    let n = a.n;
+    let chol = CholFactor::new(b)?;
+    let l = chol.l();
+    // C = L^-1 (L^-1 A)^T, symmetrized against rounding.
+    let mut c = a.clone();
+    lower_solve(l, &mut c);
+    let mut c = c.transpose();
+    lower_solve(l, &mut c);
+    let c = Matrix::from_vec(n, n, (0..n * n).map(|t| 0.5 * (c.get(t / n, t % n) + c.get(t % n, t / n))).collect());
+    let SymEig { values, mut vectors } = sym_eig(&c);
+    // X = L^-T Y.
+    for j in 0..n {
+        for i in (0..n).rev() {
+            let sum: f64 = (i + 1..n).map(|k| l.get(k, i) * vectors.get(k, j)).sum();
+            vectors.set(i, j, (vectors.get(i, j) - sum) / l.get(i, i));
+        }
+    }
+    Some(SymEig { values, vectors })
+}
+
+/// Solve L X = B in place for lower triangular l.
+fn lower_solve(l: &Matrix, b: &mut Matrix) {
+    let mut col = vec![0.0; b.m];
+    for j in 0..b.n {
+        (0..b.m).for_each(|i| col[i] = b.get(i, j));
+        solve_lower(l, &mut col);
+        (0..b.m).for_each(|i| b.set(i, j, col[i]));
+    }
+}
+
+/// Return the eigenvalues of the pencil A x = lambda B x as (re, im)
+/// pairs, in no particular order, or None if the QZ iteration fails to
+/// converge.
+///
+/// A and B are reduced to Hessenberg-triangular form and then by double
+/// shift QZ steps (Moler and Stewart) to quasi-triangular form, all with
+/// Givens rotations. Eigenvalues with a vanishing diagonal entry of B are
+/// returned as infinite. This is a plain implementation, without
+/// balancing or eigenvectors.
+pub fn eig_generalized(a: &Matrix, b: &Matrix) -> Option<Vec<(f64, f64)>> {
+    assert!(a.m == a.n && (b.m, b.n) == (a.m, a.n));
+    let n = a.n;
+    let mut h = a.clone();
+    let mut t = b.clone();
+    hessenberg_triangular(&mut h, &mut t);
+
+    let tnorm = t.as_slice().iter().map(|v| v.abs()).fold(0.0, f64::max).max(f64::MIN_POSITIVE);
+    let mut values = Vec::with_capacity(n);
+    let mut hi = n;
+    let mut iter = 0;
+    while hi > 0 {
+        let last = hi - 1;
+        // Deflate at the lowest negligible subdiagonal entry.
+        let mut lo = last;
+        while lo > 0 {
+            let scale = h.get(lo, lo).abs() + h.get(lo - 1, lo - 1).abs();
+            if h.get(lo, lo - 1).abs() <= f64::EPSILON * scale.max(f64::MIN_POSITIVE) {
+                h.set(lo, lo - 1, 0.0);
+                break;
+            }
+            lo -= 1;
+        }
+        if lo == last {
+            values.push(ratio(h.get(last, last), t.get(last, last), tnorm));
+            hi -= 1;
+            iter = 0;
+        } else if lo + 1 == last {
+            values.extend(pencil_2x2(&h, &t, lo, tnorm));
+            hi -= 2;
+            iter = 0;
+        } else {
+            iter += 1;
+            if iter > 30 * n {
+                return None;
+            }
+            qz_step(&mut h, &mut t, lo, last, iter, tnorm);
+        }
+    }
+    Some(values)
+}
+
+/// Return the eigenvalues of the square matrix a as (re, im) pairs, in no
+/// particular order, or None if the iteration fails to converge.
+///
+/// a is balanced first, then the eigenvalues come from
+/// eig_generalized() with B = I.
+pub fn eigvals(a: &Matrix) -> Option<Vec<(f64, f64)>> {
+    let n = a.n;
+    let mut b = a.clone();
+    balance(&mut b);
+    let mut id = Matrix::zero(n, n);
+    (0..n).for_each(|i| id.set(i, i, 1.0));
+    eig_generalized(&b, &id)
+}
+
+/// Return a unit eigenvector of the square matrix a for its real
+/// eigenvalue lambda, as from eigvals(), or None if lambda is not close to
+/// an eigenvalue.
+///
+/// The vector is found by inverse iteration with the balanced matrix,
+/// shifted slightly off lambda, and is then transformed back to one of a.
+pub fn eigvec(a: &Matrix, lambda: f64) -> Option<Vec<f64>> {
+    let n = a.n;
+    let mut b = a.clone();
+    let t = balance(&mut b);
+    let norm = b.as_slice().iter().map(|v| v.abs()).fold(0.0, f64::max).max(f64::MIN_POSITIVE);
+    // An exact eigenvalue makes B - lambda I singular, so move off it.
+    let lu = [1.0, 8.0, 64.0, 512.0].into_iter().find_map(|f| {
+        let mut shifted = b.clone();
+        let shift = lambda + f * f64::EPSILON * norm;
+        (0..n).for_each(|i| shifted.set(i, i, shifted.get(i, i) - shift));
+        LuFactor::new(&shifted)
+    })?;
+    let normalize = |v: &mut [f64]| {
+        let r = math::sqrt(v.iter().map(|x| x * x).sum());
+        v.iter_mut().for_each(|x| *x /= r);
+    };
+    let mut v = vec![1.0; n];
+    for _ in 0..3 {
+        lu.solve_in_place(&mut v);
+        normalize(&mut v);
+    }
+    let residual: f64 = (0..n).map(|i| (0..n).map(|j| b.get(i, j) * v[j]).sum::<f64>() - lambda * v[i]).map(|r| r * r).sum();
+    if residual.is_nan() || math::sqrt(residual) > math::sqrt(f64::EPSILON) * norm {
+        return None;
+    }
+    let mut x = Matrix::from_vec(n, 1, v);
+    t.back_transform(&mut x);
+    let mut x = x.to_vec();
+    normalize(&mut x);
+    Some(x)
+}
+
+/// Similarity transformation T = P D applied by balance(), so the balanced
+/// matrix is T^-1 A T with P a permutation and D diagonal.
+pub struct Balance {
+    /// Rows and columns lo..hi were scaled; the others hold eigenvalues
+    /// isolated by the permutation, on the diagonal.
+    pub lo: usize,
+    /// One past the last scaled row and column.
+    pub hi: usize,
+    /// The diagonal of D, powers of two.
+    pub scale: Vec<f64>,
+    /// The symmetric swaps making up P, in the order they were applied.
+    swaps: Vec<(usize, usize)>,
+}
+
+impl Balance {
+    /// Replace the rows of v, eigenvectors of the balanced matrix, with
+    /// eigenvectors of the original one: V := T V.
+    pub fn back_transform(&self, v: &mut Matrix) {
+        assert_eq!(v.m, self.scale.len());
+        for i in 0..v.m {
+            for j in 0..v.n {
+                v.set(i, j, self.scale[i] * v.get(i, j));
+            }
+        }
+        for &(i, k) in self.swaps.iter().rev() {
+            for j in 0..v.n {
+                let t = v.get(i, j);
+                v.set(i, j, v.get(k, j));
+                v.set(k, j, t);
+            }
+        }
+    }
+}
+
+/// Balance a in place as LAPACK's gebal does, before computing its
+/// eigenvalues.
+///
+/// First a permutation moves rows and columns that isolate an eigenvalue
+/// to the bottom and the top, leaving a trailing and leading triangular
+/// part. Then the rows and columns of the rest are scaled by a diagonal
+/// similarity with powers of two, so no rounding error is made, until each
+/// row and column pair has similar norm (Parlett and Reinsch). The
+/// eigenvalues are unchanged, but those of a badly scaled matrix such as a
+/// companion matrix are computed much more accurately afterwards.
+pub fn balance(a: &mut Matrix) -> Balance {
+    assert_eq!(a.m, a.n);
+    let n = a.n;
+    let mut swaps = Vec::new();
+    let swap = |a: &mut Matrix, swaps: &mut Vec<(usize, usize)>, i: usize, k: usize| {
+        for j in 0..n {
+            let t = a.get(i, j);
+            a.set(i, j, a.get(k, j));
+            a.set(k, j, t);
+        }
+        for j in 0..n {
+            let t = a.get(j, i);
+            a.set(j, i, a.get(j, k));
+            a.set(j, k, t);
+        }
+        swaps.push((i, k));
+    };
+
+    // Rows with no off-diagonal entries in the window go to the bottom.
+    let (mut lo, mut hi) = (0, n);
+    while let Some(j) = (lo..hi).rev().find(|&j| (lo..hi).all(|k| k == j || a.get(j, k) == 0.0)) {
+        swap(a, &mut swaps, j, hi - 1);
+        hi -= 1;
+    }
+    // Columns with none go to the top.
+    while let Some(j) = (lo..hi).find(|&j| (lo..hi).all(|k| k == j || a.get(k, j) == 0.0)) {
+        swap(a, &mut swaps, j, lo);
+        lo += 1;
+    }
+
+    let mut scale = vec![1.0; n];
+    let mut done = false;
+    while !done {
+        done = true;
+        for i in lo..hi {
+            let c: f64 = (lo..hi).filter(|&k| k != i).map(|k| a.get(k, i).abs()).sum();
+            let r: f64 = (lo..hi).filter(|&k| k != i).map(|k| a.get(i, k).abs()).sum();
+            if c == 0.0 || r == 0.0 {
+                continue;
+            }
+            // Find the power of two f minimizing c f + r / f; cf = c f^2.
+            let (mut f, mut cf) = (1.0, c);
+            while cf < r / 2.0 {
+                f *= 2.0;
+                cf *= 4.0;
+            }
+            while cf >= 2.0 * r {
+                f /= 2.0;
+                cf /= 4.0;
+            }
+            if f != 1.0 && (cf + r) / f < 0.95 * (c + r) {
+                done = false;
+                scale[i] *= f;
+                (0..n).for_each(|k| a.set(i, k, a.get(i, k) / f));
+                (0..n).for_each(|k| a.set(k, i, a.get(k, i) * f));
+            }
+        }
+    }
+    Balance { lo, hi, scale, swaps }
+}
+
+/// Reduce (H, T) to upper Hessenberg and upper triangular form by
+/// orthogonal equivalence.
+fn hessenberg_triangular(h: &mut Matrix, t: &mut Matrix) {
+    let n = h.n;
+    for j in 0..n {
+        for i in (j + 1..n).rev() {
+            let (g, _) = Givens::new(t.get(i - 1, j), t.get(i, j));
+            g.apply_rows(t, i - 1, i);
+            g.apply_rows(h, i - 1, i);
+            t.set(i, j, 0.0);
+        }
+    }
+    for j in 0..n.saturating_sub(2) {
+        for i in (j + 2..n).rev() {
+            left(h, t, i - 1, i, j);
+            right(h, t, i, i - 1, i);
+        }
+    }
+}
+
+/// Rotate rows i and k of H and T to zero H[k][j] against H[i][j].
+fn left(h: &mut Matrix, t: &mut Matrix, i: usize, k: usize, j: usize) {
+    let (g, _) = Givens::new(h.get(i, j), h.get(k, j));
+    g.apply_rows(h, i, k);
+    g.apply_rows(t, i, k);
+    h.set(k, j, 0.0);
+}
+
+/// Rotate columns `keep` and `zero` of H and T to zero T[r][zero] against
+/// T[r][keep].
+fn right(h: &mut Matrix, t: &mut Matrix, r: usize, zero: usize, keep: usize) {
+    let (g, _) = Givens::new(t.get(r, keep), t.get(r, zero));
+    g.apply_cols(h, keep, zero);
+    g.apply_cols(t, keep, zero);
+    t.set(r, zero, 0.0);
+}
+
+/// Return x / y, with tiny y giving an infinite eigenvalue.
+fn ratio(x: f64, y: f64, tnorm: f64) -> (f64, f64) {
+    if y.abs() <= f64::EPSILON * tnorm {
+        (f64::INFINITY, 0.0)
+    } else {
+        (x / y, 0.0)
+    }
+}
+
+/// Return the eigenvalues of the 2 x 2 pencil at rows and columns k, k + 1.
+fn pencil_2x2(h: &Matrix, t: &Matrix, k: usize, tnorm: f64) -> [(f64, f64); 2] {
+    let (h11, h12, h21, h22) = (h.get(k, k), h.get(k, k + 1), h.get(k + 1, k), h.get(k + 1, k + 1));
+    let (t11, t12, t22) = (t.get(k, k), t.get(k, k + 1), t.get(k + 1, k + 1));
+    // det(H - lambda T) = a lambda^2 + b lambda + c.
+    let a = t11 * t22;
+    let b = h21 * t12 - h11 * t22 - h22 * t11;
+    let c = h11 * h22 - h12 * h21;
+    if a.abs() <= f64::EPSILON * tnorm * tnorm {
+        let finite = if b == 0.0 { (f64::INFINITY, 0.0) } else { (-c / b, 0.0) };
+        return [finite, (f64::INFINITY, 0.0)];
+    }
+    let disc = b * b - 4.0 * a * c;
+    if disc >= 0.0 {
+        // Avoid cancellation: one root from the quadratic formula, the
+        // other from the product of the roots.
+        let q = -0.5 * (b + b.signum() * math::sqrt(disc));
+        let r1 = q / a;
+        let r2 = if q != 0.0 { c / q } else { 0.0 };
+        [(r1, 0.0), (r2, 0.0)]
+    } else {
+        let (re, im) = (-b / (2.0 * a), math::sqrt(-disc) / (2.0 * a.abs()));
+        [(re, im), (re, -im)]
+    }
+}
+
+/// One implicit double shift QZ step on the active block lo..=hi, at
+/// least 3 x 3.
+fn qz_step(h: &mut Matrix, t: &mut Matrix, lo: usize, hi: usize, iter: usize, tnorm: f64) {
+    let guard = |x: f64| if x.abs() < f64::EPSILON * tnorm { f64::EPSILON * tnorm } else { x };
+    // The shifts are the eigenvalues of the trailing 2 x 2 block of
+    // M = H T^-1, given by their sum s and product p.
+    let (s, p) = if iter.is_multiple_of(10) {
+        // Exceptional shift to break cycles.
+        let e = (h.get(hi, hi - 1).abs() + h.get(hi - 1, hi - 2).abs()) / guard(t.get(hi, hi)).abs();
+        (1.5 * e, e * e)
+    } else {
+        // Inverse of the trailing 3 x 3 block of T.
+        let k = hi - 2;
+        let mut inv = [[0.0; 3]; 3];
+        for j in 0..3 {
+            for i in (0..=j).rev() {
+                let rhs = if i == j { 1.0 } else { 0.0 };
+                let sum: f64 = (i + 1..=j).map(|q| t.get(k + i, k + q) * inv[q][j]).sum();
+                inv[i][j] = (rhs - sum) / guard(t.get(k + i, k + i));
+            }
+        }
+        let m = |r: usize, c: usize| (0..3).map(|q| h.get(k + r, k + q) * inv[q][c]).sum::<f64>();
+        let (m11, m12, m21, m22) = (m(1, 1), m(1, 2), m(2, 1), m(2, 2));
+        (m11 + m22, m11 * m22 - m12 * m21)
+    };
+
+    // First column of (M - s1)(M - s2) = M^2 - s M + p, rows lo..lo+2.
+    let v0 = h.get(lo, lo) / guard(t.get(lo, lo));
+    let v1 = h.get(lo + 1, lo) / guard(t.get(lo, lo));
+    let w1 = v1 / guard(t.get(lo + 1, lo + 1));
+    let w0 = (v0 - t.get(lo, lo + 1) * w1) / guard(t.get(lo, lo));
+    let mut x = [
+        h.get(lo, lo) * w0 + h.get(lo, lo + 1) * w1 - s * v0 + p,
+        h.get(lo + 1, lo) * w0 + h.get(lo + 1, lo + 1) * w1 - s * v1,
+        h.get(lo + 2, lo + 1) * w1,
+    ];
+
+    for k in lo..hi {
+        if k == lo {
+            // Introduce the bulge.
+            let (g, _) = Givens::new(x[1], x[2]);
+            g.apply(&mut x, 1, 2);
+            g.apply_rows(h, lo + 1, lo + 2);
+            g.apply_rows(t, lo + 1, lo + 2);
+            let (g, _) = Givens::new(x[0], x[1]);
+            g.apply_rows(h, lo, lo + 1);
+            g.apply_rows(t, lo, lo + 1);
+        } else {
+            // Return column k - 1 of H to Hessenberg form.
+            if k + 2 <= hi {
+                left(h, t, k + 1, k + 2, k - 1);
+            }
+            left(h, t, k, k + 1, k - 1);
+        }
+        // Return T to triangular form in rows k + 1 and k + 2.
+        if k + 2 <= hi {
+            right(h, t, k + 2, k, k + 2);
+            right(h, t, k + 2, k + 1, k + 2);
+        }
+        right(h, t, k + 1, k, k + 1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Lcg;
+    use crate::SparseMatrix;
+
+    #[test]
+    fn jacobi_eigendecomposition() {
+        let b = Matrix::rand_seeded(6, 6, 4);
+        let a = Matrix::from_vec(6, 6, (0..36).map(|t| b.get(t / 6, t % 6) + b.get(t % 6, t / 6)).collect());
+        let f = sym_eig(&a);
+        assert!(f.values.windows(2).all(|w| w[0] <= w[1]));
+        // A V = V diag(values).
+        let mut av = Matrix::zero(6, 6);
+        matmul(&a.view(), &f.vectors.view(), &mut av.view_mut());
+        assert!((0..6).all(|i| (0..6).all(|j| (av.get(i, j) - f.values[j] * f.vectors.get(i, j)).abs() < 1e-13)));
+        // An indefinite 2 x 2 example: eigenvalues -1 and 3.
+        let g = sym_eig(&Matrix::from_vec(2, 2, vec![1.0, 2.0, 2.0, 1.0]));
+        assert!((g.values[0] + 1.0).abs() < 1e-15 && (g.values[1] - 3.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn subspace_iteration_on_laplacian() {
+        // The 1-D Laplacian has eigenvalues 2 - 2 cos(j pi / (n + 1)).
+        let n = 40;
+        let a = SparseMatrix::laplacian(&[n]);
+        let res = subspace_iteration(&a, 3, 1e-8, 2000, &mut Lcg::new(6));
+        assert!(res.converged);
+        for j in 0..3 {
+            let exact = 2.0 - 2.0 * (((n - j) as f64) * core::f64::consts::PI / (n + 1) as f64).cos();
+            assert!((res.values[j] - exact).abs() < 1e-10, "{} {}", res.values[j], exact);
+        }
+        let mut g = Matrix::zero(3, 3);
+        matmul(&res.vectors.transpose().view(), &res.vectors.view(), &mut g.view_mut());
+        assert!((0..3).all(|i| (0..3).all(|j| (g.get(i, j) - if i == j { 1.0 } else { 0.0 }).abs() < 1e-12)));
+    }
+
+    #[test]
+    fn generalized_symmetric_definite() {
+        // Three masses on springs: stiffness K and diagonal mass M.
+        let k = Matrix::from_vec(3, 3, vec![2.0, -1.0, 0.0,
+                                            -1.0, 2.0, -1.0,
+                                            0.0, -1.0, 1.0]);
+        let m = Matrix::from_vec(3, 3, vec![1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.5]);
+        let f = sym_eig_generalized(&k, &m).unwrap();
+        let (mut kx, mut mx) = (Matrix::zero(3, 3), Matrix::zero(3, 3));
+        matmul(&k.view(), &f.vectors.view(), &mut kx.view_mut());
+        matmul(&m.view(), &f.vectors.view(), &mut mx.view_mut());
+        assert!((0..3).all(|i| (0..3).all(|j| (kx.get(i, j) - f.values[j] * mx.get(i, j)).abs() < 1e-13)));
+        let mut g = Matrix::zero(3, 3);
+        matmul(&f.vectors.transpose().view(), &mx.view(), &mut g.view_mut());
+        assert!((0..3).all(|i| (0..3).all(|j| (g.get(i, j) - if i == j { 1.0 } else { 0.0 }).abs() < 1e-13)));
+        assert!(sym_eig_generalized(&k, &Matrix::zero(3, 3)).is_none());
+
+        // QZ agrees on the same pencil.
+        let mut qz: Vec<f64> = eig_generalized(&k, &m).unwrap().iter().map(|&(re, im)| {
+            assert_eq!(im, 0.0);
+            re
+        }).collect();
+        qz.sort_by(f64::total_cmp);
+        assert!(qz.iter().zip(&f.values).all(|(p, q)| (p - q).abs() < 1e-12));
+    }
+
+    #[test]
+    fn qz_eigenvalues_of_general_pencil() {
+        let n = 7;
+        let a = Matrix::rand_seeded(n, n, 11);
+        let b = Matrix::rand_seeded(n, n, 12);
+        let values = eig_generalized(&a, &b).unwrap();
+        assert_eq!(values.len(), n);
+        // Each lambda makes A - lambda B singular; complex ones are checked
+        // through the real 2n x 2n form [P Q; -Q P] of P + iQ.
+        let bnorm = crate::svd::svd(&b).s[0];
+        for &(re, im) in &values {
+            let mut c = Matrix::zero(2 * n, 2 * n);
+            for i in 0..n {
+                for j in 0..n {
+                    let (p, q) = (a.get(i, j) - re * b.get(i, j), -im * b.get(i, j));
+                    c.set(i, j, p);
+                    c.set(i + n, j + n, p);
+                    c.set(i, j + n, q);
+                    c.set(i + n, j, -q);
+                }
+            }
+            let s = crate::svd::svd(&c).s;
+            let scale = 1.0 + (re * re + im * im).sqrt() * bnorm;
+            assert!(s[2 * n - 1] < 1e-11 * scale, "{re} {im} {}", s[2 * n - 1]);
+        }
+        // With B = I these are the ordinary eigenvalues: a rotation by 90
+        // degrees scaled by 2 has eigenvalues +-2i.
+        let r = Matrix::from_vec(3, 3, vec![0.0, -2.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 5.0]);
+        let mut id = Matrix::zero(3, 3);
+        (0..3).for_each(|i| id.set(i, i, 1.0));
+        let mut e = eig_generalized(&r, &id).unwrap();
+        e.sort_by(|x, y| x.1.total_cmp(&y.1));
+        assert!((e[0].0.abs() < 1e-14) && (e[0].1 + 2.0).abs() < 1e-14);
+        assert!((e[1].0 - 5.0).abs() < 1e-14 && e[1].1 == 0.0);
+        assert!((e[2].0.abs() < 1e-14) && (e[2].1 - 2.0).abs() < 1e-14);
+    }
+
+    #[test]
+    fn balancing_isolates_and_scales() {
+        // Row 1 and then column 2 have no off-diagonal entries, so 5 and
+        // then 7 are isolated; the rest is graded by 2^20.
+        let g = 2f64.powi(20);
+        let a = Matrix::from_vec(4, 4, vec![1.0, 0.0, g, 3.0,
+                                            0.0, 5.0, 0.0, 0.0,
+                                            1.0 / g, 4.0, 2.0, 1.0,
+                                            0.0, 2.0, 0.0, 7.0]);
+        let mut b = Matrix::from_vec(4, 4, a.as_slice().to_vec());
+        let t = balance(&mut b);
+        assert_eq!((t.lo, t.hi), (0, 2));
+        assert_eq!((b.get(3, 3), b.get(2, 2)), (5.0, 7.0));
+        // The active block is now far better scaled.
+        assert!(b.get(0, 1).abs() < 4.0 && b.get(1, 0).abs() < 4.0, "{} {}", b.get(0, 1), b.get(1, 0));
+
+        // A T = T B, checked through back_transform() on the identity.
+        let mut id = Matrix::zero(4, 4);
+        (0..4).for_each(|i| id.set(i, i, 1.0));
+        t.back_transform(&mut id);
+        let (mut at, mut tb) = (Matrix::zero(4, 4), Matrix::zero(4, 4));
+        matmul(&a.view(), &id.view(), &mut at.view_mut());
+        matmul(&id.view(), &b.view(), &mut tb.view_mut());
+        assert!((0..16).all(|k| (at.get(k / 4, k % 4) - tb.get(k / 4, k % 4)).abs() <= 1e-9 * at.get(k / 4, k % 4).abs().max(1.0)));
+
+        let mut e: Vec<f64> = eigvals(&a).unwrap().iter().map(|v| v.0).collect();
+        e.sort_by(f64::total_cmp);
+        for &lambda in &e {
+            let v = eigvec(&a, lambda).unwrap();
+            assert!((0..4).all(|i| ((0..4).map(|j| a.get(i, j) * v[j]).sum::<f64>() - lambda * v[i]).abs() < 1e-12 * g), "{lambda} {v:?}");
+        }
+        assert!(eigvec(&a, 100.0).is_none());
+        // The active block balances to [1 1; 1 2].
+        let r = 5f64.sqrt() / 2.0;
+        assert!((e[0] - 1.5 + r).abs() < 1e-14 && (e[1] - 1.5 - r).abs() < 1e-14 && e[2] == 5.0 && e[3] == 7.0, "{e:?}");
+    }
+
+    #[test]
+    fn hermitian_eigendecomposition() {
+        let n = 6;
+        let (x, y) = (Matrix::rand_seeded(n, n, 21), Matrix::rand_seeded(n, n, 22));
+        let re = Matrix::from_vec(n, n, (0..n * n).map(|t| x.get(t / n, t % n) + x.get(t % n, t / n)).collect());
+        let im = Matrix::from_vec(n, n, (0..n * n).map(|t| y.get(t / n, t % n) - y.get(t % n, t / n)).collect());
+        let HermitianEig { values, vectors_re: vr, vectors_im: vi } = hermitian_eig(&re, &im);
+        for k in 0..n {
+            for i in 0..n {
+                // (A v)_i = sum_j (re + i im)_ij (vr + i vi)_j.
+                let ar: f64 = (0..n).map(|j| re.get(i, j) * vr.get(j, k) - im.get(i, j) * vi.get(j, k)).sum();
+                let ai: f64 = (0..n).map(|j| re.get(i, j) * vi.get(j, k) + im.get(i, j) * vr.get(j, k)).sum();
+                assert!((ar - values[k] * vr.get(i, k)).abs() < 1e-12 && (ai - values[k] * vi.get(i, k)).abs() < 1e-12);
+            }
+            for l in 0..n {
+                // v_k^H v_l = delta_kl.
+                let pr: f64 = (0..n).map(|j| vr.get(j, k) * vr.get(j, l) + vi.get(j, k) * vi.get(j, l)).sum();
+                let pi: f64 = (0..n).map(|j| vr.get(j, k) * vi.get(j, l) - vi.get(j, k) * vr.get(j, l)).sum();
+                assert!((pr - if k == l { 1.0 } else { 0.0 }).abs() < 1e-13 && pi.abs() < 1e-13);
+            }
+        }
+        // The real form [re -im; im re] has each eigenvalue twice.
+        let big = Matrix::from_vec(2 * n, 2 * n, (0..4 * n * n).map(|t| {
+            let (i, j) = (t / (2 * n), t % (2 * n));
+            match (i < n, j < n) {
+                (true, true) => re.get(i, j),
+                (false, false) => re.get(i - n, j - n),
+                (true, false) => -im.get(i, j - n),
+                (false, true) => im.get(i - n, j),
+            }
+        }).collect());
+        let doubled = sym_eig(&big).values;
+        assert!((0..n).all(|k| (doubled[2 * k] - values[k]).abs() < 1e-12 && (doubled[2 * k + 1] - values[k]).abs() < 1e-12));
+    }
+}
@@ -0,0 +1,255 @@
+//! Einstein summation over matrices.
+//!
+//! einsum("ik,kj->ij", &[&a, &b]) is the matrix product, "ij,j->i" a
+//! matrix-vector product, "ii->" the trace and "ij->ji" the transpose.
+//! Each operand gets one subscript per dimension (one for a vector stored
+//! as an n x 1 or 1 x n matrix), a label repeated within an operand takes
+//! its diagonal, and labels missing from the output are summed over.
+//! Without "->" the output is the labels that appear once, in alphabetical
+//! order.
+//!
+//! The operands are contracted pairwise from the left. Each pairwise
+//! contraction is arranged as a batch of matrix products, so the work goes
+//! through the gemm and gemv kernels; intermediates may have more than two
+//! dimensions but the result must be a scalar, vector or matrix.
+use crate::operations::gemv;
+use crate::{matmul, Matrix};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Return the contraction of `operands` described by `spec`.
+///
+/// A scalar result is 1 x 1 and a vector result n x 1. Panics if the spec
+/// is malformed or the dimensions of a label disagree.
+pub fn einsum(spec: &str, operands: &[&Matrix]) -> Matrix {
+    let (inputs, output) = parse(spec);
+    assert_eq!(inputs.len(), operands.len(), "einsum: {} operands for {} subscripts", operands.len(), inputs.len());
+    assert!(output.len() <= 2, "einsum: result has more than two dimensions");
+    let mut tensors: Vec<Tensor> = inputs.iter().zip(operands).map(|(labels, a)| Tensor::from_matrix(a, labels)).collect();
+    let mut acc = tensors.remove(0);
+    for (k, t) in tensors.iter().enumerate() {
+        let keep: Vec<char> = output.iter().chain(tensors[k + 1..].iter().flat_map(|t| &t.labels)).copied().collect();
+        acc = acc.contract(t, &keep);
+    }
+    let res = acc.reduce_to(&output).permute(&output);
+    match res.dims[..] {
+        [] => Matrix::from_vec(1, 1, res.data),
+        [n] => Matrix::from_vec(n, 1, res.data),
+        [m, n] => Matrix::from_vec(m, n, res.data),
+        _ => unreachable!(),
+    }
+}
+
+/// Split "ab,bc->ac" into input and output labels.
+fn parse(spec: &str) -> (Vec<Vec<char>>, Vec<char>) {
+    let (lhs, rhs) = match spec.split_once("->") {
+        Some((l, r)) => (l, Some(r)),
+        None => (spec, None),
+    };
+    let labels = |s: &str| -> Vec<char> {
+        let s: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+        assert!(s.iter().all(|c| c.is_ascii_alphabetic()), "einsum: bad subscripts in {:?}", spec);
+        s
+    };
+    let inputs: Vec<Vec<char>> = lhs.split(',').map(labels).collect();
+    let output = match rhs {
+        Some(r) => {
+            let out = labels(r);
+            for (i, c) in out.iter().enumerate() {
+                assert!(!out[..i].contains(c), "einsum: repeated output label {}", c);
+                assert!(inputs.iter().any(|l| l.contains(c)), "einsum: output label {} not in inputs", c);
+            }
+            out
+        }
+        None => {
+            let mut once: Vec<char> = inputs.iter().flatten().copied().filter(|c| inputs.iter().flatten().filter(|d| *d == c).count() == 1).collect();
+            once.sort_unstable();
+            once
+        }
+    };
+    (inputs, output)
+}
+
+/// Dense row-major tensor with one label per dimension, all distinct.
+struct Tensor {
+    labels: Vec<char>,
+    dims: Vec<usize>,
+    data: Vec<f64>,
+}
+
+impl Tensor {
+    /// Label the matrix a, taking diagonals for repeated labels.
+    fn from_matrix(a: &Matrix, labels: &[char]) -> Tensor {
+        let data = a.clone().to_vec();
+        match labels {
+            [] => {
+                assert_eq!((a.m, a.n), (1, 1), "einsum: scalar operand is {} x {}", a.m, a.n);
+                Tensor { labels: Vec::new(), dims: Vec::new(), data }
+            }
+            [l] => {
+                assert!(a.m == 1 || a.n == 1, "einsum: vector operand is {} x {}", a.m, a.n);
+                Tensor { labels: vec![*l], dims: vec![a.m * a.n], data }
+            }
+            [l, r] if l == r => {
+                assert_eq!(a.m, a.n, "einsum: diagonal of a {} x {} matrix", a.m, a.n);
+                let diag = (0..a.n).map(|i| data[i * a.n + i]).collect();
+                Tensor { labels: vec![*l], dims: vec![a.n], data: diag }
+            }
+            [l, r] => Tensor { labels: vec![*l, *r], dims: vec![a.m, a.n], data },
+            _ => panic!("einsum: {} subscripts for a matrix", labels.len()),
+        }
+    }
+
+    fn dim(&self, label: char) -> usize {
+        self.dims[self.labels.iter().position(|&l| l == label).unwrap()]
+    }
+
+    /// Return the tensor with its dimensions reordered as `order`.
+    fn permute(&self, order: &[char]) -> Tensor {
+        assert_eq!(order.len(), self.labels.len());
+        if order == &self.labels[..] {
+            return Tensor { labels: self.labels.clone(), dims: self.dims.clone(), data: self.data.clone() };
+        }
+        let mut strides = vec![1; self.dims.len()];
+        for k in (0..self.dims.len().saturating_sub(1)).rev() {
+            strides[k] = strides[k + 1] * self.dims[k + 1];
+        }
+        let pos: Vec<usize> = order.iter().map(|c| self.labels.iter().position(|l| l == c).unwrap()).collect();
+        let dims: Vec<usize> = pos.iter().map(|&p| self.dims[p]).collect();
+        let src: Vec<usize> = pos.iter().map(|&p| strides[p]).collect();
+        let mut data = Vec::with_capacity(self.data.len());
+        let mut index = vec![0; dims.len()];
+        for _ in 0..self.data.len() {
+            data.push(self.data[index.iter().zip(&src).map(|(i, s)| i * s).sum::<usize>()]);
+            // Advance the multi-index in row-major order.
+            for k in (0..dims.len()).rev() {
+                index[k] += 1;
+                if index[k] < dims[k] {
+                    break;
+                }
+                index[k] = 0;
+            }
+        }
+        Tensor { labels: order.to_vec(), dims, data }
+    }
+
+    /// Sum over every label not in `keep`.
+    fn reduce_to(&self, keep: &[char]) -> Tensor {
+        let kept: Vec<char> = self.labels.iter().copied().filter(|l| keep.contains(l)).collect();
+        let summed: Vec<char> = self.labels.iter().copied().filter(|l| !keep.contains(l)).collect();
+        if summed.is_empty() {
+            return self.permute(&self.labels);
+        }
+        let order: Vec<char> = kept.iter().chain(&summed).copied().collect();
+        let t = self.permute(&order);
+        let inner: usize = summed.iter().map(|&l| self.dim(l)).product();
+        let data = t.data.chunks(inner.max(1)).map(|c| c.iter().sum()).collect();
+        Tensor {
+            dims: kept.iter().map(|&l| self.dim(l)).collect(),
+            labels: kept,
+            data,
+        }
+    }
+
+    /// Contract with `other`, summing the labels not in `keep`.
+    fn contract(&self, other: &Tensor, keep: &[char]) -> Tensor {
+        // Labels only one side has and nobody needs can be summed first.
+        let a = self.reduce_to(&[keep, &other.labels[..]].concat());
+        let b = other.reduce_to(&[keep, &a.labels[..]].concat());
+        let shared = |l: &char| b.labels.contains(l);
+        let batch: Vec<char> = a.labels.iter().copied().filter(|l| shared(l) && keep.contains(l)).collect();
+        let inner: Vec<char> = a.labels.iter().copied().filter(|l| shared(l) && !keep.contains(l)).collect();
+        let left: Vec<char> = a.labels.iter().copied().filter(|l| !shared(l)).collect();
+        let right: Vec<char> = b.labels.iter().copied().filter(|l| !a.labels.contains(l)).collect();
+        for l in batch.iter().chain(&inner) {
+            assert_eq!(a.dim(*l), b.dim(*l), "einsum: dimensions of label {} disagree", l);
+        }
+
+        // C[batch][left][right] = sum over inner of A[batch][left][inner] B[batch][inner][right].
+        let size = |t: &Tensor, ls: &[char]| ls.iter().map(|&l| t.dim(l)).product::<usize>();
+        let (nb, m, k, n) = (size(&a, &batch), size(&a, &left), size(&a, &inner), size(&b, &right));
+        let pa = a.permute(&[&batch[..], &left, &inner].concat());
+        let pb = b.permute(&[&batch[..], &inner, &right].concat());
+        let mut data = vec![0.0; nb * m * n];
+        for p in 0..nb {
+            let x = Matrix::from_vec(m, k, pa.data[p * m * k..(p + 1) * m * k].to_vec());
+            let c = &mut data[p * m * n..(p + 1) * m * n];
+            if n == 1 {
+                gemv(1.0, &x.view(), &pb.data[p * k..(p + 1) * k], 0.0, c);
+            } else {
+                let y = Matrix::from_vec(k, n, pb.data[p * k * n..(p + 1) * k * n].to_vec());
+                let mut z = Matrix::zero(m, n);
+                matmul(&x.view(), &y.view(), &mut z.view_mut());
+                c.copy_from_slice(z.as_slice());
+            }
+        }
+        let labels: Vec<char> = [&batch[..], &left, &right].concat();
+        let dims = batch.iter().chain(&left).map(|&l| a.dim(l)).chain(right.iter().map(|&l| b.dim(l))).collect();
+        Tensor { labels, dims, data }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MatrixIndex;
+
+    fn close(a: &Matrix, b: &Matrix) -> bool {
+        (a.m, a.n) == (b.m, b.n) && (0..a.m).all(|i| (0..a.n).all(|j| (a.get(i, j) - b.get(i, j)).abs() < 1e-12))
+    }
+
+    #[test]
+    fn products_and_reductions() {
+        let a = Matrix::rand_seeded(3, 4, 1);
+        let b = Matrix::rand_seeded(4, 2, 2);
+        let mut ab = Matrix::zero(3, 2);
+        matmul(&a.view(), &b.view(), &mut ab.view_mut());
+        assert!(close(&einsum("ik,kj->ij", &[&a, &b]), &ab));
+        assert!(close(&einsum("ik,kj", &[&a, &b]), &ab));
+        // (A B)^T = B^T A^T.
+        let abt = einsum("ik,kj->ji", &[&a, &b]);
+        assert!((0..3).all(|i| (0..2).all(|j| abt.get(j, i) == ab.get(i, j))));
+
+        let x = Matrix::rand_seeded(4, 1, 3);
+        let mut ax = vec![0.0; 3];
+        gemv(1.0, &a.view(), x.as_slice(), 0.0, &mut ax);
+        assert!(close(&einsum("ij,j->i", &[&a, &x]), &Matrix::from_vec(3, 1, ax)));
+
+        let s = Matrix::rand_seeded(4, 4, 4);
+        let trace: f64 = (0..4).map(|i| s.get(i, i)).sum();
+        assert!((einsum("ii->", &[&s]).get(0, 0) - trace).abs() < 1e-14);
+        assert!(close(&einsum("ii->i", &[&s]), &Matrix::from_vec(4, 1, (0..4).map(|i| s.get(i, i)).collect())));
+        let total: f64 = a.as_slice().iter().sum();
+        assert!((einsum("ij->", &[&a]).get(0, 0) - total).abs() < 1e-14);
+    }
+
+    #[test]
+    fn multi_operand_contractions() {
+        let a = Matrix::rand_seeded(3, 4, 1);
+        let b = Matrix::rand_seeded(4, 5, 2);
+        let c = Matrix::rand_seeded(5, 2, 3);
+        let mut ab = Matrix::zero(3, 5);
+        matmul(&a.view(), &b.view(), &mut ab.view_mut());
+        let mut abc = Matrix::zero(3, 2);
+        matmul(&ab.view(), &c.view(), &mut abc.view_mut());
+        assert!(close(&einsum("ij,jk,kl->il", &[&a, &b, &c]), &abc));
+
+        // The bilinear form x^T A y and a Hadamard product.
+        let x = Matrix::rand_seeded(1, 3, 4);
+        let y = Matrix::rand_seeded(4, 1, 5);
+        let xay: f64 = (0..3).map(|i| (0..4).map(|j| x.get(0, i) * a.get(i, j) * y.get(j, 0)).sum::<f64>()).sum();
+        assert!((einsum("i,ij,j->", &[&x, &a, &y]).get(0, 0) - xay).abs() < 1e-12);
+        let h = einsum("ij,ij->ij", &[&a, &a]);
+        assert!((0..3).all(|i| (0..4).all(|j| (h.get(i, j) - a.get(i, j) * a.get(i, j)).abs() < 1e-15)));
+
+        // An outer product through a four-dimensional intermediate.
+        let d = Matrix::rand_seeded(2, 3, 6);
+        let e = Matrix::rand_seeded(4, 3, 7);
+        let od = einsum("ij,kl,jl->ik", &[&a, &d, &e]);
+        let expected: Vec<f64> = (0..3)
+            .flat_map(|i| (0..2).map(move |k| (i, k)))
+            .map(|(i, k)| (0..4).map(|j| (0..3).map(|l| a.get(i, j) * d.get(k, l) * e.get(j, l)).sum::<f64>()).sum())
+            .collect();
+        assert!(close(&od, &Matrix::from_vec(3, 2, expected)));
+    }
+}
@@ -0,0 +1,239 @@
+//! Row and column equilibration before factorization.
+//!
+//! A matrix whose rows or columns differ greatly in size can steer partial
+//! pivoting to poor pivots, so the computed solution is inaccurate even
+//! though a rescaled matrix is well conditioned. Equilibration replaces A
+//! with R A C for diagonal R and C, chosen as in LAPACK's geequ so that the
+//! largest entry of every row and column is about 1, and Equilibrated
+//! undoes the scaling in its solves.
+use crate::factor::{self, Factor};
+use crate::{Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Rows or columns are scaled when the ratio of their smallest to largest
+/// size is below this, as in LAPACK.
+const THRESH: f64 = 0.1;
+
+/// Row and column scale factors, and how badly scaled the matrix was.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scaling {
+    /// Row scale factors, the diagonal of R.
+    pub r: Vec<f64>,
+    /// Column scale factors, the diagonal of C.
+    pub c: Vec<f64>,
+    /// Ratio of the smallest to the largest row maximum.
+    pub rowcnd: f64,
+    /// Ratio of the smallest to the largest column maximum, after row
+    /// scaling.
+    pub colcnd: f64,
+    /// Largest absolute entry of A.
+    pub amax: f64,
+    /// True if the rows are scaled; otherwise r is all ones.
+    pub rows_scaled: bool,
+    /// True if the columns are scaled; otherwise c is all ones.
+    pub cols_scaled: bool,
+}
+
+impl Scaling {
+    /// Compute the scale factors of a, or return None if it has a zero row
+    /// or column.
+    ///
+    /// Rows are scaled if rowcnd is below 0.1 or the entries are near
+    /// overflow or underflow, and columns if colcnd is below 0.1.
+    pub fn compute(a: &Matrix) -> Option<Scaling> {
+        let (m, n) = (a.m, a.n);
+        let row_max: Vec<f64> = (0..m).map(|i| (0..n).map(|j| a.get(i, j).abs()).fold(0.0, f64::max)).collect();
+        let (rmin, amax) = row_max.iter().fold((f64::INFINITY, 0.0), |(lo, hi), &v| (v.min(lo), v.max(hi)));
+        if m == 0 || rmin == 0.0 {
+            return (m == 0).then(|| Scaling::identity(0, n));
+        }
+        let rowcnd = rmin / amax;
+        let small = f64::MIN_POSITIVE / f64::EPSILON;
+        let rows_scaled = rowcnd < THRESH || amax < small || amax > 1.0 / small;
+        let r: Vec<f64> = if rows_scaled {
+            row_max.iter().map(|v| 1.0 / v).collect()
+        } else {
+            vec![1.0; m]
+        };
+
+        let col_max: Vec<f64> = (0..n).map(|j| (0..m).map(|i| r[i] * a.get(i, j).abs()).fold(0.0, f64::max)).collect();
+        let (cmin, cmax) = col_max.iter().fold((f64::INFINITY, 0.0), |(lo, hi), &v| (v.min(lo), v.max(hi)));
+        if n > 0 && cmin == 0.0 {
+            return None;
+        }
+        let colcnd = if n == 0 { 1.0 } else { cmin / cmax };
+        let cols_scaled = colcnd < THRESH;
+        let c = if cols_scaled {
+            col_max.iter().map(|v| 1.0 / v).collect()
+        } else {
+            vec![1.0; n]
+        };
+        Some(Scaling {
+            r,
+            c,
+            rowcnd,
+            colcnd,
+            amax,
+            rows_scaled,
+            cols_scaled,
+        })
+    }
+
+    /// Return the scaling that leaves an m x n matrix unchanged.
+    pub fn identity(m: usize, n: usize) -> Scaling {
+        Scaling {
+            r: vec![1.0; m],
+            c: vec![1.0; n],
+            rowcnd: 1.0,
+            colcnd: 1.0,
+            amax: 0.0,
+            rows_scaled: false,
+            cols_scaled: false,
+        }
+    }
+
+    /// Replace a with R a C.
+    pub fn apply(&self, a: &mut Matrix) {
+        assert_eq!((a.m, a.n), (self.r.len(), self.c.len()));
+        for i in 0..a.m {
+            for j in 0..a.n {
+                a.set(i, j, self.r[i] * a.get(i, j) * self.c[j]);
+            }
+        }
+    }
+}
+
+/// A factorization of R A C that solves with A.
+pub struct Equilibrated<F> {
+    factor: F,
+    scaling: Scaling,
+    norm1: f64,
+}
+
+impl<F: Factor> Equilibrated<F> {
+    /// Equilibrate a copy of the square matrix `a` if it is badly scaled
+    /// and factor it with `factor`, for instance LuFactor::from_matrix.
+    ///
+    /// Returns None if a has a zero row or column or `factor` fails.
+    pub fn new(a: &Matrix, factor: impl FnOnce(Matrix) -> Option<F>) -> Option<Equilibrated<F>> {
+        assert_eq!(a.m, a.n);
+        let scaling = Scaling::compute(a)?;
+        let mut scaled = a.clone();
+        let norm1 = factor::norm1(&scaled);
+        scaling.apply(&mut scaled);
+        Some(Equilibrated {
+            factor: factor(scaled)?,
+            scaling,
+            norm1,
+        })
+    }
+
+    /// Return the scaling that was applied.
+    pub fn scaling(&self) -> &Scaling {
+        &self.scaling
+    }
+
+    /// Return the factorization of R A C.
+    pub fn factor(&self) -> &F {
+        &self.factor
+    }
+}
+
+impl<F: Factor> Factor for Equilibrated<F> {
+    fn dim(&self) -> usize {
+        self.factor.dim()
+    }
+
+    fn solve_in_place(&self, b: &mut [f64]) {
+        // A x = b becomes (R A C) (C^-1 x) = R b.
+        b.iter_mut().zip(&self.scaling.r).for_each(|(v, r)| *v *= r);
+        self.factor.solve_in_place(b);
+        b.iter_mut().zip(&self.scaling.c).for_each(|(v, c)| *v *= c);
+    }
+
+    fn solve_transpose_in_place(&self, b: &mut [f64]) {
+        b.iter_mut().zip(&self.scaling.c).for_each(|(v, c)| *v *= c);
+        self.factor.solve_transpose_in_place(b);
+        b.iter_mut().zip(&self.scaling.r).for_each(|(v, r)| *v *= r);
+    }
+
+    fn det(&self) -> f64 {
+        let rc: f64 = self.scaling.r.iter().chain(&self.scaling.c).product();
+        self.factor.det() / rc
+    }
+
+    fn norm1(&self) -> f64 {
+        self.norm1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lu::LuFactor;
+    use crate::qr::QrFactor;
+
+    #[test]
+    fn equilibration_repairs_pivoting() {
+        // Partial pivoting keeps the first row of A, which is huge only
+        // because of its scale; x = (1, 1) comes out as (0, 1).
+        let a = Matrix::from_vec(2, 2, vec![1.0, 1e20, 1.0, 1.0]);
+        let b = [1.0 + 1e20, 2.0];
+        let plain = LuFactor::new(&a).unwrap().solve(&b);
+        assert!((plain[0] - 1.0).abs() > 0.5);
+
+        let eq = Equilibrated::new(&a, LuFactor::from_matrix).unwrap();
+        let s = eq.scaling();
+        assert!(s.rows_scaled && !s.cols_scaled);
+        assert_eq!(s.rowcnd, 1e-20);
+        assert_eq!(s.amax, 1e20);
+        let x = eq.solve(&b);
+        assert!((x[0] - 1.0).abs() < 1e-14 && (x[1] - 1.0).abs() < 1e-14);
+        assert!((eq.det() - (1.0 - 1e20)).abs() < 1e6);
+        assert_eq!(eq.norm1(), 1e20 + 1.0);
+    }
+
+    #[test]
+    fn scaled_matrix_is_equilibrated() {
+        let n = 5;
+        let mut a = Matrix::rand_seeded(n, n, 4);
+        for i in 0..n {
+            a.set(i, i, a.get(i, i) + 1.0);
+            for j in 0..n {
+                a.set(i, j, a.get(i, j) * 10f64.powi(2 * i as i32 - j as i32));
+            }
+        }
+        let s = Scaling::compute(&a).unwrap();
+        assert!(s.rows_scaled && s.cols_scaled);
+        let mut scaled = Matrix::from_vec(n, n, a.as_slice().to_vec());
+        s.apply(&mut scaled);
+        for k in 0..n {
+            let row = (0..n).map(|j| scaled.get(k, j).abs()).fold(0.0, f64::max);
+            let col = (0..n).map(|i| scaled.get(i, k).abs()).fold(0.0, f64::max);
+            assert!(row <= 1.0 + 1e-15 && row > 0.1 && (col - 1.0).abs() < 1e-15);
+        }
+
+        // Solves and the determinant are those of A itself.
+        let eq = Equilibrated::new(&a, QrFactor::from_matrix).unwrap();
+        let lu = LuFactor::new(&a).unwrap();
+        let b: Vec<f64> = (0..n).map(|i| i as f64 - 2.0).collect();
+        let x = eq.solve(&b);
+        let mut ax = vec![0.0; n];
+        crate::operations::gemv(1.0, &a.view(), &x, 0.0, &mut ax);
+        assert!(ax.iter().zip(&b).all(|(p, q)| (p - q).abs() < 1e-12 * eq.norm1()));
+        let mut t = b.clone();
+        eq.solve_transpose_in_place(&mut t);
+        let mut at = b.clone();
+        lu.solve_transpose_in_place(&mut at);
+        assert!(t.iter().zip(&at).all(|(p, q)| (p - q).abs() < 1e-9 * q.abs().max(1.0)));
+        assert!((eq.det() / lu.det() - 1.0).abs() < 1e-10);
+        // R A C is far better conditioned than A.
+        assert!(eq.factor().cond_est() * 1e6 < lu.cond_est());
+
+        // A well scaled matrix is left alone.
+        let id = Scaling::compute(&Matrix::from_vec(2, 2, vec![1.0, 0.5, 0.5, 1.0])).unwrap();
+        assert!(!id.rows_scaled && !id.cols_scaled && id.r == [1.0, 1.0]);
+        assert!(Scaling::compute(&Matrix::from_vec(2, 2, vec![1.0, 0.0, 1.0, 0.0])).is_none());
+    }
+}
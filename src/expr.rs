@@ -0,0 +1,315 @@
+//! Lazy matrix expressions for operator syntax without temporaries.
+//!
+//! `&a * &b` does not multiply; it returns a Product that remembers its
+//! operands. Adding a matrix gives a Gemm, `alpha A B + beta C`, which is
+//! evaluated by a single fma_scale() into the one result matrix:
+//!
+//! ```text
+//! let d = (&a * &b + &c).eval();     // one allocation, one kernel call
+//! (2.0 * &a * &b - &c).eval_into(&mut d);   // no allocation
+//! d += &a * &b;                      // accumulates in place
+//! ```
+//!
+//! Only these shapes are supported: products, scaled matrices, sums of two
+//! matrices, and a product plus or minus a (scaled) matrix. Anything else,
+//! such as `(&a + &b) * &c`, does not compile.
+use crate::{fma_scale, Matrix, MatrixIndex};
+use core::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// alpha A B, not yet evaluated.
+#[derive(Clone, Copy)]
+pub struct Product<'a> {
+    alpha: f64,
+    a: &'a Matrix,
+    b: &'a Matrix,
+}
+
+/// alpha A B + beta C, not yet evaluated.
+#[derive(Clone, Copy)]
+pub struct Gemm<'a> {
+    product: Product<'a>,
+    beta: f64,
+    c: &'a Matrix,
+}
+
+/// alpha A, not yet evaluated.
+#[derive(Clone, Copy)]
+pub struct Scaled<'a> {
+    alpha: f64,
+    a: &'a Matrix,
+}
+
+/// A + B or A - B, not yet evaluated.
+#[derive(Clone, Copy)]
+pub struct Sum<'a> {
+    alpha: f64,
+    a: &'a Matrix,
+    beta: f64,
+    b: &'a Matrix,
+}
+
+impl Product<'_> {
+    /// Return the result in a new matrix.
+    pub fn eval(&self) -> Matrix {
+        let mut d = Matrix::zero(self.a.m, self.b.n);
+        self.eval_into(&mut d);
+        d
+    }
+
+    /// Overwrite d with the result.
+    pub fn eval_into(&self, d: &mut Matrix) {
+        fma_scale(self.alpha, &self.a.view(), &self.b.view(), 0.0, &mut d.view_mut());
+    }
+}
+
+impl Gemm<'_> {
+    /// Return the result in a new matrix.
+    pub fn eval(&self) -> Matrix {
+        let mut d = Matrix::zero(self.c.m, self.c.n);
+        self.eval_into(&mut d);
+        d
+    }
+
+    /// Overwrite d with the result. d must not be C; use `+=` on C to
+    /// update it in place.
+    pub fn eval_into(&self, d: &mut Matrix) {
+        assert_eq!((d.m, d.n), (self.c.m, self.c.n));
+        for i in 0..d.m {
+            for j in 0..d.n {
+                d.set(i, j, self.c.get(i, j));
+            }
+        }
+        let p = &self.product;
+        fma_scale(p.alpha, &p.a.view(), &p.b.view(), self.beta, &mut d.view_mut());
+    }
+}
+
+impl Scaled<'_> {
+    /// Return the result in a new matrix.
+    pub fn eval(&self) -> Matrix {
+        let mut d = Matrix::zero(self.a.m, self.a.n);
+        self.eval_into(&mut d);
+        d
+    }
+
+    /// Overwrite d with the result.
+    pub fn eval_into(&self, d: &mut Matrix) {
+        assert_eq!((d.m, d.n), (self.a.m, self.a.n));
+        for i in 0..d.m {
+            for j in 0..d.n {
+                d.set(i, j, self.alpha * self.a.get(i, j));
+            }
+        }
+    }
+}
+
+impl Sum<'_> {
+    /// Return the result in a new matrix.
+    pub fn eval(&self) -> Matrix {
+        let mut d = Matrix::zero(self.a.m, self.a.n);
+        self.eval_into(&mut d);
+        d
+    }
+
+    /// Overwrite d with the result.
+    pub fn eval_into(&self, d: &mut Matrix) {
+        assert_eq!((self.a.m, self.a.n), (self.b.m, self.b.n));
+        assert_eq!((d.m, d.n), (self.a.m, self.a.n));
+        for i in 0..d.m {
+            for j in 0..d.n {
+                d.set(i, j, self.alpha * self.a.get(i, j) + self.beta * self.b.get(i, j));
+            }
+        }
+    }
+}
+
+impl From<Product<'_>> for Matrix {
+    fn from(e: Product) -> Matrix {
+        e.eval()
+    }
+}
+
+impl From<Gemm<'_>> for Matrix {
+    fn from(e: Gemm) -> Matrix {
+        e.eval()
+    }
+}
+
+impl From<Scaled<'_>> for Matrix {
+    fn from(e: Scaled) -> Matrix {
+        e.eval()
+    }
+}
+
+impl From<Sum<'_>> for Matrix {
+    fn from(e: Sum) -> Matrix {
+        e.eval()
+    }
+}
+
+impl<'a> Mul<&'a Matrix> for &'a Matrix {
+    type Output = Product<'a>;
+
+    fn mul(self, b: &'a Matrix) -> Product<'a> {
+        assert_eq!(self.n, b.m);
+        Product { alpha: 1.0, a: self, b }
+    }
+}
+
+/// alpha * &a * &b parses as (alpha * &a) * &b, so a scaled matrix stays
+/// lazy until it is multiplied.
+impl<'a> Mul<&'a Matrix> for f64 {
+    type Output = Scaled<'a>;
+
+    fn mul(self, a: &'a Matrix) -> Scaled<'a> {
+        Scaled { alpha: self, a }
+    }
+}
+
+impl<'a> Mul<&'a Matrix> for Scaled<'a> {
+    type Output = Product<'a>;
+
+    fn mul(self, b: &'a Matrix) -> Product<'a> {
+        assert_eq!(self.a.n, b.m);
+        Product { alpha: self.alpha, a: self.a, b }
+    }
+}
+
+impl<'a> Mul<Product<'a>> for f64 {
+    type Output = Product<'a>;
+
+    fn mul(self, p: Product<'a>) -> Product<'a> {
+        Product { alpha: self * p.alpha, ..p }
+    }
+}
+
+impl<'a> Neg for Product<'a> {
+    type Output = Product<'a>;
+
+    fn neg(self) -> Product<'a> {
+        Product { alpha: -self.alpha, ..self }
+    }
+}
+
+impl<'a> Add<&'a Matrix> for Product<'a> {
+    type Output = Gemm<'a>;
+
+    fn add(self, c: &'a Matrix) -> Gemm<'a> {
+        assert_eq!((self.a.m, self.b.n), (c.m, c.n));
+        Gemm { product: self, beta: 1.0, c }
+    }
+}
+
+impl<'a> Sub<&'a Matrix> for Product<'a> {
+    type Output = Gemm<'a>;
+
+    fn sub(self, c: &'a Matrix) -> Gemm<'a> {
+        Gemm { beta: -1.0, ..self + c }
+    }
+}
+
+impl<'a> Add<Product<'a>> for &'a Matrix {
+    type Output = Gemm<'a>;
+
+    fn add(self, p: Product<'a>) -> Gemm<'a> {
+        p + self
+    }
+}
+
+impl<'a> Sub<Product<'a>> for &'a Matrix {
+    type Output = Gemm<'a>;
+
+    fn sub(self, p: Product<'a>) -> Gemm<'a> {
+        -p + self
+    }
+}
+
+/// beta * &c in `&a * &b + beta * &c`.
+impl<'a> Add<Scaled<'a>> for Product<'a> {
+    type Output = Gemm<'a>;
+
+    fn add(self, s: Scaled<'a>) -> Gemm<'a> {
+        Gemm { beta: s.alpha, ..self + s.a }
+    }
+}
+
+impl<'a> Add<&'a Matrix> for &'a Matrix {
+    type Output = Sum<'a>;
+
+    fn add(self, b: &'a Matrix) -> Sum<'a> {
+        Sum { alpha: 1.0, a: self, beta: 1.0, b }
+    }
+}
+
+impl<'a> Sub<&'a Matrix> for &'a Matrix {
+    type Output = Sum<'a>;
+
+    fn sub(self, b: &'a Matrix) -> Sum<'a> {
+        Sum { alpha: 1.0, a: self, beta: -1.0, b }
+    }
+}
+
+impl AddAssign<Product<'_>> for Matrix {
+    fn add_assign(&mut self, p: Product) {
+        fma_scale(p.alpha, &p.a.view(), &p.b.view(), 1.0, &mut self.view_mut());
+    }
+}
+
+impl SubAssign<Product<'_>> for Matrix {
+    fn sub_assign(&mut self, p: Product) {
+        *self += -p;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matmul;
+    use alloc::vec;
+
+    fn close(a: &Matrix, b: &Matrix) -> bool {
+        (a.m, a.n) == (b.m, b.n) && (0..a.m).all(|i| (0..a.n).all(|j| (a.get(i, j) - b.get(i, j)).abs() < 1e-12))
+    }
+
+    #[test]
+    fn fused_products() {
+        let a = Matrix::rand_seeded(3, 4, 1);
+        let b = Matrix::rand_seeded(4, 2, 2);
+        let c = Matrix::rand_seeded(3, 2, 3);
+        let mut ab = Matrix::zero(3, 2);
+        matmul(&a.view(), &b.view(), &mut ab.view_mut());
+        let combo = |alpha: f64, beta: f64| {
+            Matrix::from_vec(3, 2, ab.as_slice().iter().zip(c.as_slice()).map(|(p, q)| alpha * p + beta * q).collect())
+        };
+
+        assert!(close(&(&a * &b).eval(), &ab));
+        let d: Matrix = (&a * &b + &c).into();
+        assert!(close(&d, &combo(1.0, 1.0)));
+        assert!(close(&(&a * &b - &c).eval(), &combo(1.0, -1.0)));
+        assert!(close(&(&c - &a * &b).eval(), &combo(-1.0, 1.0)));
+        assert!(close(&(2.0 * &a * &b + 0.5 * &c).eval(), &combo(2.0, 0.5)));
+        assert!(close(&(-3.0 * (&a * &b) + &c).eval(), &combo(-3.0, 1.0)));
+    }
+
+    #[test]
+    fn in_place_evaluation() {
+        let a = Matrix::rand_seeded(3, 3, 1);
+        let b = Matrix::rand_seeded(3, 3, 2);
+        let mut ab = Matrix::zero(3, 3);
+        matmul(&a.view(), &b.view(), &mut ab.view_mut());
+
+        // eval_into overwrites whatever was there.
+        let mut d = Matrix::from_vec(3, 3, vec![f64::NAN; 9]);
+        (&a * &b).eval_into(&mut d);
+        assert!(close(&d, &ab));
+        (&a * &b + &ab).eval_into(&mut d);
+        let twice = (2.0 * &ab).eval();
+        assert!(close(&d, &twice));
+        d -= &a * &b;
+        assert!(close(&d, &ab));
+        d += 2.0 * (&a * &b);
+        assert!(close(&d, &(3.0 * &ab).eval()));
+        assert!(close(&(&a + &b).eval(), &Matrix::from_vec(3, 3, a.as_slice().iter().zip(b.as_slice()).map(|(p, q)| p + q).collect())));
+        assert!((&a - &a).eval().as_slice().iter().all(|&v| v == 0.0));
+    }
+}
@@ -0,0 +1,245 @@
+//! Common interface of the matrix factorizations.
+//!
+//! `LuFactor`, `CholFactor` and `QrFactor` each keep a factorization of a
+//! square matrix for repeated use. The `Factor` trait gives them the same
+//! solve, determinant, inverse and condition estimate.
+use crate::{Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Factorization of a nonsingular n x n matrix A.
+pub trait Factor {
+    /// Return n.
+    fn dim(&self) -> usize;
+
+    /// Solve Ax = b in place.
+    fn solve_in_place(&self, b: &mut [f64]);
+
+    /// Solve A^T x = b in place.
+    fn solve_transpose_in_place(&self, b: &mut [f64]);
+
+    /// Return the determinant of A.
+    fn det(&self) -> f64;
+
+    /// Return the 1-norm of A, recorded when it was factored.
+    fn norm1(&self) -> f64;
+
+    /// Return the solution of Ax = b.
+    fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let mut x = b.to_vec();
+        self.solve_in_place(&mut x);
+        x
+    }
+
+    /// Return the solution of Ax = b with its backward error.
+    ///
+    /// `a` must be the matrix that was factored. This costs one extra
+    /// product with A.
+    fn solve_checked(&self, a: &Matrix, b: &[f64]) -> (Vec<f64>, BackwardError) {
+        let x = self.solve(b);
+        let err = backward_error(a, &x, b);
+        (x, err)
+    }
+
+    /// Return A^-1, by solving for the columns of the identity.
+    fn inverse(&self) -> Matrix {
+        let n = self.dim();
+        let mut inv = Matrix::zero(n, n);
+        let mut e = vec![0.0; n];
+        for j in 0..n {
+            e.fill(0.0);
+            e[j] = 1.0;
+            self.solve_in_place(&mut e);
+            for i in 0..n {
+                inv.set(i, j, e[i]);
+            }
+        }
+        inv
+    }
+
+    /// Estimate the 1-norm condition number ||A||_1 ||A^-1||_1.
+    ///
+    /// ||A^-1||_1 comes from Hager's method, which takes a few solves with
+    /// A and A^T instead of forming the inverse. The estimate is a lower
+    /// bound that is almost always within a factor of 3.
+    fn cond_est(&self) -> f64 {
+        self.norm1() * norm1_est(self.dim(), |x| self.solve_in_place(x), |z| self.solve_transpose_in_place(z))
+    }
+}
+
+/// Estimate ||B||_1 for an n x n B known only through products, by
+/// Hager's method. `apply` replaces x with B x and `apply_transpose` z
+/// with B^T z.
+pub(crate) fn norm1_est(n: usize, apply: impl Fn(&mut [f64]), apply_transpose: impl Fn(&mut [f64])) -> f64 {
+    if n == 0 {
+        return 0.0;
+    }
+    let mut x = vec![1.0 / n as f64; n];
+    let mut est: f64 = 0.0;
+    for _ in 0..5 {
+        let mut y = x.clone();
+        apply(&mut y);
+        est = est.max(y.iter().map(|v| v.abs()).sum());
+        let mut z: Vec<f64> = y.iter().map(|&v| if v >= 0.0 { 1.0 } else { -1.0 }).collect();
+        apply_transpose(&mut z);
+        // Stop at a local maximum: no unit vector improves on x.
+        let (j, zmax) = z.iter().enumerate().fold((0, 0.0), |best, (i, v)| if v.abs() > best.1 { (i, v.abs()) } else { best });
+        let ztx: f64 = z.iter().zip(&x).map(|(a, b)| a * b).sum();
+        if zmax <= ztx * (1.0 + f64::EPSILON) || zmax == 0.0 {
+            break;
+        }
+        x.fill(0.0);
+        x[j] = 1.0;
+    }
+    est
+}
+
+/// Residual and backward errors of an approximate solution of Ax = b.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BackwardError {
+    /// ||b - A x||_inf.
+    pub residual_norm: f64,
+    /// Smallest e such that (A + E) x = b + f with ||E|| <= e ||A|| and
+    /// ||f|| <= e ||b|| in the infinity norm.
+    pub normwise: f64,
+    /// Smallest e such that (A + E) x = b + f with |E| <= e |A| and
+    /// |f| <= e |b| entrywise.
+    pub componentwise: f64,
+}
+
+impl BackwardError {
+    /// Return whether both backward errors are at most tol.
+    pub fn within(&self, tol: f64) -> bool {
+        self.normwise <= tol && self.componentwise <= tol
+    }
+}
+
+/// Return the residual and the Rigal-Gaches normwise and Oettli-Prager
+/// componentwise backward errors of x as a solution of Ax = b.
+///
+/// A backward error near the unit roundoff certifies that x is the exact
+/// solution of a nearby problem, whatever the conditioning of A.
+pub fn backward_error(a: &Matrix, x: &[f64], b: &[f64]) -> BackwardError {
+    assert_eq!((x.len(), b.len()), (a.n, a.m));
+    let mut residual_norm: f64 = 0.0;
+    let mut a_norm: f64 = 0.0;
+    let mut componentwise: f64 = 0.0;
+    for i in 0..a.m {
+        let (mut ax, mut abs_ax, mut row) = (0.0, 0.0, 0.0);
+        for j in 0..a.n {
+            let aij = a.get(i, j);
+            ax += aij * x[j];
+            abs_ax += (aij * x[j]).abs();
+            row += aij.abs();
+        }
+        let r = (b[i] - ax).abs();
+        let scale = abs_ax + b[i].abs();
+        // 0 / 0 counts as an exact row.
+        if r > 0.0 {
+            componentwise = componentwise.max(if scale > 0.0 { r / scale } else { f64::INFINITY });
+        }
+        residual_norm = residual_norm.max(r);
+        a_norm = a_norm.max(row);
+    }
+    let x_norm = x.iter().fold(0.0, |m: f64, v| m.max(v.abs()));
+    let b_norm = b.iter().fold(0.0, |m: f64, v| m.max(v.abs()));
+    let scale = a_norm * x_norm + b_norm;
+    let normwise = if residual_norm == 0.0 {
+        0.0
+    } else if scale > 0.0 {
+        residual_norm / scale
+    } else {
+        f64::INFINITY
+    };
+    BackwardError {
+        residual_norm,
+        normwise,
+        componentwise,
+    }
+}
+
+/// Return the 1-norm (largest absolute column sum) of a.
+pub(crate) fn norm1(a: &Matrix) -> f64 {
+    (0..a.n).map(|j| (0..a.m).map(|i| a.get(i, j).abs()).sum::<f64>()).fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cholesky::CholFactor;
+    use crate::lu::LuFactor;
+    use crate::qr::QrFactor;
+    use crate::SparseMatrix;
+
+    #[test]
+    fn factorizations_agree() {
+        let a = SparseMatrix::laplacian(&[6]).to_dense();
+        let factors: [&dyn Factor; 3] = [
+            &LuFactor::new(&a).unwrap(),
+            &CholFactor::new(&a).unwrap(),
+            &QrFactor::new(&a).unwrap(),
+        ];
+        let b: Vec<f64> = (0..6).map(|i| i as f64 + 1.0).collect();
+        let expected = factors[0].solve(&b);
+        let det = factors[0].det();
+        // The n x n 1-D Laplacian has determinant n + 1.
+        assert!((det - 7.0).abs() < 1e-12);
+        for f in factors {
+            assert_eq!(f.dim(), 6);
+            assert!(f.solve(&b).iter().zip(&expected).all(|(x, y)| (x - y).abs() < 1e-12));
+            assert!((f.det() - det).abs() < 1e-9 * det);
+            let mut t = b.clone();
+            f.solve_transpose_in_place(&mut t);
+            assert!(t.iter().zip(&expected).all(|(x, y)| (x - y).abs() < 1e-12));
+            let inv = f.inverse();
+            assert!((0..6).all(|i| (0..6).all(|j| {
+                let aai: f64 = (0..6).map(|k| a.get(i, k) * inv.get(k, j)).sum();
+                (aai - if i == j { 1.0 } else { 0.0 }).abs() < 1e-12
+            })));
+        }
+    }
+
+    #[test]
+    fn condition_estimate() {
+        // A nonsymmetric matrix, so that transpose solves matter.
+        let mut a = Matrix::rand_seeded(8, 8, 2);
+        for i in 0..8 {
+            a.set(i, i, a.get(i, i) + 0.5);
+        }
+        let lu = LuFactor::new(&a).unwrap();
+        let exact = norm1(&a) * norm1(&lu.inverse());
+        let qr = QrFactor::new(&a).unwrap();
+        for est in [lu.cond_est(), qr.cond_est()] {
+            assert!(est <= exact * (1.0 + 1e-10) && est >= exact / 3.0);
+        }
+        // The identity is perfectly conditioned; a scaled one too.
+        let mut d = Matrix::zero(3, 3);
+        (0..3).for_each(|i| d.set(i, i, 2.0));
+        assert!((CholFactor::new(&d).unwrap().cond_est() - 1.0).abs() < 1e-14);
+        assert!((LuFactor::new(&d).unwrap().det() - 8.0).abs() < 1e-14);
+        d.set(1, 1, 0.0);
+        assert!(QrFactor::new(&d).is_none());
+    }
+
+    #[test]
+    fn backward_errors() {
+        let a = Matrix::rand_seeded(20, 20, 5);
+        let b: Vec<f64> = (0..20).map(|i| (i as f64).sin()).collect();
+        let lu = LuFactor::new(&a).unwrap();
+        let (mut x, err) = lu.solve_checked(&a, &b);
+        assert!(err.within(1e-14));
+        assert!(err.normwise <= err.componentwise);
+
+        // A perturbed solution is the exact solution of no nearby problem.
+        x[3] += 1e-6;
+        let worse = backward_error(&a, &x, &b);
+        assert!(!worse.within(1e-10) && worse.residual_norm > 1e-8);
+
+        // Exact integer data have no error, and x = 0 with b != 0 has
+        // componentwise error 1.
+        let a = Matrix::from_vec(2, 2, vec![2.0, 0.0, 0.0, 4.0]);
+        assert_eq!(backward_error(&a, &[1.0, 0.5], &[2.0, 2.0]).componentwise, 0.0);
+        let zero = backward_error(&a, &[0.0, 0.0], &[2.0, 2.0]);
+        assert_eq!((zero.normwise, zero.componentwise, zero.residual_norm), (1.0, 1.0, 2.0));
+    }
+}
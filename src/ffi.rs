@@ -0,0 +1,253 @@
+//! C interface.
+//!
+//! Matrices are passed across the boundary as opaque `RnlaMatrix` handles
+//! that must be released with `rnla_matrix_free`. Functions that can fail
+//! return an `RNLA_*` status code. Panics never unwind into the caller:
+//! they are caught and reported as `RNLA_PANIC`, a null handle or NaN.
+//!
+//! The `rnla-capi` crate in `capi/` builds these functions as a shared and
+//! a static library. The `header` feature writes the C header to
+//! `$OUT_DIR/rnla.h` with cbindgen.
+use crate::lu::{lu_slice, lu_solve_slice};
+use crate::{matmul, Matrix, MatrixIndex};
+use core::ffi::c_int;
+use core::ptr;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Success.
+pub const RNLA_OK: c_int = 0;
+/// Dimensions of the arguments do not match.
+pub const RNLA_DIM_MISMATCH: c_int = -1;
+/// A null handle or pointer was passed.
+pub const RNLA_NULL: c_int = -2;
+/// The library panicked, for example on a failed allocation.
+pub const RNLA_PANIC: c_int = -3;
+/// The matrix is singular.
+pub const RNLA_SINGULAR: c_int = 1;
+
+/// Opaque matrix handle.
+pub struct RnlaMatrix(Matrix);
+
+/// Run f, returning `on_panic` instead of unwinding across the boundary.
+fn guard<T>(on_panic: T, f: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+/// Allocate a zeroed m x n matrix, or return null if it is too large.
+#[no_mangle]
+pub extern "C" fn rnla_matrix_new(m: usize, n: usize) -> *mut RnlaMatrix {
+    guard(ptr::null_mut(), || {
+        if m.checked_mul(n).is_none() {
+            return ptr::null_mut();
+        }
+        Box::into_raw(Box::new(RnlaMatrix(Matrix::zero(m, n))))
+    })
+}
+
+/// Allocate an m x n matrix copied from row-major `data`, or return null
+/// if `data` is null or m * n overflows.
+///
+/// # Safety
+///
+/// `data` must point to at least `m * n` readable doubles.
+#[no_mangle]
+pub unsafe extern "C" fn rnla_matrix_from_data(m: usize, n: usize, data: *const f64) -> *mut RnlaMatrix {
+    guard(ptr::null_mut(), || {
+        let Some(len) = m.checked_mul(n) else {
+            return ptr::null_mut();
+        };
+        if data.is_null() {
+            return ptr::null_mut();
+        }
+        let data = core::slice::from_raw_parts(data, len).to_vec();
+        Box::into_raw(Box::new(RnlaMatrix(Matrix::from_vec(m, n, data))))
+    })
+}
+
+/// Release a matrix handle. Null is ignored.
+///
+/// # Safety
+///
+/// `a` must be null or a handle returned by this library that was not
+/// already freed.
+#[no_mangle]
+pub unsafe extern "C" fn rnla_matrix_free(a: *mut RnlaMatrix) {
+    if !a.is_null() {
+        guard((), || drop(Box::from_raw(a)));
+    }
+}
+
+/// Return the number of rows, or 0 for a null handle.
+///
+/// # Safety
+///
+/// `a` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn rnla_matrix_rows(a: *const RnlaMatrix) -> usize {
+    guard(0, || a.as_ref().map_or(0, |a| a.0.m))
+}
+
+/// Return the number of columns, or 0 for a null handle.
+///
+/// # Safety
+///
+/// `a` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn rnla_matrix_cols(a: *const RnlaMatrix) -> usize {
+    guard(0, || a.as_ref().map_or(0, |a| a.0.n))
+}
+
+/// Return a pointer to the row-major entries.
+///
+/// The pointer stays valid until the handle is freed.
+///
+/// # Safety
+///
+/// `a` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn rnla_matrix_data(a: *mut RnlaMatrix) -> *mut f64 {
+    guard(ptr::null_mut(), || match a.as_mut() {
+        Some(a) => a.0.as_mut_slice().as_mut_ptr(),
+        None => ptr::null_mut(),
+    })
+}
+
+/// Return entry (i, j). Out of range indices return NaN.
+///
+/// # Safety
+///
+/// `a` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn rnla_matrix_get(a: *const RnlaMatrix, i: usize, j: usize) -> f64 {
+    guard(f64::NAN, || match a.as_ref() {
+        Some(a) if i < a.0.m && j < a.0.n => a.0.get(i, j),
+        _ => f64::NAN,
+    })
+}
+
+/// Set entry (i, j).
+///
+/// # Safety
+///
+/// `a` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn rnla_matrix_set(a: *mut RnlaMatrix, i: usize, j: usize, value: f64) -> c_int {
+    guard(RNLA_PANIC, || match a.as_mut() {
+        Some(a) if i < a.0.m && j < a.0.n => {
+            a.0.set(i, j, value);
+            RNLA_OK
+        }
+        Some(_) => RNLA_DIM_MISMATCH,
+        None => RNLA_NULL,
+    })
+}
+
+/// Compute c = a * b.
+///
+/// # Safety
+///
+/// All arguments must be valid handles, and `c` must not alias `a` or `b`.
+#[no_mangle]
+pub unsafe extern "C" fn rnla_matmul(a: *const RnlaMatrix, b: *const RnlaMatrix, c: *mut RnlaMatrix) -> c_int {
+    guard(RNLA_PANIC, || {
+        let (Some(a), Some(b), Some(c)) = (a.as_ref(), b.as_ref(), c.as_mut()) else {
+            return RNLA_NULL;
+        };
+        if a.0.m != c.0.m || a.0.n != b.0.m || b.0.n != c.0.n {
+            return RNLA_DIM_MISMATCH;
+        }
+        matmul(&a.0.view(), &b.0.view(), &mut c.0.view_mut());
+        RNLA_OK
+    })
+}
+
+/// LU factor a square matrix in place with partial pivoting.
+///
+/// `piv` receives n pivot indices.
+///
+/// # Safety
+///
+/// `a` must be a valid handle and `piv` must point to n writable entries.
+#[no_mangle]
+pub unsafe extern "C" fn rnla_lu(a: *mut RnlaMatrix, piv: *mut usize) -> c_int {
+    guard(RNLA_PANIC, || {
+        let Some(a) = a.as_mut() else {
+            return RNLA_NULL;
+        };
+        if piv.is_null() {
+            return RNLA_NULL;
+        }
+        if a.0.m != a.0.n {
+            return RNLA_DIM_MISMATCH;
+        }
+        let (n, ld) = (a.0.n, a.0.ld());
+        let piv = core::slice::from_raw_parts_mut(piv, n);
+        if lu_slice(n, ld, a.0.as_mut_slice(), piv) {
+            RNLA_OK
+        } else {
+            RNLA_SINGULAR
+        }
+    })
+}
+
+/// Solve Ax = b in place using the output of `rnla_lu`.
+///
+/// # Safety
+///
+/// `lu` must be a valid handle, and `piv` and `b` must point to n entries.
+#[no_mangle]
+pub unsafe extern "C" fn rnla_lu_solve(lu: *const RnlaMatrix, piv: *const usize, b: *mut f64) -> c_int {
+    guard(RNLA_PANIC, || {
+        let Some(lu) = lu.as_ref() else {
+            return RNLA_NULL;
+        };
+        if piv.is_null() || b.is_null() {
+            return RNLA_NULL;
+        }
+        if lu.0.m != lu.0.n {
+            return RNLA_DIM_MISMATCH;
+        }
+        let n = lu.0.n;
+        let piv = core::slice::from_raw_parts(piv, n);
+        if piv.iter().any(|p| *p >= n) {
+            return RNLA_DIM_MISMATCH;
+        }
+        let b = core::slice::from_raw_parts_mut(b, n);
+        lu_solve_slice(n, lu.0.ld(), lu.0.as_slice(), piv, b);
+        RNLA_OK
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::approx;
+
+    #[test]
+    fn ffi_matmul_lu() {
+        unsafe {
+            let a = rnla_matrix_from_data(2, 2, [2.0, 1.0, 1.0, 3.0].as_ptr());
+            let x = rnla_matrix_from_data(2, 1, [1.0, 2.0].as_ptr());
+            let b = rnla_matrix_new(2, 1);
+            assert_eq!(rnla_matmul(a, x, b), RNLA_OK);
+            assert_eq!(rnla_matmul(x, a, b), RNLA_DIM_MISMATCH);
+
+            let mut piv = [0; 2];
+            let mut rhs = [rnla_matrix_get(b, 0, 0), rnla_matrix_get(b, 1, 0)];
+            assert_eq!(rhs, [4.0, 7.0]);
+            assert_eq!(rnla_lu(a, piv.as_mut_ptr()), RNLA_OK);
+            assert_eq!(rnla_lu_solve(a, piv.as_ptr(), rhs.as_mut_ptr()), RNLA_OK);
+            assert!(approx(rhs[0], 1.0) && approx(rhs[1], 2.0));
+
+            rnla_matrix_free(a);
+            rnla_matrix_free(x);
+            rnla_matrix_free(b);
+
+            let huge = usize::MAX / 2 + 1;
+            assert!(rnla_matrix_from_data(huge, 2, [0.0].as_ptr()).is_null());
+            assert!(rnla_matrix_new(huge, 4).is_null());
+            // Fits in usize but not in an allocation: the panic is caught.
+            assert!(rnla_matrix_new(1 << 40, 1 << 20).is_null());
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Fast Fourier transforms and the circulant and Toeplitz operators built
+//! on them.
+//!
+//! Complex vectors are split into real and imaginary slices, as in
+//! eig::hermitian_eig. The forward transform is
+//! X_k = sum_j x_j exp(-2 pi i jk / n) and the inverse divides by n.
+//! Powers of two use the iterative radix-2 algorithm. Other lengths are
+//! split recursively by their prime factors (mixed radix), costing
+//! O(n sum p) for the factors p, and lengths with a prime factor above 32
+//! go through Bluestein's chirp transform, a convolution of power-of-two
+//! length, so every length costs O(n log n).
+use crate::iterative::LinearOperator;
+use crate::math;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+/// Prime factors up to this are handled by mixed radix.
+const MAX_RADIX: usize = 32;
+
+/// Transform (re, im) in place.
+pub fn fft(re: &mut [f64], im: &mut [f64]) {
+    transform(re, im, -1.0);
+}
+
+/// Inverse transform (re, im) in place, including the 1 / n.
+pub fn ifft(re: &mut [f64], im: &mut [f64]) {
+    transform(re, im, 1.0);
+    let scale = 1.0 / re.len().max(1) as f64;
+    re.iter_mut().chain(im.iter_mut()).for_each(|v| *v *= scale);
+}
+
+/// Return the n / 2 + 1 nonnegative-frequency coefficients of the real
+/// vector x; the rest follow from X_(n-k) = conj(X_k).
+///
+/// For even n the transform packs x into a complex vector of half the
+/// length.
+pub fn rfft(x: &[f64]) -> (Vec<f64>, Vec<f64>) {
+    let n = x.len();
+    if n % 2 == 1 || n < 2 {
+        let (mut re, mut im) = (x.to_vec(), vec![0.0; n]);
+        fft(&mut re, &mut im);
+        re.truncate(n / 2 + 1);
+        im.truncate(n / 2 + 1);
+        return (re, im);
+    }
+    let h = n / 2;
+    let mut zr: Vec<f64> = (0..h).map(|t| x[2 * t]).collect();
+    let mut zi: Vec<f64> = (0..h).map(|t| x[2 * t + 1]).collect();
+    fft(&mut zr, &mut zi);
+    // With E and O the transforms of the even and odd entries,
+    // Z_k = E_k + i O_k and X_k = E_k + w^k O_k.
+    let (mut re, mut im) = (vec![0.0; h + 1], vec![0.0; h + 1]);
+    for k in 0..=h {
+        let (ar, ai) = (zr[k % h], zi[k % h]);
+        let (br, bi) = (zr[(h - k) % h], -zi[(h - k) % h]);
+        let (er, ei) = (0.5 * (ar + br), 0.5 * (ai + bi));
+        let (or, oi) = (0.5 * (ai - bi), -0.5 * (ar - br));
+        let (s, c) = math::sin_cos(-2.0 * PI * k as f64 / n as f64);
+        re[k] = er + c * or - s * oi;
+        im[k] = ei + c * oi + s * or;
+    }
+    (re, im)
+}
+
+/// Return the real vector of length n whose rfft() is (re, im).
+pub fn irfft(re: &[f64], im: &[f64], n: usize) -> Vec<f64> {
+    assert!(re.len() == n / 2 + 1 && im.len() == re.len());
+    let mut fr: Vec<f64> = (0..n).map(|k| if k <= n / 2 { re[k] } else { re[n - k] }).collect();
+    let mut fi: Vec<f64> = (0..n).map(|k| if k <= n / 2 { im[k] } else { -im[n - k] }).collect();
+    ifft(&mut fr, &mut fi);
+    fr
+}
+
+/// Transform with exp(sign 2 pi i jk / n).
+fn transform(re: &mut [f64], im: &mut [f64], sign: f64) {
+    let n = re.len();
+    assert_eq!(im.len(), n);
+    if n <= 1 {
+        return;
+    }
+    if n.is_power_of_two() {
+        radix2(re, im, sign);
+    } else if largest_prime_factor(n) > MAX_RADIX {
+        bluestein(re, im, sign);
+    } else {
+        let (r, i) = mixed_radix(re, im, sign);
+        re.copy_from_slice(&r);
+        im.copy_from_slice(&i);
+    }
+}
+
+fn largest_prime_factor(mut n: usize) -> usize {
+    let mut largest = 1;
+    let mut p = 2;
+    while p * p <= n {
+        while n.is_multiple_of(p) {
+            n /= p;
+            largest = p;
+        }
+        p += 1;
+    }
+    if n > 1 { n } else { largest }
+}
+
+/// Return exp(sign 2 pi i k / n) for k < len.
+fn twiddles(n: usize, len: usize, sign: f64) -> (Vec<f64>, Vec<f64>) {
+    (0..len).map(|k| {
+        let (s, c) = math::sin_cos(sign * 2.0 * PI * k as f64 / n as f64);
+        (c, s)
+    }).unzip()
+}
+
+/// Iterative Cooley-Tukey for n a power of two.
+fn radix2(re: &mut [f64], im: &mut [f64], sign: f64) {
+    let n = re.len();
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let (wr, wi) = twiddles(n, n / 2, sign);
+    let mut len = 2;
+    while len <= n {
+        let step = n / len;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (a, b) = (start + k, start + k + len / 2);
+                let (c, s) = (wr[k * step], wi[k * step]);
+                let (tr, ti) = (c * re[b] - s * im[b], c * im[b] + s * re[b]);
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len *= 2;
+    }
+}
+
+/// Decimation in time by the smallest prime factor p of n = p m:
+/// X_(k + m q) = sum_r w^(r (k + m q)) Y_r(k), with Y_r the transform of
+/// the entries r, r + p, ...
+fn mixed_radix(re: &[f64], im: &[f64], sign: f64) -> (Vec<f64>, Vec<f64>) {
+    let n = re.len();
+    if n == 1 {
+        return (re.to_vec(), im.to_vec());
+    }
+    let p = (2..=n).find(|&p| n.is_multiple_of(p)).unwrap();
+    let m = n / p;
+    let subs: Vec<(Vec<f64>, Vec<f64>)> = (0..p).map(|r| {
+        let sr: Vec<f64> = (0..m).map(|t| re[r + p * t]).collect();
+        let si: Vec<f64> = (0..m).map(|t| im[r + p * t]).collect();
+        if m.is_power_of_two() {
+            let (mut sr, mut si) = (sr, si);
+            transform(&mut sr, &mut si, sign);
+            (sr, si)
+        } else {
+            mixed_radix(&sr, &si, sign)
+        }
+    }).collect();
+    let (wr, wi) = twiddles(n, n, sign);
+    let (mut xr, mut xi) = (vec![0.0; n], vec![0.0; n]);
+    for k in 0..n {
+        let (mut sr, mut si) = (0.0, 0.0);
+        for (r, (yr, yi)) in subs.iter().enumerate() {
+            let w = (r * k) % n;
+            let (a, b) = (yr[k % m], yi[k % m]);
+            sr += wr[w] * a - wi[w] * b;
+            si += wr[w] * b + wi[w] * a;
+        }
+        xr[k] = sr;
+        xi[k] = si;
+    }
+    (xr, xi)
+}
+
+/// Bluestein: jk = (j^2 + k^2 - (k - j)^2) / 2 turns the transform into a
+/// convolution with the chirp exp(-sign pi i m^2 / n), done by power-of-two
+/// transforms of length at least 2n - 1.
+fn bluestein(re: &mut [f64], im: &mut [f64], sign: f64) {
+    let n = re.len();
+    let len = (2 * n - 1).next_power_of_two();
+    // k^2 mod 2n keeps the angle small and accurate.
+    let (cr, ci): (Vec<f64>, Vec<f64>) = (0..n).map(|k| {
+        let (s, c) = math::sin_cos(sign * PI * ((k * k) % (2 * n)) as f64 / n as f64);
+        (c, s)
+    }).unzip();
+    let (mut ar, mut ai) = (vec![0.0; len], vec![0.0; len]);
+    for k in 0..n {
+        ar[k] = re[k] * cr[k] - im[k] * ci[k];
+        ai[k] = re[k] * ci[k] + im[k] * cr[k];
+    }
+    let (mut br, mut bi) = (vec![0.0; len], vec![0.0; len]);
+    for k in 0..n {
+        br[k] = cr[k];
+        bi[k] = -ci[k];
+        if k > 0 {
+            br[len - k] = cr[k];
+            bi[len - k] = -ci[k];
+        }
+    }
+    radix2(&mut ar, &mut ai, -1.0);
+    radix2(&mut br, &mut bi, -1.0);
+    for k in 0..len {
+        let (r, i) = (ar[k] * br[k] - ai[k] * bi[k], ar[k] * bi[k] + ai[k] * br[k]);
+        ar[k] = r;
+        ai[k] = i;
+    }
+    ifft(&mut ar, &mut ai);
+    for k in 0..n {
+        re[k] = ar[k] * cr[k] - ai[k] * ci[k];
+        im[k] = ar[k] * ci[k] + ai[k] * cr[k];
+    }
+}
+
+/// The n x n circulant matrix with first column c, C_ij = c_((i - j) mod n).
+///
+/// The DFT diagonalizes it, so products and solves cost O(n log n).
+pub struct Circulant {
+    n: usize,
+    /// rfft() of c: the eigenvalues for the nonnegative frequencies.
+    eig_re: Vec<f64>,
+    eig_im: Vec<f64>,
+}
+
+impl Circulant {
+    /// Return the circulant matrix with first column c.
+    pub fn new(c: &[f64]) -> Circulant {
+        let (eig_re, eig_im) = rfft(c);
+        Circulant { n: c.len(), eig_re, eig_im }
+    }
+
+    /// Return the solution of C x = b, or None if C is singular.
+    pub fn solve(&self, b: &[f64]) -> Option<Vec<f64>> {
+        assert_eq!(b.len(), self.n);
+        let (mut re, mut im) = rfft(b);
+        let scale = self.eig_re.iter().chain(&self.eig_im).fold(0.0, |m: f64, v| m.max(v.abs()));
+        for k in 0..re.len() {
+            let (lr, li) = (self.eig_re[k], self.eig_im[k]);
+            let d = lr * lr + li * li;
+            if math::sqrt(d) <= f64::EPSILON * self.n as f64 * scale {
+                return None;
+            }
+            let (r, i) = ((re[k] * lr + im[k] * li) / d, (im[k] * lr - re[k] * li) / d);
+            re[k] = r;
+            im[k] = i;
+        }
+        Some(irfft(&re, &im, self.n))
+    }
+}
+
+impl LinearOperator for Circulant {
+    fn nrows(&self) -> usize {
+        self.n
+    }
+
+    fn ncols(&self) -> usize {
+        self.n
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        assert_eq!((x.len(), y.len()), (self.n, self.n));
+        let (mut re, mut im) = rfft(x);
+        for k in 0..re.len() {
+            let (lr, li) = (self.eig_re[k], self.eig_im[k]);
+            let r = re[k] * lr - im[k] * li;
+            im[k] = re[k] * li + im[k] * lr;
+            re[k] = r;
+        }
+        y.copy_from_slice(&irfft(&re, &im, self.n));
+    }
+}
+
+/// The m x n Toeplitz matrix T_ij = t_(i - j) with first column col and
+/// first row row, applied by embedding it in a circulant matrix of
+/// power-of-two size at least m + n - 1.
+pub struct Toeplitz {
+    m: usize,
+    n: usize,
+    circulant: Circulant,
+}
+
+impl Toeplitz {
+    /// Return the Toeplitz matrix with the given first column and row,
+    /// which share their first entry.
+    pub fn new(col: &[f64], row: &[f64]) -> Toeplitz {
+        let (m, n) = (col.len(), row.len());
+        assert!(m > 0 && n > 0 && col[0] == row[0]);
+        let len = (m + n - 1).next_power_of_two();
+        let mut c = vec![0.0; len];
+        c[..m].copy_from_slice(col);
+        for j in 1..n {
+            c[len - j] = row[j];
+        }
+        Toeplitz { m, n, circulant: Circulant::new(&c) }
+    }
+}
+
+impl LinearOperator for Toeplitz {
+    fn nrows(&self) -> usize {
+        self.m
+    }
+
+    fn ncols(&self) -> usize {
+        self.n
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        assert_eq!((x.len(), y.len()), (self.n, self.m));
+        let len = self.circulant.n;
+        let mut padded = vec![0.0; len];
+        padded[..self.n].copy_from_slice(x);
+        let mut out = vec![0.0; len];
+        self.circulant.apply(&padded, &mut out);
+        y.copy_from_slice(&out[..self.m]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dft(re: &[f64], im: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let n = re.len();
+        (0..n).map(|k| {
+            (0..n).fold((0.0, 0.0), |(sr, si), j| {
+                let (s, c) = (-2.0 * PI * ((j * k) % n) as f64 / n as f64).sin_cos();
+                (sr + c * re[j] - s * im[j], si + c * im[j] + s * re[j])
+            })
+        }).unzip()
+    }
+
+    #[test]
+    fn matches_the_dft_for_every_length() {
+        // Radix 2, mixed radix, a prime for Bluestein and one with a large
+        // prime factor.
+        for n in [1, 2, 8, 64, 6, 12, 45, 7, 37, 97, 2 * 41] {
+            let re: Vec<f64> = (0..n).map(|j| (j as f64 * 0.7).sin()).collect();
+            let im: Vec<f64> = (0..n).map(|j| (j as f64 * 1.3).cos()).collect();
+            let (er, ei) = dft(&re, &im);
+            let (mut fr, mut fi) = (re.clone(), im.clone());
+            fft(&mut fr, &mut fi);
+            let err = (0..n).map(|k| (fr[k] - er[k]).abs().max((fi[k] - ei[k]).abs())).fold(0.0, f64::max);
+            assert!(err < 1e-12 * n as f64, "{n}: {err}");
+            ifft(&mut fr, &mut fi);
+            assert!((0..n).all(|k| (fr[k] - re[k]).abs() < 1e-13 && (fi[k] - im[k]).abs() < 1e-13), "{n}");
+
+            let (rr, ri) = rfft(&re);
+            let (er, ei) = dft(&re, &vec![0.0; n]);
+            assert!((0..=n / 2).all(|k| (rr[k] - er[k]).abs() < 1e-12 && (ri[k] - ei[k]).abs() < 1e-12), "{n}");
+            let back = irfft(&rr, &ri, n);
+            assert!(back.iter().zip(&re).all(|(a, b)| (a - b).abs() < 1e-13), "{n}");
+        }
+    }
+
+    #[test]
+    fn circulant_and_toeplitz_products() {
+        let c = [4.0, 1.0, 0.0, 0.5, -1.0];
+        let circ = Circulant::new(&c);
+        let x = [1.0, -2.0, 3.0, 0.5, 2.0];
+        let mut y = [0.0; 5];
+        circ.apply(&x, &mut y);
+        for i in 0..5 {
+            let exact: f64 = (0..5).map(|j| c[(i + 5 - j) % 5] * x[j]).sum();
+            assert!((y[i] - exact).abs() < 1e-13);
+        }
+        let back = circ.solve(&y).unwrap();
+        assert!(back.iter().zip(&x).all(|(a, b)| (a - b).abs() < 1e-13));
+        // All ones has the eigenvalue 0 at every nonzero frequency.
+        assert!(Circulant::new(&[1.0; 4]).solve(&[1.0; 4]).is_none());
+
+        let (col, row) = ([1.0, 2.0, 3.0, 4.0], [1.0, -1.0, 0.5, 5.0, 6.0, -2.0]);
+        let t = Toeplitz::new(&col, &row);
+        let x: Vec<f64> = (0..6).map(|j| j as f64 - 2.0).collect();
+        let mut y = [0.0; 4];
+        t.apply(&x, &mut y);
+        for i in 0..4 {
+            let exact: f64 = (0..6).map(|j| if i >= j { col[i - j] } else { row[j - i] } * x[j]).sum();
+            assert!((y[i] - exact).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Kalman filter predict and update steps.
+//!
+//! The state estimate x has covariance P. A prediction through the model
+//! x := F x + w, w ~ N(0, Q), and an update with a measurement
+//! z = H x + v, v ~ N(0, R), give the usual linear Kalman filter. The
+//! update solves with the Cholesky factor of the innovation covariance
+//! rather than inverting it, and uses the Joseph form for P, which keeps
+//! it symmetric positive semidefinite despite rounding.
+use crate::cholesky::CholFactor;
+use crate::factor::Factor;
+use crate::matfun::product;
+use crate::operations::gemv;
+use crate::{Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// State estimate of a linear Kalman filter.
+pub struct KalmanFilter {
+    /// The mean of the state.
+    pub x: Vec<f64>,
+    /// The n x n covariance of the state.
+    pub p: Matrix,
+}
+
+impl KalmanFilter {
+    /// Start from the estimate x with covariance p.
+    pub fn new(x: Vec<f64>, p: Matrix) -> KalmanFilter {
+        assert!(p.m == x.len() && p.n == x.len());
+        KalmanFilter { x, p }
+    }
+
+    /// Predict through the model with transition f and process noise
+    /// covariance q: x := F x, P := F P F^T + Q.
+    ///
+    /// A known control input is added to x afterwards.
+    pub fn predict(&mut self, f: &Matrix, q: &Matrix) {
+        let n = self.x.len();
+        assert!((f.m, f.n) == (n, n) && (q.m, q.n) == (n, n));
+        let mut x = vec![0.0; n];
+        gemv(1.0, &f.view(), &self.x, 0.0, &mut x);
+        self.x = x;
+        let fpf = product(&product(f, &self.p), &f.transpose());
+        self.p = Matrix::from_vec(n, n, (0..n * n).map(|t| fpf.get(t / n, t % n) + q.get(t / n, t % n)).collect());
+        self.p.symmetrize();
+    }
+
+    /// Update with the measurement z of the model H x with noise
+    /// covariance r, returning the normalized innovation squared
+    /// y^T S^-1 y of the innovation y = z - H x, or None, leaving the
+    /// estimate unchanged, if S = H P H^T + R is not positive definite.
+    ///
+    /// The result has a chi-squared distribution with len(z) degrees of
+    /// freedom when the model is right, so it can gate outliers.
+    pub fn update(&mut self, h: &Matrix, r: &Matrix, z: &[f64]) -> Option<f64> {
+        let (n, m) = (self.x.len(), z.len());
+        assert!((h.m, h.n) == (m, n) && (r.m, r.n) == (m, m));
+        let mut y = z.to_vec();
+        gemv(-1.0, &h.view(), &self.x, 1.0, &mut y);
+        let hp = product(h, &self.p);
+        let hph = product(&hp, &h.transpose());
+        let s = Matrix::from_vec(m, m, (0..m * m).map(|t| hph.get(t / m, t % m) + r.get(t / m, t % m)).collect());
+        let chol = CholFactor::new(&s)?;
+
+        // K^T = S^-1 H P, one column of H P at a time.
+        let mut kt = Matrix::zero(m, n);
+        let mut col = vec![0.0; m];
+        for j in 0..n {
+            (0..m).for_each(|i| col[i] = hp.get(i, j));
+            chol.solve_in_place(&mut col);
+            (0..m).for_each(|i| kt.set(i, j, col[i]));
+        }
+        let k = kt.transpose();
+        let w = chol.solve(&y);
+        let nis = y.iter().zip(&w).map(|(a, b)| a * b).sum();
+        gemv(1.0, &k.view(), &y, 1.0, &mut self.x);
+
+        // Joseph form P := (I - K H) P (I - K H)^T + K R K^T.
+        let kh = product(&k, h);
+        let a = Matrix::from_vec(n, n, (0..n * n).map(|t| if t / n == t % n { 1.0 } else { 0.0 } - kh.get(t / n, t % n)).collect());
+        let apa = product(&product(&a, &self.p), &a.transpose());
+        let krk = product(&product(&k, r), &kt);
+        self.p = Matrix::from_vec(n, n, (0..n * n).map(|t| apa.get(t / n, t % n) + krk.get(t / n, t % n)).collect());
+        self.p.symmetrize();
+        Some(nis)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lu::LuFactor;
+
+    #[test]
+    fn scalar_update() {
+        let mut kf = KalmanFilter::new(vec![0.0], Matrix::from_vec(1, 1, vec![1.0]));
+        let one = Matrix::from_vec(1, 1, vec![1.0]);
+        // Equal prior and measurement variances: the gain is 1/2.
+        let nis = kf.update(&one, &one, &[2.0]).unwrap();
+        assert!((kf.x[0] - 1.0).abs() < 1e-15 && (kf.p.get(0, 0) - 0.5).abs() < 1e-15 && (nis - 2.0).abs() < 1e-15);
+        kf.predict(&Matrix::from_vec(1, 1, vec![2.0]), &one);
+        assert!((kf.x[0] - 2.0).abs() < 1e-15 && (kf.p.get(0, 0) - 3.0).abs() < 1e-15);
+        // S = 3 - 4 is negative.
+        let minus = Matrix::from_vec(1, 1, vec![-4.0]);
+        assert!(kf.update(&one, &minus, &[0.0]).is_none());
+        assert!((kf.x[0] - 2.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn static_filter_matches_least_squares() {
+        // With F = I and Q = 0, filtering the measurements one by one gives
+        // the regularized least squares estimate and its covariance.
+        let (n, steps) = (3, 8);
+        let heights = Matrix::rand_seeded(steps, n, 2);
+        let x0 = vec![0.5, -1.0, 2.0];
+        let p0 = Matrix::from_vec(n, n, vec![4.0, 1.0, 0.0, 1.0, 3.0, 0.5, 0.0, 0.5, 2.0]);
+        let r = 0.25;
+        let mut kf = KalmanFilter::new(x0.clone(), Matrix::from_vec(n, n, p0.as_slice().to_vec()));
+        let mut id = Matrix::zero(n, n);
+        (0..n).for_each(|i| id.set(i, i, 1.0));
+        let zero = Matrix::zero(n, n);
+
+        // Information form: (P0^-1 + sum h h^T / r) x = P0^-1 x0 + sum h z / r.
+        let mut info = LuFactor::new(&p0).unwrap().inverse();
+        let mut rhs = vec![0.0; n];
+        gemv(1.0, &info.view(), &x0, 0.0, &mut rhs);
+        for k in 0..steps {
+            let h = Matrix::from_vec(1, n, (0..n).map(|j| heights.get(k, j)).collect());
+            let z = (k as f64).cos();
+            kf.predict(&id, &zero);
+            kf.update(&h, &Matrix::from_vec(1, 1, vec![r]), &[z]).unwrap();
+            for i in 0..n {
+                rhs[i] += h.get(0, i) * z / r;
+                for j in 0..n {
+                    info.set(i, j, info.get(i, j) + h.get(0, i) * h.get(0, j) / r);
+                }
+            }
+        }
+        let f = LuFactor::new(&info).unwrap();
+        let x = f.solve(&rhs);
+        let p = f.inverse();
+        assert!(x.iter().zip(&kf.x).all(|(a, b)| (a - b).abs() < 1e-12));
+        assert!(p.as_slice().iter().zip(kf.p.as_slice()).all(|(a, b)| (a - b).abs() < 1e-12));
+    }
+}
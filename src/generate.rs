@@ -0,0 +1,137 @@
+//! Random matrices with controlled spectra for testing solvers.
+use crate::math;
+use crate::rng::{Distribution, Rng};
+use crate::{Matrix, MatrixIndex};
+use alloc::vec::Vec;
+
+impl Matrix {
+    /// Return a random n x n orthogonal matrix from the Haar distribution.
+    pub fn rand_orthogonal(n: usize, rng: &mut impl Rng) -> Matrix {
+        // Gram-Schmidt on a Gaussian matrix gives an R factor with a positive
+        // diagonal, which makes Q Haar distributed.
+        let mut q = Matrix::rand_dist(n, n, Distribution::Normal, rng);
+        for j in 0..n {
+            for k in 0..j {
+                let r: f64 = (0..n).map(|i| q.get(i, k) * q.get(i, j)).sum();
+                for i in 0..n {
+                    q.set(i, j, q.get(i, j) - r * q.get(i, k));
+                }
+            }
+            let norm = math::sqrt((0..n).map(|i| q.get(i, j) * q.get(i, j)).sum());
+            for i in 0..n {
+                q.set(i, j, q.get(i, j) / norm);
+            }
+        }
+        q
+    }
+
+    /// Return a random m x n matrix with the given singular values.
+    ///
+    /// `sigma` must hold min(m, n) values. The singular vectors are Haar
+    /// distributed.
+    pub fn rand_with_singular_values(m: usize, n: usize, sigma: &[f64], rng: &mut impl Rng) -> Matrix {
+        assert_eq!(sigma.len(), m.min(n));
+        let u = Matrix::rand_orthogonal(m, rng);
+        let v = Matrix::rand_orthogonal(n, rng);
+        let mut res = Matrix::zero(m, n);
+        for i in 0..m {
+            for j in 0..n {
+                let value = (0..sigma.len()).map(|k| u.get(i, k) * sigma[k] * v.get(j, k)).sum();
+                res.set(i, j, value);
+            }
+        }
+        res
+    }
+
+    /// Return a random m x n matrix with 2-norm condition number `cond`.
+    ///
+    /// The singular values are spaced geometrically from 1 down to 1 / cond.
+    pub fn rand_with_condition(m: usize, n: usize, cond: f64, rng: &mut impl Rng) -> Matrix {
+        Matrix::rand_with_singular_values(m, n, &geometric(m.min(n), cond), rng)
+    }
+
+    /// Return a random n x n symmetric positive definite matrix.
+    pub fn rand_spd(n: usize, rng: &mut impl Rng) -> Matrix {
+        // B B^T + n I, with B Gaussian.
+        let b = Matrix::rand_dist(n, n, Distribution::Normal, rng);
+        let mut res = Matrix::zero(n, n);
+        for i in 0..n {
+            for j in 0..=i {
+                let mut value: f64 = (0..n).map(|k| b.get(i, k) * b.get(j, k)).sum();
+                if i == j {
+                    value += n as f64;
+                }
+                res.set(i, j, value);
+                res.set(j, i, value);
+            }
+        }
+        res
+    }
+
+    /// Return a random n x n SPD matrix with condition number `cond`.
+    ///
+    /// The eigenvalues are spaced geometrically from 1 down to 1 / cond.
+    pub fn rand_spd_with_condition(n: usize, cond: f64, rng: &mut impl Rng) -> Matrix {
+        assert!(cond >= 1.0);
+        let lambda = geometric(n, cond);
+        let q = Matrix::rand_orthogonal(n, rng);
+        let mut res = Matrix::zero(n, n);
+        for i in 0..n {
+            for j in 0..=i {
+                let value = (0..n).map(|k| q.get(i, k) * lambda[k] * q.get(j, k)).sum();
+                res.set(i, j, value);
+                res.set(j, i, value);
+            }
+        }
+        res
+    }
+}
+
+/// Return k values spaced geometrically from 1 down to 1 / cond.
+fn geometric(k: usize, cond: f64) -> Vec<f64> {
+    if k == 1 {
+        return alloc::vec![1.0];
+    }
+    (0..k).map(|i| math::powf(cond, -(i as f64) / (k - 1) as f64)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Lcg;
+    use crate::{approx, matmul};
+
+    #[test]
+    fn rand_orthogonal_is_orthogonal() {
+        let q = Matrix::rand_orthogonal(6, &mut Lcg::new(3));
+        let mut qt = Matrix::zero(6, 6);
+        for i in 0..6 {
+            for j in 0..6 {
+                qt.set(i, j, q.get(j, i));
+            }
+        }
+        let mut p = Matrix::zero(6, 6);
+        matmul(&qt.view(), &q.view(), &mut p.view_mut());
+        for i in 0..6 {
+            for j in 0..6 {
+                assert!(approx(p.get(i, j), if i == j { 1.0 } else { 0.0 }));
+            }
+        }
+    }
+
+    #[test]
+    fn rand_spd_with_condition_spectrum() {
+        let mut rng = Lcg::new(4);
+        let a = Matrix::rand_spd_with_condition(5, 100.0, &mut rng);
+        // The trace is the sum of the eigenvalues.
+        let trace: f64 = (0..5).map(|i| a.get(i, i)).sum();
+        assert!(approx(trace, geometric(5, 100.0).iter().sum()));
+
+        // Symmetric, and x^T A x > 0 for random x.
+        let x = Matrix::rand_dist(5, 1, Distribution::Normal, &mut rng);
+        let mut ax = Matrix::zero(5, 1);
+        matmul(&a.view(), &x.view(), &mut ax.view_mut());
+        assert!((0..5).map(|i| x.get(i, 0) * ax.get(i, 0)).sum::<f64>() > 0.0);
+        assert!((0..5).all(|i| (0..5).all(|j| a.get(i, j) == a.get(j, i))));
+    }
+}
@@ -0,0 +1,232 @@
+//! Givens plane rotations.
+//!
+//! A rotation G = [c s; -s c] acts on a pair of entries (x, y) as
+//! (c x + s y, -s x + c y). Applied to rows i and k of a matrix it is the
+//! product G A in that plane; applied to columns it is A G^T.
+//!
+//! ComplexGivens is the unitary analogue [c s; -conj(s) c] with c real.
+//! There is no complex type, so complex numbers are (re, im) pairs and
+//! complex matrices are pairs of real matrices holding the real and
+//! imaginary parts.
+use crate::math;
+use crate::{Matrix, MatrixIndex};
+
+/// A plane rotation with cosine c and sine s.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Givens {
+    pub c: f64,
+    pub s: f64,
+}
+
+impl Givens {
+    /// Return the rotation taking (a, b) to (r, 0), and r.
+    ///
+    /// r has the sign of a, or is |b| when a is zero; (0, 0) gives the
+    /// identity.
+    pub fn new(a: f64, b: f64) -> (Givens, f64) {
+        if b == 0.0 {
+            return (Givens::identity(), a);
+        }
+        if a == 0.0 {
+            return (Givens { c: 0.0, s: b.signum() }, b.abs());
+        }
+        let r = math::hypot(a, b);
+        let r = if a < 0.0 { -r } else { r };
+        (Givens { c: a / r, s: b / r }, r)
+    }
+
+    /// Return the identity rotation.
+    pub fn identity() -> Givens {
+        Givens { c: 1.0, s: 0.0 }
+    }
+
+    /// Return the Jacobi rotation that diagonalizes the symmetric matrix
+    /// [app apq; apq aqq] as G A G^T, choosing the smaller angle.
+    pub fn jacobi(app: f64, apq: f64, aqq: f64) -> Givens {
+        if apq == 0.0 {
+            return Givens::identity();
+        }
+        let tau = (aqq - app) / (2.0 * apq);
+        let t = if tau >= 0.0 { 1.0 } else { -1.0 } / (tau.abs() + math::sqrt(1.0 + tau * tau));
+        let c = 1.0 / math::sqrt(1.0 + t * t);
+        Givens { c, s: -t * c }
+    }
+
+    /// Return the inverse rotation.
+    pub fn transpose(&self) -> Givens {
+        Givens { c: self.c, s: -self.s }
+    }
+
+    /// Rotate the pair (x, y).
+    #[inline]
+    pub fn rotate(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.c * x + self.s * y, self.c * y - self.s * x)
+    }
+
+    /// Rotate entries i and k of v.
+    pub fn apply(&self, v: &mut [f64], i: usize, k: usize) {
+        (v[i], v[k]) = self.rotate(v[i], v[k]);
+    }
+
+    /// Rotate rows i and k of a, replacing A with G A.
+    pub fn apply_rows(&self, a: &mut Matrix, i: usize, k: usize) {
+        assert!(i != k && i < a.m && k < a.m);
+        let (n, ld) = (a.n, a.ld());
+        let data = a.as_mut_slice();
+        for j in 0..n {
+            (data[i * ld + j], data[k * ld + j]) = self.rotate(data[i * ld + j], data[k * ld + j]);
+        }
+    }
+
+    /// Rotate columns i and k of a, replacing A with A G^T.
+    pub fn apply_cols(&self, a: &mut Matrix, i: usize, k: usize) {
+        assert!(i != k && i < a.n && k < a.n);
+        let (m, ld) = (a.m, a.ld());
+        for row in a.as_mut_slice().chunks_mut(ld).take(m) {
+            self.apply(row, i, k);
+        }
+    }
+}
+
+/// A complex plane rotation [c s; -conj(s) c] with real cosine c and
+/// complex sine s = (re, im), c^2 + |s|^2 = 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ComplexGivens {
+    pub c: f64,
+    pub s: (f64, f64),
+}
+
+impl ComplexGivens {
+    /// Return the rotation taking (a, b) to (r, 0), and r.
+    ///
+    /// r has the phase of a, or is |b| when a is zero.
+    pub fn new(a: (f64, f64), b: (f64, f64)) -> (ComplexGivens, (f64, f64)) {
+        let (abs_a, abs_b) = (math::hypot(a.0, a.1), math::hypot(b.0, b.1));
+        if abs_b == 0.0 {
+            return (ComplexGivens { c: 1.0, s: (0.0, 0.0) }, a);
+        }
+        if abs_a == 0.0 {
+            return (ComplexGivens { c: 0.0, s: (b.0 / abs_b, -b.1 / abs_b) }, (abs_b, 0.0));
+        }
+        let rho = math::hypot(abs_a, abs_b);
+        // s = (a / |a|) conj(b) / rho.
+        let phase = (a.0 / abs_a, a.1 / abs_a);
+        let s = mul(phase, (b.0 / rho, -b.1 / rho));
+        (ComplexGivens { c: abs_a / rho, s }, (phase.0 * rho, phase.1 * rho))
+    }
+
+    /// Return the rotation that diagonalizes the Hermitian matrix
+    /// [app apq; conj(apq) aqq] as G A G^H, choosing the smaller angle.
+    pub fn jacobi(app: f64, apq: (f64, f64), aqq: f64) -> ComplexGivens {
+        // With apq = r e^(i phi), this is the real Jacobi rotation of
+        // [app r; r aqq] with its sine given the phase of apq.
+        let r = math::hypot(apq.0, apq.1);
+        if r == 0.0 {
+            return ComplexGivens { c: 1.0, s: (0.0, 0.0) };
+        }
+        let g = Givens::jacobi(app, r, aqq);
+        ComplexGivens { c: g.c, s: (g.s * apq.0 / r, g.s * apq.1 / r) }
+    }
+
+    /// Rotate the pair (x, y).
+    #[inline]
+    pub fn rotate(&self, x: (f64, f64), y: (f64, f64)) -> ((f64, f64), (f64, f64)) {
+        let (sy, sx) = (mul(self.s, y), mul((self.s.0, -self.s.1), x));
+        ((self.c * x.0 + sy.0, self.c * x.1 + sy.1), (self.c * y.0 - sx.0, self.c * y.1 - sx.1))
+    }
+
+    /// Rotate rows i and k of the complex matrix (re, im), replacing A with
+    /// G A.
+    pub fn apply_rows(&self, re: &mut Matrix, im: &mut Matrix, i: usize, k: usize) {
+        assert!(i != k && i < re.m && k < re.m && (re.m, re.n) == (im.m, im.n));
+        for j in 0..re.n {
+            let (x, y) = self.rotate((re.get(i, j), im.get(i, j)), (re.get(k, j), im.get(k, j)));
+            re.set(i, j, x.0);
+            im.set(i, j, x.1);
+            re.set(k, j, y.0);
+            im.set(k, j, y.1);
+        }
+    }
+
+    /// Rotate columns i and k of the complex matrix (re, im), replacing A
+    /// with A G^H.
+    pub fn apply_cols(&self, re: &mut Matrix, im: &mut Matrix, i: usize, k: usize) {
+        assert!(i != k && i < re.n && k < re.n && (re.m, re.n) == (im.m, im.n));
+        // A G^H acts on each row as the rotation with sine conj(s).
+        let g = ComplexGivens { c: self.c, s: (self.s.0, -self.s.1) };
+        for j in 0..re.m {
+            let (x, y) = g.rotate((re.get(j, i), im.get(j, i)), (re.get(j, k), im.get(j, k)));
+            re.set(j, i, x.0);
+            im.set(j, i, x.1);
+            re.set(j, k, y.0);
+            im.set(j, k, y.1);
+        }
+    }
+}
+
+/// Return the complex product a b.
+pub(crate) fn mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn givens_zeroes_entries() {
+        for (a, b) in [(3.0, 4.0), (-3.0, 4.0), (0.0, -2.0), (5.0, 0.0), (0.0, 0.0)] {
+            let (g, r) = Givens::new(a, b);
+            let (x, y) = g.rotate(a, b);
+            assert!((x - r).abs() < 1e-15 && y.abs() < 1e-15);
+            assert!((g.c * g.c + g.s * g.s - 1.0).abs() < 1e-15);
+        }
+        let (g, _) = Givens::new(1.0, 2.0);
+        let (u, v) = g.rotate(0.3, -0.7);
+        let (x, y) = g.transpose().rotate(u, v);
+        assert!((x - 0.3).abs() < 1e-15 && (y + 0.7).abs() < 1e-15);
+
+        // Reducing a column to a multiple of e_0 with rotations on rows.
+        let mut a = Matrix::rand_seeded(4, 3, 1);
+        let norm = (0..4).map(|i| a.get(i, 0) * a.get(i, 0)).sum::<f64>().sqrt();
+        for k in (1..4).rev() {
+            let (g, _) = Givens::new(a.get(k - 1, 0), a.get(k, 0));
+            g.apply_rows(&mut a, k - 1, k);
+            assert!(a.get(k, 0).abs() < 1e-15);
+        }
+        assert!((a.get(0, 0).abs() - norm).abs() < 1e-14);
+    }
+
+    #[test]
+    fn jacobi_rotation_diagonalizes() {
+        let mut a = Matrix::from_vec(3, 3, vec![4.0, 1.0, 2.0,
+                                                1.0, 3.0, 0.5,
+                                                2.0, 0.5, 1.0]);
+        let g = Givens::jacobi(a.get(0, 0), a.get(0, 2), a.get(2, 2));
+        g.apply_rows(&mut a, 0, 2);
+        g.apply_cols(&mut a, 0, 2);
+        assert!(a.get(0, 2).abs() < 1e-14 && a.get(2, 0).abs() < 1e-14);
+        // The trace is unchanged and the result still symmetric.
+        assert!((a.get(0, 0) + a.get(1, 1) + a.get(2, 2) - 8.0).abs() < 1e-14);
+        assert!((a.get(0, 1) - a.get(1, 0)).abs() < 1e-14);
+    }
+
+    #[test]
+    fn complex_givens_is_unitary() {
+        for (a, b) in [((3.0, -1.0), (2.0, 4.0)), ((0.0, 0.0), (0.0, -2.0)), ((1.0, 1.0), (0.0, 0.0))] {
+            let (g, r) = ComplexGivens::new(a, b);
+            let (x, y) = g.rotate(a, b);
+            assert!((x.0 - r.0).abs() < 1e-15 && (x.1 - r.1).abs() < 1e-15 && y.0.abs() < 1e-15 && y.1.abs() < 1e-15);
+            assert!((g.c * g.c + g.s.0 * g.s.0 + g.s.1 * g.s.1 - 1.0).abs() < 1e-15);
+        }
+        // [2, 1 - i; 1 + i, 3] has eigenvalues 1 and 4.
+        let mut re = Matrix::from_vec(2, 2, vec![2.0, 1.0, 1.0, 3.0]);
+        let mut im = Matrix::from_vec(2, 2, vec![0.0, -1.0, 1.0, 0.0]);
+        let g = ComplexGivens::jacobi(2.0, (1.0, -1.0), 3.0);
+        g.apply_rows(&mut re, &mut im, 0, 1);
+        g.apply_cols(&mut re, &mut im, 0, 1);
+        assert!([re.get(0, 1), im.get(0, 1), re.get(1, 0), im.get(1, 0), im.get(0, 0), im.get(1, 1)].iter().all(|v| v.abs() < 1e-14));
+        assert!((re.get(0, 0) - 1.0).abs() < 1e-14 && (re.get(1, 1) - 4.0).abs() < 1e-14);
+    }
+}
@@ -0,0 +1,440 @@
+//! GPU backend built on wgpu compute shaders.
+//!
+//! Device matrices are stored in single precision, since WGSL has no f64
+//! type. Data moves explicitly between host and device with
+//! `GpuContext::upload` and `GpuContext::download`; sparse matrices are
+//! uploaded in CSR form with `GpuContext::upload_sparse`.
+use crate::{Matrix, SparseMatrix};
+use std::borrow::Cow;
+use std::sync::mpsc;
+
+/// Tile width used by the gemm shader.
+const TILE: u32 = 16;
+
+/// Threads per workgroup used by the elementwise shaders.
+const GROUP_SIZE: u32 = 256;
+
+/// Maximum workgroup count in a single dispatch dimension.
+const MAX_GROUPS: u32 = 65535;
+
+const GEMM_SHADER: &str = r#"
+struct Dims {
+    m: u32,
+    k: u32,
+    n: u32,
+    pad: u32,
+}
+
+@group(0) @binding(0) var<storage, read> a: array<f32>;
+@group(0) @binding(1) var<storage, read> b: array<f32>;
+@group(0) @binding(2) var<storage, read_write> c: array<f32>;
+@group(0) @binding(3) var<uniform> dims: Dims;
+
+const TILE: u32 = 16u;
+var<workgroup> ta: array<array<f32, 16>, 16>;
+var<workgroup> tb: array<array<f32, 16>, 16>;
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>,
+        @builtin(local_invocation_id) lid: vec3<u32>) {
+    let row = gid.y;
+    let col = gid.x;
+    var acc = 0.0;
+    let tiles = (dims.k + TILE - 1u) / TILE;
+    for (var t = 0u; t < tiles; t = t + 1u) {
+        let ac = t * TILE + lid.x;
+        let br = t * TILE + lid.y;
+        if (row < dims.m && ac < dims.k) {
+            ta[lid.y][lid.x] = a[row * dims.k + ac];
+        } else {
+            ta[lid.y][lid.x] = 0.0;
+        }
+        if (br < dims.k && col < dims.n) {
+            tb[lid.y][lid.x] = b[br * dims.n + col];
+        } else {
+            tb[lid.y][lid.x] = 0.0;
+        }
+        workgroupBarrier();
+        for (var i = 0u; i < TILE; i = i + 1u) {
+            acc = acc + ta[lid.y][i] * tb[i][lid.x];
+        }
+        workgroupBarrier();
+    }
+    if (row < dims.m && col < dims.n) {
+        c[row * dims.n + col] = acc;
+    }
+}
+"#;
+
+const AXPY_SHADER: &str = r#"
+struct Params {
+    len: u32,
+    row_len: u32,
+    alpha: f32,
+    pad: u32,
+}
+
+@group(0) @binding(0) var<storage, read> x: array<f32>;
+@group(0) @binding(1) var<storage, read_write> y: array<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i = gid.y * params.row_len + gid.x;
+    if (i < params.len) {
+        y[i] = params.alpha * x[i] + y[i];
+    }
+}
+"#;
+
+const SPMV_SHADER: &str = r#"
+struct Params {
+    rows: u32,
+    row_len: u32,
+    pad0: u32,
+    pad1: u32,
+}
+
+@group(0) @binding(0) var<storage, read> row_ptr: array<u32>;
+@group(0) @binding(1) var<storage, read> col_idx: array<u32>;
+@group(0) @binding(2) var<storage, read> values: array<f32>;
+@group(0) @binding(3) var<storage, read> x: array<f32>;
+@group(0) @binding(4) var<storage, read_write> y: array<f32>;
+@group(0) @binding(5) var<uniform> params: Params;
+
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i = gid.y * params.row_len + gid.x;
+    if (i < params.rows) {
+        var acc = 0.0;
+        for (var k = row_ptr[i]; k < row_ptr[i + 1u]; k = k + 1u) {
+            acc = acc + values[k] * x[col_idx[k]];
+        }
+        y[i] = acc;
+    }
+}
+"#;
+
+/// Matrix stored in a device buffer.
+pub struct GpuMatrix {
+    pub m: usize,
+    pub n: usize,
+    buffer: wgpu::Buffer,
+}
+
+/// CSR sparse matrix stored in device buffers.
+pub struct GpuSparseMatrix {
+    pub m: usize,
+    pub n: usize,
+    row_ptr: wgpu::Buffer,
+    col_idx: wgpu::Buffer,
+    values: wgpu::Buffer,
+}
+
+/// GPU device, queue and compiled kernels.
+pub struct GpuContext {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    gemm: wgpu::ComputePipeline,
+    axpy: wgpu::ComputePipeline,
+    spmv: wgpu::ComputePipeline,
+}
+
+impl GpuContext {
+    /// Open the default GPU, or return None if no adapter is available.
+    ///
+    /// The device is opened with the adapter's own limits rather than the
+    /// portable defaults, whose 128 MiB cap on storage buffers is too
+    /// small for large matrices.
+    pub fn new() -> Option<GpuContext> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let adapter = pollster::block_on(
+            instance.request_adapter(&wgpu::RequestAdapterOptions::default())
+        ).ok()?;
+        let descriptor = wgpu::DeviceDescriptor {
+            required_limits: adapter.limits(),
+            ..Default::default()
+        };
+        let (device, queue) = pollster::block_on(adapter.request_device(&descriptor)).ok()?;
+        let gemm = pipeline(&device, "gemm", GEMM_SHADER);
+        let axpy = pipeline(&device, "axpy", AXPY_SHADER);
+        let spmv = pipeline(&device, "spmv", SPMV_SHADER);
+        Some(GpuContext {
+            device,
+            queue,
+            gemm,
+            axpy,
+            spmv,
+        })
+    }
+
+    /// Allocate a zeroed device matrix.
+    pub fn zero(&self, m: usize, n: usize) -> GpuMatrix {
+        assert!(m * n > 0);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (m * n * std::mem::size_of::<f32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        GpuMatrix {
+            m,
+            n,
+            buffer,
+        }
+    }
+
+    /// Copy a host matrix to the device, rounding to single precision.
+    pub fn upload(&self, a: &Matrix) -> GpuMatrix {
+        let res = self.zero(a.m, a.n);
+        let bytes: Vec<u8> = a.as_slice()
+            .chunks(a.ld())
+            .flat_map(|row| &row[..a.n])
+            .flat_map(|value| (*value as f32).to_ne_bytes())
+            .collect();
+        self.queue.write_buffer(&res.buffer, 0, &bytes);
+        res
+    }
+
+    /// Copy a sparse matrix to the device, rounding to single precision.
+    pub fn upload_sparse(&self, a: &SparseMatrix) -> GpuSparseMatrix {
+        let index_bytes = |v: &[usize]| -> Vec<u8> {
+            v.iter().flat_map(|&i| u32::try_from(i).expect("index too large for the GPU").to_ne_bytes()).collect()
+        };
+        let values: Vec<u8> = a.values().iter().flat_map(|&v| (v as f32).to_ne_bytes()).collect();
+        GpuSparseMatrix {
+            m: a.m,
+            n: a.n,
+            row_ptr: self.storage(&index_bytes(a.row_ptr())),
+            col_idx: self.storage(&index_bytes(a.col_idx())),
+            values: self.storage(&values),
+        }
+    }
+
+    /// Copy a device matrix back to the host.
+    pub fn download(&self, a: &GpuMatrix) -> Matrix {
+        let size = a.buffer.size();
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(&a.buffer, 0, &staging, 0, size);
+        self.queue.submit([encoder.finish()]);
+
+        let slice = staging.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| {
+            let _ = sender.send(res);
+        });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .expect("failed to wait for the GPU");
+        receiver.recv()
+            .expect("map callback dropped")
+            .expect("failed to map buffer");
+
+        let data = {
+            let view = slice.get_mapped_range().expect("failed to read buffer");
+            view.chunks_exact(std::mem::size_of::<f32>())
+                .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()) as f64)
+                .collect()
+        };
+        staging.unmap();
+        Matrix::from_vec(a.m, a.n, data)
+    }
+
+    /// Compute c = a * b on the device.
+    pub fn matmul(&self, a: &GpuMatrix, b: &GpuMatrix, c: &mut GpuMatrix) {
+        assert_eq!(a.m, c.m);
+        assert_eq!(a.n, b.m);
+        assert_eq!(b.n, c.n);
+
+        let dims = [a.m as u32, a.n as u32, b.n as u32, 0];
+        let dims = self.uniform(&dims.map(u32::to_ne_bytes).concat());
+        let groups = (c.n.div_ceil(TILE as usize) as u32, c.m.div_ceil(TILE as usize) as u32);
+        self.dispatch(&self.gemm, &[&a.buffer, &b.buffer, &c.buffer, &dims], groups);
+    }
+
+    /// Compute y = alpha * x + y elementwise on the device.
+    pub fn axpy(&self, alpha: f32, x: &GpuMatrix, y: &mut GpuMatrix) {
+        assert_eq!(x.m, y.m);
+        assert_eq!(x.n, y.n);
+
+        let len = (x.m * x.n) as u32;
+        let (groups, row_len) = groups_1d(len);
+        let params = [len.to_ne_bytes(), row_len.to_ne_bytes(), alpha.to_ne_bytes(), [0; 4]];
+        let params = self.uniform(&params.concat());
+        self.dispatch(&self.axpy, &[&x.buffer, &y.buffer, &params], groups);
+    }
+
+    /// Compute y = a * x on the device, one thread per row, where x and y
+    /// are vectors stored as device matrices of any shape.
+    pub fn spmv(&self, a: &GpuSparseMatrix, x: &GpuMatrix, y: &mut GpuMatrix) {
+        assert_eq!(x.m * x.n, a.n);
+        assert_eq!(y.m * y.n, a.m);
+
+        let rows = a.m as u32;
+        let (groups, row_len) = groups_1d(rows);
+        let params = self.uniform(&[rows, row_len, 0, 0].map(u32::to_ne_bytes).concat());
+        let buffers = [&a.row_ptr, &a.col_idx, &a.values, &x.buffer, &y.buffer, &params];
+        self.dispatch(&self.spmv, &buffers, groups);
+    }
+
+    /// Create a storage buffer holding `bytes`, at least one element long
+    /// since empty bindings are invalid.
+    fn storage(&self, bytes: &[u8]) -> wgpu::Buffer {
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: bytes.len().max(std::mem::size_of::<f32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        self.queue.write_buffer(&buffer, 0, bytes);
+        buffer
+    }
+
+    /// Create a uniform buffer holding `bytes`.
+    fn uniform(&self, bytes: &[u8]) -> wgpu::Buffer {
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: bytes.len() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        self.queue.write_buffer(&buffer, 0, bytes);
+        buffer
+    }
+
+    /// Bind `buffers` in order and run the pipeline over `groups` workgroups.
+    fn dispatch(&self, pipeline: &wgpu::ComputePipeline, buffers: &[&wgpu::Buffer], groups: (u32, u32)) {
+        let entries: Vec<wgpu::BindGroupEntry> = buffers.iter()
+            .enumerate()
+            .map(|(i, buffer)| wgpu::BindGroupEntry {
+                binding: i as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(groups.0, groups.1, 1);
+        }
+        self.queue.submit([encoder.finish()]);
+    }
+}
+
+/// Return the workgroup grid covering `len` threads, and the number of
+/// threads in each row of the grid.
+fn groups_1d(len: u32) -> ((u32, u32), u32) {
+    let groups = len.div_ceil(GROUP_SIZE);
+    let groups = (groups.min(MAX_GROUPS), groups.div_ceil(MAX_GROUPS));
+    (groups, groups.0 * GROUP_SIZE)
+}
+
+/// Compile a compute pipeline with entry point `main`.
+fn pipeline(device: &wgpu::Device, label: &str, source: &str) -> wgpu::ComputePipeline {
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(label),
+        layout: None,
+        module: &module,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{matmul, MatrixIndex};
+
+    /// Open the GPU, or report that the calling test is skipped.
+    fn context() -> Option<GpuContext> {
+        let ctx = GpuContext::new();
+        if ctx.is_none() {
+            eprintln!("skipping GPU test: no adapter available");
+        }
+        ctx
+    }
+
+    #[test]
+    fn gpu_matmul_matches_cpu() {
+        let Some(ctx) = context() else {
+            return;
+        };
+        let x = Matrix::rand(37, 20);
+        let y = Matrix::rand(20, 19);
+        let mut z = Matrix::zero(37, 19);
+        matmul(&x.view(), &y.view(), &mut z.view_mut());
+
+        let gx = ctx.upload(&x);
+        let gy = ctx.upload(&y);
+        let mut gz = ctx.zero(37, 19);
+        ctx.matmul(&gx, &gy, &mut gz);
+        let w = ctx.download(&gz);
+
+        for i in 0..37 {
+            for j in 0..19 {
+                assert!((w.get(i, j) - z.get(i, j)).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn gpu_axpy() {
+        let Some(ctx) = context() else {
+            return;
+        };
+        let x = Matrix::rand(300, 5);
+        let y = Matrix::rand(300, 5);
+        let gx = ctx.upload(&x);
+        let mut gy = ctx.upload(&y);
+        ctx.axpy(2.0, &gx, &mut gy);
+        let w = ctx.download(&gy);
+
+        for i in 0..300 {
+            for j in 0..5 {
+                assert!((w.get(i, j) - (2.0 * x.get(i, j) + y.get(i, j))).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn gpu_spmv_matches_cpu() {
+        let Some(ctx) = context() else {
+            return;
+        };
+        let mut rng = crate::rng::Lcg::new(5);
+        let a = SparseMatrix::rand(300, 120, 0.05, &mut rng);
+        let x: Vec<f64> = (0..120).map(|j| (j as f64 * 0.1).sin()).collect();
+        let mut expected = vec![0.0; 300];
+        a.matvec(&x, &mut expected);
+
+        let ga = ctx.upload_sparse(&a);
+        let gx = ctx.upload(&Matrix::from_vec(120, 1, x));
+        let mut gy = ctx.zero(300, 1);
+        ctx.spmv(&ga, &gx, &mut gy);
+        let w = ctx.download(&gy);
+
+        for i in 0..300 {
+            assert!((w.get(i, 0) - expected[i]).abs() < 1e-4);
+        }
+    }
+}
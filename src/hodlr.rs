@@ -0,0 +1,243 @@
+//! Hierarchically off-diagonal low-rank (HODLR) matrices.
+//!
+//! The matrix is split into 2 x 2 blocks; the off-diagonal blocks are
+//! stored in low-rank form and the diagonal ones are split again, down to
+//! small dense leaves. For kernels of a distance between points ordered so
+//! that nearby indices are nearby points, as in boundary elements, the
+//! off-diagonal ranks stay small and storage and products cost
+//! O(k n log n) instead of n^2. The blocks are compressed by adaptive
+//! cross approximation (Bebendorf), which evaluates only a few of their
+//! rows and columns, followed by an SVD of the small cross factors.
+use crate::iterative::LinearOperator;
+use crate::lowrank::LowRank;
+use crate::operations::gemv;
+use crate::orth::{gram_schmidt, GramSchmidt};
+use crate::svd::svd;
+use crate::{matmul, Matrix, MatrixIndex};
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Return a low-rank approximation of the m x n matrix with entries
+/// entry(i, j) by partially pivoted adaptive cross approximation, which
+/// stops when the Frobenius norm of the latest cross is below tol times
+/// that of the approximation, or at max_rank crosses.
+///
+/// The crosses are recompressed by an SVD, dropping singular values below
+/// tol times the largest. ACA is a heuristic: a block whose entries it
+/// never samples, such as a single large entry away from the pivots, can
+/// be missed, which smooth kernels rule out.
+pub fn aca(m: usize, n: usize, entry: impl Fn(usize, usize) -> f64, tol: f64, max_rank: usize) -> LowRank {
+    let max_rank = max_rank.min(m).min(n);
+    let (mut us, mut vs): (Vec<Vec<f64>>, Vec<Vec<f64>>) = (Vec::new(), Vec::new());
+    let mut used = vec![false; m];
+    let mut norm2 = 0.0;
+    let mut i = 0;
+    while us.len() < max_rank {
+        used[i] = true;
+        let mut row: Vec<f64> = (0..n).map(|j| entry(i, j)).collect();
+        for (u, v) in us.iter().zip(&vs) {
+            row.iter_mut().zip(v).for_each(|(r, vj)| *r -= u[i] * vj);
+        }
+        let j = (0..n).fold(0, |b, j| if row[j].abs() > row[b].abs() { j } else { b });
+        if row[j] == 0.0 {
+            // The row is already reproduced: try another.
+            match (0..m).find(|&r| !used[r]) {
+                Some(r) => i = r,
+                None => break,
+            }
+            continue;
+        }
+        let v: Vec<f64> = row.iter().map(|r| r / row[j]).collect();
+        let mut u: Vec<f64> = (0..m).map(|r| entry(r, j)).collect();
+        for (ul, vl) in us.iter().zip(&vs) {
+            u.iter_mut().zip(ul).for_each(|(c, ur)| *c -= ur * vl[j]);
+        }
+        // ||S + u v^T||_F^2 = ||S||_F^2 + 2 sum (u_l . u)(v_l . v) + |u|^2 |v|^2.
+        let (uu, vv): (f64, f64) = (u.iter().map(|x| x * x).sum(), v.iter().map(|x| x * x).sum());
+        for (ul, vl) in us.iter().zip(&vs) {
+            let uu_l: f64 = ul.iter().zip(&u).map(|(a, b)| a * b).sum();
+            let vv_l: f64 = vl.iter().zip(&v).map(|(a, b)| a * b).sum();
+            norm2 += 2.0 * uu_l * vv_l;
+        }
+        norm2 += uu * vv;
+        let next = (0..m).filter(|&r| !used[r]).fold(None, |b: Option<usize>, r| match b {
+            Some(b) if u[b].abs() >= u[r].abs() => Some(b),
+            _ => Some(r),
+        });
+        us.push(u);
+        vs.push(v);
+        match next {
+            Some(r) if uu * vv > tol * tol * norm2 => i = r,
+            _ => break,
+        }
+    }
+    recompress(m, n, &us, &vs, tol)
+}
+
+/// Return the truncated SVD of sum_l u_l v_l^T: QR factor both sides,
+/// then factor the small k x k core R_u R_v^T.
+fn recompress(m: usize, n: usize, us: &[Vec<f64>], vs: &[Vec<f64>], tol: f64) -> LowRank {
+    let k = us.len();
+    if k == 0 {
+        return LowRank { u: Matrix::zero(m, 0), s: Vec::new(), vt: Matrix::zero(0, n) };
+    }
+    let u = Matrix::from_vec(m, k, (0..m * k).map(|t| us[t % k][t / k]).collect());
+    let v = Matrix::from_vec(n, k, (0..n * k).map(|t| vs[t % k][t / k]).collect());
+    let (qu, ru) = gram_schmidt(&u, GramSchmidt::Classical, f64::EPSILON);
+    let (qv, rv) = gram_schmidt(&v, GramSchmidt::Classical, f64::EPSILON);
+    let mut core = Matrix::zero(k, k);
+    matmul(&ru.view(), &rv.transpose().view(), &mut core.view_mut());
+    let f = svd(&core);
+    let r = f.rank(tol);
+    let mut left = Matrix::zero(m, k);
+    matmul(&qu.view(), &f.u.view(), &mut left.view_mut());
+    let mut right = Matrix::zero(k, n);
+    matmul(&f.vt.view(), &qv.transpose().view(), &mut right.view_mut());
+    LowRank {
+        u: Matrix::from_vec(m, r, (0..m * r).map(|t| left.get(t / r, t % r)).collect()),
+        s: f.s[..r].to_vec(),
+        vt: Matrix::from_vec(r, n, (0..r * n).map(|t| right.get(t / n, t % n)).collect()),
+    }
+}
+
+enum Node {
+    Leaf(Matrix),
+    Split {
+        /// Size of the leading diagonal block.
+        mid: usize,
+        diag: Box<(Node, Node)>,
+        /// The blocks (0, 1) and (1, 0).
+        off: Box<(LowRank, LowRank)>,
+    },
+}
+
+impl Node {
+    fn build(lo: usize, hi: usize, entry: &impl Fn(usize, usize) -> f64, leaf_size: usize, tol: f64) -> Node {
+        let n = hi - lo;
+        if n <= leaf_size {
+            return Node::Leaf(Matrix::from_vec(n, n, (0..n * n).map(|t| entry(lo + t / n, lo + t % n)).collect()));
+        }
+        let mid = n / 2;
+        let split = lo + mid;
+        let upper = aca(mid, hi - split, |i, j| entry(lo + i, split + j), tol, n);
+        let lower = aca(hi - split, mid, |i, j| entry(split + i, lo + j), tol, n);
+        let diag = Box::new((Node::build(lo, split, entry, leaf_size, tol), Node::build(split, hi, entry, leaf_size, tol)));
+        Node::Split { mid, diag, off: Box::new((upper, lower)) }
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        match self {
+            Node::Leaf(a) => gemv(1.0, &a.view(), x, 0.0, y),
+            Node::Split { mid, diag, off } => {
+                let (x1, x2) = x.split_at(*mid);
+                let (y1, y2) = y.split_at_mut(*mid);
+                diag.0.apply(x1, y1);
+                diag.1.apply(x2, y2);
+                let mut t = vec![0.0; y1.len()];
+                off.0.apply(x2, &mut t);
+                y1.iter_mut().zip(&t).for_each(|(y, t)| *y += t);
+                let mut t = vec![0.0; y2.len()];
+                off.1.apply(x1, &mut t);
+                y2.iter_mut().zip(&t).for_each(|(y, t)| *y += t);
+            }
+        }
+    }
+
+    fn storage(&self) -> usize {
+        let factored = |b: &LowRank| b.rank() * (b.u.m + b.vt.n + 1);
+        match self {
+            Node::Leaf(a) => a.m * a.n,
+            Node::Split { diag, off, .. } => diag.0.storage() + diag.1.storage() + factored(&off.0) + factored(&off.1),
+        }
+    }
+
+    fn max_rank(&self) -> usize {
+        match self {
+            Node::Leaf(_) => 0,
+            Node::Split { diag, off, .. } => off.0.rank().max(off.1.rank()).max(diag.0.max_rank()).max(diag.1.max_rank()),
+        }
+    }
+}
+
+/// An n x n HODLR matrix.
+pub struct Hodlr {
+    n: usize,
+    root: Node,
+}
+
+impl Hodlr {
+    /// Build the HODLR approximation of the n x n matrix with entries
+    /// entry(i, j), splitting diagonal blocks until they have at most
+    /// leaf_size rows and compressing off-diagonal blocks to relative
+    /// accuracy about tol.
+    pub fn from_fn(n: usize, entry: impl Fn(usize, usize) -> f64, leaf_size: usize, tol: f64) -> Hodlr {
+        assert!(leaf_size > 0);
+        Hodlr { n, root: Node::build(0, n, &entry, leaf_size, tol) }
+    }
+
+    /// Return the number of stored entries, n^2 for a dense matrix.
+    pub fn storage(&self) -> usize {
+        self.root.storage()
+    }
+
+    /// Return the largest rank of an off-diagonal block.
+    pub fn max_rank(&self) -> usize {
+        self.root.max_rank()
+    }
+}
+
+impl LinearOperator for Hodlr {
+    fn nrows(&self) -> usize {
+        self.n
+    }
+
+    fn ncols(&self) -> usize {
+        self.n
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        assert_eq!((x.len(), y.len()), (self.n, self.n));
+        self.root.apply(x, y);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn aca_recovers_a_low_rank_block() {
+        // Rank 3: sum of three separable terms.
+        let entry = |i: usize, j: usize| {
+            let (x, y) = (i as f64 * 0.1, j as f64 * 0.2);
+            1.0 + x * y + (x * x) * (y * y)
+        };
+        let r = aca(30, 20, entry, 1e-12, 20);
+        assert_eq!(r.rank(), 3);
+        let dense = r.to_dense();
+        assert!((0..600).all(|t| (dense.get(t / 20, t % 20) - entry(t / 20, t % 20)).abs() < 1e-12));
+        // A zero block has rank zero.
+        assert_eq!(aca(5, 4, |_, _| 0.0, 1e-12, 4).rank(), 0);
+    }
+
+    #[test]
+    fn hodlr_product_matches_dense() {
+        // A smooth kernel on points on a line, with a singular-free
+        // diagonal.
+        let n = 256;
+        let point = |i: usize| i as f64 / n as f64;
+        let entry = |i: usize, j: usize| 1.0 / (0.01 + (point(i) - point(j)).abs());
+        let h = Hodlr::from_fn(n, entry, 16, 1e-10);
+        assert!(h.storage() < n * n / 2, "{}", h.storage());
+        assert!(h.max_rank() < 16, "{}", h.max_rank());
+
+        let x: Vec<f64> = (0..n).map(|i| (i as f64 * 0.37).sin()).collect();
+        let mut y = vec![0.0; n];
+        h.apply(&x, &mut y);
+        let exact: Vec<f64> = (0..n).map(|i| (0..n).map(|j| entry(i, j) * x[j]).sum()).collect();
+        let err: f64 = y.iter().zip(&exact).map(|(a, b)| (a - b) * (a - b)).sum();
+        let norm: f64 = exact.iter().map(|b| b * b).sum();
+        assert!(err.sqrt() < 1e-8 * norm.sqrt());
+    }
+}
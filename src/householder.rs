@@ -0,0 +1,312 @@
+//! Householder reflectors and their compact WY accumulation.
+//!
+//! A reflector H = I - beta v v^T is stored as (v, beta) with v[0] = 1.
+//! Several reflectors Q = H_1 H_2 ... H_k are accumulated as
+//! Q = I - V T V^T with T upper triangular, so applying Q is three matrix
+//! products instead of k rank-1 updates.
+//!
+//! The complex reflectors H = I - beta v v^H act on vectors of (re, im)
+//! pairs and on complex matrices held as real and imaginary parts.
+use crate::givens::mul;
+use crate::math;
+use crate::{fma_scale, matmul, Matrix, MatrixIndex, MatrixViewMut};
+use alloc::vec::Vec;
+
+/// Return (v, beta) such that (I - beta v v^T) x = ||x|| e_1.
+///
+/// beta is zero when x is already a nonnegative multiple of e_1.
+pub fn house(x: &[f64]) -> (Vec<f64>, f64) {
+    assert!(!x.is_empty());
+    let mut v = x.to_vec();
+    v[0] = 1.0;
+    let sigma: f64 = x[1..].iter().map(|a| a * a).sum();
+    if sigma == 0.0 {
+        return (v, if x[0] < 0.0 { 2.0 } else { 0.0 });
+    }
+    let mu = math::sqrt(x[0] * x[0] + sigma);
+    // Avoid cancellation in x_0 - mu when x_0 > 0.
+    let v0 = if x[0] <= 0.0 { x[0] - mu } else { -sigma / (x[0] + mu) };
+    let beta = 2.0 * v0 * v0 / (sigma + v0 * v0);
+    v[1..].iter_mut().for_each(|a| *a /= v0);
+    (v, beta)
+}
+
+/// Replace A with (I - beta v v^T) A.
+pub fn apply_house_left(v: &[f64], beta: f64, a: &mut MatrixViewMut) {
+    assert_eq!(v.len(), a.m);
+    if beta == 0.0 {
+        return;
+    }
+    for j in 0..a.n {
+        let w: f64 = (0..a.m).map(|i| v[i] * a.get(i, j)).sum();
+        for i in 0..a.m {
+            a.set(i, j, a.get(i, j) - beta * w * v[i]);
+        }
+    }
+}
+
+/// Replace A with A (I - beta v v^T).
+pub fn apply_house_right(v: &[f64], beta: f64, a: &mut MatrixViewMut) {
+    assert_eq!(v.len(), a.n);
+    if beta == 0.0 {
+        return;
+    }
+    for i in 0..a.m {
+        let w: f64 = (0..a.n).map(|j| a.get(i, j) * v[j]).sum();
+        for j in 0..a.n {
+            a.set(i, j, a.get(i, j) - beta * w * v[j]);
+        }
+    }
+}
+
+/// Return (v, beta, alpha) such that (I - beta v v^H) x = alpha e_1, for
+/// complex x.
+///
+/// H is Hermitian and unitary. alpha is -||x|| times the phase of x_0, so
+/// forming v[0] does not cancel; beta is zero when x is already a multiple
+/// of e_1.
+pub fn complex_house(x: &[(f64, f64)]) -> (Vec<(f64, f64)>, f64, (f64, f64)) {
+    assert!(!x.is_empty());
+    let mut v = x.to_vec();
+    v[0] = (1.0, 0.0);
+    let sigma: f64 = x[1..].iter().map(|a| a.0 * a.0 + a.1 * a.1).sum();
+    if sigma == 0.0 {
+        return (v, 0.0, x[0]);
+    }
+    let abs0 = math::hypot(x[0].0, x[0].1);
+    let phase = if abs0 == 0.0 { (1.0, 0.0) } else { (x[0].0 / abs0, x[0].1 / abs0) };
+    let mu = math::sqrt(abs0 * abs0 + sigma);
+    // v_0 = x_0 - alpha = phase (|x_0| + mu).
+    let v0 = (phase.0 * (abs0 + mu), phase.1 * (abs0 + mu));
+    let r = v0.0 * v0.0 + v0.1 * v0.1;
+    let inv = (v0.0 / r, -v0.1 / r);
+    v[1..].iter_mut().for_each(|a| *a = mul(*a, inv));
+    let beta = 2.0 * r / (r + sigma);
+    (v, beta, (-phase.0 * mu, -phase.1 * mu))
+}
+
+/// Replace the complex matrix A = (re, im) with (I - beta v v^H) A.
+pub fn apply_complex_house_left(v: &[(f64, f64)], beta: f64, re: &mut Matrix, im: &mut Matrix) {
+    assert!(v.len() == re.m && (re.m, re.n) == (im.m, im.n));
+    if beta == 0.0 {
+        return;
+    }
+    for j in 0..re.n {
+        // w = v^H A e_j.
+        let w = (0..re.m).fold((0.0, 0.0), |w, i| {
+            let p = mul((v[i].0, -v[i].1), (re.get(i, j), im.get(i, j)));
+            (w.0 + p.0, w.1 + p.1)
+        });
+        for (i, &vi) in v.iter().enumerate() {
+            let p = mul(vi, w);
+            re.set(i, j, re.get(i, j) - beta * p.0);
+            im.set(i, j, im.get(i, j) - beta * p.1);
+        }
+    }
+}
+
+/// Replace the complex matrix A = (re, im) with A (I - beta v v^H).
+pub fn apply_complex_house_right(v: &[(f64, f64)], beta: f64, re: &mut Matrix, im: &mut Matrix) {
+    assert!(v.len() == re.n && (re.m, re.n) == (im.m, im.n));
+    if beta == 0.0 {
+        return;
+    }
+    for i in 0..re.m {
+        // w = e_i^T A v.
+        let w = (0..re.n).fold((0.0, 0.0), |w, j| {
+            let p = mul((re.get(i, j), im.get(i, j)), v[j]);
+            (w.0 + p.0, w.1 + p.1)
+        });
+        for (j, &vj) in v.iter().enumerate() {
+            let p = mul(w, (vj.0, -vj.1));
+            re.set(i, j, re.get(i, j) - beta * p.0);
+            im.set(i, j, im.get(i, j) - beta * p.1);
+        }
+    }
+}
+
+/// Product of Householder reflectors in compact WY form I - V T V^T.
+pub struct BlockReflector {
+    m: usize,
+    /// V^T, one reflector per row.
+    vt: Matrix,
+    t: Matrix,
+}
+
+impl BlockReflector {
+    /// Return the identity on vectors of length m.
+    pub fn new(m: usize) -> BlockReflector {
+        BlockReflector {
+            m,
+            vt: Matrix::zero(0, m),
+            t: Matrix::zero(0, 0),
+        }
+    }
+
+    /// Return the length of the vectors it acts on.
+    pub fn dim(&self) -> usize {
+        self.m
+    }
+
+    /// Return the number of reflectors.
+    pub fn len(&self) -> usize {
+        self.t.m
+    }
+
+    /// Return whether there are no reflectors.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return V^T, with one reflector per row.
+    pub fn vt(&self) -> &Matrix {
+        &self.vt
+    }
+
+    /// Return the upper triangular factor T.
+    pub fn t(&self) -> &Matrix {
+        &self.t
+    }
+
+    /// Multiply on the right by the reflector (v, beta), with v acting on
+    /// the last v.len() entries.
+    pub fn push(&mut self, v: &[f64], beta: f64) {
+        assert!(v.len() <= self.m);
+        let (k, m) = (self.len(), self.m);
+        let offset = m - v.len();
+        // The new column of T is -beta T V^T v.
+        let vtv: Vec<f64> = (0..k).map(|i| (0..v.len()).map(|j| self.vt.get(i, offset + j) * v[j]).sum()).collect();
+        let z: Vec<f64> = (0..k).map(|i| -beta * (i..k).map(|j| self.t.get(i, j) * vtv[j]).sum::<f64>()).collect();
+
+        let mut vt = core::mem::replace(&mut self.vt, Matrix::zero(0, 0)).to_vec();
+        vt.extend(core::iter::repeat_n(0.0, offset));
+        vt.extend_from_slice(v);
+        self.vt = Matrix::from_vec(k + 1, m, vt);
+        let mut t = Matrix::zero(k + 1, k + 1);
+        for i in 0..k {
+            for j in i..k {
+                t.set(i, j, self.t.get(i, j));
+            }
+            t.set(i, k, z[i]);
+        }
+        t.set(k, k, beta);
+        self.t = t;
+    }
+
+    /// Replace A with Q A, or Q^T A if `transpose` is set.
+    pub fn apply_left(&self, a: &mut MatrixViewMut, transpose: bool) {
+        assert_eq!(a.m, self.m);
+        let k = self.len();
+        if k == 0 {
+            return;
+        }
+        // W = op(T) V^T A, then A -= V W.
+        let mut w = Matrix::zero(k, a.n);
+        matmul(&self.vt.view(), &a.view(), &mut w.view_mut());
+        let (tt, mut tw) = (self.op_t(transpose), Matrix::zero(k, a.n));
+        matmul(&tt.view(), &w.view(), &mut tw.view_mut());
+        let v = self.vt.transpose();
+        fma_scale(-1.0, &v.view(), &tw.view(), 1.0, a);
+    }
+
+    /// Replace A with A Q, or A Q^T if `transpose` is set.
+    pub fn apply_right(&self, a: &mut MatrixViewMut, transpose: bool) {
+        assert_eq!(a.n, self.m);
+        let k = self.len();
+        if k == 0 {
+            return;
+        }
+        // W = A V op(T), then A -= W V^T.
+        let mut w = Matrix::zero(a.m, k);
+        matmul(&a.view(), &self.vt.transpose().view(), &mut w.view_mut());
+        let (tt, mut wt) = (self.op_t(transpose), Matrix::zero(a.m, k));
+        matmul(&w.view(), &tt.view(), &mut wt.view_mut());
+        fma_scale(-1.0, &wt.view(), &self.vt.view(), 1.0, a);
+    }
+
+    /// Return T, or T^T if `transpose` is set.
+    fn op_t(&self, transpose: bool) -> Matrix {
+        if transpose {
+            self.t.transpose()
+        } else {
+            self.t.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn house_reflects_onto_e1() {
+        for x in [vec![3.0, 4.0, 0.0, 12.0], vec![-3.0, 4.0], vec![2.0, 0.0], vec![-2.0, 0.0, 0.0]] {
+            let (v, beta) = house(&x);
+            let norm = x.iter().map(|a| a * a).sum::<f64>().sqrt();
+            let mut a = Matrix::from_vec(x.len(), 1, x.clone());
+            apply_house_left(&v, beta, &mut a.view_mut());
+            assert!((a.get(0, 0) - norm).abs() < 1e-14);
+            assert!((1..x.len()).all(|i| a.get(i, 0).abs() < 1e-14));
+        }
+
+        // On the right, H applied to a row of x^T does the same.
+        let x = [1.0, 2.0, 2.0];
+        let (v, beta) = house(&x);
+        let mut a = Matrix::from_vec(2, 3, vec![1.0, 2.0, 2.0, 0.5, -1.0, 3.0]);
+        apply_house_right(&v, beta, &mut a.submatrix_mut(0..2, 0..3));
+        assert!((a.get(0, 0) - 3.0).abs() < 1e-14 && a.get(0, 1).abs() < 1e-14 && a.get(0, 2).abs() < 1e-14);
+    }
+
+    #[test]
+    fn complex_house_reflects_onto_e1() {
+        for x in [vec![(1.0, 2.0), (0.0, -1.0), (3.0, 0.5)], vec![(0.0, 0.0), (2.0, 1.0)], vec![(2.0, -1.0), (0.0, 0.0)]] {
+            let (v, beta, alpha) = complex_house(&x);
+            let norm = x.iter().map(|a| a.0 * a.0 + a.1 * a.1).sum::<f64>().sqrt();
+            assert!((alpha.0.hypot(alpha.1) - norm).abs() < 1e-14);
+            let n = x.len();
+            let mut re = Matrix::from_vec(n, 1, x.iter().map(|a| a.0).collect());
+            let mut im = Matrix::from_vec(n, 1, x.iter().map(|a| a.1).collect());
+            apply_complex_house_left(&v, beta, &mut re, &mut im);
+            assert!((re.get(0, 0) - alpha.0).abs() < 1e-14 && (im.get(0, 0) - alpha.1).abs() < 1e-14);
+            assert!((1..n).all(|i| re.get(i, 0).abs() < 1e-14 && im.get(i, 0).abs() < 1e-14));
+
+            // x^H H = (H x)^H, since H is Hermitian.
+            let mut re = Matrix::from_vec(1, n, x.iter().map(|a| a.0).collect());
+            let mut im = Matrix::from_vec(1, n, x.iter().map(|a| -a.1).collect());
+            apply_complex_house_right(&v, beta, &mut re, &mut im);
+            assert!((re.get(0, 0) - alpha.0).abs() < 1e-14 && (im.get(0, 0) + alpha.1).abs() < 1e-14);
+            assert!((1..n).all(|j| re.get(0, j).abs() < 1e-14 && im.get(0, j).abs() < 1e-14));
+        }
+    }
+
+    #[test]
+    fn compact_wy_matches_sequential_reflectors() {
+        let (m, n) = (6, 4);
+        let a = Matrix::rand_seeded(m, n, 3);
+        let mut seq = Matrix::from_vec(m, n, a.as_slice().to_vec());
+        let mut block = BlockReflector::new(m);
+        // Householder QR of the first three columns, one reflector at a time.
+        for j in 0..3 {
+            let x: Vec<f64> = (j..m).map(|i| seq.get(i, j)).collect();
+            let (v, beta) = house(&x);
+            apply_house_left(&v, beta, &mut seq.submatrix_mut(j..m, 0..n));
+            block.push(&v, beta);
+        }
+        assert_eq!(block.len(), 3);
+        assert!((1..m).all(|i| seq.get(i, 0).abs() < 1e-14));
+
+        // Q^T A from the block form matches H_3 H_2 H_1 A.
+        let mut wy = Matrix::from_vec(m, n, a.as_slice().to_vec());
+        block.apply_left(&mut wy.view_mut(), true);
+        assert!((0..m * n).all(|k| (wy.get(k / n, k % n) - seq.get(k / n, k % n)).abs() < 1e-13));
+        // And Q undoes it.
+        block.apply_left(&mut wy.view_mut(), false);
+        assert!((0..m * n).all(|k| (wy.get(k / n, k % n) - a.get(k / n, k % n)).abs() < 1e-13));
+
+        // (A^T Q)^T = Q^T A.
+        let mut at = a.transpose();
+        block.apply_right(&mut at.view_mut(), false);
+        assert!((0..m * n).all(|k| (at.get(k % n, k / n) - seq.get(k / n, k % n)).abs() < 1e-13));
+    }
+}
@@ -0,0 +1,334 @@
+//! Interval arithmetic for verified computation.
+//!
+//! An Interval [lo, hi] stands for every real number between its bounds.
+//! Each operation rounds its bounds outward, one ulp past the rounded
+//! result, so the computed interval always contains the exact result for
+//! any real inputs in the operands. That is cruder than switching rounding
+//! modes but needs nothing beyond `core`. IntervalMatrix::solve() uses this
+//! to return rigorous bounds on the solution of a linear system.
+use crate::factor::Factor;
+use crate::lu::LuFactor;
+use crate::{Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+/// A closed interval of reals with f64 bounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+impl Interval {
+    /// Return [lo, hi].
+    pub fn new(lo: f64, hi: f64) -> Interval {
+        assert!(lo <= hi, "empty interval [{}, {}]", lo, hi);
+        Interval { lo, hi }
+    }
+
+    /// Return [x, x].
+    pub fn point(x: f64) -> Interval {
+        Interval { lo: x, hi: x }
+    }
+
+    /// Return the interval with bounds rounded one ulp outward.
+    fn outward(lo: f64, hi: f64) -> Interval {
+        Interval {
+            lo: lo.next_down(),
+            hi: hi.next_up(),
+        }
+    }
+
+    /// Return an f64 near the midpoint.
+    pub fn mid(&self) -> f64 {
+        self.lo + 0.5 * (self.hi - self.lo)
+    }
+
+    /// Return hi - lo, rounded up.
+    pub fn width(&self) -> f64 {
+        (self.hi - self.lo).next_up()
+    }
+
+    /// Return the largest absolute value in the interval.
+    pub fn mag(&self) -> f64 {
+        self.lo.abs().max(self.hi.abs())
+    }
+
+    /// Return whether x lies in the interval.
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    /// Return whether the interval lies strictly inside `other`.
+    pub fn interior_of(&self, other: &Interval) -> bool {
+        other.lo < self.lo && self.hi < other.hi
+    }
+
+    /// Return the smallest interval containing both.
+    pub fn hull(&self, other: &Interval) -> Interval {
+        Interval {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
+
+    /// Return the square root, for an interval of nonnegative reals.
+    pub fn sqrt(&self) -> Interval {
+        assert!(self.lo >= 0.0);
+        let lo = crate::math::sqrt(self.lo).next_down().max(0.0);
+        Interval {
+            lo,
+            hi: crate::math::sqrt(self.hi).next_up(),
+        }
+    }
+}
+
+impl From<f64> for Interval {
+    fn from(x: f64) -> Interval {
+        Interval::point(x)
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Interval {
+        Interval {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+}
+
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, other: Interval) -> Interval {
+        Interval::outward(self.lo + other.lo, self.hi + other.hi)
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, other: Interval) -> Interval {
+        Interval::outward(self.lo - other.hi, self.hi - other.lo)
+    }
+}
+
+impl Mul for Interval {
+    type Output = Interval;
+
+    fn mul(self, other: Interval) -> Interval {
+        let p = [self.lo * other.lo, self.lo * other.hi, self.hi * other.lo, self.hi * other.hi];
+        let lo = p.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+        let hi = p.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+        Interval::outward(lo, hi)
+    }
+}
+
+/// Division panics if the divisor contains zero.
+impl Div for Interval {
+    type Output = Interval;
+
+    fn div(self, other: Interval) -> Interval {
+        assert!(!other.contains(0.0), "division by an interval containing zero");
+        let q = [self.lo / other.lo, self.lo / other.hi, self.hi / other.lo, self.hi / other.hi];
+        let lo = q.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+        let hi = q.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+        Interval::outward(lo, hi)
+    }
+}
+
+/// Row-major matrix of intervals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntervalMatrix {
+    pub m: usize,
+    pub n: usize,
+    data: Vec<Interval>,
+}
+
+impl IntervalMatrix {
+    /// Return the m x n matrix of zeros.
+    pub fn zero(m: usize, n: usize) -> IntervalMatrix {
+        IntervalMatrix {
+            m,
+            n,
+            data: vec![Interval::point(0.0); m * n],
+        }
+    }
+
+    /// Return the matrix of points [a_ij, a_ij].
+    pub fn from_matrix(a: &Matrix) -> IntervalMatrix {
+        IntervalMatrix::from_bounds(a, a)
+    }
+
+    /// Return the matrix of intervals [lo_ij, hi_ij].
+    pub fn from_bounds(lo: &Matrix, hi: &Matrix) -> IntervalMatrix {
+        assert_eq!((lo.m, lo.n), (hi.m, hi.n));
+        let mut res = IntervalMatrix::zero(lo.m, lo.n);
+        for i in 0..lo.m {
+            for j in 0..lo.n {
+                res.set(i, j, Interval::new(lo.get(i, j), hi.get(i, j)));
+            }
+        }
+        res
+    }
+
+    /// Return entry (i, j).
+    pub fn get(&self, i: usize, j: usize) -> Interval {
+        self.data[i * self.n + j]
+    }
+
+    /// Set entry (i, j).
+    pub fn set(&mut self, i: usize, j: usize, v: Interval) {
+        self.data[i * self.n + j] = v;
+    }
+
+    /// Return the matrix of midpoints.
+    pub fn mid(&self) -> Matrix {
+        Matrix::from_vec(self.m, self.n, self.data.iter().map(Interval::mid).collect())
+    }
+
+    /// Return an enclosure of every product of matrices in self and other.
+    pub fn matmul(&self, other: &IntervalMatrix) -> IntervalMatrix {
+        assert_eq!(self.n, other.m);
+        let mut res = IntervalMatrix::zero(self.m, other.n);
+        for i in 0..self.m {
+            for k in 0..self.n {
+                let aik = self.get(i, k);
+                for j in 0..other.n {
+                    res.set(i, j, res.get(i, j) + aik * other.get(k, j));
+                }
+            }
+        }
+        res
+    }
+
+    /// Return an enclosure of A x for every A in self and x in `x`.
+    pub fn matvec(&self, x: &[Interval]) -> Vec<Interval> {
+        assert_eq!(x.len(), self.n);
+        (0..self.m)
+            .map(|i| (0..self.n).fold(Interval::point(0.0), |s, j| s + self.get(i, j) * x[j]))
+            .collect()
+    }
+
+    /// Return an enclosure of the solutions of A x = b for every A in self
+    /// and b in `b`, or None if it could not be verified.
+    ///
+    /// This is Krawczyk's method as in Rump's verifylss: with R an
+    /// approximate inverse of mid(A) and x~ an approximate solution, an
+    /// interval Y with R (b - A x~) + (I - R A) Y inside the interior of Y
+    /// proves A nonsingular and the solution in x~ + Y. It fails when A is
+    /// too ill-conditioned or its intervals too wide.
+    pub fn solve(&self, b: &[Interval]) -> Option<Vec<Interval>> {
+        assert_eq!(self.m, self.n);
+        assert_eq!(b.len(), self.n);
+        let n = self.n;
+        let mid = self.mid();
+        let lu = LuFactor::new(&mid)?;
+        let bm: Vec<f64> = b.iter().map(Interval::mid).collect();
+        let x0 = lu.solve_refined(&mid, &bm);
+        let r = IntervalMatrix::from_matrix(&lu.inverse());
+
+        let x0i: Vec<Interval> = x0.iter().map(|&v| Interval::point(v)).collect();
+        let res: Vec<Interval> = b.iter().zip(self.matvec(&x0i)).map(|(&bi, ax)| bi - ax).collect();
+        let z = r.matvec(&res);
+        let mut c = r.matmul(self);
+        for i in 0..n {
+            for j in 0..n {
+                let id = Interval::point(if i == j { 1.0 } else { 0.0 });
+                c.set(i, j, id - c.get(i, j));
+            }
+        }
+
+        let mut x = z.clone();
+        for _ in 0..10 {
+            // Widen X a little so that the new iterate can fall inside it.
+            let y: Vec<Interval> = x
+                .iter()
+                .map(|v| {
+                    let e = 0.1 * v.width() + f64::MIN_POSITIVE;
+                    Interval::outward(v.lo - e, v.hi + e)
+                })
+                .collect();
+            let cy = c.matvec(&y);
+            x = z.iter().zip(&cy).map(|(&zi, &ci)| zi + ci).collect();
+            if x.iter().zip(&y).all(|(xi, yi)| xi.interior_of(yi)) {
+                return Some(x0i.iter().zip(&x).map(|(&a, &d)| a + d).collect());
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interval_arithmetic_encloses() {
+        let a = Interval::new(1.0, 2.0);
+        let b = Interval::new(-1.0, 3.0);
+        let p = a * b;
+        assert!(p.contains(-2.0) && p.contains(6.0) && p.width() < 8.0 + 1e-14);
+        let q = a / Interval::new(2.0, 4.0);
+        assert!(q.contains(0.25) && q.contains(1.0));
+        // The dependency problem: x - x is not [0, 0].
+        assert!((a - a).contains(-1.0) && (a - a).contains(1.0));
+        assert_eq!(-b, Interval::new(-3.0, 1.0));
+        assert!(Interval::new(4.0, 9.0).sqrt().contains(2.0));
+
+        // Rounding errors make the float sum miss 3/10, but the rounded
+        // out enclosure of the real numbers 1/10 and 2/10 contains it.
+        let tenth = Interval::new(0.1f64.next_down(), 0.1);
+        let fifth = Interval::new(0.2f64.next_down(), 0.2);
+        let s = tenth + fifth;
+        assert_ne!(0.1 + 0.2, 0.3);
+        assert!(s.contains(0.3) && s.width() < 1e-15);
+        assert!(tenth.interior_of(&s.hull(&Interval::new(0.0, 1.0))));
+    }
+
+    #[test]
+    fn verified_solve_encloses_solution() {
+        let n = 6;
+        // Integer data, so that b = A x* is exact.
+        let mut a = Matrix::zero(n, n);
+        for i in 0..n {
+            for j in 0..n {
+                a.set(i, j, ((i * 7 + j * 3) % 5) as f64 - 2.0 + if i == j { 6.0 } else { 0.0 });
+            }
+        }
+        let xs: Vec<f64> = (0..n).map(|i| i as f64 - 2.0).collect();
+        let mut b = vec![0.0; n];
+        crate::operations::gemv(1.0, &a.view(), &xs, 0.0, &mut b);
+        let bi: Vec<Interval> = b.iter().map(|&v| v.into()).collect();
+
+        let ai = IntervalMatrix::from_matrix(&a);
+        let x = ai.solve(&bi).unwrap();
+        assert!(x.iter().zip(&xs).all(|(xi, &v)| xi.contains(v) && xi.width() < 1e-13));
+
+        // The product of point matrices encloses the float product.
+        let aa = ai.matmul(&ai);
+        let mut fa = Matrix::zero(n, n);
+        crate::matmul(&a.view(), &a.view(), &mut fa.view_mut());
+        assert!((0..n).all(|i| (0..n).all(|j| aa.get(i, j).contains(fa.get(i, j)))));
+
+        // With uncertain entries the enclosure covers every system.
+        let mut lo = Matrix::from_vec(n, n, a.as_slice().to_vec());
+        let mut hi = Matrix::from_vec(n, n, a.as_slice().to_vec());
+        for i in 0..n {
+            lo.set(i, i, a.get(i, i) - 1e-8);
+            hi.set(i, i, a.get(i, i) + 1e-8);
+        }
+        let wide = IntervalMatrix::from_bounds(&lo, &hi).solve(&bi).unwrap();
+        assert!(wide.iter().zip(&xs).all(|(xi, &v)| xi.contains(v) && xi.width() < 1e-6));
+        let x_lo = LuFactor::new(&lo).unwrap().solve(&b);
+        assert!(wide.iter().zip(&x_lo).all(|(xi, &v)| xi.contains(v)));
+
+        // A singular matrix cannot be verified.
+        let s = IntervalMatrix::from_matrix(&Matrix::from_vec(2, 2, vec![1.0, 2.0, 2.0, 4.0]));
+        assert!(s.solve(&[1.0.into(), 2.0.into()]).is_none());
+    }
+}
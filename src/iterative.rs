@@ -0,0 +1,737 @@
+//! Shared-memory iterative solvers.
+use crate::cholesky::{cholesky_slice, cholesky_solve_slice};
+use crate::givens::Givens;
+use crate::math;
+use crate::operations::{axpy, dot, gemv, norm2, scale};
+use crate::rng::Rng;
+use crate::solver::{Breakdown, Monitor, SolveResult, StoppingCriterion};
+use crate::spectrum;
+use crate::triangular::solve_upper;
+use crate::{matmul, Matrix, MatrixIndex, SparseMatrix};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Fraction of its norm below which a block vector is treated as linearly
+/// dependent on the earlier ones during orthogonalization.
+const DROP_TOL: f64 = 1.5e-8;
+
+/// Linear operator y = A x, which need not be stored explicitly.
+pub trait LinearOperator {
+    /// Return the number of rows.
+    fn nrows(&self) -> usize;
+
+    /// Return the number of columns.
+    fn ncols(&self) -> usize;
+
+    /// Compute y = A x.
+    fn apply(&self, x: &[f64], y: &mut [f64]);
+
+    /// Compute Y = A X, applying the operator to each column of X.
+    ///
+    /// The default handles one column at a time; implementations should
+    /// override it when a block can be applied more cheaply.
+    fn apply_block(&self, x: &Matrix, y: &mut Matrix) {
+        assert_eq!(x.m, self.ncols());
+        assert_eq!((y.m, y.n), (self.nrows(), x.n));
+        let mut xc = vec![0.0; x.m];
+        let mut yc = vec![0.0; y.m];
+        for j in 0..x.n {
+            for i in 0..x.m {
+                xc[i] = x.get(i, j);
+            }
+            self.apply(&xc, &mut yc);
+            for i in 0..y.m {
+                y.set(i, j, yc[i]);
+            }
+        }
+    }
+}
+
+impl LinearOperator for Matrix {
+    fn nrows(&self) -> usize {
+        self.m
+    }
+
+    fn ncols(&self) -> usize {
+        self.n
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        gemv(1.0, &self.view(), x, 0.0, y);
+    }
+
+    fn apply_block(&self, x: &Matrix, y: &mut Matrix) {
+        matmul(&self.view(), &x.view(), &mut y.view_mut());
+    }
+}
+
+impl LinearOperator for SparseMatrix {
+    fn nrows(&self) -> usize {
+        self.m
+    }
+
+    fn ncols(&self) -> usize {
+        self.n
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        self.matvec(x, y);
+    }
+
+    fn apply_block(&self, x: &Matrix, y: &mut Matrix) {
+        self.matmat(x, y);
+    }
+}
+
+/// Operator that can also apply its transpose.
+pub trait TransposeOperator: LinearOperator {
+    /// Compute y = A^T x.
+    fn apply_transpose(&self, x: &[f64], y: &mut [f64]);
+}
+
+impl TransposeOperator for Matrix {
+    fn apply_transpose(&self, x: &[f64], y: &mut [f64]) {
+        assert_eq!((x.len(), y.len()), (self.m, self.n));
+        y.fill(0.0);
+        for i in 0..self.m {
+            axpy(x[i], &self.as_slice()[i * self.ld()..i * self.ld() + self.n], y);
+        }
+    }
+}
+
+impl TransposeOperator for SparseMatrix {
+    fn apply_transpose(&self, x: &[f64], y: &mut [f64]) {
+        assert_eq!((x.len(), y.len()), (self.m, self.n));
+        y.fill(0.0);
+        for i in 0..self.m {
+            for k in self.row_ptr()[i]..self.row_ptr()[i + 1] {
+                y[self.col_idx()[k]] += self.values()[k] * x[i];
+            }
+        }
+    }
+}
+
+/// Preconditioner z = M^-1 r, approximating the inverse of an operator.
+pub trait Preconditioner {
+    fn apply(&self, r: &[f64], z: &mut [f64]);
+}
+
+/// No preconditioning, z = r.
+#[derive(Clone, Copy, Debug)]
+pub struct Identity;
+
+impl Preconditioner for Identity {
+    fn apply(&self, r: &[f64], z: &mut [f64]) {
+        z.copy_from_slice(r);
+    }
+}
+
+impl<F: Fn(&[f64], &mut [f64])> Preconditioner for F {
+    fn apply(&self, r: &[f64], z: &mut [f64]) {
+        self(r, z);
+    }
+}
+
+/// Polynomial preconditioner from a fixed number of Chebyshev steps.
+///
+/// With the eigenvalues of the SPD operator in (0, lmax], the polynomial is
+/// SPD and works with cg(). It needs no inner products, so applying it
+/// involves no global communication. `lmax` must bound the spectrum from
+/// above; `lmin` may lie above the smallest eigenvalue.
+pub struct ChebyshevPreconditioner<'a, A: LinearOperator> {
+    a: &'a A,
+    lmin: f64,
+    lmax: f64,
+    degree: usize,
+}
+
+impl<'a, A: LinearOperator> ChebyshevPreconditioner<'a, A> {
+    /// Create a polynomial of the given degree in A targeting [lmin, lmax].
+    ///
+    /// Each application costs `degree` products with A.
+    pub fn new(a: &'a A, lmin: f64, lmax: f64, degree: usize) -> ChebyshevPreconditioner<'a, A> {
+        assert!(0.0 < lmin && lmin < lmax);
+        ChebyshevPreconditioner { a, lmin, lmax, degree }
+    }
+
+    /// Create a polynomial of the given degree, estimating the interval
+    /// with `spectrum::chebyshev_interval`.
+    pub fn estimate(a: &'a A, degree: usize, rng: &mut impl Rng) -> ChebyshevPreconditioner<'a, A> {
+        let (lmin, lmax) = spectrum::chebyshev_interval(a, rng);
+        ChebyshevPreconditioner::new(a, lmin, lmax, degree)
+    }
+}
+
+impl<A: LinearOperator> Preconditioner for ChebyshevPreconditioner<'_, A> {
+    fn apply(&self, r: &[f64], z: &mut [f64]) {
+        // Chebyshev iteration for A z = r starting from zero.
+        let (theta, delta) = ((self.lmax + self.lmin) / 2.0, (self.lmax - self.lmin) / 2.0);
+        let mut res = r.to_vec();
+        let mut d = r.to_vec();
+        scale(1.0 / theta, &mut d);
+        let mut ad = vec![0.0; r.len()];
+        let mut rho = delta / theta;
+        z.fill(0.0);
+        for k in 0..=self.degree {
+            axpy(1.0, &d, z);
+            if k == self.degree {
+                break;
+            }
+            self.a.apply(&d, &mut ad);
+            axpy(-1.0, &ad, &mut res);
+            let rho_next = 1.0 / (2.0 * theta / delta - rho);
+            scale(rho_next * rho, &mut d);
+            axpy(2.0 * rho_next / delta, &res, &mut d);
+            rho = rho_next;
+        }
+    }
+}
+
+/// Solve Ax = b for SPD A with preconditioned conjugate gradients.
+///
+/// `x` is the initial guess on entry and `m` must be SPD. `stop` is checked
+/// before every iteration and sees the M^-1-norm of the residual as the
+/// preconditioned residual.
+pub fn cg(
+    a: &impl LinearOperator,
+    b: &[f64],
+    x: &mut [f64],
+    m: &impl Preconditioner,
+    mut stop: impl StoppingCriterion,
+    mut monitor: Option<Monitor>,
+) -> SolveResult {
+    let mut r = vec![0.0; b.len()];
+    let mut ap = vec![0.0; b.len()];
+    residual(a, b, x, &mut r);
+    let mut z = vec![0.0; b.len()];
+    m.apply(&r, &mut z);
+    let mut p = z.clone();
+    let mut rz = dot(&r, &z);
+    let rz0 = rz;
+    let b_norm = norm2(b);
+    let mut res = SolveResult::new(norm2(&r));
+
+    while res.check(&mut stop, norm2(&r), b_norm, Some((math::sqrt(rz), math::sqrt(rz0)))) {
+        a.apply(&p, &mut ap);
+        let pap = dot(&p, &ap);
+        if pap <= 0.0 {
+            res.breakdown = Some(Breakdown::NotPositiveDefinite);
+            break;
+        }
+        let alpha = rz / pap;
+        axpy(alpha, &p, x);
+        axpy(-alpha, &ap, &mut r);
+        if !res.record(norm2(&r), &mut monitor) {
+            break;
+        }
+        m.apply(&r, &mut z);
+        let rz_next = dot(&r, &z);
+        if rz_next < 0.0 {
+            res.breakdown = Some(Breakdown::NotPositiveDefinite);
+            break;
+        }
+        scale(rz_next / rz, &mut p);
+        axpy(1.0, &z, &mut p);
+        rz = rz_next;
+    }
+    res
+}
+
+/// Solve Ax = b for SPD A with Chebyshev semi-iteration.
+///
+/// The eigenvalues of A must lie in [lmin, lmax]. Unlike CG, the iteration
+/// itself needs no inner products; only `stop` uses the residual norm.
+pub fn chebyshev(
+    a: &impl LinearOperator,
+    b: &[f64],
+    x: &mut [f64],
+    lmin: f64,
+    lmax: f64,
+    mut stop: impl StoppingCriterion,
+    mut monitor: Option<Monitor>,
+) -> SolveResult {
+    assert!(0.0 < lmin && lmin < lmax);
+    let (theta, delta) = ((lmax + lmin) / 2.0, (lmax - lmin) / 2.0);
+    let mut r = vec![0.0; b.len()];
+    residual(a, b, x, &mut r);
+    let mut d = r.clone();
+    scale(1.0 / theta, &mut d);
+    let mut ad = vec![0.0; b.len()];
+    let mut rho = delta / theta;
+    let b_norm = norm2(b);
+    let mut res = SolveResult::new(norm2(&r));
+
+    while res.check(&mut stop, norm2(&r), b_norm, None) {
+        axpy(1.0, &d, x);
+        a.apply(&d, &mut ad);
+        axpy(-1.0, &ad, &mut r);
+        if !res.record(norm2(&r), &mut monitor) {
+            break;
+        }
+        let rho_next = 1.0 / (2.0 * theta / delta - rho);
+        scale(rho_next * rho, &mut d);
+        axpy(2.0 * rho_next / delta, &r, &mut d);
+        rho = rho_next;
+    }
+    res
+}
+
+/// Compute r = b - A x.
+fn residual(a: &impl LinearOperator, b: &[f64], x: &[f64], r: &mut [f64]) {
+    a.apply(x, r);
+    scale(-1.0, r);
+    axpy(1.0, b, r);
+}
+
+/// Solve A X = B for SPD A and a block of right-hand sides with block
+/// conjugate gradients.
+///
+/// `x` holds the initial guesses on entry. All columns share one search
+/// space, so A is applied to a block of vectors per iteration and fewer
+/// iterations are needed than for separate solves. Search directions that
+/// become linearly dependent are dropped, and each column leaves the block
+/// once `stop` stops it. `monitor` sees the largest residual of the running
+/// columns each iteration. Returns one result per column.
+pub fn block_cg(
+    a: &impl LinearOperator,
+    b: &Matrix,
+    x: &mut Matrix,
+    mut stop: impl StoppingCriterion,
+    mut monitor: Option<Monitor>,
+) -> Vec<SolveResult> {
+    assert_eq!(a.nrows(), a.ncols());
+    assert_eq!((b.m, x.m, x.n), (a.nrows(), a.ncols(), b.n));
+    let n = b.m;
+    let mut cols: Vec<usize> = (0..b.n).collect();
+    let mut xa = gather(x, &cols);
+    let mut ra = block_residual(a, b, &xa, &cols);
+    let b_norm: Vec<f64> = (0..b.n).map(|j| col_norm(b, j)).collect();
+    let mut res: Vec<SolveResult> = (0..b.n).map(|j| SolveResult::new(col_norm(&ra, j))).collect();
+    // Search directions P (orthonormal), Q = A P and the factored P^T A P.
+    let mut p = Matrix::zero(n, 0);
+    let mut q = Matrix::zero(n, 0);
+    let mut g = Vec::new();
+    let mut iteration = 0;
+
+    loop {
+        let keep: Vec<usize> = (0..cols.len())
+            .filter(|&t| res[cols[t]].check(&mut stop, col_norm(&ra, t), b_norm[cols[t]], None))
+            .collect();
+        if keep.len() < cols.len() {
+            scatter(x, &cols, &xa);
+            xa = gather(&xa, &keep);
+            ra = gather(&ra, &keep);
+            cols = keep.iter().map(|&t| cols[t]).collect();
+        }
+        if cols.is_empty() {
+            break;
+        }
+
+        // New directions R + P beta, A-conjugate to the previous ones.
+        let mut z = gather(&ra, &(0..cols.len()).collect::<Vec<_>>());
+        if p.n > 0 {
+            let mut beta = gram(&q, &ra);
+            cholesky_solve_slice(p.n, p.n, &g, cols.len(), &mut beta);
+            beta.iter_mut().for_each(|v| *v = -*v);
+            update(&mut z, &p, &beta);
+        }
+        let r = orthonormalize(&mut z);
+        let kept: Vec<usize> = (0..z.n).filter(|&j| r[j * z.n + j] != 0.0).collect();
+        p = gather(&z, &kept);
+        if p.n == 0 {
+            break;
+        }
+        q = Matrix::zero(n, p.n);
+        a.apply_block(&p, &mut q);
+        g = gram(&p, &q);
+        if !cholesky_slice(p.n, p.n, &mut g) {
+            for &j in &cols {
+                res[j].breakdown = Some(Breakdown::NotPositiveDefinite);
+            }
+            break;
+        }
+
+        let mut alpha = gram(&p, &ra);
+        cholesky_solve_slice(p.n, p.n, &g, cols.len(), &mut alpha);
+        update(&mut xa, &p, &alpha);
+        alpha.iter_mut().for_each(|v| *v = -*v);
+        update(&mut ra, &q, &alpha);
+        iteration += 1;
+        let mut finite = true;
+        let mut worst = 0.0f64;
+        for (t, &j) in cols.iter().enumerate() {
+            let r_norm = col_norm(&ra, t);
+            finite &= res[j].record(r_norm, &mut None);
+            worst = worst.max(r_norm);
+        }
+        if let Some(f) = &mut monitor {
+            f(iteration, worst);
+        }
+        if !finite {
+            break;
+        }
+    }
+    scatter(x, &cols, &xa);
+    res
+}
+
+/// Solve A X = B for a block of right-hand sides with restarted block GMRES.
+///
+/// `x` holds the initial guesses on entry. Each cycle builds a block Krylov
+/// space of up to `restart` blocks from the residuals of the columns still
+/// running. The recorded residuals are the least squares estimates; `stop`
+/// sees these during a cycle and the true residuals at each restart.
+/// `monitor` sees the largest estimate of the running columns each
+/// iteration. Returns one result per column.
+pub fn block_gmres(
+    a: &impl LinearOperator,
+    b: &Matrix,
+    x: &mut Matrix,
+    restart: usize,
+    mut stop: impl StoppingCriterion,
+    mut monitor: Option<Monitor>,
+) -> Vec<SolveResult> {
+    assert!(restart > 0);
+    assert_eq!(a.nrows(), a.ncols());
+    assert_eq!((b.m, x.m, x.n), (a.nrows(), a.ncols(), b.n));
+    let n = b.m;
+    let mut cols: Vec<usize> = (0..b.n).collect();
+    let mut r = block_residual(a, b, x, &cols);
+    let b_norm: Vec<f64> = (0..b.n).map(|j| col_norm(b, j)).collect();
+    let mut res: Vec<SolveResult> = (0..b.n).map(|j| SolveResult::new(col_norm(&r, j))).collect();
+    let mut iteration = 0;
+
+    loop {
+        let keep: Vec<usize> = (0..cols.len())
+            .filter(|&t| {
+                let j = cols[t];
+                res[j].breakdown.is_none() && res[j].check(&mut stop, col_norm(&r, t), b_norm[j], None)
+            })
+            .collect();
+        r = gather(&r, &keep);
+        cols = keep.iter().map(|&t| cols[t]).collect();
+        if cols.is_empty() {
+            break;
+        }
+
+        // Block Arnoldi with the band Hessenberg matrix reduced to
+        // triangular form by Givens rotations as it grows.
+        let w = cols.len();
+        let s = orthonormalize(&mut r);
+        let mut v = vec![r];
+        let mut h = vec![vec![0.0; restart * w]; (restart + 1) * w];
+        let mut g = vec![vec![0.0; w]; (restart + 1) * w];
+        for i in 0..w {
+            g[i].copy_from_slice(&s[i * w..(i + 1) * w]);
+        }
+        let mut rotations: Vec<(usize, usize, Givens)> = Vec::new();
+        let mut live = vec![true; w];
+        let mut k = 0;
+        while k < restart && live.contains(&true) {
+            let mut z = Matrix::zero(n, w);
+            a.apply_block(&v[k], &mut z);
+            for j in 0..=k {
+                let mut hjk = gram(&v[j], &z);
+                for i in 0..w {
+                    h[j * w + i][k * w..(k + 1) * w].copy_from_slice(&hjk[i * w..(i + 1) * w]);
+                }
+                hjk.iter_mut().for_each(|v| *v = -*v);
+                update(&mut z, &v[j], &hjk);
+            }
+            let sub = orthonormalize(&mut z);
+            for i in 0..w {
+                h[(k + 1) * w + i][k * w..(k + 1) * w].copy_from_slice(&sub[i * w..(i + 1) * w]);
+            }
+            v.push(z);
+
+            let block = k * w..(k + 1) * w;
+            for &(i, l, rot) in &rotations {
+                rotate(&mut h, i, l, rot, block.clone());
+            }
+            for c in block.clone() {
+                for l in c + 1..=c + w {
+                    let (hc, hl) = (h[c][c], h[l][c]);
+                    if hl == 0.0 {
+                        continue;
+                    }
+                    let (rot, _) = Givens::new(hc, hl);
+                    rotate(&mut h, c, l, rot, c..block.end);
+                    rotate(&mut g, c, l, rot, 0..w);
+                    rotations.push((c, l, rot));
+                }
+            }
+            k += 1;
+
+            iteration += 1;
+
+            let mut worst = 0.0f64;
+            for (t, &j) in cols.iter().enumerate() {
+                if live[t] {
+                    let est = math::sqrt((k * w..(k + 1) * w).map(|i| g[i][t] * g[i][t]).sum());
+                    live[t] = res[j].record(est, &mut None) && res[j].check(&mut stop, est, b_norm[j], None);
+                    worst = worst.max(est);
+                }
+            }
+            if let Some(f) = &mut monitor {
+                f(iteration, worst);
+            }
+        }
+
+        // Back substitution, with a unit row for each direction that was
+        // dropped so that its coefficient is zero.
+        let m = k * w;
+        let dropped = |i: usize| h[i][i] == 0.0;
+        let mut hm = Matrix::zero(m, m);
+        for i in 0..m {
+            if dropped(i) {
+                hm.set(i, i, 1.0);
+            } else {
+                (i..m).for_each(|j| hm.set(i, j, h[i][j]));
+            }
+        }
+        let mut y = vec![0.0; m * w];
+        let mut col = vec![0.0; m];
+        for c in 0..w {
+            (0..m).for_each(|i| col[i] = if dropped(i) { 0.0 } else { g[i][c] });
+            solve_upper(&hm, &mut col);
+            (0..m).for_each(|i| y[i * w + c] = col[i]);
+        }
+        let mut xa = gather(x, &cols);
+        for j in 0..k {
+            update(&mut xa, &v[j], &y[j * w * w..(j + 1) * w * w]);
+        }
+        scatter(x, &cols, &xa);
+        r = block_residual(a, b, &xa, &cols);
+    }
+    res
+}
+
+/// Return B[:, cols] - A X, where X holds the matching columns of the
+/// solution.
+fn block_residual(a: &impl LinearOperator, b: &Matrix, x: &Matrix, cols: &[usize]) -> Matrix {
+    let mut res = Matrix::zero(b.m, cols.len());
+    a.apply_block(x, &mut res);
+    for i in 0..b.m {
+        for (t, &j) in cols.iter().enumerate() {
+            res.set(i, t, b.get(i, j) - res.get(i, t));
+        }
+    }
+    res
+}
+
+/// Return the columns `cols` of x.
+pub(crate) fn gather(x: &Matrix, cols: &[usize]) -> Matrix {
+    let mut res = Matrix::zero(x.m, cols.len());
+    for i in 0..x.m {
+        for (t, &j) in cols.iter().enumerate() {
+            res.set(i, t, x.get(i, j));
+        }
+    }
+    res
+}
+
+/// Copy the columns of xa into the columns `cols` of x.
+fn scatter(x: &mut Matrix, cols: &[usize], xa: &Matrix) {
+    for i in 0..x.m {
+        for (t, &j) in cols.iter().enumerate() {
+            x.set(i, j, xa.get(i, t));
+        }
+    }
+}
+
+/// Return the 2-norm of column j.
+fn col_norm(x: &Matrix, j: usize) -> f64 {
+    math::sqrt((0..x.m).map(|i| x.get(i, j) * x.get(i, j)).sum())
+}
+
+/// Return X^T Y as a row-major x.n x y.n array.
+pub(crate) fn gram(x: &Matrix, y: &Matrix) -> Vec<f64> {
+    assert_eq!(x.m, y.m);
+    let mut res = vec![0.0; x.n * y.n];
+    let (xs, ys) = (x.as_slice(), y.as_slice());
+    for i in 0..x.m {
+        let yrow = &ys[i * y.ld()..i * y.ld() + y.n];
+        for (k, xv) in xs[i * x.ld()..i * x.ld() + x.n].iter().enumerate() {
+            for (r, yv) in res[k * y.n..(k + 1) * y.n].iter_mut().zip(yrow) {
+                *r += xv * yv;
+            }
+        }
+    }
+    res
+}
+
+/// Compute Y += X C for a row-major x.n x y.n array C.
+pub(crate) fn update(y: &mut Matrix, x: &Matrix, c: &[f64]) {
+    assert_eq!(x.m, y.m);
+    assert_eq!(c.len(), x.n * y.n);
+    let (n, yld) = (y.n, y.ld());
+    let ys = y.as_mut_slice();
+    let xs = x.as_slice();
+    for i in 0..x.m {
+        let yrow = &mut ys[i * yld..i * yld + n];
+        for (k, xv) in xs[i * x.ld()..i * x.ld() + x.n].iter().enumerate() {
+            for (yv, cv) in yrow.iter_mut().zip(&c[k * n..(k + 1) * n]) {
+                *yv += xv * cv;
+            }
+        }
+    }
+}
+
+/// Orthonormalize the columns of z in place with modified Gram-Schmidt and
+/// return the row-major upper triangular R with Z = Q R.
+///
+/// Columns that are numerically dependent on the earlier ones are zeroed,
+/// with a zero diagonal entry in R.
+pub(crate) fn orthonormalize(z: &mut Matrix) -> Vec<f64> {
+    let (m, k) = (z.m, z.n);
+    let mut r = vec![0.0; k * k];
+    for j in 0..k {
+        let norm0 = col_norm(z, j);
+        for i in 0..j {
+            if r[i * k + i] == 0.0 {
+                continue;
+            }
+            let rij: f64 = (0..m).map(|l| z.get(l, i) * z.get(l, j)).sum();
+            r[i * k + j] = rij;
+            for l in 0..m {
+                z.set(l, j, z.get(l, j) - rij * z.get(l, i));
+            }
+        }
+        let norm = col_norm(z, j);
+        let keep = norm > DROP_TOL * norm0;
+        r[j * k + j] = if keep { norm } else { 0.0 };
+        let factor = if keep { 1.0 / norm } else { 0.0 };
+        for l in 0..m {
+            z.set(l, j, factor * z.get(l, j));
+        }
+    }
+    r
+}
+
+/// Apply the rotation to rows i and l of a, over `range`.
+fn rotate(a: &mut [Vec<f64>], i: usize, l: usize, rot: Givens, range: core::ops::Range<usize>) {
+    for c in range {
+        (a[i][c], a[l][c]) = rot.rotate(a[i][c], a[l][c]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Lcg;
+    use crate::solver::{tolerance, MaxIterations, PreconditionedResidual};
+
+    /// Return the n x n tridiagonal matrix with `diag` on the diagonal and
+    /// -1 beside it.
+    fn tridiag(n: usize, diag: f64) -> SparseMatrix {
+        let mut triplets = Vec::new();
+        for i in 0..n {
+            triplets.push((i, i, diag));
+            if i > 0 {
+                triplets.push((i, i - 1, -1.0));
+                triplets.push((i - 1, i, -1.0));
+            }
+        }
+        SparseMatrix::from_triplets(n, n, &triplets)
+    }
+
+    /// Return the largest relative residual over the columns of X.
+    fn max_rel_residual(a: &impl LinearOperator, b: &Matrix, x: &Matrix) -> f64 {
+        let cols: Vec<usize> = (0..b.n).collect();
+        let r = block_residual(a, b, x, &cols);
+        cols.iter().map(|&j| col_norm(&r, j) / col_norm(b, j)).fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn chebyshev_solver_and_preconditioner() {
+        // The eigenvalues of tridiag(2.1) lie in (0.1, 4.1).
+        let a = tridiag(100, 2.1);
+        let b: Vec<f64> = (0..100).map(|i| (i as f64).sin()).collect();
+        let mut x = vec![0.0; 100];
+        let res = chebyshev(&a, &b, &mut x, 0.1, 4.1, tolerance(1e-10, 500), None);
+        assert!(res.converged);
+        let mut r = vec![0.0; 100];
+        residual(&a, &b, &x, &mut r);
+        assert!(norm2(&r) <= 1e-10 * norm2(&b));
+
+        let mut x = vec![0.0; 100];
+        let plain = cg(&a, &b, &mut x, &Identity, tolerance(1e-10, 500), None);
+        let mut x = vec![0.0; 100];
+        let m = ChebyshevPreconditioner::new(&a, 0.1, 4.1, 4);
+        let res = cg(&a, &b, &mut x, &m, tolerance(1e-10, 500), None);
+        assert!(plain.converged && res.converged);
+        assert!(res.iterations * 3 < plain.iterations);
+        residual(&a, &b, &x, &mut r);
+        assert!(norm2(&r) <= 1e-10 * norm2(&b));
+
+        let mut x = vec![0.0; 100];
+        let m = ChebyshevPreconditioner::estimate(&a, 4, &mut Lcg::new(6));
+        assert!(cg(&a, &b, &mut x, &m, tolerance(1e-10, 500), None).converged);
+    }
+
+    #[test]
+    fn cg_with_closure_preconditioner() {
+        let a = SparseMatrix::from_triplets(3, 3, &[(0, 0, 4.0), (1, 1, 100.0), (2, 2, 0.5), (0, 1, 1.0), (1, 0, 1.0)]);
+        let diag = [4.0, 100.0, 0.5];
+        let jacobi = |r: &[f64], z: &mut [f64]| {
+            for i in 0..3 {
+                z[i] = r[i] / diag[i];
+            }
+        };
+        let mut x = vec![0.0; 3];
+        let res = cg(&a, &[5.0, 101.0, 0.5], &mut x, &jacobi, PreconditionedResidual(1e-12).or(MaxIterations(10)), None);
+        assert!(res.converged);
+        assert!(x.iter().all(|xi| (xi - 1.0).abs() < 1e-10));
+
+        let neg = Matrix::from_vec(1, 1, vec![-1.0]);
+        let res = cg(&neg, &[1.0], &mut [0.0], &Identity, tolerance(1e-10, 10), None);
+        assert_eq!(res.breakdown, Some(Breakdown::NotPositiveDefinite));
+    }
+
+    #[test]
+    fn block_cg_shares_search_space() {
+        let a = tridiag(60, 2.1);
+        // The last right-hand side repeats the first, so the block becomes
+        // rank deficient.
+        let mut b = Matrix::rand_seeded(60, 4, 1);
+        for i in 0..60 {
+            b.set(i, 3, b.get(i, 0));
+        }
+        let mut x = Matrix::zero(60, 4);
+        let mut seen = Vec::new();
+        let mut log = |k: usize, r: f64| seen.push((k, r));
+        let res = block_cg(&a, &b, &mut x, tolerance(1e-10, 200), Some(&mut log));
+        assert!(res.iter().all(|r| r.converged && r.breakdown.is_none()));
+        assert_eq!(seen.len(), res.iter().map(|r| r.iterations).max().unwrap());
+        assert!(max_rel_residual(&a, &b, &x) < 1e-9);
+
+        let b1 = gather(&b, &[1]);
+        let mut x1 = Matrix::zero(60, 1);
+        let single = block_cg(&a, &b1, &mut x1, tolerance(1e-10, 200), None);
+        assert!(single[0].converged);
+        assert!(res[1].iterations < single[0].iterations);
+
+        let neg = Matrix::from_vec(2, 2, vec![-1.0, 0.0, 0.0, -2.0]);
+        let res = block_cg(&neg, &Matrix::rand_seeded(2, 2, 2), &mut Matrix::zero(2, 2), tolerance(1e-10, 10), None);
+        assert_eq!(res[0].breakdown, Some(Breakdown::NotPositiveDefinite));
+    }
+
+    #[test]
+    fn block_gmres_nonsymmetric() {
+        let a = SparseMatrix::rand_diag_dominant(80, 0.1, &mut Lcg::new(2));
+        let b = Matrix::rand_seeded(80, 3, 3);
+        let mut x = Matrix::zero(80, 3);
+        let mut seen = 0;
+        let mut count = |_: usize, _: f64| seen += 1;
+        let res = block_gmres(&a, &b, &mut x, 4, tolerance(1e-10, 300), Some(&mut count));
+        assert!(res.iter().all(|r| r.converged));
+        assert_eq!(seen, res.iter().map(|r| r.iterations).max().unwrap());
+        assert!(max_rel_residual(&a, &b, &x) < 1e-9);
+
+        // The dense operator gives the same answer.
+        let mut y = Matrix::zero(80, 3);
+        let res = block_gmres(&a.to_dense(), &b, &mut y, 4, tolerance(1e-10, 300), None);
+        assert!(res.iter().all(|r| r.converged));
+        assert!((0..80).all(|i| (0..3).all(|j| (x.get(i, j) - y.get(i, j)).abs() < 1e-8)));
+    }
+}
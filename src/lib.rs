@@ -1,14 +1,108 @@
 //! Rusty Numerical Linear Algebra package.
 //!
 //! Some experimental NLA code in Rust.
-
+//!
+//! The core matrix types and kernels only need `alloc`. Disabling the
+//! default `std` feature (and enabling `libm` for the float functions)
+//! builds the crate for `no_std` targets.
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::needless_range_loop)]
+
+extern crate alloc;
+
+pub mod accumulate;
+pub mod amg;
+pub mod assembly;
+pub mod autodiff;
+pub mod band;
+pub mod batch;
+#[cfg(feature = "std")]
 pub mod bench;
-mod operations;
-use rand::prelude::*;
+pub mod block;
+pub mod bsr;
+pub mod cholesky;
+pub mod coloring;
+pub mod control;
+pub mod conv;
+pub mod dct;
+pub mod deflation;
+#[cfg(feature = "std")]
+pub mod dist;
+pub mod eig;
+pub mod einsum;
+pub mod equilibrate;
+pub mod expr;
+pub mod factor;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fft;
+pub mod filters;
+mod generate;
+pub mod givens;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod hodlr;
+pub mod householder;
+pub mod interval;
+pub mod iterative;
+pub mod lowprec;
+pub mod lowrank;
+pub mod lu;
+pub mod matfun;
+mod math;
+pub mod multigrid;
+pub mod nystrom;
+#[cfg(feature = "ooc")]
+pub mod ooc;
+pub mod operations;
+pub mod orth;
+pub mod parallel;
+pub mod pca;
+pub mod permutation;
+pub mod perturb;
+pub mod poly;
+pub mod procrustes;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod qmc;
+pub mod qr;
+pub mod rng;
+pub mod saddle;
+pub mod sell;
+pub mod shared;
+pub mod smatrix;
+pub mod solver;
+pub mod sparse;
+pub mod spectrum;
+pub mod sptrsv;
+pub mod stationary;
+pub mod stats;
+pub mod stochastic;
+pub mod structured;
+pub mod svd;
+pub mod tensor;
+pub mod tls;
+pub mod triangular;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod woodbury;
+pub use parallel::{set_num_threads, with_threads};
+pub use rng::Rng;
+pub use shared::ArcMatrix;
+pub use smatrix::SMatrix;
+pub use sparse::SparseMatrix;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+use core::ops::Range;
 
 pub struct Matrix {
     pub m: usize,
     pub n: usize,
+    /// Leading dimension (distance between the starts of two rows).
+    ld: usize,
+    /// Offset of the first entry into data, used for alignment.
+    offset: usize,
     data: Vec<f64>,
 }
 
@@ -19,6 +113,8 @@ impl Matrix {
         Matrix {
             m,
             n,
+            ld: n,
+            offset: 0,
             data,
         }
     }
@@ -28,29 +124,117 @@ impl Matrix {
         Matrix {
             m,
             n,
+            ld: n,
+            offset: 0,
             data: (0..m * n).map(|_| 0.0).collect(),
         }
     }
 
+    /// Return a zeroed matrix whose first entry is aligned to `align` bytes.
+    ///
+    /// If `pad_rows` is set, the leading dimension is rounded up so that
+    /// every row starts on an aligned boundary and kernels can skip
+    /// remainder handling.
+    pub fn zero_aligned(m: usize, n: usize, align: usize, pad_rows: bool) -> Matrix {
+        let size = core::mem::size_of::<f64>();
+        assert!(align.is_power_of_two() && align >= size);
+        let lanes = align / size;
+        let ld = if pad_rows { n.div_ceil(lanes) * lanes } else { n };
+        let data: Vec<f64> = vec![0.0; m * ld + lanes - 1];
+        let offset = data.as_ptr().align_offset(align);
+        assert!(offset < lanes);
+        Matrix {
+            m,
+            n,
+            ld,
+            offset,
+            data,
+        }
+    }
+
+    /// Return an uninitialized matrix.
+    ///
+    /// Every entry must be written before calling
+    /// [`UninitMatrix::assume_init`].
+    pub fn uninit(m: usize, n: usize) -> UninitMatrix {
+        let mut data = Vec::with_capacity(m * n);
+        data.resize_with(m * n, MaybeUninit::uninit);
+        UninitMatrix {
+            m,
+            n,
+            data,
+        }
+    }
+
     /// Return a randomly generated matrix.
+    #[cfg(feature = "std")]
     pub fn rand(m: usize, n: usize) -> Matrix {
-        let mut rng = rand::rng();
+        Matrix::rand_with(m, n, &mut rng::Xoshiro256::from_entropy())
+    }
+
+    /// Return a reproducible random matrix.
+    ///
+    /// The same seed gives the same entries on every run and platform. This
+    /// uses `rng::Lcg`; pass an `rng::Xoshiro256` to rand_with() when the
+    /// quality of the stream matters.
+    pub fn rand_seeded(m: usize, n: usize, seed: u64) -> Matrix {
+        Matrix::rand_with(m, n, &mut rng::Lcg::new(seed))
+    }
+
+    /// Return a matrix with entries drawn uniformly from [0, 1) using `rng`.
+    pub fn rand_with(m: usize, n: usize, rng: &mut impl Rng) -> Matrix {
         Matrix {
             m,
             n,
-            data: (0..m * n).map(|_| rng.random()).collect(),
+            ld: n,
+            offset: 0,
+            data: (0..m * n).map(|_| rng.next_f64()).collect(),
         }
     }
 
+    /// Return a matrix with entries drawn from `dist` using `rng`.
+    pub fn rand_dist(m: usize, n: usize, dist: rng::Distribution, rng: &mut impl Rng) -> Matrix {
+        let mut res = Matrix::zero(m, n);
+        dist.fill(rng, res.as_mut_slice());
+        res
+    }
+
+    /// Return the leading dimension.
+    pub fn ld(&self) -> usize {
+        self.ld
+    }
+
+    /// Return the underlying storage, including any row padding.
+    ///
+    /// Entry (i, j) is at index `i * self.ld() + j`.
+    pub fn as_slice(&self) -> &[f64] {
+        &self.data[self.offset..self.offset + self.m * self.ld]
+    }
+
+    /// Return the underlying mutable storage, including any row padding.
+    pub fn as_mut_slice(&mut self) -> &mut [f64] {
+        &mut self.data[self.offset..self.offset + self.m * self.ld]
+    }
+
     /// Return the internal vector data.
     pub fn to_vec(self) -> Vec<f64> {
-        self.data
+        if self.ld == self.n && self.offset == 0 {
+            return self.data;
+        }
+        self.as_slice()
+            .chunks(self.ld)
+            .flat_map(|row| &row[..self.n])
+            .copied()
+            .collect()
     }
 
     /// Return a matrix view.
-    pub fn view(&self) -> MatrixView {
+    pub fn view(&self) -> MatrixView<'_> {
         let view_data = (0..self.m)
-            .map(|i| &self.data[i * self.n..i * self.n + self.n])
+            .map(|i| {
+                let start = self.offset + i * self.ld;
+                &self.data[start..start + self.n]
+            })
             .collect();
         MatrixView {
             m: self.m,
@@ -60,20 +244,140 @@ impl Matrix {
     }
 
     /// Return a mutable matrix view.
-    pub fn view_mut(&mut self) -> MatrixViewMut {
+    pub fn view_mut(&mut self) -> MatrixViewMut<'_> {
         let mut view_data = vec![];
 
-        let mut remain = &mut self.data[..];
-        for _ in 0..self.m {
-            let (row, left) = remain.split_at_mut(self.n);
-            view_data.push(row);
+        let (m, n, ld) = (self.m, self.n, self.ld);
+        let mut remain = &mut self.as_mut_slice()[..];
+        for _ in 0..m {
+            let (row, left) = remain.split_at_mut(ld);
+            view_data.push(&mut row[..n]);
             remain = left;
         }
 
         MatrixViewMut {
+            m,
+            n,
+            data: view_data,
+        }
+    }
+
+    /// Return the transpose as a new matrix.
+    pub fn transpose(&self) -> Matrix {
+        let mut t = Matrix::zero(self.n, self.m);
+        for i in 0..self.m {
+            for j in 0..self.n {
+                t.data[j * self.m + i] = self.data[self.offset + i * self.ld + j];
+            }
+        }
+        t
+    }
+
+    /// Return a view of the block with the given rows and columns.
+    pub fn submatrix(&self, rows: Range<usize>, cols: Range<usize>) -> MatrixView<'_> {
+        assert!(rows.end <= self.m && cols.end <= self.n);
+        let view_data = rows
+            .clone()
+            .map(|i| {
+                let start = self.offset + i * self.ld;
+                &self.data[start + cols.start..start + cols.end]
+            })
+            .collect();
+        MatrixView {
+            m: rows.len(),
+            n: cols.len(),
+            data: view_data,
+        }
+    }
+
+    /// Return a mutable view of the block with the given rows and columns.
+    pub fn submatrix_mut(&mut self, rows: Range<usize>, cols: Range<usize>) -> MatrixViewMut<'_> {
+        assert!(rows.end <= self.m && cols.end <= self.n);
+        let ld = self.ld;
+        let (m, n) = (rows.len(), cols.len());
+        let view_data = self.as_mut_slice()[rows.start * ld..]
+            .chunks_mut(ld)
+            .take(m)
+            .map(|row| &mut row[cols.clone()])
+            .collect();
+        MatrixViewMut {
+            m,
+            n,
+            data: view_data,
+        }
+    }
+
+    /// Return disjoint mutable views of blocks of `k` rows (the last may be
+    /// shorter), each with the index of its first row.
+    ///
+    /// The views are Send, so each can be moved to its own thread.
+    pub fn chunks_rows_mut(&mut self, k: usize) -> impl Iterator<Item = (usize, MatrixViewMut<'_>)> {
+        assert!(k > 0);
+        let (n, ld) = (self.n, self.ld);
+        self.as_mut_slice()
+            .chunks_mut((k * ld).max(1))
+            .enumerate()
+            .map(move |(b, block)| (b * k, row_block(block, n, ld)))
+    }
+
+    /// Parallel version of chunks_rows_mut(), for rayon.
+    #[cfg(feature = "parallel")]
+    pub fn par_chunks_rows_mut(&mut self, k: usize) -> impl rayon::iter::IndexedParallelIterator<Item = (usize, MatrixViewMut<'_>)> {
+        use rayon::prelude::*;
+        assert!(k > 0);
+        let (n, ld) = (self.n, self.ld);
+        self.as_mut_slice()
+            .par_chunks_mut((k * ld).max(1))
+            .enumerate()
+            .map(move |(b, block)| (b * k, row_block(block, n, ld)))
+    }
+}
+
+/// Return the view of the first n columns of the rows in `block`.
+fn row_block(block: &mut [f64], n: usize, ld: usize) -> MatrixViewMut<'_> {
+    let data: Vec<&mut [f64]> = block.chunks_mut(ld).map(|row| &mut row[..n]).collect();
+    MatrixViewMut { m: data.len(), n, data }
+}
+
+/// Write-only matrix backed by uninitialized memory.
+pub struct UninitMatrix {
+    pub m: usize,
+    pub n: usize,
+    data: Vec<MaybeUninit<f64>>,
+}
+
+impl UninitMatrix {
+    /// Write a matrix entry.
+    #[inline]
+    pub fn write(&mut self, i: usize, j: usize, value: f64) {
+        assert!(j < self.n);
+        self.data[i * self.n + j].write(value);
+    }
+
+    /// Return the mutable row `i`, initializing it with `value`.
+    fn fill_row(&mut self, i: usize, value: f64) -> &mut [f64] {
+        let row = &mut self.data[i * self.n..i * self.n + self.n];
+        for entry in row.iter_mut() {
+            entry.write(value);
+        }
+        // Every entry of the row was written above.
+        unsafe { &mut *(row as *mut [MaybeUninit<f64>] as *mut [f64]) }
+    }
+
+    /// Convert into an initialized matrix.
+    ///
+    /// # Safety
+    ///
+    /// Every entry must have been written.
+    pub unsafe fn assume_init(self) -> Matrix {
+        let mut data = core::mem::ManuallyDrop::new(self.data);
+        let (ptr, len, cap) = (data.as_mut_ptr(), data.len(), data.capacity());
+        Matrix {
             m: self.m,
             n: self.n,
-            data: view_data,
+            ld: self.n,
+            offset: 0,
+            data: Vec::from_raw_parts(ptr as *mut f64, len, cap),
         }
     }
 }
@@ -87,35 +391,60 @@ pub trait MatrixIndex {
     fn set(&mut self, i: usize, j: usize, value: f64);
 
     /// Get a matrix entry without bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// `i` and `j` must be within the matrix dimensions.
     unsafe fn get_unchecked(&self, i: usize, j: usize) -> f64;
 
     /// Set a matrix entry without bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// `i` and `j` must be within the matrix dimensions.
     unsafe fn set_unchecked(&mut self, i: usize, j: usize, value: f64);
 }
 
+/// Cloning copies the entries into a compact matrix; row padding and
+/// alignment are not kept.
+impl Clone for Matrix {
+    fn clone(&self) -> Matrix {
+        let data = (0..self.m)
+            .flat_map(|i| {
+                let start = self.offset + i * self.ld;
+                &self.data[start..start + self.n]
+            })
+            .copied()
+            .collect();
+        Matrix::from_vec(self.m, self.n, data)
+    }
+}
+
 impl MatrixIndex for Matrix {
     /// Get a matrix entry.
     #[inline]
     fn get(&self, i: usize, j: usize) -> f64 {
-        self.data[i * self.n + j]
+        assert!(j < self.n);
+        self.data[self.offset + i * self.ld + j]
     }
 
     /// Set a value in the matrix.
     #[inline]
     fn set(&mut self, i: usize, j: usize, value: f64) {
-        self.data[i * self.n + j] = value;
+        assert!(j < self.n);
+        self.data[self.offset + i * self.ld + j] = value;
     }
 
     /// Get a matrix entry.
     #[inline]
     unsafe fn get_unchecked(&self, i: usize, j: usize) -> f64 {
-        *self.data.get_unchecked(i * self.n + j)
+        *self.data.get_unchecked(self.offset + i * self.ld + j)
     }
 
     /// Set a value in the matrix.
     #[inline]
     unsafe fn set_unchecked(&mut self, i: usize, j: usize, value: f64) {
-        *self.data.get_unchecked_mut(i * self.n + j) = value;
+        *self.data.get_unchecked_mut(self.offset + i * self.ld + j) = value;
     }
 }
 
@@ -132,7 +461,7 @@ impl<'a> MatrixIndex for MatrixView<'a> {
     }
 
     #[inline]
-    fn set(&mut self, i: usize, j: usize, value: f64) {
+    fn set(&mut self, _i: usize, _j: usize, _value: f64) {
         panic!("cannot set entries with MatrixView");
     }
 
@@ -144,7 +473,7 @@ impl<'a> MatrixIndex for MatrixView<'a> {
     }
 
     #[inline]
-    unsafe fn set_unchecked(&mut self, i: usize, j: usize, value: f64) {
+    unsafe fn set_unchecked(&mut self, _i: usize, _j: usize, _value: f64) {
         panic!("cannot set entries with MatrixView");
     }
 }
@@ -155,6 +484,31 @@ pub struct MatrixViewMut<'a> {
     data: Vec<&'a mut [f64]>,
 }
 
+impl<'a> MatrixView<'a> {
+    /// Create from row slices, each n long.
+    pub(crate) fn from_rows(n: usize, data: Vec<&'a [f64]>) -> MatrixView<'a> {
+        assert!(data.iter().all(|row| row.len() == n));
+        MatrixView { m: data.len(), n, data }
+    }
+}
+
+impl<'a> MatrixViewMut<'a> {
+    /// Create from mutable row slices, each n long.
+    pub(crate) fn from_rows(n: usize, data: Vec<&'a mut [f64]>) -> MatrixViewMut<'a> {
+        assert!(data.iter().all(|row| row.len() == n));
+        MatrixViewMut { m: data.len(), n, data }
+    }
+
+    /// Return a read-only view of the same entries.
+    pub fn view(&self) -> MatrixView<'_> {
+        MatrixView {
+            m: self.m,
+            n: self.n,
+            data: self.data.iter().map(|row| &**row).collect(),
+        }
+    }
+}
+
 impl<'a> MatrixIndex for MatrixViewMut<'a> {
     #[inline]
     fn get(&self, i: usize, j: usize) -> f64 {
@@ -182,29 +536,67 @@ impl<'a> MatrixIndex for MatrixViewMut<'a> {
 }
 
 pub fn matmul(a: &MatrixView, b: &MatrixView, c: &mut MatrixViewMut) {
+    fma_scale(1.0, a, b, 0.0, c);
+}
+
+/// Accumulate the product of a and b into c, without zeroing c first.
+pub fn matmul_acc(a: &MatrixView, b: &MatrixView, c: &mut MatrixViewMut) {
+    fma_scale(1.0, a, b, 1.0, c);
+}
+
+/// Compute c = alpha * a * b + beta * c.
+///
+/// A beta of zero overwrites c, so it may hold garbage (including NaN) on
+/// entry. A beta of one skips the scaling pass entirely.
+pub fn fma_scale(alpha: f64, a: &MatrixView, b: &MatrixView, beta: f64, c: &mut MatrixViewMut) {
     assert_eq!(a.m, c.m);
     assert_eq!(a.n, b.m);
     assert_eq!(b.n, c.n);
 
-    for i in 0..c.m {
-        for j in 0..c.n {
-            unsafe { c.set_unchecked(i, j, 0.0) };
+    if beta != 1.0 {
+        for i in 0..c.m {
+            for j in 0..c.n {
+                unsafe {
+                    let value = if beta == 0.0 { 0.0 } else { beta * c.get_unchecked(i, j) };
+                    c.set_unchecked(i, j, value);
+                }
+            }
         }
     }
 
     for i in 0..a.m {
         for k in 0..a.n {
+            let aik = alpha * unsafe { a.get_unchecked(i, k) };
             for j in 0..b.n {
                 unsafe {
                     c.set_unchecked(i, j, c.get_unchecked(i, j)
-                                          + a.get_unchecked(i, k)
-                                            * b.get_unchecked(k, j));
+                                          + aik * b.get_unchecked(k, j));
                 }
             }
         }
     }
 }
 
+/// Multiply a and b into an uninitialized matrix, skipping the zero pass.
+pub fn matmul_uninit(a: &MatrixView, b: &MatrixView, mut c: UninitMatrix) -> Matrix {
+    assert_eq!(a.m, c.m);
+    assert_eq!(a.n, b.m);
+    assert_eq!(b.n, c.n);
+
+    for i in 0..a.m {
+        let row = c.fill_row(i, 0.0);
+        for k in 0..a.n {
+            let aik = unsafe { a.get_unchecked(i, k) };
+            for (j, cij) in row.iter_mut().enumerate() {
+                *cij += aik * unsafe { b.get_unchecked(k, j) };
+            }
+        }
+    }
+
+    // Each row was initialized by fill_row().
+    unsafe { c.assume_init() }
+}
+
 const EPSILON: f64 = 10e-8;
 
 /// Return whether a is approximately equal to b.
@@ -222,7 +614,7 @@ mod test {
         let n = 4;
         let p = 8;
         let x = Matrix::zero(m, n);
-        let y = Matrix::rand(n, p);
+        let y = Matrix::rand_seeded(n, p, 1);
         let mut z = Matrix::zero(m, p);
 
         matmul(&x.view(), &y.view(), &mut z.view_mut());
@@ -249,4 +641,136 @@ mod test {
         let expected = vec![1.0, 18.0, 3.0, 38.0, 5.0, 58.0];
         assert!(z.iter().zip(&expected).all(|(a, b)| approx(*a, *b)));
     }
+
+    #[test]
+    fn matmul_uninit_matches_matmul() {
+        let x = Matrix::rand_seeded(5, 3, 2);
+        let y = Matrix::rand_seeded(3, 7, 3);
+        let mut z = Matrix::zero(5, 7);
+
+        matmul(&x.view(), &y.view(), &mut z.view_mut());
+        let w = matmul_uninit(&x.view(), &y.view(), Matrix::uninit(5, 7));
+
+        let z = z.to_vec();
+        let w = w.to_vec();
+        assert!(z.iter().zip(&w).all(|(a, b)| approx(*a, *b)));
+    }
+
+    #[test]
+    fn matmul_acc_accumulates() {
+        let x = Matrix::from_vec(2, 2, vec![1.0, 2.0,
+                                            3.0, 4.0]);
+        let y = Matrix::from_vec(2, 2, vec![1.0, 0.0,
+                                            0.0, 1.0]);
+        let mut z = Matrix::from_vec(2, 2, vec![1.0, 1.0,
+                                                1.0, 1.0]);
+
+        matmul_acc(&x.view(), &y.view(), &mut z.view_mut());
+        assert_eq!(z.to_vec(), vec![2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn fma_scale_simple() {
+        let x = Matrix::from_vec(2, 2, vec![1.0, 2.0,
+                                            3.0, 4.0]);
+        let y = Matrix::from_vec(2, 2, vec![1.0, 0.0,
+                                            0.0, 1.0]);
+        let mut z = Matrix::from_vec(2, 2, vec![1.0, 1.0,
+                                                1.0, 1.0]);
+        fma_scale(2.0, &x.view(), &y.view(), -1.0, &mut z.view_mut());
+        assert_eq!(z.to_vec(), vec![1.0, 3.0, 5.0, 7.0]);
+
+        let mut w = Matrix::from_vec(2, 2, vec![f64::NAN; 4]);
+        fma_scale(1.0, &x.view(), &y.view(), 0.0, &mut w.view_mut());
+        assert_eq!(w.to_vec(), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn zero_aligned_padded_rows() {
+        let mut x = Matrix::zero_aligned(3, 5, 64, true);
+        assert_eq!(x.ld(), 8);
+        assert_eq!(x.as_slice().as_ptr() as usize % 64, 0);
+
+        x.set(2, 4, 1.0);
+        assert_eq!(x.as_slice()[2 * x.ld() + 4], 1.0);
+
+        let y = Matrix::zero_aligned(5, 3, 32, false);
+        assert_eq!(y.ld(), 3);
+        assert_eq!(y.as_slice().as_ptr() as usize % 32, 0);
+
+        // Clones and transposes of padded matrices are compact.
+        x.set(0, 1, 2.0);
+        let c = x.clone();
+        assert_eq!((c.ld(), c.get(2, 4), c.get(0, 1)), (5, 1.0, 2.0));
+        let t = x.transpose();
+        assert_eq!((t.m, t.n, t.get(4, 2), t.get(1, 0)), (5, 3, 1.0, 2.0));
+    }
+
+    #[test]
+    fn matmul_aligned() {
+        let x = Matrix::from_vec(3, 2, vec![1.0, 2.0,
+                                            3.0, 4.0,
+                                            5.0, 6.0]);
+        let y = Matrix::from_vec(2, 2, vec![1.0, 2.0,
+                                            0.0, 8.0]);
+        let mut z = Matrix::zero_aligned(3, 2, 64, true);
+
+        matmul(&x.view(), &y.view(), &mut z.view_mut());
+
+        assert_eq!(z.to_vec(), vec![1.0, 18.0, 3.0, 38.0, 5.0, 58.0]);
+    }
+
+    #[test]
+    fn uninit_write_assume_init() {
+        let mut x = Matrix::uninit(2, 2);
+        x.write(0, 0, 1.0);
+        x.write(0, 1, 2.0);
+        x.write(1, 0, 3.0);
+        x.write(1, 1, 4.0);
+        let x = unsafe { x.assume_init() };
+        assert_eq!(x.to_vec(), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn rand_seeded_reproducible() {
+        let x = Matrix::rand_seeded(3, 4, 7);
+        let y = Matrix::rand_seeded(3, 4, 7);
+        let z = Matrix::rand_seeded(3, 4, 8);
+        assert_eq!(x.as_slice(), y.as_slice());
+        assert_ne!(x.as_slice(), z.as_slice());
+        assert_eq!(x.get(0, 0).to_bits(), 0x3fe68a733d6dbaa8);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn row_blocks_on_threads() {
+        fn assert_send<T: Send>(_: &T) {}
+        let mut a = Matrix::zero_aligned(7, 3, 64, true);
+        let blocks: Vec<(usize, MatrixViewMut)> = a.chunks_rows_mut(3).collect();
+        assert_eq!(blocks.iter().map(|(start, v)| (*start, v.m, v.n)).collect::<Vec<_>>(), vec![(0, 3, 3), (3, 3, 3), (6, 1, 3)]);
+        assert_send(&blocks[0].1);
+        std::thread::scope(|s| {
+            for (start, mut block) in blocks {
+                s.spawn(move || {
+                    for i in 0..block.m {
+                        for j in 0..block.n {
+                            block.set(i, j, ((start + i) * 10 + j) as f64);
+                        }
+                    }
+                });
+            }
+        });
+        assert!((0..7).all(|i| (0..3).all(|j| a.get(i, j) == (i * 10 + j) as f64)));
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            a.par_chunks_rows_mut(2).for_each(|(start, mut block)| {
+                for i in 0..block.m {
+                    block.set(i, 0, -((start + i) as f64));
+                }
+            });
+            assert!((0..7).all(|i| a.get(i, 0) == -(i as f64)));
+        }
+    }
 }
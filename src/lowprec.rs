@@ -0,0 +1,242 @@
+//! Simulated low-precision arithmetic with stochastic rounding.
+//!
+//! Values stay in f64 but are rounded to a narrower floating-point Format
+//! (fp32, fp16, bfloat16, ...) after every operation, as MATLAB's chop
+//! does. Stochastic rounding rounds x up to the next representable number
+//! with probability equal to its distance from the one below, in ulps, so
+//! the rounding is unbiased and long sums do not stagnate. The random bits
+//! come from an explicit Rng, so a seeded generator reproduces a run
+//! exactly.
+use crate::math;
+use crate::rng::Rng;
+use crate::{MatrixIndex, MatrixView, MatrixViewMut};
+
+/// A binary floating-point format with gradual underflow.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Format {
+    /// Significand bits, including the implicit leading bit.
+    pub precision: u32,
+    /// Exponent of the smallest normal number.
+    pub emin: i32,
+    /// Exponent of the largest finite number.
+    pub emax: i32,
+}
+
+impl Format {
+    /// IEEE single precision.
+    pub const FP32: Format = Format { precision: 24, emin: -126, emax: 127 };
+    /// NVIDIA TensorFloat-32.
+    pub const TF32: Format = Format { precision: 11, emin: -126, emax: 127 };
+    /// IEEE half precision.
+    pub const FP16: Format = Format { precision: 11, emin: -14, emax: 15 };
+    /// Google bfloat16.
+    pub const BF16: Format = Format { precision: 8, emin: -126, emax: 127 };
+
+    /// Return the largest finite number.
+    pub fn max(&self) -> f64 {
+        (2.0 - pow2(1 - self.precision as i32)) * pow2(self.emax)
+    }
+
+    /// Return the unit roundoff 2^-precision of round to nearest.
+    pub fn unit_roundoff(&self) -> f64 {
+        pow2(-(self.precision as i32))
+    }
+
+    /// Return the neighbours lo <= x < lo + ulp in the format, and the
+    /// fraction (x - lo) / ulp.
+    fn bracket(&self, x: f64) -> (f64, f64, f64) {
+        let e = exponent(x).max(self.emin);
+        let ulp = pow2(e + 1 - self.precision as i32);
+        let q = x / ulp;
+        let fl = math::floor(q);
+        (fl * ulp, ulp, q - fl)
+    }
+
+    /// Return x rounded to nearest, ties to even.
+    pub fn round(&self, x: f64) -> f64 {
+        if x == 0.0 || !x.is_finite() {
+            return x;
+        }
+        let (lo, ulp, frac) = self.bracket(x);
+        let odd = math::floor(lo / ulp) % 2.0 != 0.0;
+        let up = frac > 0.5 || (frac == 0.5 && odd);
+        self.saturate(if up { lo + ulp } else { lo })
+    }
+
+    /// Return x rounded stochastically: up with probability equal to its
+    /// fractional distance from the number below.
+    pub fn round_stochastic(&self, x: f64, rng: &mut impl Rng) -> f64 {
+        if x == 0.0 || !x.is_finite() {
+            return x;
+        }
+        let (lo, ulp, frac) = self.bracket(x);
+        // Draw even when x is representable, so the stream of random
+        // numbers does not depend on the data.
+        let up = rng.next_f64() < frac;
+        self.saturate(if up { lo + ulp } else { lo })
+    }
+
+    /// Return x, or an infinity if it is beyond the finite range.
+    fn saturate(&self, x: f64) -> f64 {
+        if x.abs() > self.max() {
+            f64::INFINITY.copysign(x)
+        } else {
+            x
+        }
+    }
+}
+
+/// How a Rounder rounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rounding {
+    Nearest,
+    Stochastic,
+}
+
+/// Rounds results to a Format, with the generator for stochastic rounding.
+pub struct Rounder<R> {
+    pub format: Format,
+    pub rounding: Rounding,
+    rng: R,
+}
+
+impl<R: Rng> Rounder<R> {
+    /// Return a rounder to `format`; `rng` is only drawn from for
+    /// stochastic rounding.
+    pub fn new(format: Format, rounding: Rounding, rng: R) -> Rounder<R> {
+        Rounder { format, rounding, rng }
+    }
+
+    /// Return x rounded.
+    #[inline]
+    pub fn round(&mut self, x: f64) -> f64 {
+        match self.rounding {
+            Rounding::Nearest => self.format.round(x),
+            Rounding::Stochastic => self.format.round_stochastic(x, &mut self.rng),
+        }
+    }
+
+    /// Round every entry of x, as in a conversion to the format.
+    pub fn round_slice(&mut self, x: &mut [f64]) {
+        x.iter_mut().for_each(|v| *v = self.round(*v));
+    }
+
+    /// Return the recursive sum of x, rounding after every addition.
+    pub fn sum(&mut self, x: &[f64]) -> f64 {
+        x.iter().fold(0.0, |s, &v| self.round(s + v))
+    }
+
+    /// Return the dot product of x and y, rounding every product and
+    /// addition.
+    pub fn dot(&mut self, x: &[f64], y: &[f64]) -> f64 {
+        assert_eq!(x.len(), y.len());
+        x.iter().zip(y).fold(0.0, |s, (a, b)| {
+            let p = self.round(a * b);
+            self.round(s + p)
+        })
+    }
+}
+
+/// Compute C = A B + C in mixed precision, as on tensor cores.
+///
+/// The entries of A and B are first rounded to nearest in `input`, their
+/// products formed exactly (true when `input` has at most 26 bits of
+/// precision), and the sums, which start from C, rounded by `acc`.
+pub fn mixed_gemm<R: Rng>(input: Format, acc: &mut Rounder<R>, a: &MatrixView, b: &MatrixView, c: &mut MatrixViewMut) {
+    assert_eq!(a.m, c.m);
+    assert_eq!(a.n, b.m);
+    assert_eq!(b.n, c.n);
+    for i in 0..c.m {
+        for j in 0..c.n {
+            let mut s = acc.round(c.get(i, j));
+            for k in 0..a.n {
+                let p = input.round(a.get(i, k)) * input.round(b.get(k, j));
+                s = acc.round(s + p);
+            }
+            c.set(i, j, s);
+        }
+    }
+}
+
+/// Return 2^k for k in the normal range of f64.
+fn pow2(k: i32) -> f64 {
+    assert!((-1022..=1023).contains(&k));
+    f64::from_bits(((k + 1023) as u64) << 52)
+}
+
+/// Return the exponent e with 2^e <= |x| < 2^(e+1), or -1023 for the
+/// subnormal range of f64.
+fn exponent(x: f64) -> i32 {
+    ((x.to_bits() >> 52) & 0x7ff) as i32 - 1023
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Xoshiro256;
+    use crate::Matrix;
+    use alloc::vec;
+
+    #[test]
+    fn round_to_nearest_formats() {
+        let h = Format::FP16;
+        let u = h.unit_roundoff();
+        assert_eq!(h.max(), 65504.0);
+        // Ties go to the even neighbour.
+        assert_eq!(h.round(1.0 + u), 1.0);
+        assert_eq!(h.round(1.0 + 3.0 * u), 1.0 + 4.0 * u);
+        assert_eq!(h.round(-(1.0 + 1.5 * u)), -(1.0 + 2.0 * u));
+        assert_eq!(h.round(65519.0), 65504.0);
+        assert_eq!(h.round(65520.0), f64::INFINITY);
+        // Subnormals are spaced by 2^-24.
+        assert_eq!(h.round(3.0 * pow2(-25)), pow2(-23));
+        assert_eq!(h.round(pow2(-26)), 0.0);
+        assert_eq!(Format::BF16.round(1.0 / 3.0), 0.333984375);
+        assert_eq!(Format::FP32.round(0.1), 0.1f32 as f64);
+    }
+
+    #[test]
+    fn stochastic_rounding_is_unbiased() {
+        let h = Format::FP16;
+        let ulp = 2.0 * h.unit_roundoff();
+        let x = 1.0 + 0.3 * ulp;
+        let mut sr = Rounder::new(h, Rounding::Stochastic, Xoshiro256::new(7));
+        let n = 20000;
+        let mut mean = 0.0;
+        for _ in 0..n {
+            let r = sr.round(x);
+            assert!(r == 1.0 || r == 1.0 + ulp);
+            mean += r / n as f64;
+        }
+        assert!((mean - x).abs() < 0.02 * ulp);
+
+        // Adding a quarter ulp at a time stagnates under round to nearest.
+        let steps = vec![ulp / 4.0; 4096];
+        let mut terms = vec![1.0];
+        terms.extend_from_slice(&steps);
+        let mut rn = Rounder::new(h, Rounding::Nearest, Xoshiro256::new(0));
+        assert_eq!(rn.sum(&terms), 1.0);
+        let s = sr.sum(&terms);
+        let exact = 1.0 + 1024.0 * ulp;
+        assert!((s - exact).abs() < 0.05 * exact);
+        // The same seed gives the same result.
+        assert_eq!(Rounder::new(h, Rounding::Stochastic, Xoshiro256::new(3)).sum(&terms),
+                   Rounder::new(h, Rounding::Stochastic, Xoshiro256::new(3)).sum(&terms));
+        assert_eq!(sr.dot(&[1.0; 3], &[0.0; 3]), 0.0);
+    }
+
+    #[test]
+    fn mixed_precision_gemm() {
+        let a = Matrix::rand_seeded(4, 64, 1);
+        let b = Matrix::rand_seeded(64, 3, 2);
+        let mut exact = Matrix::zero(4, 3);
+        crate::matmul(&a.view(), &b.view(), &mut exact.view_mut());
+        let mut c = Matrix::zero(4, 3);
+        let mut acc = Rounder::new(Format::FP32, Rounding::Stochastic, Xoshiro256::new(1));
+        mixed_gemm(Format::FP16, &mut acc, &a.view(), &b.view(), &mut c.view_mut());
+        // Dominated by rounding the inputs to fp16.
+        let err = (0..12).map(|k| (c.get(k / 3, k % 3) - exact.get(k / 3, k % 3)).abs()).fold(0.0, f64::max);
+        assert!(err > 0.0 && err < 64.0 * 2.0 * Format::FP16.unit_roundoff());
+        assert!(c.as_slice().iter().all(|&v| Format::FP32.round(v) == v));
+    }
+}
@@ -0,0 +1,212 @@
+//! Truncated SVD approximations stored in factored form.
+//!
+//! By Eckart-Young, keeping the k largest singular triplets of A gives the
+//! best rank-k approximation in the 2- and Frobenius norms, with
+//! Frobenius error the root sum of squares of the dropped singular values.
+//! Storing U, s and V^T takes (m + n + 1) k numbers instead of mn, and a
+//! product costs (m + n) k operations. For operators too large for a dense
+//! SVD, the randomized range finder of Halko, Martinsson and Tropp needs
+//! only products with A and A^T.
+use crate::iterative::{LinearOperator, TransposeOperator};
+use crate::operations::{gemv, gemv_t};
+use crate::orth::{gram_schmidt, GramSchmidt};
+use crate::rng::{Distribution, Rng};
+use crate::svd::{svd, Svd};
+use crate::{math, matmul, Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Rank-k approximation A ~ U diag(s) V^T.
+pub struct LowRank {
+    /// m x k, orthonormal columns.
+    pub u: Matrix,
+    /// The k singular values, in decreasing order.
+    pub s: Vec<f64>,
+    /// k x n, orthonormal rows.
+    pub vt: Matrix,
+}
+
+impl LowRank {
+    /// Return the rank k.
+    pub fn rank(&self) -> usize {
+        self.s.len()
+    }
+
+    /// Return the m x n matrix U diag(s) V^T.
+    pub fn to_dense(&self) -> Matrix {
+        let k = self.rank();
+        let us = Matrix::from_vec(self.u.m, k, (0..self.u.m * k).map(|t| self.u.get(t / k, t % k) * self.s[t % k]).collect());
+        let mut a = Matrix::zero(self.u.m, self.vt.n);
+        matmul(&us.view(), &self.vt.view(), &mut a.view_mut());
+        a
+    }
+
+    /// Keep the leading k triplets of f.
+    fn truncate(f: Svd, k: usize) -> LowRank {
+        let (m, n) = (f.u.m, f.vt.n);
+        let u = Matrix::from_vec(m, k, (0..m * k).map(|t| f.u.get(t / k, t % k)).collect());
+        let vt = Matrix::from_vec(k, n, (0..k * n).map(|t| f.vt.get(t / n, t % n)).collect());
+        LowRank { u, s: f.s[..k].to_vec(), vt }
+    }
+}
+
+impl LinearOperator for LowRank {
+    fn nrows(&self) -> usize {
+        self.u.m
+    }
+
+    fn ncols(&self) -> usize {
+        self.vt.n
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        let mut t = vec![0.0; self.rank()];
+        gemv(1.0, &self.vt.view(), x, 0.0, &mut t);
+        t.iter_mut().zip(&self.s).for_each(|(ti, s)| *ti *= s);
+        gemv(1.0, &self.u.view(), &t, 0.0, y);
+    }
+}
+
+impl TransposeOperator for LowRank {
+    fn apply_transpose(&self, x: &[f64], y: &mut [f64]) {
+        let mut t = vec![0.0; self.rank()];
+        gemv_t(1.0, &self.u.view(), x, 0.0, &mut t);
+        t.iter_mut().zip(&self.s).for_each(|(ti, s)| *ti *= s);
+        gemv_t(1.0, &self.vt.view(), &t, 0.0, y);
+    }
+}
+
+/// Return the best approximation of a with rank at most `rank`.
+pub fn low_rank_approx(a: &Matrix, rank: usize) -> LowRank {
+    let f = svd(a);
+    let k = rank.min(f.s.len()).min(f.rank(0.0));
+    LowRank::truncate(f, k)
+}
+
+/// Return the approximation of a of least rank with
+/// ||A - U diag(s) V^T||_F <= eps ||A||_F.
+pub fn low_rank_approx_tol(a: &Matrix, eps: f64) -> LowRank {
+    let f = svd(a);
+    let total: f64 = f.s.iter().map(|s| s * s).sum();
+    // Drop singular values from the smallest while the tail fits.
+    let mut k = f.rank(0.0);
+    let mut tail = 0.0;
+    while k > 0 && tail + f.s[k - 1] * f.s[k - 1] <= eps * eps * total {
+        tail += f.s[k - 1] * f.s[k - 1];
+        k -= 1;
+    }
+    LowRank::truncate(f, k)
+}
+
+/// Return a rank-`rank` approximation of the operator a by the randomized
+/// SVD, from products with rank + oversample random vectors followed by
+/// `power_iters` rounds of subspace iteration.
+///
+/// Oversampling by 5 to 10 makes the error close to optimal with high
+/// probability when the singular values decay quickly; each power
+/// iteration sharpens it when they decay slowly, at the cost of two more
+/// passes over a.
+pub fn randomized_low_rank(a: &impl TransposeOperator, rank: usize, oversample: usize, power_iters: usize, rng: &mut impl Rng) -> LowRank {
+    let (m, n) = (a.nrows(), a.ncols());
+    let l = (rank + oversample).min(m).min(n);
+    let mut omega = vec![0.0; n * l];
+    Distribution::Normal.fill(rng, &mut omega);
+    let mut q = orthonormal_columns(&apply_columns(a, &Matrix::from_vec(n, l, omega), false));
+    for _ in 0..power_iters {
+        let z = orthonormal_columns(&apply_columns(a, &q, true));
+        q = orthonormal_columns(&apply_columns(a, &z, false));
+    }
+    // B = Q^T A is small: factor it and map its left vectors back.
+    let b = apply_columns(a, &q, true).transpose();
+    let f = svd(&b);
+    let mut u = Matrix::zero(m, f.u.n);
+    matmul(&q.view(), &f.u.view(), &mut u.view_mut());
+    let k = rank.min(f.rank(0.0));
+    LowRank::truncate(Svd { u, s: f.s, vt: f.vt }, k)
+}
+
+/// Return A X, or A^T X with `transpose`, column by column.
+fn apply_columns(a: &impl TransposeOperator, x: &Matrix, transpose: bool) -> Matrix {
+    let rows = if transpose { a.ncols() } else { a.nrows() };
+    let mut y = Matrix::zero(rows, x.n);
+    let mut col = vec![0.0; x.m];
+    let mut out = vec![0.0; rows];
+    for j in 0..x.n {
+        (0..x.m).for_each(|i| col[i] = x.get(i, j));
+        if transpose {
+            a.apply_transpose(&col, &mut out);
+        } else {
+            a.apply(&col, &mut out);
+        }
+        (0..rows).for_each(|i| y.set(i, j, out[i]));
+    }
+    y
+}
+
+/// Return an orthonormal basis of the columns of y, dropping dependent ones.
+fn orthonormal_columns(y: &Matrix) -> Matrix {
+    let (q, _) = gram_schmidt(y, GramSchmidt::Classical, math::sqrt(f64::EPSILON));
+    let keep: Vec<usize> = (0..q.n).filter(|&j| (0..q.m).any(|i| q.get(i, j) != 0.0)).collect();
+    let r = keep.len();
+    Matrix::from_vec(q.m, r, (0..q.m * r).map(|t| q.get(t / r, keep[t % r])).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Xoshiro256;
+
+    /// Return U diag(s) V^T for random orthonormal U and V.
+    fn with_singular_values(m: usize, n: usize, s: &[f64]) -> Matrix {
+        let k = s.len();
+        let u = orthonormal_columns(&Matrix::rand_seeded(m, k, 1));
+        let v = orthonormal_columns(&Matrix::rand_seeded(n, k, 2));
+        LowRank { u, s: s.to_vec(), vt: v.transpose() }.to_dense()
+    }
+
+    fn frobenius_distance(a: &Matrix, b: &Matrix) -> f64 {
+        math::sqrt(a.as_slice().iter().zip(b.as_slice()).map(|(p, q)| (p - q) * (p - q)).sum())
+    }
+
+    #[test]
+    fn truncation_error_is_the_tail() {
+        let s = [10.0, 5.0, 1.0, 0.1, 0.01];
+        let a = with_singular_values(12, 8, &s);
+        let r = low_rank_approx(&a, 2);
+        assert_eq!(r.rank(), 2);
+        let err = frobenius_distance(&a, &r.to_dense());
+        assert!((err - math::sqrt(1.0 + 0.01 + 1e-4)).abs() < 1e-12);
+        // The product agrees with the dense approximation.
+        let x: Vec<f64> = (0..8).map(|i| (i as f64).sin()).collect();
+        let (mut y, mut z) = (vec![0.0; 12], vec![0.0; 12]);
+        r.apply(&x, &mut y);
+        gemv(1.0, &r.to_dense().view(), &x, 0.0, &mut z);
+        assert!(y.iter().zip(&z).all(|(p, q)| (p - q).abs() < 1e-12));
+
+        // 1% of ||A||_F ~ 11.2 allows dropping 0.1 and 0.01 but not 1.
+        let t = low_rank_approx_tol(&a, 0.01);
+        assert_eq!(t.rank(), 3);
+        assert_eq!(low_rank_approx_tol(&a, 1e-12).rank(), 5);
+        // A rank above min(m, n) is capped.
+        assert!(low_rank_approx(&a, 20).rank() <= 8);
+    }
+
+    #[test]
+    fn randomized_matches_exact_for_fast_decay() {
+        let s: Vec<f64> = (0..10).map(|k| math::powf(10.0, -(k as f64))).collect();
+        let a = with_singular_values(40, 30, &s);
+        let exact = low_rank_approx(&a, 4);
+        let mut rng = Xoshiro256::new(3);
+        let r = randomized_low_rank(&a, 4, 6, 1, &mut rng);
+        assert_eq!((r.u.m, r.rank(), r.vt.n), (40, 4, 30));
+        assert!(r.s.iter().zip(&exact.s).all(|(p, q)| (p - q).abs() < 1e-10));
+        let best = frobenius_distance(&a, &exact.to_dense());
+        assert!(frobenius_distance(&a, &r.to_dense()) < 1.01 * best);
+        // The transpose product of the factored form.
+        let x: Vec<f64> = (0..40).map(|i| (i as f64).cos()).collect();
+        let (mut y, mut z) = (vec![0.0; 30], vec![0.0; 30]);
+        r.apply_transpose(&x, &mut y);
+        r.to_dense().apply_transpose(&x, &mut z);
+        assert!(y.iter().zip(&z).all(|(p, q)| (p - q).abs() < 1e-12));
+    }
+}
@@ -0,0 +1,245 @@
+//! Matrix powers, the matrix sign function and spectral projectors.
+//!
+//! Matrix::powi() computes A^k by repeated squaring, in about 2 log2(k)
+//! products, as for the k-step transition matrix of a Markov chain.
+//!
+//! For A with no eigenvalues on the imaginary axis, sign(A) has the
+//! eigenvectors of A and eigenvalues +-1 by the sign of the real part, so
+//! (I + sign(A)) / 2 projects onto the invariant subspace of the
+//! eigenvalues in the right half-plane along the rest. A Mobius transform
+//! maps a disc onto a half-plane, giving projectors for circles too. Such
+//! projectors split a spectrum in two without computing eigenvalues, the
+//! basis of spectral divide-and-conquer, and appear directly in control as
+//! the stable and unstable subspaces.
+use crate::factor::Factor;
+use crate::lu::LuFactor;
+use crate::svd::svd;
+use crate::{math, matmul, Matrix, MatrixIndex};
+use alloc::vec::Vec;
+
+/// Part of the complex plane selecting eigenvalues for spectral_projector().
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Region {
+    /// Re lambda < 0, the stable eigenvalues of a continuous-time system.
+    LeftHalfPlane,
+    /// Re lambda > 0.
+    RightHalfPlane,
+    /// |lambda - center| < radius with a real center, such as the stable
+    /// eigenvalues of a discrete-time system for center 0 and radius 1.
+    Disc { center: f64, radius: f64 },
+    /// |lambda - center| > radius.
+    Exterior { center: f64, radius: f64 },
+}
+
+impl Matrix {
+    /// Return A^k for a square matrix, the identity for k = 0.
+    pub fn powi(&self, k: u32) -> Matrix {
+        assert_eq!(self.m, self.n);
+        let mut result = identity(self.n);
+        if k == 0 {
+            return result;
+        }
+        // Multiply in the squares A^(2^i) for the set bits of k.
+        let mut square = self.clone();
+        let mut k = k;
+        let mut first = true;
+        loop {
+            if k & 1 == 1 {
+                result = if first { square.clone() } else { product(&result, &square) };
+                first = false;
+            }
+            k >>= 1;
+            if k == 0 {
+                return result;
+            }
+            square = product(&square, &square);
+        }
+    }
+}
+
+/// Return sign(A), or None if A is (numerically) singular at some step or
+/// the iteration does not converge, as when A has eigenvalues on or very
+/// near the imaginary axis.
+///
+/// This is the Newton iteration X := (mu X + (mu X)^-1) / 2 from X = A,
+/// with determinantal scaling mu = |det X|^(-1/n) while X is far from
+/// converged (Byers). It converges quadratically, at the cost of one
+/// inverse per step.
+pub fn signm(a: &Matrix) -> Option<Matrix> {
+    assert_eq!(a.m, a.n);
+    let n = a.n;
+    let mut x = a.clone();
+    let mut scaled = true;
+    for _ in 0..100 {
+        let f = LuFactor::new(&x)?;
+        let inv = f.inverse();
+        let mu = if scaled {
+            let log_det: f64 = (0..n).map(|i| math::ln(f.factors().get(i, i).abs())).sum();
+            math::powf(core::f64::consts::E, -log_det / n as f64)
+        } else {
+            1.0
+        };
+        if !mu.is_finite() || !inv.as_slice().iter().all(|v| v.is_finite()) {
+            return None;
+        }
+        let next = Matrix::from_vec(n, n, (0..n * n).map(|t| 0.5 * (mu * x.get(t / n, t % n) + inv.get(t / n, t % n) / mu)).collect());
+        let change = norm1(&Matrix::from_vec(n, n, (0..n * n).map(|t| next.get(t / n, t % n) - x.get(t / n, t % n)).collect()));
+        let size = norm1(&next);
+        x = next;
+        if change <= n as f64 * f64::EPSILON * size {
+            return Some(x);
+        }
+        // Scaling only helps in the early steps and spoils the final
+        // quadratic convergence.
+        scaled = scaled && change > 1e-2 * size;
+    }
+    None
+}
+
+/// Return the spectral projector onto the invariant subspace of A for its
+/// eigenvalues in `region`, along the subspace of the others, or None if
+/// an eigenvalue is on or too near the boundary.
+///
+/// P is idempotent and commutes with A; it is an orthogonal projector
+/// only if the two subspaces are orthogonal, as for normal A.
+pub fn spectral_projector(a: &Matrix, region: Region) -> Option<Matrix> {
+    assert_eq!(a.m, a.n);
+    let n = a.n;
+    let (s, inside) = match region {
+        Region::LeftHalfPlane => (signm(a)?, -1.0),
+        Region::RightHalfPlane => (signm(a)?, 1.0),
+        Region::Disc { center, radius } | Region::Exterior { center, radius } => {
+            // Z = (A - center I) / radius has the disc as the unit disc,
+            // and (I - Z)^-1 (I + Z) maps that onto the right half-plane.
+            assert!(radius > 0.0);
+            let z = |t: usize, sign: f64| {
+                let id = if t / n == t % n { 1.0 } else { 0.0 };
+                id + sign * (a.get(t / n, t % n) - center * id) / radius
+            };
+            let mut m = Matrix::from_vec(n, n, (0..n * n).map(|t| z(t, 1.0)).collect());
+            LuFactor::new(&Matrix::from_vec(n, n, (0..n * n).map(|t| z(t, -1.0)).collect()))?.solve_matrix(&mut m);
+            let inside = if matches!(region, Region::Disc { .. }) { 1.0 } else { -1.0 };
+            (signm(&m)?, inside)
+        }
+    };
+    Some(Matrix::from_vec(n, n, (0..n * n).map(|t| 0.5 * ((t / n == t % n) as u8 as f64 + inside * s.get(t / n, t % n))).collect()))
+}
+
+/// Return an orthonormal basis of the invariant subspace of A for its
+/// eigenvalues in `region`, as the columns of an n x k matrix Q, or None
+/// as for spectral_projector().
+///
+/// Then A Q = Q (Q^T A Q), and Q^T A Q has exactly those eigenvalues, so
+/// an eigenvalue problem splits into two smaller ones. k is the trace of
+/// the projector, rounded.
+pub fn invariant_subspace(a: &Matrix, region: Region) -> Option<Matrix> {
+    let p = spectral_projector(a, region)?;
+    let n = a.n;
+    let trace: f64 = (0..n).map(|i| p.get(i, i)).sum();
+    let k = (math::floor(trace + 0.5).max(0.0) as usize).min(n);
+    let u = svd(&p).u;
+    let cols: Vec<f64> = (0..n * k).map(|t| u.get(t / k, t % k)).collect();
+    Some(Matrix::from_vec(n, k, cols))
+}
+
+/// Return the product a b.
+pub(crate) fn product(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut c = Matrix::zero(a.m, b.n);
+    matmul(&a.view(), &b.view(), &mut c.view_mut());
+    c
+}
+
+/// Return the n x n identity.
+pub(crate) fn identity(n: usize) -> Matrix {
+    Matrix::from_vec(n, n, (0..n * n).map(|t| if t / n == t % n { 1.0 } else { 0.0 }).collect())
+}
+
+fn norm1(a: &Matrix) -> f64 {
+    (0..a.n).map(|j| (0..a.m).map(|i| a.get(i, j).abs()).sum::<f64>()).fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::eig::eigvals;
+    use alloc::vec;
+
+    /// Return V B V^-1 for a fixed, moderately conditioned V.
+    fn similar(b: &Matrix) -> Matrix {
+        let n = b.n;
+        let r = Matrix::rand_seeded(n, n, 4);
+        let v = Matrix::from_vec(n, n, (0..n * n).map(|t| r.get(t / n, t % n) + if t / n == t % n { 2.0 } else { 0.0 }).collect());
+        product(&product(&v, b), &LuFactor::new(&v).unwrap().inverse())
+    }
+
+    fn diag(d: &[f64]) -> Matrix {
+        let n = d.len();
+        Matrix::from_vec(n, n, (0..n * n).map(|t| if t / n == t % n { d[t / n] } else { 0.0 }).collect())
+    }
+
+    fn max_abs_diff(a: &Matrix, b: &Matrix) -> f64 {
+        a.as_slice().iter().zip(b.as_slice()).map(|(p, q)| (p - q).abs()).fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn sign_function_and_half_plane_projectors() {
+        let a = similar(&diag(&[-3.0, -0.5, 0.25, 2.0, 40.0]));
+        let s = signm(&a).unwrap();
+        let mut id = Matrix::zero(5, 5);
+        (0..5).for_each(|i| id.set(i, i, 1.0));
+        assert!(max_abs_diff(&product(&s, &s), &id) < 1e-12);
+        assert!(max_abs_diff(&product(&s, &a), &product(&a, &s)) < 1e-10);
+
+        let p = spectral_projector(&a, Region::LeftHalfPlane).unwrap();
+        let q = spectral_projector(&a, Region::RightHalfPlane).unwrap();
+        assert!(max_abs_diff(&product(&p, &p), &p) < 1e-12);
+        let sum = Matrix::from_vec(5, 5, (0..25).map(|t| p.as_slice()[t] + q.as_slice()[t]).collect());
+        assert!(max_abs_diff(&sum, &id) < 1e-14);
+        assert!(((0..5).map(|i| p.get(i, i)).sum::<f64>() - 2.0).abs() < 1e-12);
+
+        // A rotation has eigenvalues +-i on the axis, where sign is undefined.
+        let r = Matrix::from_vec(2, 2, vec![0.0, -1.0, 1.0, 0.0]);
+        assert!(signm(&r).is_none());
+    }
+
+    #[test]
+    fn disc_splits_the_spectrum() {
+        // Complex eigenvalues 0.5 +- 0.5i lie inside the unit disc, the
+        // real ones 0.2 inside and -3, 2 outside.
+        let mut b = diag(&[0.2, -3.0, 2.0, 0.5, 0.5]);
+        b.set(3, 4, 0.5);
+        b.set(4, 3, -0.5);
+        let a = similar(&b);
+        let q = invariant_subspace(&a, Region::Disc { center: 0.0, radius: 1.0 }).unwrap();
+        assert_eq!(q.n, 3);
+        // A Q = Q (Q^T A Q), and the small matrix has the inner eigenvalues.
+        let aq = product(&a, &q);
+        let small = product(&q.transpose(), &aq);
+        assert!(max_abs_diff(&aq, &product(&q, &small)) < 1e-10);
+        assert!(eigvals(&small).unwrap().iter().all(|(re, im)| re * re + im * im < 1.0));
+        let outer = invariant_subspace(&a, Region::Exterior { center: 0.0, radius: 1.0 }).unwrap();
+        assert_eq!(outer.n, 2);
+        // A disc around 2 holds just that eigenvalue.
+        assert_eq!(invariant_subspace(&a, Region::Disc { center: 2.5, radius: 1.0 }).unwrap().n, 1);
+    }
+
+    #[test]
+    fn matrix_powers_by_squaring() {
+        let a = Matrix::from_vec(3, 3, vec![1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0]);
+        assert!(max_abs_diff(&a.powi(0), &identity(3)) == 0.0);
+        let mut p = identity(3);
+        for k in 1..12 {
+            p = product(&p, &a);
+            assert!(max_abs_diff(&a.powi(k), &p) == 0.0, "{k}");
+        }
+        // A Jordan block: A^k has k and k (k - 1) / 2 above the diagonal.
+        let big = a.powi(1000);
+        assert_eq!((big.get(0, 1), big.get(0, 2)), (1000.0, 499500.0));
+
+        // The k-step transitions of a Markov chain tend to rows equal to
+        // the stationary distribution, here (0.25, 0.5, 0.25).
+        let t = Matrix::from_vec(3, 3, vec![0.5, 0.5, 0.0, 0.25, 0.5, 0.25, 0.0, 0.5, 0.5]);
+        let limit = t.powi(60);
+        assert!((0..3).all(|i| (limit.get(i, 0) - 0.25).abs() < 1e-14 && (limit.get(i, 1) - 0.5).abs() < 1e-14));
+    }
+}
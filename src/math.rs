@@ -0,0 +1,57 @@
+//! Float functions that are not available in `core`.
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("either the `std` or the `libm` feature must be enabled");
+
+/// Return the square root of x.
+#[inline]
+pub(crate) fn sqrt(x: f64) -> f64 {
+    #[cfg(feature = "std")]
+    return x.sqrt();
+    #[cfg(not(feature = "std"))]
+    return libm::sqrt(x);
+}
+
+/// Return the natural logarithm of x.
+#[inline]
+pub(crate) fn ln(x: f64) -> f64 {
+    #[cfg(feature = "std")]
+    return x.ln();
+    #[cfg(not(feature = "std"))]
+    return libm::log(x);
+}
+
+/// Return the sine and cosine of x.
+#[inline]
+pub(crate) fn sin_cos(x: f64) -> (f64, f64) {
+    #[cfg(feature = "std")]
+    return x.sin_cos();
+    #[cfg(not(feature = "std"))]
+    return libm::sincos(x);
+}
+
+/// Return the largest integer less than or equal to x.
+#[inline]
+pub(crate) fn floor(x: f64) -> f64 {
+    #[cfg(feature = "std")]
+    return x.floor();
+    #[cfg(not(feature = "std"))]
+    return libm::floor(x);
+}
+
+/// Return x raised to the power y.
+#[inline]
+pub(crate) fn powf(x: f64, y: f64) -> f64 {
+    #[cfg(feature = "std")]
+    return x.powf(y);
+    #[cfg(not(feature = "std"))]
+    return libm::pow(x, y);
+}
+
+/// Return sqrt(x^2 + y^2) without undue overflow.
+#[inline]
+pub(crate) fn hypot(x: f64, y: f64) -> f64 {
+    #[cfg(feature = "std")]
+    return x.hypot(y);
+    #[cfg(not(feature = "std"))]
+    return libm::hypot(x, y);
+}
@@ -0,0 +1,441 @@
+//! Geometric multigrid for structured grids.
+//!
+//! Each dimension with an odd number 2k + 1 of interior points is coarsened
+//! to k points, keeping every other point; prolongation is multilinear
+//! interpolation and the coarse operators are the Galerkin products
+//! P^T A P. Lengths of the form 2^k - 1 coarsen all the way down.
+//!
+//! The coarsest grid is factored densely only up to `max_direct` points.
+//! When coarsening stops early, at `max_levels` or because no dimension is
+//! odd, a larger coarsest grid, or a singular one, is smoothed with
+//! COARSE_SWEEPS sweeps of the smoother instead.
+use crate::coloring::Coloring;
+use crate::iterative::Preconditioner;
+use crate::lu::{lu, lu_solve};
+use crate::operations::{axpy, norm2};
+use crate::solver::{Monitor, SolveResult, StoppingCriterion};
+use crate::stationary::{inverse_diagonal, jacobi_sweep, multicolor_sor_sweep, sor_sweep};
+use crate::{Matrix, SparseMatrix};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Relaxation method applied on each level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Smoother {
+    /// Weighted Jacobi with the given weight.
+    Jacobi(f64),
+    /// Gauss-Seidel, forward before and backward after the coarse
+    /// correction.
+    GaussSeidel,
+    /// SOR with the given weight, ordered like `GaussSeidel`.
+    Sor(f64),
+    /// Gauss-Seidel in the greedy multicolor ordering, colors forward
+    /// before and backward after the coarse correction. Each color is
+    /// relaxed in parallel with the `parallel` feature.
+    MulticolorGaussSeidel,
+}
+
+/// Number of coarse corrections per level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cycle {
+    /// One coarse correction.
+    V,
+    /// Two coarse corrections.
+    W,
+}
+
+/// Multigrid settings, built with `MultigridOptions::new().cycle(Cycle::W)`.
+#[derive(Clone, Debug)]
+pub struct MultigridOptions {
+    smoother: Smoother,
+    pre_sweeps: usize,
+    post_sweeps: usize,
+    cycle: Cycle,
+    /// Maximum number of levels, including the finest.
+    max_levels: usize,
+    /// Number of points at or below which a level is solved directly.
+    coarse_size: usize,
+    /// Largest coarsest level that is factored densely.
+    max_direct: usize,
+}
+
+/// Smoothing sweeps on a coarsest level that is not factored.
+const COARSE_SWEEPS: usize = 20;
+
+impl MultigridOptions {
+    /// Return the default options: a V-cycle with one Gauss-Seidel sweep
+    /// before and after, and a direct solve below 64 points, factoring at
+    /// most 2000.
+    pub fn new() -> MultigridOptions {
+        MultigridOptions {
+            smoother: Smoother::GaussSeidel,
+            pre_sweeps: 1,
+            post_sweeps: 1,
+            cycle: Cycle::V,
+            max_levels: 20,
+            coarse_size: 64,
+            max_direct: 2000,
+        }
+    }
+
+    /// Set the smoother.
+    pub fn smoother(mut self, smoother: Smoother) -> MultigridOptions {
+        self.smoother = smoother;
+        self
+    }
+
+    /// Set the number of smoothing sweeps before and after the coarse
+    /// correction.
+    pub fn sweeps(mut self, pre: usize, post: usize) -> MultigridOptions {
+        self.pre_sweeps = pre;
+        self.post_sweeps = post;
+        self
+    }
+
+    /// Set the cycle type.
+    pub fn cycle(mut self, cycle: Cycle) -> MultigridOptions {
+        self.cycle = cycle;
+        self
+    }
+
+    /// Set the maximum number of levels.
+    pub fn max_levels(mut self, max_levels: usize) -> MultigridOptions {
+        assert!(max_levels > 0);
+        self.max_levels = max_levels;
+        self
+    }
+
+    /// Set the number of points at or below which a level is solved
+    /// directly.
+    pub fn coarse_size(mut self, coarse_size: usize) -> MultigridOptions {
+        self.coarse_size = coarse_size;
+        self
+    }
+
+    /// Set the largest coarsest level that is factored densely; larger
+    /// ones are smoothed instead.
+    pub fn max_direct(mut self, max_direct: usize) -> MultigridOptions {
+        self.max_direct = max_direct;
+        self
+    }
+}
+
+impl Default for MultigridOptions {
+    fn default() -> MultigridOptions {
+        MultigridOptions::new()
+    }
+}
+
+/// One grid of the hierarchy above the coarsest.
+struct GridLevel {
+    dims: Vec<usize>,
+    a: SparseMatrix,
+    /// Interpolation from the next coarser grid, and its transpose.
+    p: SparseMatrix,
+    r: SparseMatrix,
+    inv_diag: Vec<f64>,
+    /// The coloring for the multicolor smoother.
+    coloring: Option<Coloring>,
+}
+
+/// Solver on the coarsest grid.
+enum Coarse {
+    /// LU factors of the operator.
+    Direct { lu: Matrix, piv: Vec<usize> },
+    /// COARSE_SWEEPS sweeps of the smoother from zero, alternately forward
+    /// and backward so that the result stays symmetric.
+    Smoothed { inv_diag: Vec<f64>, coloring: Option<Coloring> },
+}
+
+/// Geometric multigrid hierarchy for an operator on a structured grid.
+///
+/// With equal pre- and post-sweeps the cycle is symmetric, so for SPD A it
+/// can precondition cg().
+pub struct Multigrid {
+    levels: Vec<GridLevel>,
+    coarse_dims: Vec<usize>,
+    /// Coarsest operator and its solver.
+    coarse_a: SparseMatrix,
+    coarse: Coarse,
+    opts: MultigridOptions,
+}
+
+impl Multigrid {
+    /// Build the hierarchy for `a`, whose unknowns are the points of a grid
+    /// with the given dimensions numbered as in `SparseMatrix::laplacian`.
+    pub fn new(a: &SparseMatrix, dims: &[usize], opts: &MultigridOptions) -> Multigrid {
+        assert_eq!(a.m, a.n);
+        assert_eq!(a.m, dims.iter().product::<usize>());
+        let mut levels = Vec::new();
+        let mut a = a.clone();
+        let mut dims = dims.to_vec();
+        while levels.len() + 1 < opts.max_levels && a.m > opts.coarse_size {
+            let coarse_dims: Vec<usize> = dims.iter().map(|&n| if coarsens(n) { n / 2 } else { n }).collect();
+            if coarse_dims == dims {
+                break;
+            }
+            let p = prolongator(&dims);
+            let r = p.transpose();
+            let coarse = r.matmul(&a).matmul(&p);
+            let inv_diag = inverse_diagonal(&a);
+            let coloring = (opts.smoother == Smoother::MulticolorGaussSeidel).then(|| Coloring::greedy(&a));
+            levels.push(GridLevel {
+                dims,
+                a,
+                p,
+                r,
+                inv_diag,
+                coloring,
+            });
+            a = coarse;
+            dims = coarse_dims;
+        }
+        let direct = (a.m <= opts.max_direct).then(|| {
+            let mut lu_factors = a.to_dense();
+            lu(&mut lu_factors).map(|piv| Coarse::Direct { lu: lu_factors, piv })
+        });
+        let coarse = direct.flatten().unwrap_or_else(|| Coarse::Smoothed {
+            inv_diag: inverse_diagonal(&a),
+            coloring: (opts.smoother == Smoother::MulticolorGaussSeidel).then(|| Coloring::greedy(&a)),
+        });
+        Multigrid {
+            levels,
+            coarse_dims: dims,
+            coarse_a: a,
+            coarse,
+            opts: opts.clone(),
+        }
+    }
+
+    /// Return the grid dimensions on each level, finest first.
+    pub fn grids(&self) -> Vec<Vec<usize>> {
+        let mut res: Vec<Vec<usize>> = self.levels.iter().map(|l| l.dims.clone()).collect();
+        res.push(self.coarse_dims.clone());
+        res
+    }
+
+    /// Improve x as a solution of A x = b with one cycle.
+    pub fn cycle(&self, b: &[f64], x: &mut [f64]) {
+        self.cycle_level(0, b, x);
+    }
+
+    /// Solve A x = b by repeated cycles, checking `stop` before each one.
+    pub fn solve(
+        &self,
+        b: &[f64],
+        x: &mut [f64],
+        mut stop: impl StoppingCriterion,
+        mut monitor: Option<Monitor>,
+    ) -> SolveResult {
+        let a = self.levels.first().map_or(&self.coarse_a, |l| &l.a);
+        let mut r = vec![0.0; b.len()];
+        let residual = |x: &[f64], r: &mut [f64]| {
+            a.matvec(x, r);
+            r.iter_mut().zip(b).for_each(|(ri, bi)| *ri = bi - *ri);
+            norm2(r)
+        };
+        let b_norm = norm2(b);
+        let mut r_norm = residual(x, &mut r);
+        let mut res = SolveResult::new(r_norm);
+        while res.check(&mut stop, r_norm, b_norm, None) {
+            self.cycle(b, x);
+            r_norm = residual(x, &mut r);
+            if !res.record(r_norm, &mut monitor) {
+                break;
+            }
+        }
+        res
+    }
+
+    fn cycle_level(&self, k: usize, b: &[f64], x: &mut [f64]) {
+        let Some(level) = self.levels.get(k) else {
+            match &self.coarse {
+                Coarse::Direct { lu, piv } => {
+                    x.copy_from_slice(b);
+                    lu_solve(lu, piv, x);
+                }
+                Coarse::Smoothed { inv_diag, coloring } => {
+                    x.fill(0.0);
+                    for sweep in 0..COARSE_SWEEPS {
+                        self.smooth(&self.coarse_a, inv_diag, coloring.as_ref(), b, x, sweep % 2 == 0);
+                    }
+                }
+            }
+            return;
+        };
+        let mut r = vec![0.0; b.len()];
+        for _ in 0..self.opts.pre_sweeps {
+            self.smooth(&level.a, &level.inv_diag, level.coloring.as_ref(), b, x, true);
+        }
+        let corrections = match self.opts.cycle {
+            Cycle::V => 1,
+            Cycle::W => 2,
+        };
+        let mut bc = vec![0.0; level.r.m];
+        let mut xc = vec![0.0; level.r.m];
+        for _ in 0..corrections {
+            level.a.matvec(x, &mut r);
+            r.iter_mut().zip(b).for_each(|(ri, bi)| *ri = bi - *ri);
+            level.r.matvec(&r, &mut bc);
+            xc.fill(0.0);
+            self.cycle_level(k + 1, &bc, &mut xc);
+            level.p.matvec(&xc, &mut r);
+            axpy(1.0, &r, x);
+        }
+        for _ in 0..self.opts.post_sweeps {
+            self.smooth(&level.a, &level.inv_diag, level.coloring.as_ref(), b, x, false);
+        }
+    }
+
+    /// Apply one smoothing sweep.
+    fn smooth(&self, a: &SparseMatrix, inv_diag: &[f64], coloring: Option<&Coloring>, b: &[f64], x: &mut [f64], forward: bool) {
+        match self.opts.smoother {
+            Smoother::Jacobi(omega) => jacobi_sweep(a, inv_diag, b, x, omega),
+            Smoother::GaussSeidel => sor_sweep(a, inv_diag, b, x, 1.0, forward),
+            Smoother::Sor(omega) => sor_sweep(a, inv_diag, b, x, omega, forward),
+            Smoother::MulticolorGaussSeidel => {
+                multicolor_sor_sweep(a, inv_diag, coloring.unwrap(), b, x, 1.0, forward)
+            }
+        }
+    }
+}
+
+impl Preconditioner for Multigrid {
+    fn apply(&self, r: &[f64], z: &mut [f64]) {
+        z.fill(0.0);
+        self.cycle(r, z);
+    }
+}
+
+/// Return whether a dimension of n interior points can be coarsened.
+fn coarsens(n: usize) -> bool {
+    n >= 3 && n % 2 == 1
+}
+
+/// Return the interpolation from the coarse grid to the grid `dims`, the
+/// tensor product of linear interpolation in each coarsened dimension.
+fn prolongator(dims: &[usize]) -> SparseMatrix {
+    // Per dimension, the (coarse index, weight) pairs of each fine point.
+    let weights: Vec<Vec<Vec<(usize, f64)>>> = dims
+        .iter()
+        .map(|&n| {
+            (0..n)
+                .map(|i| {
+                    if !coarsens(n) {
+                        vec![(i, 1.0)]
+                    } else if i % 2 == 1 {
+                        vec![(i / 2, 1.0)]
+                    } else {
+                        let mut w = Vec::new();
+                        if i > 0 {
+                            w.push((i / 2 - 1, 0.5));
+                        }
+                        if i / 2 < n / 2 {
+                            w.push((i / 2, 0.5));
+                        }
+                        w
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let coarse_dims: Vec<usize> = dims.iter().map(|&n| if coarsens(n) { n / 2 } else { n }).collect();
+
+    let n: usize = dims.iter().product();
+    let mut triplets = Vec::new();
+    for row in 0..n {
+        // Expand the product of the per-dimension weights.
+        let mut terms = vec![(0usize, 1.0)];
+        let mut rest = row;
+        let mut index = vec![0; dims.len()];
+        for d in (0..dims.len()).rev() {
+            index[d] = rest % dims[d];
+            rest /= dims[d];
+        }
+        for d in 0..dims.len() {
+            let len = coarse_dims[d];
+            terms = terms
+                .iter()
+                .flat_map(|&(col, w)| weights[d][index[d]].iter().map(move |&(c, v)| (col * len + c, w * v)))
+                .collect();
+        }
+        triplets.extend(terms.into_iter().map(|(col, w)| (row, col, w)));
+    }
+    SparseMatrix::from_triplets(n, coarse_dims.iter().product(), &triplets)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iterative::{cg, Identity};
+    use crate::solver::tolerance;
+
+    #[test]
+    fn multigrid_grid_hierarchy() {
+        let a = SparseMatrix::laplacian(&[31, 15, 8]);
+        let mg = Multigrid::new(&a, &[31, 15, 8], &MultigridOptions::new().coarse_size(1));
+        assert_eq!(mg.grids(), vec![vec![31, 15, 8], vec![15, 7, 8], vec![7, 3, 8], vec![3, 1, 8], vec![1, 1, 8]]);
+
+        // Interpolation reproduces linear functions away from the boundary.
+        let p = prolongator(&[7]);
+        let mut fine = [0.0; 7];
+        p.matvec(&[1.0, 2.0, 3.0], &mut fine);
+        assert_eq!(fine, [0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 1.5]);
+
+        let mg = Multigrid::new(&a, &[31, 15, 8], &MultigridOptions::new().max_levels(3));
+        assert_eq!(mg.grids().len(), 3);
+    }
+
+    #[test]
+    fn multigrid_solve_and_precondition() {
+        let dims = [31, 31];
+        let a = SparseMatrix::laplacian(&dims);
+        let b: Vec<f64> = (0..a.m).map(|i| ((i * 7 % 13) as f64) - 6.0).collect();
+        for opts in [
+            MultigridOptions::new(),
+            MultigridOptions::new().smoother(Smoother::Jacobi(0.8)).sweeps(2, 2),
+            MultigridOptions::new().cycle(Cycle::W),
+            MultigridOptions::new().smoother(Smoother::Sor(1.2)),
+            MultigridOptions::new().smoother(Smoother::MulticolorGaussSeidel),
+        ] {
+            let mg = Multigrid::new(&a, &dims, &opts);
+            let mut x = vec![0.0; a.m];
+            let res = mg.solve(&b, &mut x, tolerance(1e-8, 30), None);
+            assert!(res.converged);
+        }
+
+        let mut x = vec![0.0; a.m];
+        let plain = cg(&a, &b, &mut x, &Identity, tolerance(1e-8, 2000), None);
+        let mg = Multigrid::new(&a, &dims, &MultigridOptions::new());
+        let mut x = vec![0.0; a.m];
+        let res = cg(&a, &b, &mut x, &mg, tolerance(1e-8, 2000), None);
+        assert!(res.converged);
+        assert!(res.iterations * 5 < plain.iterations);
+    }
+
+    #[test]
+    fn multigrid_even_grid_smooths_the_coarsest_level() {
+        // No dimension of a 64 x 64 grid coarsens, and 4096 points are too
+        // many to factor, so the only level is smoothed.
+        let dims = [64, 64];
+        let a = SparseMatrix::laplacian(&dims);
+        let mg = Multigrid::new(&a, &dims, &MultigridOptions::new());
+        assert_eq!(mg.grids(), vec![vec![64, 64]]);
+        assert!(matches!(mg.coarse, Coarse::Smoothed { .. }));
+        let b: Vec<f64> = (0..a.m).map(|i| ((i * 7 % 13) as f64) - 6.0).collect();
+        let mut x = vec![0.0; a.m];
+        let plain = cg(&a, &b, &mut x, &Identity, tolerance(1e-8, 2000), None);
+        let mut x = vec![0.0; a.m];
+        let res = cg(&a, &b, &mut x, &mg, tolerance(1e-8, 2000), None);
+        assert!(res.converged && res.iterations * 2 < plain.iterations);
+
+        // Smoothing a stopped-early hierarchy still preconditions.
+        let dims = [31, 31];
+        let a = SparseMatrix::laplacian(&dims);
+        let opts = MultigridOptions::new().max_levels(2).max_direct(100);
+        let mg = Multigrid::new(&a, &dims, &opts);
+        let mut x = vec![0.0; a.m];
+        let res = cg(&a, &b[..a.m], &mut x, &mg, tolerance(1e-8, 200), None);
+        assert!(res.converged);
+    }
+}
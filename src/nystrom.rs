@@ -0,0 +1,198 @@
+//! Nystrom low-rank approximation of SPD matrices.
+//!
+//! Sampling k columns C = K[:, I] of an SPD n x n K, with W = K[I, I],
+//! gives K ~ C W^+ C^T. Only the nk sampled entries are needed, which for
+//! a kernel matrix means nk kernel evaluations instead of n^2. The
+//! approximation is kept as an eigendecomposition U diag(lambda) U^T, from
+//! which products, a factor and a preconditioner for K + mu I follow.
+use crate::iterative::{LinearOperator, Preconditioner};
+use crate::operations::{gemv, gemv_t};
+use crate::rng::{sample_indices, Rng};
+use crate::svd::svd;
+use crate::{math, Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Rank-r approximation K ~ U diag(lambda) U^T with orthonormal U.
+pub struct Nystrom {
+    /// n x r.
+    u: Matrix,
+    /// Decreasing, positive.
+    lambda: Vec<f64>,
+}
+
+impl Nystrom {
+    /// Approximate the n x n SPD matrix with entries kernel(i, j) from the
+    /// columns `cols`.
+    ///
+    /// Eigenvalues of the core W below tol times its largest are dropped
+    /// in the pseudo-inverse, so duplicated or nearly dependent columns
+    /// lower the rank rather than spoiling the result.
+    pub fn from_kernel(n: usize, cols: &[usize], kernel: impl Fn(usize, usize) -> f64, tol: f64) -> Nystrom {
+        let k = cols.len();
+        assert!(cols.iter().all(|&j| j < n));
+        let mut c = Matrix::zero(n, k);
+        for i in 0..n {
+            for (jj, &j) in cols.iter().enumerate() {
+                c.set(i, jj, kernel(i, j));
+            }
+        }
+        let w = Matrix::from_vec(k, k, (0..k * k).map(|t| c.get(cols[t / k], t % k)).collect());
+
+        // W = V diag(s) V^T, and F = C V_r diag(s_r)^-1/2 has F F^T = C W^+ C^T.
+        let fw = svd(&w);
+        let r = fw.rank(tol);
+        let mut f = Matrix::zero(n, r);
+        for i in 0..n {
+            for q in 0..r {
+                let sum: f64 = (0..k).map(|j| c.get(i, j) * fw.vt.get(q, j)).sum();
+                f.set(i, q, sum / math::sqrt(fw.s[q]));
+            }
+        }
+        // The eigendecomposition of F F^T is the SVD of F.
+        let ff = svd(&f);
+        let r = ff.rank(f64::EPSILON);
+        let u = Matrix::from_vec(n, r, (0..n * r).map(|t| ff.u.get(t / r, t % r)).collect());
+        Nystrom { u, lambda: ff.s[..r].iter().map(|s| s * s).collect() }
+    }
+
+    /// Approximate the SPD matrix k from the columns `cols`.
+    pub fn from_matrix(k: &Matrix, cols: &[usize], tol: f64) -> Nystrom {
+        assert_eq!(k.m, k.n);
+        Nystrom::from_kernel(k.n, cols, |i, j| k.get(i, j), tol)
+    }
+
+    /// Approximate k from `rank` columns sampled uniformly.
+    pub fn sample(k: &Matrix, rank: usize, rng: &mut impl Rng) -> Nystrom {
+        Nystrom::from_matrix(k, &sample_indices(rng, k.n, rank), 1e-12)
+    }
+
+    /// Return the rank r of the approximation.
+    pub fn rank(&self) -> usize {
+        self.lambda.len()
+    }
+
+    /// Return the eigenvalues of the approximation, in decreasing order.
+    pub fn eigenvalues(&self) -> &[f64] {
+        &self.lambda
+    }
+
+    /// Return the n x r orthonormal eigenvectors.
+    pub fn eigenvectors(&self) -> &Matrix {
+        &self.u
+    }
+
+    /// Return the n x r factor F = U diag(lambda)^1/2, with F F^T the
+    /// approximation.
+    pub fn factor(&self) -> Matrix {
+        let (n, r) = (self.u.m, self.rank());
+        Matrix::from_vec(n, r, (0..n * r).map(|t| self.u.get(t / r, t % r) * math::sqrt(self.lambda[t % r])).collect())
+    }
+
+    /// Return a preconditioner for K + mu I, mu > 0.
+    ///
+    /// It is exact on the span of U, scaled so that the rest of the
+    /// spectrum is mapped near lambda_r + mu, following Frangella, Tropp
+    /// and Udell's Nystrom PCG. CG on K + mu I then converges at a rate
+    /// set by how quickly the eigenvalues of K beyond r decay.
+    pub fn preconditioner(&self, mu: f64) -> NystromPreconditioner<'_> {
+        assert!(mu > 0.0);
+        NystromPreconditioner { approx: self, mu }
+    }
+
+    /// Compute t = U^T x.
+    fn project(&self, x: &[f64]) -> Vec<f64> {
+        let mut t = vec![0.0; self.rank()];
+        gemv_t(1.0, &self.u.view(), x, 0.0, &mut t);
+        t
+    }
+}
+
+impl LinearOperator for Nystrom {
+    fn nrows(&self) -> usize {
+        self.u.m
+    }
+
+    fn ncols(&self) -> usize {
+        self.u.m
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        let mut t = self.project(x);
+        t.iter_mut().zip(&self.lambda).for_each(|(ti, l)| *ti *= l);
+        gemv(1.0, &self.u.view(), &t, 0.0, y);
+    }
+}
+
+/// Preconditioner (lambda_r + mu) U (diag(lambda) + mu I)^-1 U^T + I - U U^T
+/// for K + mu I, from Nystrom::preconditioner().
+pub struct NystromPreconditioner<'a> {
+    approx: &'a Nystrom,
+    mu: f64,
+}
+
+impl Preconditioner for NystromPreconditioner<'_> {
+    fn apply(&self, r: &[f64], z: &mut [f64]) {
+        let a = self.approx;
+        let Some(&last) = a.lambda.last() else {
+            z.copy_from_slice(r);
+            return;
+        };
+        let mut t = a.project(r);
+        t.iter_mut().zip(&a.lambda).for_each(|(ti, l)| *ti *= (last + self.mu) / (l + self.mu) - 1.0);
+        z.copy_from_slice(r);
+        gemv(1.0, &a.u.view(), &t, 1.0, z);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iterative::{cg, Identity};
+    use crate::rng::Lcg;
+    use crate::solver::tolerance;
+
+    fn rbf(n: usize) -> Matrix {
+        let x: Vec<f64> = (0..n).map(|i| i as f64 / n as f64).collect();
+        Matrix::from_vec(n, n, (0..n * n).map(|t| (-(x[t / n] - x[t % n]).powi(2) / 0.1).exp()).collect())
+    }
+
+    #[test]
+    fn nystrom_approximates_kernel() {
+        let (n, k) = (60, rbf(60));
+        let mut rng = Lcg::new(3);
+        let approx = Nystrom::sample(&k, 20, &mut rng);
+        let f = approx.factor();
+        let mut err: f64 = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let fij: f64 = (0..approx.rank()).map(|q| f.get(i, q) * f.get(j, q)).sum();
+                err = err.max((fij - k.get(i, j)).abs());
+            }
+        }
+        assert!(err < 1e-3, "{err}");
+        assert!(approx.eigenvalues().windows(2).all(|w| w[0] >= w[1] && w[1] > 0.0));
+
+        // Sampled columns are reproduced exactly, and repeating one does
+        // not raise the rank.
+        let exact = Nystrom::from_matrix(&k, &[3, 30, 30], 1e-12);
+        assert_eq!(exact.rank(), 2);
+        let (mut e, mut y) = (vec![0.0; n], vec![0.0; n]);
+        e[30] = 1.0;
+        exact.apply(&e, &mut y);
+        assert!((0..n).all(|i| (y[i] - k.get(i, 30)).abs() < 1e-10));
+    }
+
+    #[test]
+    fn nystrom_preconditioned_cg() {
+        let (n, k, mu) = (80, rbf(80), 1e-4);
+        let shifted = Matrix::from_vec(n, n, (0..n * n).map(|t| k.get(t / n, t % n) + if t / n == t % n { mu } else { 0.0 }).collect());
+        let b: Vec<f64> = (0..n).map(|i| (i as f64).cos()).collect();
+        let approx = Nystrom::sample(&k, 30, &mut Lcg::new(8));
+        let mut x = vec![0.0; n];
+        let plain = cg(&shifted, &b, &mut x, &Identity, tolerance(1e-8, 1000), None);
+        let mut y = vec![0.0; n];
+        let pre = cg(&shifted, &b, &mut y, &approx.preconditioner(mu), tolerance(1e-8, 1000), None);
+        assert!(pre.converged && pre.iterations * 3 < plain.iterations, "{} vs {}", pre.iterations, plain.iterations);
+    }
+}
@@ -0,0 +1,196 @@
+//! Out-of-core matrices backed by memory-mapped files.
+//!
+//! Files hold the raw row-major entries in native byte order with no header.
+//! `ooc_matmul` streams tiles of the operands through a bounded in-memory
+//! working set, so the matrices can be larger than RAM.
+use crate::{matmul_acc, Matrix};
+use memmap2::{Mmap, MmapMut};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+/// Reinterpret mapped bytes as f64 entries.
+fn as_f64(bytes: &[u8]) -> &[f64] {
+    let (prefix, data, suffix) = unsafe { bytes.align_to::<f64>() };
+    assert!(prefix.is_empty() && suffix.is_empty());
+    data
+}
+
+/// Reinterpret mutable mapped bytes as f64 entries.
+fn as_f64_mut(bytes: &mut [u8]) -> &mut [f64] {
+    let (prefix, data, suffix) = unsafe { bytes.align_to_mut::<f64>() };
+    assert!(prefix.is_empty() && suffix.is_empty());
+    data
+}
+
+/// Read-only matrix stored in a file.
+pub struct FileMatrix {
+    pub m: usize,
+    pub n: usize,
+    map: Mmap,
+}
+
+impl FileMatrix {
+    /// Map an existing m x n matrix file.
+    pub fn open<P: AsRef<Path>>(path: P, m: usize, n: usize) -> io::Result<FileMatrix> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() != (m * n * std::mem::size_of::<f64>()) as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "file size does not match dimensions"));
+        }
+        // The mapping is only valid while no one else truncates the file.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(FileMatrix {
+            m,
+            n,
+            map,
+        })
+    }
+
+    /// Write a matrix to a new file.
+    pub fn write<P: AsRef<Path>>(path: P, a: &Matrix) -> io::Result<()> {
+        let mut out = FileMatrixMut::create(path, a.m, a.n)?;
+        for (i, row) in a.as_slice().chunks(a.ld()).enumerate() {
+            out.data_mut()[i * a.n..(i + 1) * a.n].copy_from_slice(&row[..a.n]);
+        }
+        out.flush()
+    }
+
+    /// Return the entries in row-major order.
+    pub fn data(&self) -> &[f64] {
+        as_f64(&self.map)
+    }
+
+    /// Copy the block with rows `i0..i1` and columns `j0..j1` into memory.
+    pub fn block(&self, i0: usize, i1: usize, j0: usize, j1: usize) -> Matrix {
+        let data = self.data();
+        let values = (i0..i1)
+            .flat_map(|i| &data[i * self.n + j0..i * self.n + j1])
+            .copied()
+            .collect();
+        Matrix::from_vec(i1 - i0, j1 - j0, values)
+    }
+}
+
+/// Writable matrix stored in a file.
+pub struct FileMatrixMut {
+    pub m: usize,
+    pub n: usize,
+    map: MmapMut,
+}
+
+impl FileMatrixMut {
+    /// Create (or truncate) a zeroed m x n matrix file.
+    pub fn create<P: AsRef<Path>>(path: P, m: usize, n: usize) -> io::Result<FileMatrixMut> {
+        assert!(m * n > 0);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len((m * n * std::mem::size_of::<f64>()) as u64)?;
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        Ok(FileMatrixMut {
+            m,
+            n,
+            map,
+        })
+    }
+
+    /// Return the entries in row-major order.
+    pub fn data(&self) -> &[f64] {
+        as_f64(&self.map)
+    }
+
+    /// Return the mutable entries in row-major order.
+    pub fn data_mut(&mut self) -> &mut [f64] {
+        as_f64_mut(&mut self.map)
+    }
+
+    /// Write the in-memory block `c` back at row `i0` and column `j0`.
+    pub fn set_block(&mut self, i0: usize, j0: usize, c: &Matrix) {
+        let n = self.n;
+        let data = self.data_mut();
+        for (i, row) in c.as_slice().chunks(c.ld()).enumerate() {
+            let start = (i0 + i) * n + j0;
+            data[start..start + c.n].copy_from_slice(&row[..c.n]);
+        }
+    }
+
+    /// Flush modified pages to the file.
+    pub fn flush(&self) -> io::Result<()> {
+        self.map.flush()
+    }
+}
+
+/// Compute c = a * b with at most about `working_set` bytes of tiles in
+/// memory at once.
+///
+/// C is computed one square tile at a time, accumulating over matching tiles
+/// of A and B read from the mapped files.
+pub fn ooc_matmul(a: &FileMatrix, b: &FileMatrix, c: &mut FileMatrixMut, working_set: usize) -> io::Result<()> {
+    assert_eq!(a.m, c.m);
+    assert_eq!(a.n, b.m);
+    assert_eq!(b.n, c.n);
+
+    // Three tiles (of A, B and C) must fit.
+    let tile = ((working_set / (3 * std::mem::size_of::<f64>())) as f64).sqrt() as usize;
+    let tile = tile.max(1);
+
+    for i0 in (0..a.m).step_by(tile) {
+        let i1 = (i0 + tile).min(a.m);
+        for j0 in (0..b.n).step_by(tile) {
+            let j1 = (j0 + tile).min(b.n);
+            let mut ctile = Matrix::zero(i1 - i0, j1 - j0);
+            for k0 in (0..a.n).step_by(tile) {
+                let k1 = (k0 + tile).min(a.n);
+                let atile = a.block(i0, i1, k0, k1);
+                let btile = b.block(k0, k1, j0, j1);
+                matmul_acc(&atile.view(), &btile.view(), &mut ctile.view_mut());
+            }
+            c.set_block(i0, j0, &ctile);
+        }
+    }
+    c.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{approx, matmul, MatrixIndex};
+
+    #[test]
+    fn ooc_matmul_matches_matmul() {
+        let dir = std::env::temp_dir().join(format!("rnla-ooc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let x = Matrix::rand(13, 9);
+        let y = Matrix::rand(9, 11);
+        let mut z = Matrix::zero(13, 11);
+        matmul(&x.view(), &y.view(), &mut z.view_mut());
+
+        FileMatrix::write(dir.join("a"), &x).unwrap();
+        FileMatrix::write(dir.join("b"), &y).unwrap();
+        let a = FileMatrix::open(dir.join("a"), 13, 9).unwrap();
+        let b = FileMatrix::open(dir.join("b"), 9, 11).unwrap();
+        let mut c = FileMatrixMut::create(dir.join("c"), 13, 11).unwrap();
+        // Room for three 4 x 4 tiles.
+        ooc_matmul(&a, &b, &mut c, 3 * 16 * 8).unwrap();
+
+        let w = FileMatrix::open(dir.join("c"), 13, 11).unwrap().block(0, 13, 0, 11);
+        for i in 0..13 {
+            for j in 0..11 {
+                assert!(approx(w.get(i, j), z.get(i, j)));
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn open_wrong_size() {
+        let path = std::env::temp_dir().join(format!("rnla-ooc-size-{}", std::process::id()));
+        FileMatrix::write(&path, &Matrix::zero(2, 2)).unwrap();
+        assert!(FileMatrix::open(&path, 3, 3).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
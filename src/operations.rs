@@ -1,10 +1,181 @@
-use crate::Matrix;
+//! Level-1 and level-2 vector and matrix operations.
+//!
+//! With the `parallel` feature, inputs at least `PARALLEL_MIN_LEN` long are
+//! processed with rayon; smaller inputs stay serial to avoid the overhead.
+use crate::math::sqrt;
+use crate::MatrixView;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "parallel")]
+use crate::parallel;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
-fn saxpy(alpha: f32, x: &[f32], y: &mut [f32]) {
+/// Minimum amount of work before an operation is run in parallel.
+pub const PARALLEL_MIN_LEN: usize = 1 << 14;
+
+/// Compute y = alpha * x + y.
+pub fn axpy(alpha: f64, x: &[f64], y: &mut [f64]) {
     assert_eq!(x.len(), y.len());
+    #[cfg(feature = "parallel")]
+    if x.len() >= PARALLEL_MIN_LEN {
+        parallel::run(|| y.par_iter_mut().zip(x).for_each(|(yval, xval)| *yval += alpha * *xval));
+        return;
+    }
     for (xval, yval) in x.iter().zip(y.iter_mut()) {
-        *yval = alpha * *xval + *yval;
+        *yval += alpha * *xval;
+    }
+}
+
+/// Compute x = alpha * x.
+pub fn scale(alpha: f64, x: &mut [f64]) {
+    #[cfg(feature = "parallel")]
+    if x.len() >= PARALLEL_MIN_LEN {
+        parallel::run(|| x.par_iter_mut().for_each(|xval| *xval *= alpha));
+        return;
+    }
+    for xval in x.iter_mut() {
+        *xval *= alpha;
+    }
+}
+
+/// Compute the elementwise product z = x .* y.
+pub fn hadamard(x: &[f64], y: &[f64], z: &mut [f64]) {
+    assert_eq!(x.len(), y.len());
+    assert_eq!(x.len(), z.len());
+    #[cfg(feature = "parallel")]
+    if x.len() >= PARALLEL_MIN_LEN {
+        parallel::run(|| {
+            z.par_iter_mut()
+                .zip(x.par_iter().zip(y))
+                .for_each(|(zval, (xval, yval))| *zval = xval * yval)
+        });
+        return;
+    }
+    for (zval, (xval, yval)) in z.iter_mut().zip(x.iter().zip(y)) {
+        *zval = xval * yval;
+    }
+}
+
+/// Return the dot product of x and y.
+pub fn dot(x: &[f64], y: &[f64]) -> f64 {
+    assert_eq!(x.len(), y.len());
+    #[cfg(feature = "parallel")]
+    if x.len() >= PARALLEL_MIN_LEN {
+        return parallel::run(|| x.par_iter().zip(y).map(|(a, b)| a * b).sum());
+    }
+    x.iter().zip(y).map(|(a, b)| a * b).sum()
+}
+
+/// Return the Euclidean norm of x.
+pub fn norm2(x: &[f64]) -> f64 {
+    sqrt(dot(x, x))
+}
+
+/// Return the sum of absolute values of x.
+pub fn norm1(x: &[f64]) -> f64 {
+    #[cfg(feature = "parallel")]
+    if x.len() >= PARALLEL_MIN_LEN {
+        return parallel::run(|| x.par_iter().map(|a| a.abs()).sum());
     }
+    x.iter().map(|a| a.abs()).sum()
 }
 
-fn gaxpy() {}
+/// Return the largest absolute value in x.
+pub fn norm_inf(x: &[f64]) -> f64 {
+    #[cfg(feature = "parallel")]
+    if x.len() >= PARALLEL_MIN_LEN {
+        return parallel::run(|| x.par_iter().map(|a| a.abs()).reduce(|| 0.0, f64::max));
+    }
+    x.iter().map(|a| a.abs()).fold(0.0, f64::max)
+}
+
+/// Compute y = alpha * a * x + beta * y.
+pub fn gemv(alpha: f64, a: &MatrixView, x: &[f64], beta: f64, y: &mut [f64]) {
+    assert_eq!(a.n, x.len());
+    assert_eq!(a.m, y.len());
+
+    let row_op = |(row, yval): (&&[f64], &mut f64)| {
+        let ax: f64 = row.iter().zip(x).map(|(aij, xj)| aij * xj).sum();
+        *yval = if beta == 0.0 { alpha * ax } else { alpha * ax + beta * *yval };
+    };
+    #[cfg(feature = "parallel")]
+    if a.m * a.n >= PARALLEL_MIN_LEN {
+        parallel::run(|| a.data.par_iter().zip(y.par_iter_mut()).for_each(row_op));
+        return;
+    }
+    a.data.iter().zip(y.iter_mut()).for_each(row_op);
+}
+
+/// Compute y = alpha * a^T * x + beta * y, without forming a^T.
+pub fn gemv_t(alpha: f64, a: &MatrixView, x: &[f64], beta: f64, y: &mut [f64]) {
+    assert_eq!(a.m, x.len());
+    assert_eq!(a.n, y.len());
+
+    let add_row = |mut acc: Vec<f64>, (row, xi): (&&[f64], &f64)| {
+        acc.iter_mut().zip(row.iter()).for_each(|(s, aij)| *s += aij * xi);
+        acc
+    };
+    #[cfg(feature = "parallel")]
+    let atx = if a.m * a.n >= PARALLEL_MIN_LEN {
+        parallel::run(|| {
+            a.data.par_iter().zip(x).fold(|| vec![0.0; a.n], add_row).reduce(|| vec![0.0; a.n], |mut p, q| {
+                p.iter_mut().zip(&q).for_each(|(pj, qj)| *pj += qj);
+                p
+            })
+        })
+    } else {
+        a.data.iter().zip(x).fold(vec![0.0; a.n], add_row)
+    };
+    #[cfg(not(feature = "parallel"))]
+    let atx = a.data.iter().zip(x).fold(vec![0.0; a.n], add_row);
+    for (yval, s) in y.iter_mut().zip(&atx) {
+        *yval = if beta == 0.0 { alpha * s } else { alpha * s + beta * *yval };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{approx, Matrix};
+
+    #[test]
+    fn level1_simple() {
+        let x = vec![3.0, -4.0];
+        let mut y = vec![1.0, 1.0];
+        axpy(2.0, &x, &mut y);
+        assert_eq!(y, vec![7.0, -7.0]);
+        assert!(approx(dot(&x, &y), 49.0));
+        assert!(approx(norm2(&x), 5.0));
+        assert!(approx(norm1(&x), 7.0));
+        assert!(approx(norm_inf(&x), 4.0));
+    }
+
+    #[test]
+    fn gemv_simple() {
+        let a = Matrix::from_vec(3, 2, vec![1.0, 2.0,
+                                            3.0, 4.0,
+                                            5.0, 6.0]);
+        let mut x = vec![1.0, 1.0];
+        let mut y = vec![1.0, 1.0, 1.0];
+        gemv(1.0, &a.view(), &x, 2.0, &mut y);
+        assert_eq!(y, vec![5.0, 9.0, 13.0]);
+        gemv_t(1.0, &a.view(), &y, 0.0, &mut x);
+        assert_eq!(x, vec![97.0, 124.0]);
+    }
+
+    #[test]
+    fn large_reductions() {
+        let n = 2 * PARALLEL_MIN_LEN + 3;
+        let x = vec![1.0; n];
+        let mut y = vec![2.0; n];
+        axpy(1.0, &x, &mut y);
+        assert!(approx(dot(&x, &y), 3.0 * n as f64));
+        assert!(approx(norm_inf(&y), 3.0));
+
+        let a = Matrix::from_vec(200, 100, vec![1.0; 200 * 100]);
+        let mut z = vec![0.0; 100];
+        gemv_t(0.5, &a.view(), &x[..200], 0.0, &mut z);
+        assert!(z.iter().all(|&v| v == 100.0));
+    }
+}
@@ -0,0 +1,151 @@
+//! Gram-Schmidt orthogonalization and orthonormal bases.
+//!
+//! One pass of classical Gram-Schmidt loses orthogonality in proportion to
+//! the condition number squared, and modified Gram-Schmidt in proportion
+//! to the condition number. Repeating the pass once ("twice is enough",
+//! Giraud et al.) gives vectors orthogonal to working precision unless the
+//! input is numerically rank deficient. Classical needs only matrix-vector
+//! products, so it is the one to use when the inner products are reduced
+//! across processes.
+use crate::operations::{axpy, dot, norm2, scale};
+use crate::svd::svd;
+use crate::{Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Variant of Gram-Schmidt, each with one reorthogonalization pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GramSchmidt {
+    /// Classical Gram-Schmidt run twice (CGS2).
+    Classical,
+    /// Modified Gram-Schmidt run twice.
+    Modified,
+}
+
+/// Make v orthogonal to the orthonormal vectors in basis, returning the
+/// coefficients h with v_old = sum_i h_i basis_i + v_new.
+pub fn orthogonalize<V: AsRef<[f64]>>(basis: &[V], v: &mut [f64], method: GramSchmidt) -> Vec<f64> {
+    let mut h = vec![0.0; basis.len()];
+    for _ in 0..2 {
+        match method {
+            GramSchmidt::Classical => {
+                let c: Vec<f64> = basis.iter().map(|q| dot(q.as_ref(), v)).collect();
+                for (q, ci) in basis.iter().zip(&c) {
+                    axpy(-ci, q.as_ref(), v);
+                }
+                h.iter_mut().zip(&c).for_each(|(hi, ci)| *hi += ci);
+            }
+            GramSchmidt::Modified => {
+                for (q, hi) in basis.iter().zip(h.iter_mut()) {
+                    let c = dot(q.as_ref(), v);
+                    axpy(-c, q.as_ref(), v);
+                    *hi += c;
+                }
+            }
+        }
+    }
+    h
+}
+
+/// Return the thin QR factorization A = Q R of an m x n matrix, m >= n,
+/// by Gram-Schmidt on its columns.
+///
+/// A column that is dependent on the earlier ones, with less than tol of
+/// its norm left after orthogonalization, gets a zero column in Q and a
+/// zero diagonal in R.
+pub fn gram_schmidt(a: &Matrix, method: GramSchmidt, tol: f64) -> (Matrix, Matrix) {
+    let (m, n) = (a.m, a.n);
+    assert!(m >= n);
+    let mut basis: Vec<Vec<f64>> = Vec::with_capacity(n);
+    // Column of A that each basis vector came from.
+    let mut cols = Vec::with_capacity(n);
+    let mut q = Matrix::zero(m, n);
+    let mut r = Matrix::zero(n, n);
+    for j in 0..n {
+        let mut v: Vec<f64> = (0..m).map(|i| a.get(i, j)).collect();
+        let before = norm2(&v);
+        let h = orthogonalize(&basis, &mut v, method);
+        for (&i, hi) in cols.iter().zip(&h) {
+            r.set(i, j, *hi);
+        }
+        let norm = norm2(&v);
+        if norm > tol * before && norm > 0.0 {
+            scale(1.0 / norm, &mut v);
+            (0..m).for_each(|i| q.set(i, j, v[i]));
+            r.set(j, j, norm);
+            basis.push(v);
+            cols.push(j);
+        }
+    }
+    (q, r)
+}
+
+/// Return an orthonormal basis of the range of a, as the columns of an
+/// m x r matrix with r the numerical rank.
+///
+/// It uses the SVD, the most reliable way to decide the rank: singular
+/// values below max(m, n) eps times the largest are treated as zero.
+pub fn orth(a: &Matrix) -> Matrix {
+    let f = svd(a);
+    let r = f.rank(a.m.max(a.n) as f64 * f64::EPSILON);
+    Matrix::from_vec(a.m, r, (0..a.m * r).map(|t| f.u.get(t / r, t % r)).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matmul;
+
+    fn orthonormal_error(q: &Matrix) -> f64 {
+        let mut g = Matrix::zero(q.n, q.n);
+        matmul(&q.transpose().view(), &q.view(), &mut g.view_mut());
+        (0..q.n).flat_map(|i| (0..q.n).map(move |j| (i, j))).map(|(i, j)| (g.get(i, j) - if i == j { 1.0 } else { 0.0 }).abs()).fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn reorthogonalized_gram_schmidt() {
+        // Columns of a Lauchli-like matrix are nearly parallel; one pass of
+        // either variant loses orthogonality badly.
+        let eps = 1e-7;
+        let mut a = Matrix::zero(5, 4);
+        for j in 0..4 {
+            a.set(0, j, 1.0);
+            a.set(j + 1, j, eps);
+        }
+        for method in [GramSchmidt::Classical, GramSchmidt::Modified] {
+            let (q, r) = gram_schmidt(&a, method, 1e-14);
+            assert!(orthonormal_error(&q) < 1e-14, "{method:?}");
+            let mut qr = Matrix::zero(5, 4);
+            matmul(&q.view(), &r.view(), &mut qr.view_mut());
+            assert!((0..5).all(|i| (0..4).all(|j| (qr.get(i, j) - a.get(i, j)).abs() < 1e-15)));
+        }
+
+        let basis = vec![vec![1.0, 0.0, 0.0], vec![0.0, 0.6, 0.8]];
+        let mut v = vec![2.0, 1.0, -3.0];
+        let h = orthogonalize(&basis, &mut v, GramSchmidt::Classical);
+        assert!((h[0] - 2.0).abs() < 1e-15 && (h[1] + 1.8).abs() < 1e-15);
+        assert!(dot(&v, &basis[0]).abs() < 1e-15 && dot(&v, &basis[1]).abs() < 1e-15);
+    }
+
+    #[test]
+    fn orth_of_rank_deficient_matrix() {
+        // The third column is the sum of the first two.
+        let a = Matrix::from_vec(4, 3, vec![1.0, 0.0, 1.0,
+                                            2.0, 1.0, 3.0,
+                                            0.0, 1.0, 1.0,
+                                            1.0, 1.0, 2.0]);
+        let q = orth(&a);
+        assert_eq!(q.n, 2);
+        assert!(orthonormal_error(&q) < 1e-14);
+        // Q Q^T A = A.
+        let mut qta = Matrix::zero(2, 3);
+        matmul(&q.transpose().view(), &a.view(), &mut qta.view_mut());
+        let mut p = Matrix::zero(4, 3);
+        matmul(&q.view(), &qta.view(), &mut p.view_mut());
+        assert!((0..4).all(|i| (0..3).all(|j| (p.get(i, j) - a.get(i, j)).abs() < 1e-14)));
+
+        let (q, r) = gram_schmidt(&a, GramSchmidt::Modified, 1e-12);
+        assert_eq!(r.get(2, 2), 0.0);
+        assert!((0..4).all(|i| q.get(i, 2) == 0.0));
+    }
+}
@@ -0,0 +1,103 @@
+//! Thread configuration for the parallel kernels.
+//!
+//! By default the kernels use rayon's global pool. `set_num_threads` switches
+//! every kernel to a dedicated pool of the given size, while `with_threads`
+//! applies a thread count only for the duration of a closure. Without the
+//! `parallel` feature these are accepted but have no effect.
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "parallel")]
+use std::sync::{Arc, Mutex};
+
+/// Minimum number of independent rows, such as a level of a triangular
+/// solve, before they are processed in parallel.
+pub const PARALLEL_MIN_ROWS: usize = 256;
+
+/// Requested thread count, or zero for the default.
+static NUM_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "parallel")]
+static POOL: Mutex<Option<Arc<rayon::ThreadPool>>> = Mutex::new(None);
+
+#[cfg(feature = "parallel")]
+fn build_pool(n: usize) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(n)
+        .build()
+        .expect("failed to build thread pool")
+}
+
+/// Set the number of threads used by all parallel kernels.
+///
+/// Passing zero restores the default.
+pub fn set_num_threads(n: usize) {
+    NUM_THREADS.store(n, Ordering::SeqCst);
+    #[cfg(feature = "parallel")]
+    {
+        let pool = if n == 0 { None } else { Some(Arc::new(build_pool(n))) };
+        *POOL.lock().unwrap() = pool;
+    }
+}
+
+/// Return the number of threads the parallel kernels will use.
+pub fn num_threads() -> usize {
+    #[cfg(feature = "parallel")]
+    {
+        if rayon::current_thread_index().is_some() {
+            return rayon::current_num_threads();
+        }
+        match NUM_THREADS.load(Ordering::SeqCst) {
+            0 => rayon::current_num_threads(),
+            n => n,
+        }
+    }
+    #[cfg(not(feature = "parallel"))]
+    1
+}
+
+/// Run `f` with all parallel kernels inside restricted to `n` threads.
+pub fn with_threads<F, R>(n: usize, f: F) -> R
+where
+    F: FnOnce() -> R + Send,
+    R: Send,
+{
+    assert!(n > 0);
+    #[cfg(feature = "parallel")]
+    {
+        build_pool(n).install(f)
+    }
+    #[cfg(not(feature = "parallel"))]
+    f()
+}
+
+/// Run a parallel kernel on the configured pool.
+#[cfg(feature = "parallel")]
+pub(crate) fn run<F, R>(op: F) -> R
+where
+    F: FnOnce() -> R + Send,
+    R: Send,
+{
+    // Already on a pool thread, e.g. inside with_threads().
+    if rayon::current_thread_index().is_some() {
+        return op();
+    }
+    let pool = POOL.lock().unwrap().clone();
+    match pool {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn with_threads_scoped() {
+        let n = with_threads(2, num_threads);
+        if cfg!(feature = "parallel") {
+            assert_eq!(n, 2);
+        } else {
+            assert_eq!(n, 1);
+        }
+    }
+}
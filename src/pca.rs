@@ -0,0 +1,148 @@
+//! Principal component analysis.
+//!
+//! The data are an m x d matrix with one sample per row. The components
+//! are the leading right singular vectors of the centered data, found
+//! either from the SVD of the data themselves or from the d x d covariance
+//! matrix. The covariance is much cheaper when m is far larger than d but
+//! squares the condition number, so small variances lose accuracy.
+use crate::svd::svd;
+use crate::{fma_scale, Matrix, MatrixIndex};
+use alloc::vec::Vec;
+
+/// How pca_with() finds the components.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PcaMethod {
+    /// Covariance for tall data (more than 2d samples), SVD otherwise.
+    Auto,
+    /// Decompose the d x d covariance matrix.
+    Covariance,
+    /// Decompose the centered m x d data matrix.
+    Svd,
+}
+
+/// A fitted PCA.
+pub struct Pca {
+    /// Mean of each of the d features.
+    pub mean: Vec<f64>,
+    /// k x d, one unit component per row, in decreasing order of variance.
+    pub components: Matrix,
+    /// Variance of the data along each component.
+    pub explained_variance: Vec<f64>,
+    /// Total variance of the data, the sum over all d directions.
+    pub total_variance: f64,
+}
+
+impl Pca {
+    /// Return the fraction of the total variance along each component.
+    pub fn explained_variance_ratio(&self) -> Vec<f64> {
+        self.explained_variance.iter().map(|v| if self.total_variance > 0.0 { v / self.total_variance } else { 0.0 }).collect()
+    }
+
+    /// Return the m x k coordinates of the rows of data in the components.
+    pub fn transform(&self, data: &Matrix) -> Matrix {
+        assert_eq!(data.n, self.mean.len());
+        let centered = center(data, &self.mean);
+        let mut scores = Matrix::zero(data.m, self.components.m);
+        fma_scale(1.0, &centered.view(), &self.components.transpose().view(), 0.0, &mut scores.view_mut());
+        scores
+    }
+
+    /// Return the m x d points with the given m x k coordinates, the
+    /// projection of the original data onto the components.
+    pub fn inverse_transform(&self, scores: &Matrix) -> Matrix {
+        assert_eq!(scores.n, self.components.m);
+        let d = self.mean.len();
+        let mut data = Matrix::from_vec(scores.m, d, (0..scores.m * d).map(|t| self.mean[t % d]).collect());
+        fma_scale(1.0, &scores.view(), &self.components.view(), 1.0, &mut data.view_mut());
+        data
+    }
+}
+
+/// Return the first k principal components of data, choosing the method
+/// automatically.
+pub fn pca(data: &Matrix, k: usize) -> Pca {
+    pca_with(data, k, PcaMethod::Auto)
+}
+
+/// Return the first k principal components of data, k <= min(m, d).
+///
+/// Variances are normalized by m - 1. Each component is given the sign
+/// that makes its largest entry positive, so both methods agree.
+pub fn pca_with(data: &Matrix, k: usize, method: PcaMethod) -> Pca {
+    let (m, d) = (data.m, data.n);
+    assert!(m >= 2 && k <= m.min(d));
+    let mean: Vec<f64> = (0..d).map(|j| (0..m).map(|i| data.get(i, j)).sum::<f64>() / m as f64).collect();
+    let x = center(data, &mean);
+    let scale = 1.0 / (m - 1) as f64;
+    let covariance = match method {
+        PcaMethod::Auto => m > 2 * d,
+        PcaMethod::Covariance => true,
+        PcaMethod::Svd => false,
+    };
+
+    let (vt, variance) = if covariance {
+        // The covariance is SPD, so its SVD is its eigendecomposition.
+        let mut c = Matrix::zero(d, d);
+        fma_scale(scale, &x.transpose().view(), &x.view(), 0.0, &mut c.view_mut());
+        let f = svd(&c);
+        (f.vt, f.s)
+    } else {
+        let f = svd(&x);
+        (f.vt, f.s.iter().map(|s| s * s * scale).collect())
+    };
+
+    let mut components = Matrix::zero(k, d);
+    for q in 0..k {
+        let big = (0..d).map(|j| vt.get(q, j)).fold(0.0, |a: f64, b| if b.abs() > a.abs() { b } else { a });
+        let sign = if big < 0.0 { -1.0 } else { 1.0 };
+        (0..d).for_each(|j| components.set(q, j, sign * vt.get(q, j)));
+    }
+    let total_variance = scale * x.as_slice().iter().map(|v| v * v).sum::<f64>();
+    Pca { mean, components, explained_variance: variance[..k].to_vec(), total_variance }
+}
+
+fn center(data: &Matrix, mean: &[f64]) -> Matrix {
+    let mut x = Matrix::zero(data.m, data.n);
+    for i in 0..data.m {
+        for j in 0..data.n {
+            x.set(i, j, data.get(i, j) - mean[j]);
+        }
+    }
+    x
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pca_methods_agree() {
+        let data = Matrix::rand_seeded(40, 5, 1);
+        let a = pca_with(&data, 3, PcaMethod::Svd);
+        let b = pca_with(&data, 3, PcaMethod::Covariance);
+        assert!(a.components.as_slice().iter().zip(b.components.as_slice()).all(|(p, q)| (p - q).abs() < 1e-10));
+        assert!(a.explained_variance.iter().zip(&b.explained_variance).all(|(p, q)| (p - q).abs() < 1e-12));
+        // All components together explain everything.
+        let full = pca(&data, 5);
+        assert!((full.explained_variance_ratio().iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        let back = full.inverse_transform(&full.transform(&data));
+        assert!(back.as_slice().iter().zip(data.as_slice()).all(|(p, q)| (p - q).abs() < 1e-12));
+    }
+
+    #[test]
+    fn pca_finds_a_line() {
+        // Points on the line through (1, 2) with direction (3, 4) / 5,
+        // plus a small perpendicular wiggle uncorrelated with the position.
+        let data = Matrix::from_vec(6, 2, (0..6).flat_map(|i| {
+            let (t, e) = (i as f64 - 2.5, if i == 0 || i == 5 { 0.02 } else { -0.01 });
+            [1.0 + 0.6 * t - 0.8 * e, 2.0 + 0.8 * t + 0.6 * e]
+        }).collect());
+        let p = pca(&data, 1);
+        assert!((p.components.get(0, 0) - 0.6).abs() < 1e-4 && (p.components.get(0, 1) - 0.8).abs() < 1e-4);
+        assert!((p.mean[0] - 1.0).abs() < 1e-14 && (p.mean[1] - 2.0).abs() < 1e-14);
+        assert!(p.explained_variance_ratio()[0] > 0.9999);
+        // The scores are the positions along the line.
+        let s = p.transform(&data);
+        assert!((0..6).all(|i| (s.get(i, 0) - (i as f64 - 2.5)).abs() < 1e-3));
+    }
+}
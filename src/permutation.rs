@@ -0,0 +1,168 @@
+//! Permutations and row/column selection.
+use crate::rng::{self, Rng};
+use crate::{Matrix, MatrixIndex};
+use alloc::vec::Vec;
+
+/// Permutation of [0, n), stored as the image of each index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Permutation {
+    perm: Vec<usize>,
+}
+
+impl Permutation {
+    /// Return the identity permutation.
+    pub fn identity(n: usize) -> Permutation {
+        Permutation { perm: (0..n).collect() }
+    }
+
+    /// Return a uniformly random permutation.
+    pub fn rand(n: usize, rng: &mut impl Rng) -> Permutation {
+        let mut res = Permutation::identity(n);
+        rng::shuffle(rng, &mut res.perm);
+        res
+    }
+
+    /// Create from the image of each index. Panics if `perm` is not a
+    /// permutation.
+    pub fn from_vec(perm: Vec<usize>) -> Permutation {
+        let mut seen = alloc::vec![false; perm.len()];
+        for &p in &perm {
+            assert!(p < perm.len() && !seen[p], "not a permutation");
+            seen[p] = true;
+        }
+        Permutation { perm }
+    }
+
+    /// Create from LAPACK-style pivots, as returned by lu::lu().
+    pub fn from_pivots(piv: &[usize]) -> Permutation {
+        let mut res = Permutation::identity(piv.len());
+        for (k, &p) in piv.iter().enumerate() {
+            res.perm.swap(k, p);
+        }
+        res
+    }
+
+    /// Return the size.
+    pub fn len(&self) -> usize {
+        self.perm.len()
+    }
+
+    /// Return true for the empty permutation.
+    pub fn is_empty(&self) -> bool {
+        self.perm.is_empty()
+    }
+
+    /// Return the image of each index.
+    pub fn as_slice(&self) -> &[usize] {
+        &self.perm
+    }
+
+    /// Return the inverse permutation.
+    pub fn inverse(&self) -> Permutation {
+        let mut inv = alloc::vec![0; self.perm.len()];
+        for (i, &p) in self.perm.iter().enumerate() {
+            inv[p] = i;
+        }
+        Permutation { perm: inv }
+    }
+
+    /// Return x permuted so that entry i is x[perm[i]].
+    pub fn apply<T: Copy>(&self, x: &[T]) -> Vec<T> {
+        assert_eq!(x.len(), self.perm.len());
+        self.perm.iter().map(|&p| x[p]).collect()
+    }
+
+    /// Return P A, whose row i is row perm[i] of a.
+    pub fn permute_rows(&self, a: &Matrix) -> Matrix {
+        assert_eq!(a.m, self.perm.len());
+        a.select_rows(&self.perm)
+    }
+
+    /// Return A P^T, whose column j is column perm[j] of a.
+    pub fn permute_cols(&self, a: &Matrix) -> Matrix {
+        assert_eq!(a.n, self.perm.len());
+        a.select_cols(&self.perm)
+    }
+}
+
+impl Matrix {
+    /// Return the matrix made of the given rows, in order.
+    pub fn select_rows(&self, rows: &[usize]) -> Matrix {
+        let mut res = Matrix::zero(rows.len(), self.n);
+        for (i, &r) in rows.iter().enumerate() {
+            for j in 0..self.n {
+                res.set(i, j, self.get(r, j));
+            }
+        }
+        res
+    }
+
+    /// Return the matrix made of the given columns, in order.
+    pub fn select_cols(&self, cols: &[usize]) -> Matrix {
+        let mut res = Matrix::zero(self.m, cols.len());
+        for i in 0..self.m {
+            for (j, &c) in cols.iter().enumerate() {
+                res.set(i, j, self.get(i, c));
+            }
+        }
+        res
+    }
+
+    /// Sample k rows without replacement. Returns the rows and their indices.
+    pub fn sample_rows(&self, k: usize, rng: &mut impl Rng) -> (Matrix, Vec<usize>) {
+        let rows = rng::sample_indices(rng, self.m, k);
+        (self.select_rows(&rows), rows)
+    }
+
+    /// Sample k columns without replacement. Returns the columns and their
+    /// indices.
+    pub fn sample_cols(&self, k: usize, rng: &mut impl Rng) -> (Matrix, Vec<usize>) {
+        let cols = rng::sample_indices(rng, self.n, k);
+        (self.select_cols(&cols), cols)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::approx;
+    use crate::lu::lu;
+    use crate::rng::Lcg;
+
+    #[test]
+    fn permutation_inverse_and_pivots() {
+        let p = Permutation::rand(10, &mut Lcg::new(1));
+        let x: Vec<usize> = (0..10).map(|i| i * i).collect();
+        assert_eq!(p.inverse().apply(&p.apply(&x)), x);
+
+        // P A = L U for the pivots of a matrix that needs row swaps.
+        let a = Matrix::from_vec(3, 3, alloc::vec![0.0, 2.0, 1.0,
+                                                   1.0, 1.0, 1.0,
+                                                   2.0, 1.0, 3.0]);
+        let mut f = Matrix::from_vec(3, 3, a.as_slice().to_vec());
+        let piv = lu(&mut f).unwrap();
+        let pa = Permutation::from_pivots(&piv).permute_rows(&a);
+        for i in 0..3 {
+            for j in 0..3 {
+                let lu: f64 = (0..=i.min(j))
+                    .map(|k| if k == i { f.get(k, j) } else { f.get(i, k) * f.get(k, j) })
+                    .sum();
+                assert!(approx(pa.get(i, j), lu));
+            }
+        }
+    }
+
+    #[test]
+    fn sample_rows_and_cols() {
+        let a = Matrix::from_vec(3, 2, alloc::vec![1.0, 2.0,
+                                                   3.0, 4.0,
+                                                   5.0, 6.0]);
+        let (r, rows) = a.sample_rows(2, &mut Lcg::new(3));
+        for (i, &row) in rows.iter().enumerate() {
+            assert_eq!(r.get(i, 1), a.get(row, 1));
+        }
+        let (c, cols) = a.sample_cols(1, &mut Lcg::new(3));
+        assert_eq!(c.as_slice(), a.select_cols(&cols).as_slice());
+        assert_eq!(a.select_cols(&[1, 0]).to_vec(), alloc::vec![2.0, 1.0, 4.0, 3.0, 6.0, 5.0]);
+    }
+}
@@ -0,0 +1,214 @@
+//! Forward error bounds from backward errors and condition numbers.
+//!
+//! A computed solution with backward error eta, on a problem with
+//! condition number cond, has relative forward error at most about
+//! eta * cond. The routines here estimate both factors for the actual
+//! data and return the bounds, normwise and componentwise, in the
+//! infinity norm for square systems and the 2-norm for least squares.
+use crate::factor::{backward_error, norm1_est, BackwardError, Factor};
+use crate::svd::svd;
+use crate::{math, Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Error bounds for a computed solution x of A x = b.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolveBounds {
+    /// Backward errors of x.
+    pub backward: BackwardError,
+    /// Estimate of kappa(A) = ||A|| ||A^-1||.
+    pub cond: f64,
+    /// Estimate of the normwise condition number of the solution,
+    /// ||A^-1|| (||A|| ||x|| + ||b||) / ||x||.
+    pub cond_normwise: f64,
+    /// Estimate of Skeel's componentwise condition number of the solution,
+    /// || |A^-1| (|A| |x| + |b|) || / ||x||.
+    pub cond_componentwise: f64,
+    /// Bound on ||x - x*|| / ||x|| from the normwise backward error.
+    pub normwise: f64,
+    /// Bound on ||x - x*|| / ||x|| from the componentwise backward error,
+    /// much smaller than the normwise one for badly scaled A.
+    pub componentwise: f64,
+    /// LAPACK-style bound from the residual r itself,
+    /// || |A^-1| (|r| + (n + 1) eps (|A| |x| + |b|)) || / ||x||, which also
+    /// covers rounding errors in computing r.
+    pub residual: f64,
+}
+
+/// Return error bounds for x as a solution of A x = b, given a
+/// factorization of a.
+///
+/// The inverse norms are estimated with a few solves with A and A^T
+/// (Hager, Higham), so the bounds are estimates too, but rarely
+/// optimistic by more than a factor of 3. The normwise and componentwise
+/// bounds are first order, eta cond / (1 - eta cond), and infinite when
+/// eta cond >= 1.
+pub fn solve_bounds<F: Factor>(factor: &F, a: &Matrix, x: &[f64], b: &[f64]) -> SolveBounds {
+    let n = factor.dim();
+    assert!(a.m == n && a.n == n && x.len() == n && b.len() == n);
+    let backward = backward_error(a, x, b);
+    let x_norm = inf_norm(x);
+    let b_norm = inf_norm(b);
+    let a_norm = (0..n).map(|i| (0..n).map(|j| a.get(i, j).abs()).sum::<f64>()).fold(0.0, f64::max);
+
+    // ||A^-1||_inf = ||A^-T||_1.
+    let inv_norm = norm1_est(n, |v| factor.solve_transpose_in_place(v), |v| factor.solve_in_place(v));
+    let cond = a_norm * inv_norm;
+    let cond_normwise = if x_norm > 0.0 { inv_norm * (a_norm * x_norm + b_norm) / x_norm } else { f64::INFINITY };
+
+    let mut abs_ax = vec![0.0; n];
+    let mut r = b.to_vec();
+    for i in 0..n {
+        for j in 0..n {
+            abs_ax[i] += (a.get(i, j) * x[j]).abs();
+            r[i] -= a.get(i, j) * x[j];
+        }
+    }
+    let g: Vec<f64> = (0..n).map(|i| abs_ax[i] + b[i].abs()).collect();
+    let cond_componentwise = abs_inverse_norm(factor, &g) / x_norm;
+    let eps = f64::EPSILON / 2.0;
+    let h: Vec<f64> = (0..n).map(|i| r[i].abs() + (n + 1) as f64 * eps * g[i]).collect();
+
+    SolveBounds {
+        backward,
+        cond,
+        cond_normwise,
+        cond_componentwise,
+        normwise: amplify(backward.normwise, cond_normwise),
+        componentwise: amplify(backward.componentwise, cond_componentwise),
+        residual: abs_inverse_norm(factor, &h) / x_norm,
+    }
+}
+
+/// Error bounds for a least squares solution x of min ||A x - b||_2.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LstsqBounds {
+    /// kappa_2(A) = s_max / s_min.
+    pub cond: f64,
+    /// ||b - A x||_2.
+    pub residual_norm: f64,
+    /// Condition number of the least squares solution,
+    /// kappa (2 + (kappa + 1) ||r|| / (||A|| ||x||)), which grows like
+    /// kappa^2 when the residual is large.
+    pub cond_lstsq: f64,
+    /// Bound on ||x - x*||_2 / ||x||_2 for a solver with normwise backward
+    /// error eta.
+    pub bound: f64,
+}
+
+/// Return error bounds for x as a least squares solution of A x = b, m >=
+/// n > 0, computed by a solver with normwise backward error eta in the
+/// 2-norm, such as a small multiple of eps for Householder QR.
+///
+/// The bound is Wedin's, as in Higham's Theorem 20.1. It uses the SVD of
+/// a, so it is meant for problems of moderate size.
+pub fn lstsq_bounds(a: &Matrix, x: &[f64], b: &[f64], eta: f64) -> LstsqBounds {
+    assert!(a.m >= a.n && a.n > 0 && x.len() == a.n && b.len() == a.m);
+    let s = svd(a).s;
+    let (smax, smin) = (s[0], s[a.n - 1]);
+    let cond = if smin > 0.0 { smax / smin } else { f64::INFINITY };
+    let mut r2 = 0.0;
+    for i in 0..a.m {
+        let ri = b[i] - (0..a.n).map(|j| a.get(i, j) * x[j]).sum::<f64>();
+        r2 += ri * ri;
+    }
+    let residual_norm = math::sqrt(r2);
+    let x_norm = math::sqrt(x.iter().map(|v| v * v).sum());
+    let cond_lstsq = cond * (2.0 + (cond + 1.0) * residual_norm / (smax * x_norm));
+    let bound = if eta * cond < 1.0 { eta * cond_lstsq / (1.0 - eta * cond) } else { f64::INFINITY };
+    LstsqBounds { cond, residual_norm, cond_lstsq, bound }
+}
+
+/// Estimate || |A^-1| g ||_inf = ||A^-1 diag(g)||_inf for g >= 0, as the
+/// 1-norm of its transpose diag(g) A^-T (Arioli, Demmel and Duff).
+fn abs_inverse_norm<F: Factor>(factor: &F, g: &[f64]) -> f64 {
+    let scale = |v: &mut [f64]| v.iter_mut().zip(g).for_each(|(vi, gi)| *vi *= gi);
+    norm1_est(
+        g.len(),
+        |v| {
+            factor.solve_transpose_in_place(v);
+            scale(v);
+        },
+        |v| {
+            scale(v);
+            factor.solve_in_place(v);
+        },
+    )
+}
+
+fn amplify(eta: f64, cond: f64) -> f64 {
+    if eta * cond < 1.0 { eta * cond / (1.0 - eta * cond) } else { f64::INFINITY }
+}
+
+fn inf_norm(x: &[f64]) -> f64 {
+    x.iter().fold(0.0, |m: f64, v| m.max(v.abs()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lu::LuFactor;
+    use crate::qr::LeastSquares;
+
+    #[test]
+    fn bounds_cover_the_true_error() {
+        // An integer matrix whose last row nearly repeats the sum of the
+        // others, and an integer solution, so b is exact.
+        let n = 6;
+        let r = Matrix::rand_seeded(n, n, 3);
+        let mut a = Matrix::from_vec(n, n, (0..n * n).map(|t| (100.0 * r.get(t / n, t % n)).round()).collect());
+        for j in 0..n {
+            let sum: f64 = (0..n - 1).map(|i| a.get(i, j)).sum();
+            a.set(n - 1, j, sum + if j == 0 { 2f64.powi(-10) } else { 0.0 });
+        }
+        let exact: Vec<f64> = (0..n).map(|i| i as f64 - 2.0).collect();
+        let b: Vec<f64> = (0..n).map(|i| (0..n).map(|j| a.get(i, j) * exact[j]).sum()).collect();
+        let check = |a: &Matrix, b: &[f64]| {
+            let f = LuFactor::new(a).unwrap();
+            let x = f.solve(b);
+            let err = x.iter().zip(&exact).map(|(p, q)| (p - q).abs()).fold(0.0, f64::max) / inf_norm(&x);
+            let bounds = solve_bounds(&f, a, &x, b);
+            assert!(err > 0.0 && err <= bounds.normwise && err <= bounds.componentwise && err <= bounds.residual, "{err} {bounds:?}");
+            assert!(bounds.residual < 1e4 * err);
+            bounds
+        };
+        let plain = check(&a, &b);
+        let inv = LuFactor::new(&a).unwrap().inverse();
+        let kappa = matrix_norm_inf(&a) * matrix_norm_inf(&inv);
+        assert!(plain.cond <= kappa * (1.0 + 1e-6) && plain.cond >= kappa / 3.0);
+
+        // Scaling a row by a power of two keeps the data exact and the
+        // componentwise condition number, but ruins the normwise one.
+        let d = 2f64.powi(-30);
+        let mut sa = Matrix::from_vec(n, n, a.as_slice().to_vec());
+        (0..n).for_each(|j| sa.set(0, j, d * a.get(0, j)));
+        let mut sb = b.clone();
+        sb[0] *= d;
+        let scaled = check(&sa, &sb);
+        assert!(scaled.cond > 1e6 * plain.cond);
+        assert!(scaled.cond_componentwise < 10.0 * plain.cond_componentwise);
+    }
+
+    fn matrix_norm_inf(a: &Matrix) -> f64 {
+        (0..a.m).map(|i| (0..a.n).map(|j| a.get(i, j).abs()).sum::<f64>()).fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn least_squares_bound() {
+        let a = Matrix::rand_seeded(20, 4, 1);
+        let b: Vec<f64> = (0..20).map(|i| (i as f64).sin()).collect();
+        let x = LeastSquares::from_rows(&a, &b).solve();
+        let eta = 1e-8;
+        let bounds = lstsq_bounds(&a, &x, &b, eta);
+        assert!(bounds.cond > 1.0 && bounds.cond_lstsq > 2.0 * bounds.cond);
+
+        // Perturb A and b by relative eta and solve again.
+        let e = Matrix::rand_seeded(20, 4, 2);
+        let pa = Matrix::from_vec(20, 4, (0..80).map(|t| a.get(t / 4, t % 4) * (1.0 + eta * (2.0 * e.get(t / 4, t % 4) - 1.0) / 4.0)).collect());
+        let pb: Vec<f64> = b.iter().enumerate().map(|(i, v)| v * (1.0 + eta * if i % 2 == 0 { 0.5 } else { -0.5 })).collect();
+        let y = LeastSquares::from_rows(&pa, &pb).solve();
+        let diff = math::sqrt(x.iter().zip(&y).map(|(p, q)| (p - q) * (p - q)).sum());
+        let x_norm = math::sqrt(x.iter().map(|v| v * v).sum());
+        assert!(diff / x_norm <= bounds.bound, "{} {}", diff / x_norm, bounds.bound);
+    }
+}
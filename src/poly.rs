@@ -0,0 +1,264 @@
+//! Polynomials.
+//!
+//! Coefficients are stored highest degree first, so [1, -3, 2] is
+//! x^2 - 3x + 2.
+use crate::eig::eigvals;
+use crate::iterative::LinearOperator;
+use crate::matfun::{identity, product};
+use crate::operations::{norm2, scale};
+use crate::orth::{orthogonalize, GramSchmidt};
+use crate::{matmul, Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Return the roots of the polynomial as (re, im) pairs, or None if the
+/// eigenvalue iteration fails to converge.
+///
+/// The roots are the eigenvalues of the companion matrix, which eigvals()
+/// balances first since its entries may differ in size by many orders of
+/// magnitude.
+/// Leading zero coefficients are ignored and trailing ones give roots at
+/// zero exactly. As for any method, multiple roots are only found to about
+/// eps^(1/multiplicity).
+pub fn roots(coeffs: &[f64]) -> Option<Vec<(f64, f64)>> {
+    let start = coeffs.iter().position(|&c| c != 0.0).unwrap_or(coeffs.len());
+    let c = &coeffs[start..];
+    let zeros = c.iter().rev().take_while(|&&v| v == 0.0).count();
+    let c = &c[..c.len() - zeros];
+    let mut res = Vec::with_capacity(c.len() + zeros);
+    if c.len() > 1 {
+        // First row -c[1..] / c[0], ones on the subdiagonal.
+        let n = c.len() - 1;
+        let mut a = Matrix::zero(n, n);
+        for j in 0..n {
+            a.set(0, j, -c[j + 1] / c[0]);
+        }
+        (1..n).for_each(|i| a.set(i, i - 1, 1.0));
+        res.extend(eigvals(&a)?);
+    }
+    res.extend((0..zeros).map(|_| (0.0, 0.0)));
+    Some(res)
+}
+
+/// Return the value of the polynomial at x by Horner's rule.
+pub fn polyval(coeffs: &[f64], x: f64) -> f64 {
+    coeffs.iter().fold(0.0, |acc, &c| acc * x + c)
+}
+
+/// Return the matrix polynomial p(A) = c_0 A^d + ... + c_(d-1) A + c_d I
+/// for square a, with the coefficients highest degree first as for
+/// polyval().
+///
+/// This uses the Paterson-Stockmeyer scheme: with s about sqrt(d), it
+/// forms A^2 .. A^s and runs Horner's rule in A^s on polynomials of degree
+/// below s, taking about 2 sqrt(d) matrix products instead of d.
+pub fn polyval_matrix(coeffs: &[f64], a: &Matrix) -> Matrix {
+    assert_eq!(a.m, a.n);
+    let n = a.n;
+    let mut result = Matrix::zero(n, n);
+    if coeffs.is_empty() {
+        return result;
+    }
+    let d = coeffs.len() - 1;
+    let mut s = 1;
+    while s * s < d + 1 {
+        s += 1;
+    }
+    // powers[i] = A^i for i <= s.
+    let mut powers = vec![identity(n)];
+    for i in 1..=s.min(d) {
+        powers.push(product(&powers[i - 1], a));
+    }
+    let ascending: Vec<f64> = coeffs.iter().rev().copied().collect();
+    let top = &powers[s.min(d)];
+    // Horner in A^s over the blocks of s ascending coefficients, from the
+    // top block down.
+    for (b, chunk) in ascending.chunks(s).enumerate().rev() {
+        if b + 1 < ascending.len().div_ceil(s) {
+            result = product(&result, top);
+        }
+        for (i, &c) in chunk.iter().enumerate() {
+            if c != 0.0 {
+                let p = &powers[i];
+                for t in 0..n * n {
+                    let (r, q) = (t / n, t % n);
+                    result.set(r, q, result.get(r, q) + c * p.get(r, q));
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Return the characteristic polynomial det(x I - A) of the square matrix
+/// a, monic and of degree n, by the Faddeev-LeVerrier recurrence.
+///
+/// It takes n matrix products and is numerically unstable, losing
+/// accuracy quickly as n grows; use it for small n, or exact input.
+pub fn charpoly(a: &Matrix) -> Vec<f64> {
+    assert_eq!(a.m, a.n);
+    let n = a.n;
+    let mut c = vec![0.0; n + 1];
+    c[0] = 1.0;
+    // M_k = A M_(k-1) + c_(k-1) I, c_k = -tr(A M_k) / k.
+    let mut mk = Matrix::zero(n, n);
+    let mut am = Matrix::zero(n, n);
+    for k in 1..=n {
+        for i in 0..n {
+            mk.set(i, i, mk.get(i, i) + c[k - 1]);
+        }
+        matmul(&a.view(), &mk.view(), &mut am.view_mut());
+        c[k] = -(0..n).map(|i| am.get(i, i)).sum::<f64>() / k as f64;
+        core::mem::swap(&mut mk, &mut am);
+    }
+    c
+}
+
+/// Return the minimal polynomial of A with respect to v, the monic p of
+/// least degree with p(A) v = 0, from the Krylov sequence v, A v, ....
+///
+/// A^k v is taken to depend on the earlier vectors when less than tol of
+/// its norm is left after orthogonalization against them. For v with
+/// components along every eigenvector (for example random) this is the
+/// minimal polynomial of A. The power basis is badly conditioned, so only
+/// low degrees are found reliably.
+pub fn minimal_polynomial(a: &impl LinearOperator, v: &[f64], tol: f64) -> Vec<f64> {
+    let n = a.nrows();
+    assert!(a.ncols() == n && v.len() == n);
+    // u_j = A^j v / s_j with unit norm; norms[j] = s_j / s_(j-1).
+    let mut u = v.to_vec();
+    let v_norm = norm2(&u);
+    assert!(v_norm > 0.0);
+    scale(1.0 / v_norm, &mut u);
+    let mut norms = vec![v_norm];
+    // Q R factorization of [u_0 ... u_(k-1)], R by columns.
+    let mut q: Vec<Vec<f64>> = vec![u.clone()];
+    let mut r: Vec<Vec<f64>> = vec![vec![1.0]];
+    let mut w = vec![0.0; n];
+    loop {
+        a.apply(&u, &mut w);
+        let w_norm = norm2(&w);
+        let k = q.len();
+        if w_norm == 0.0 {
+            // A^k v = 0, so p = x^k.
+            let mut p = vec![0.0; k + 1];
+            p[0] = 1.0;
+            return p;
+        }
+        scale(1.0 / w_norm, &mut w);
+        norms.push(w_norm);
+        let mut rest = w.clone();
+        let mut h = orthogonalize(&q, &mut rest, GramSchmidt::Classical);
+        let left = norm2(&rest);
+        if left <= tol || k == n {
+            // u_k = sum d_j u_j with R d = h, and A^k v = sum c_j A^j v
+            // with c_j = d_j s_k / s_j.
+            for j in (0..k).rev() {
+                let sum: f64 = (j + 1..k).map(|i| r[i][j] * h[i]).sum();
+                h[j] = (h[j] - sum) / r[j][j];
+            }
+            let mut p = vec![0.0; k + 1];
+            p[0] = 1.0;
+            let mut ratio = 1.0;
+            for j in (0..k).rev() {
+                ratio *= norms[j + 1];
+                p[k - j] = -h[j] * ratio;
+            }
+            return p;
+        }
+        scale(1.0 / left, &mut rest);
+        h.push(left);
+        q.push(rest);
+        r.push(h);
+        u.copy_from_slice(&w);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sorted(mut r: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+        r.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        r
+    }
+
+    #[test]
+    fn roots_of_small_polynomials() {
+        let r = sorted(roots(&[1.0, -6.0, 11.0, -6.0]).unwrap());
+        assert!(r.iter().zip([1.0, 2.0, 3.0]).all(|(&(re, im), x)| (re - x).abs() < 1e-13 && im == 0.0));
+        let r = sorted(roots(&[0.0, 2.0, 0.0, 2.0]).unwrap());
+        assert!(r[0].0.abs() < 1e-15 && (r[0].1 + 1.0).abs() < 1e-15);
+        assert!(r[1].0.abs() < 1e-15 && (r[1].1 - 1.0).abs() < 1e-15);
+        // x^2 (x - 2).
+        assert_eq!(sorted(roots(&[1.0, -2.0, 0.0, 0.0]).unwrap()), vec![(0.0, 0.0), (0.0, 0.0), (2.0, 0.0)]);
+        assert!(roots(&[3.0]).unwrap().is_empty());
+        assert_eq!(polyval(&[1.0, -6.0, 11.0, -6.0], 4.0), 6.0);
+    }
+
+    #[test]
+    fn balancing_helps_badly_scaled_roots() {
+        // Roots 1e-4, 1, 1e4 and -1e8: coefficients span 17 orders of
+        // magnitude.
+        let expected = [1e-4, 1.0, 1e4, -1e8];
+        let mut c = vec![1.0];
+        for &z in &expected {
+            c.push(0.0);
+            for k in (1..c.len()).rev() {
+                c[k] -= z * c[k - 1];
+            }
+        }
+        let r = sorted(roots(&c).unwrap());
+        let mut e = expected.to_vec();
+        e.sort_by(f64::total_cmp);
+        assert!(r.iter().zip(&e).all(|(&(re, im), x)| (re - x).abs() < 1e-10 * x.abs() && im == 0.0), "{r:?}");
+    }
+
+    #[test]
+    fn characteristic_polynomial() {
+        let a = Matrix::from_vec(2, 2, vec![2.0, 1.0, 1.0, 3.0]);
+        assert_eq!(charpoly(&a), vec![1.0, -5.0, 5.0]);
+        let b = Matrix::from_vec(3, 3, vec![1.0, 2.0, 0.0,
+                                            0.0, 3.0, 1.0,
+                                            4.0, 0.0, 2.0]);
+        // Cayley-Hamilton: det = 14, trace 6, sum of principal 2 x 2 minors 11.
+        let p = charpoly(&b);
+        assert!(p.iter().zip(&[1.0, -6.0, 11.0, -14.0]).all(|(x, y)| (x - y).abs() < 1e-13));
+    }
+
+    #[test]
+    fn matrix_polynomial() {
+        let a = Matrix::rand_seeded(4, 4, 9);
+        // Cayley-Hamilton: A satisfies its own characteristic polynomial.
+        let zero = polyval_matrix(&charpoly(&a), &a);
+        assert!(zero.as_slice().iter().all(|v| v.abs() < 1e-12));
+        for degree in 0..9 {
+            let c: Vec<f64> = (0..=degree).map(|i| 1.0 + 0.5 * i as f64).collect();
+            let p = polyval_matrix(&c, &a);
+            let mut want = Matrix::zero(4, 4);
+            for (i, &ci) in c.iter().enumerate() {
+                let power = a.powi((degree - i) as u32);
+                (0..16).for_each(|t| want.set(t / 4, t % 4, want.get(t / 4, t % 4) + ci * power.get(t / 4, t % 4)));
+            }
+            assert!((0..16).all(|t| (p.get(t / 4, t % 4) - want.get(t / 4, t % 4)).abs() < 1e-12), "{degree}");
+        }
+    }
+
+    #[test]
+    fn minimal_polynomial_from_krylov() {
+        // diag(1, 1, 2, 2, 3) has minimal polynomial (x - 1)(x - 2)(x - 3).
+        let mut d = Matrix::zero(5, 5);
+        for (i, x) in [1.0, 1.0, 2.0, 2.0, 3.0].into_iter().enumerate() {
+            d.set(i, i, x);
+        }
+        let v = [1.0, 0.5, -1.0, 2.0, 1.0];
+        let p = minimal_polynomial(&d, &v, 1e-10);
+        assert!(p.len() == 4 && p.iter().zip(&[1.0, -6.0, 11.0, -6.0]).all(|(x, y)| (x - y).abs() < 1e-10), "{p:?}");
+        // Missing the eigenvalue 3 in v lowers the degree.
+        let p = minimal_polynomial(&d, &[1.0, 1.0, 1.0, 0.0, 0.0], 1e-10);
+        assert!(p.len() == 3 && p.iter().zip(&[1.0, -3.0, 2.0]).all(|(x, y)| (x - y).abs() < 1e-10));
+        // A nilpotent Jordan block: x^3.
+        let j = Matrix::from_vec(3, 3, vec![0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(minimal_polynomial(&j, &[0.0, 0.0, 1.0], 1e-10), vec![1.0, 0.0, 0.0, 0.0]);
+    }
+}
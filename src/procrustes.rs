@@ -0,0 +1,158 @@
+//! Orthogonal Procrustes alignment of point sets.
+//!
+//! Given d x n matrices A and B holding n corresponding points as columns,
+//! the orthogonal R minimizing ||R A - B||_F is U V^T from the SVD
+//! B A^T = U S V^T (Schonemann). With translation both sets are centered
+//! first, and with scaling the best factor is trace(S) / ||A||_F^2, as for
+//! aligning point clouds or embeddings up to a similarity transform.
+use crate::operations::norm2;
+use crate::orth::{orthogonalize, GramSchmidt};
+use crate::svd::svd;
+use crate::{matmul, Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A similarity transform x -> scale R x + translation.
+pub struct Procrustes {
+    /// The d x d orthogonal R; it may be a reflection, with det -1.
+    pub rotation: Matrix,
+    /// The scale factor, 1 without scaling.
+    pub scale: f64,
+    /// The translation, zero without translation.
+    pub translation: Vec<f64>,
+    /// ||scale R A + translation - B||_F.
+    pub residual: f64,
+}
+
+impl Procrustes {
+    /// Return the d x n matrix of the transformed points.
+    pub fn apply(&self, a: &Matrix) -> Matrix {
+        let mut ra = Matrix::zero(self.rotation.m, a.n);
+        matmul(&self.rotation.view(), &a.view(), &mut ra.view_mut());
+        for i in 0..ra.m {
+            for j in 0..ra.n {
+                ra.set(i, j, self.scale * ra.get(i, j) + self.translation[i]);
+            }
+        }
+        ra
+    }
+}
+
+/// Return the orthogonal R minimizing ||R A - B||_F.
+pub fn procrustes(a: &Matrix, b: &Matrix) -> Procrustes {
+    procrustes_with(a, b, false, false)
+}
+
+/// Return the transform minimizing ||s R A + t 1^T - B||_F over orthogonal
+/// R, and over the scale s > 0 and translation t when `scaling` and
+/// `translation` are set.
+pub fn procrustes_with(a: &Matrix, b: &Matrix, scaling: bool, translation: bool) -> Procrustes {
+    assert_eq!((a.m, a.n), (b.m, b.n));
+    let (d, n) = (a.m, a.n);
+    let mean = |x: &Matrix| -> Vec<f64> {
+        (0..d).map(|i| if translation { (0..n).map(|j| x.get(i, j)).sum::<f64>() / n as f64 } else { 0.0 }).collect()
+    };
+    let (ma, mb) = (mean(a), mean(b));
+    let ac = Matrix::from_vec(d, n, (0..d * n).map(|t| a.get(t / n, t % n) - ma[t / n]).collect());
+    let bc = Matrix::from_vec(d, n, (0..d * n).map(|t| b.get(t / n, t % n) - mb[t / n]).collect());
+
+    let mut m = Matrix::zero(d, d);
+    matmul(&bc.view(), &ac.transpose().view(), &mut m.view_mut());
+    let f = svd(&m);
+    let mut u = f.u;
+    complete(&mut u);
+    let mut rotation = Matrix::zero(d, d);
+    matmul(&u.view(), &f.vt.view(), &mut rotation.view_mut());
+
+    let a_norm2: f64 = ac.as_slice().iter().map(|v| v * v).sum();
+    let scale = if scaling && a_norm2 > 0.0 { f.s.iter().sum::<f64>() / a_norm2 } else { 1.0 };
+    let mut rm = vec![0.0; d];
+    for i in 0..d {
+        rm[i] = (0..d).map(|k| rotation.get(i, k) * ma[k]).sum();
+    }
+    let shift: Vec<f64> = (0..d).map(|i| mb[i] - scale * rm[i]).collect();
+    let mut res = Procrustes { rotation, scale, translation: shift, residual: 0.0 };
+    let fitted = res.apply(a);
+    let diff: Vec<f64> = fitted.as_slice().iter().zip(b.as_slice()).map(|(p, q)| p - q).collect();
+    res.residual = norm2(&diff);
+    res
+}
+
+/// Replace the zero columns that svd() leaves in U for zero singular
+/// values with an orthonormal completion of the others.
+fn complete(u: &mut Matrix) {
+    let d = u.m;
+    let mut basis: Vec<Vec<f64>> = (0..u.n).map(|j| (0..d).map(|i| u.get(i, j)).collect()).filter(|c: &Vec<f64>| norm2(c) > 0.5).collect();
+    for j in 0..u.n {
+        if (0..d).any(|i| u.get(i, j) != 0.0) {
+            continue;
+        }
+        // The coordinate vector with the most left after projection.
+        let mut best = vec![0.0; d];
+        for k in 0..d {
+            let mut e = vec![0.0; d];
+            e[k] = 1.0;
+            orthogonalize(&basis, &mut e, GramSchmidt::Classical);
+            if norm2(&e) > norm2(&best) {
+                best = e;
+            }
+        }
+        let norm = norm2(&best);
+        best.iter_mut().for_each(|v| *v /= norm);
+        (0..d).for_each(|i| u.set(i, j, best[i]));
+        basis.push(best);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Return the rotation by angle t about the z axis, then by s about x.
+    fn rotation(t: f64, s: f64) -> Matrix {
+        let rz = Matrix::from_vec(3, 3, vec![t.cos(), -t.sin(), 0.0, t.sin(), t.cos(), 0.0, 0.0, 0.0, 1.0]);
+        let rx = Matrix::from_vec(3, 3, vec![1.0, 0.0, 0.0, 0.0, s.cos(), -s.sin(), 0.0, s.sin(), s.cos()]);
+        let mut r = Matrix::zero(3, 3);
+        matmul(&rx.view(), &rz.view(), &mut r.view_mut());
+        r
+    }
+
+    fn orthogonality_error(r: &Matrix) -> f64 {
+        let mut g = Matrix::zero(3, 3);
+        matmul(&r.transpose().view(), &r.view(), &mut g.view_mut());
+        (0..9).map(|t| (g.get(t / 3, t % 3) - if t / 3 == t % 3 { 1.0 } else { 0.0 }).abs()).fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn recovers_a_similarity_transform() {
+        let a = Matrix::rand_seeded(3, 10, 5);
+        let r = rotation(0.7, -0.3);
+        let t = [1.0, -2.0, 0.5];
+        let mut b = Matrix::zero(3, 10);
+        matmul(&r.view(), &a.view(), &mut b.view_mut());
+        let b = Matrix::from_vec(3, 10, (0..30).map(|k| 2.5 * b.get(k / 10, k % 10) + t[k / 10]).collect());
+
+        let fit = procrustes_with(&a, &b, true, true);
+        assert!(fit.residual < 1e-13 && (fit.scale - 2.5).abs() < 1e-14);
+        assert!(fit.rotation.as_slice().iter().zip(r.as_slice()).all(|(p, q)| (p - q).abs() < 1e-14));
+        assert!(fit.translation.iter().zip(&t).all(|(p, q)| (p - q).abs() < 1e-13));
+        // Without scaling and translation the fit is worse but R is still
+        // orthogonal.
+        let plain = procrustes(&a, &b);
+        assert!(plain.residual > 1.0 && orthogonality_error(&plain.rotation) < 1e-14);
+        assert_eq!((plain.scale, plain.translation.clone()), (1.0, vec![0.0; 3]));
+    }
+
+    #[test]
+    fn planar_points_give_an_orthogonal_rotation() {
+        // Collinear points: B A^T has rank one, so svd() leaves two zero
+        // columns in U.
+        let a = Matrix::from_vec(3, 4, (0..12).map(|k| if k / 4 == 0 { k as f64 } else { 0.0 }).collect());
+        let r = rotation(0.4, 1.1);
+        let mut b = Matrix::zero(3, 4);
+        matmul(&r.view(), &a.view(), &mut b.view_mut());
+        let fit = procrustes(&a, &b);
+        assert!(orthogonality_error(&fit.rotation) < 1e-14);
+        assert!(fit.residual < 1e-13);
+    }
+}
@@ -0,0 +1,250 @@
+//! Python bindings.
+//!
+//! `Matrix` implements the buffer protocol as a writable 2-D array of
+//! doubles, so `numpy.asarray(m)` views the matrix storage without copying.
+//! Matrices with padded rows are only exported to consumers that accept
+//! strides; contiguity requests they cannot meet raise `BufferError`.
+//! Any C-contiguous 2-D double buffer (NumPy arrays included) can be copied
+//! in with `Matrix.from_buffer`.
+//!
+//! Build the extension module with
+//! `cargo rustc --release --lib --features python,pyo3/extension-module --crate-type cdylib`
+//! and install the library as `rnla.so`.
+use crate::lu::{lu_slice, lu_solve_slice};
+use crate::{matmul, Matrix, MatrixIndex};
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::{PyBufferError, PyIndexError, PyValueError};
+use pyo3::ffi;
+use pyo3::prelude::*;
+use std::ffi::{c_int, c_void, CStr};
+use std::ptr;
+
+/// Buffer format string for f64 entries.
+const FORMAT: &CStr = c"d";
+
+#[pyclass(name = "Matrix")]
+pub struct PyMatrix(pub Matrix);
+
+impl PyMatrix {
+    /// Check and convert a (row, column) index.
+    fn index(&self, (i, j): (usize, usize)) -> PyResult<(usize, usize)> {
+        if i < self.0.m && j < self.0.n {
+            Ok((i, j))
+        } else {
+            Err(PyIndexError::new_err("matrix index out of range"))
+        }
+    }
+}
+
+#[pymethods]
+impl PyMatrix {
+    /// Return a zeroed m x n matrix.
+    #[new]
+    fn new(m: usize, n: usize) -> PyMatrix {
+        PyMatrix(Matrix::zero(m, n))
+    }
+
+    /// Return a randomly generated m x n matrix.
+    #[staticmethod]
+    fn rand(m: usize, n: usize) -> PyMatrix {
+        PyMatrix(Matrix::rand(m, n))
+    }
+
+    /// Copy a C-contiguous 2-D buffer of doubles.
+    #[staticmethod]
+    fn from_buffer(py: Python<'_>, obj: &Bound<'_, PyAny>) -> PyResult<PyMatrix> {
+        let buf = PyBuffer::<f64>::get(obj)?;
+        if buf.dimensions() != 2 || !buf.is_c_contiguous() {
+            return Err(PyBufferError::new_err("expected a C-contiguous 2-D buffer"));
+        }
+        let (m, n) = (buf.shape()[0], buf.shape()[1]);
+        Ok(PyMatrix(Matrix::from_vec(m, n, buf.to_vec(py)?)))
+    }
+
+    /// Return (rows, columns).
+    #[getter]
+    fn shape(&self) -> (usize, usize) {
+        (self.0.m, self.0.n)
+    }
+
+    fn __getitem__(&self, idx: (usize, usize)) -> PyResult<f64> {
+        let (i, j) = self.index(idx)?;
+        Ok(self.0.get(i, j))
+    }
+
+    fn __setitem__(&mut self, idx: (usize, usize), value: f64) -> PyResult<()> {
+        let (i, j) = self.index(idx)?;
+        self.0.set(i, j, value);
+        Ok(())
+    }
+
+    /// Return the entries as a list of rows.
+    fn tolist(&self) -> Vec<Vec<f64>> {
+        self.0.as_slice()
+            .chunks(self.0.ld())
+            .map(|row| row[..self.0.n].to_vec())
+            .collect()
+    }
+
+    unsafe fn __getbuffer__(slf: Bound<'_, Self>, view: *mut ffi::Py_buffer, flags: c_int) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("view is null"));
+        }
+        let (m, n, ld, data) = {
+            let mut a = slf.borrow_mut();
+            let (m, n, ld) = (a.0.m, a.0.n, a.0.ld());
+            (m, n, ld, a.0.as_mut_slice().as_mut_ptr())
+        };
+        // The storage is always writable, so PyBUF_WRITABLE needs no check,
+        // but padded rows are neither C- nor Fortran-contiguous.
+        let wants = |flag: c_int| flags & flag == flag;
+        let c_contiguous = ld == n || m <= 1;
+        let f_contiguous = c_contiguous && (m <= 1 || n <= 1);
+        if (wants(ffi::PyBUF_C_CONTIGUOUS) && !c_contiguous)
+            || (wants(ffi::PyBUF_F_CONTIGUOUS) && !f_contiguous)
+            || (wants(ffi::PyBUF_ANY_CONTIGUOUS) && !c_contiguous)
+        {
+            return Err(PyBufferError::new_err("matrix is not contiguous in the requested order"));
+        }
+        if !wants(ffi::PyBUF_STRIDES) && !c_contiguous {
+            return Err(PyBufferError::new_err("matrix has padded rows; request a strided buffer"));
+        }
+        let size = std::mem::size_of::<f64>() as isize;
+        // Shape followed by strides; freed in __releasebuffer__().
+        let dims = Box::into_raw(Box::new([m as isize, n as isize, ld as isize * size, size]));
+
+        (*view).obj = slf.into_any().into_ptr();
+        (*view).buf = data as *mut c_void;
+        (*view).len = (m * n) as isize * size;
+        (*view).readonly = 0;
+        (*view).itemsize = size;
+        (*view).format = if flags & ffi::PyBUF_FORMAT == ffi::PyBUF_FORMAT {
+            FORMAT.as_ptr() as *mut _
+        } else {
+            ptr::null_mut()
+        };
+        // Without PyBUF_ND the consumer sees a flat array of bytes.
+        (*view).ndim = if wants(ffi::PyBUF_ND) { 2 } else { 1 };
+        (*view).shape = if wants(ffi::PyBUF_ND) { (*dims).as_mut_ptr() } else { ptr::null_mut() };
+        (*view).strides = if wants(ffi::PyBUF_STRIDES) { (*dims).as_mut_ptr().add(2) } else { ptr::null_mut() };
+        (*view).suboffsets = ptr::null_mut();
+        (*view).internal = dims as *mut c_void;
+        Ok(())
+    }
+
+    unsafe fn __releasebuffer__(&self, view: *mut ffi::Py_buffer) {
+        drop(Box::from_raw((*view).internal as *mut [isize; 4]));
+    }
+}
+
+/// Return the product of a and b.
+#[pyfunction(name = "matmul")]
+fn py_matmul(a: PyRef<'_, PyMatrix>, b: PyRef<'_, PyMatrix>) -> PyResult<PyMatrix> {
+    if a.0.n != b.0.m {
+        return Err(PyValueError::new_err("matrix dimensions do not match"));
+    }
+    let mut c = Matrix::zero(a.0.m, b.0.n);
+    matmul(&a.0.view(), &b.0.view(), &mut c.view_mut());
+    Ok(PyMatrix(c))
+}
+
+/// Return the packed LU factors and pivots of a square matrix.
+#[pyfunction(name = "lu")]
+fn py_lu(a: PyRef<'_, PyMatrix>) -> PyResult<(PyMatrix, Vec<usize>)> {
+    if a.0.m != a.0.n {
+        return Err(PyValueError::new_err("matrix must be square"));
+    }
+    let n = a.0.n;
+    let mut data: Vec<f64> = a.tolist().into_iter().flatten().collect();
+    let mut piv = vec![0; n];
+    if !lu_slice(n, n, &mut data, &mut piv) {
+        return Err(PyValueError::new_err("matrix is singular"));
+    }
+    Ok((PyMatrix(Matrix::from_vec(n, n, data)), piv))
+}
+
+/// Solve Ax = b given the output of lu().
+#[pyfunction(name = "lu_solve")]
+fn py_lu_solve(lu: PyRef<'_, PyMatrix>, piv: Vec<usize>, mut b: Vec<f64>) -> PyResult<Vec<f64>> {
+    let n = lu.0.n;
+    if lu.0.m != n || piv.len() != n || b.len() != n || piv.iter().any(|p| *p >= n) {
+        return Err(PyValueError::new_err("dimensions do not match"));
+    }
+    lu_solve_slice(n, lu.0.ld(), lu.0.as_slice(), &piv, &mut b);
+    Ok(b)
+}
+
+#[pymodule]
+#[pyo3(name = "rnla")]
+fn rnla_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyMatrix>()?;
+    m.add_function(wrap_pyfunction!(py_matmul, m)?)?;
+    m.add_function(wrap_pyfunction!(py_lu, m)?)?;
+    m.add_function(wrap_pyfunction!(py_lu_solve, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pyo3::types::PyMemoryView;
+
+    #[test]
+    fn python_buffer_is_zero_copy() {
+        Python::initialize();
+        Python::attach(|py| {
+            let a = Bound::new(py, PyMatrix::new(2, 3)).unwrap();
+            let view = PyMemoryView::from(&a).unwrap();
+            assert_eq!(view.getattr("shape").unwrap().extract::<(usize, usize)>().unwrap(), (2, 3));
+            view.set_item((1, 2), 5.0).unwrap();
+            assert_eq!(a.borrow().0.get(1, 2), 5.0);
+
+            let b = PyMatrix::from_buffer(py, &view).unwrap();
+            assert_eq!(b.tolist(), vec![vec![0.0, 0.0, 0.0], vec![0.0, 0.0, 5.0]]);
+        });
+    }
+
+    #[test]
+    fn python_buffer_checks_flags() {
+        Python::initialize();
+        Python::attach(|py| {
+            // Whether a buffer request with `flags` succeeds.
+            let request = |obj: &Bound<'_, PyMatrix>, flags: c_int| unsafe {
+                let mut view = std::mem::MaybeUninit::<ffi::Py_buffer>::uninit();
+                if ffi::PyObject_GetBuffer(obj.as_ptr(), view.as_mut_ptr(), flags) == 0 {
+                    ffi::PyBuffer_Release(view.as_mut_ptr());
+                    true
+                } else {
+                    assert!(PyErr::take(py).unwrap().is_instance_of::<PyBufferError>(py));
+                    false
+                }
+            };
+            let dense = Bound::new(py, PyMatrix::new(3, 5)).unwrap();
+            for flags in [ffi::PyBUF_SIMPLE, ffi::PyBUF_WRITABLE, ffi::PyBUF_C_CONTIGUOUS, ffi::PyBUF_FULL] {
+                assert!(request(&dense, flags));
+            }
+            assert!(!request(&dense, ffi::PyBUF_F_CONTIGUOUS));
+
+            let padded = Bound::new(py, PyMatrix(Matrix::zero_aligned(3, 5, 64, true))).unwrap();
+            assert!(padded.borrow().0.ld() > 5);
+            assert!(request(&padded, ffi::PyBUF_STRIDES | ffi::PyBUF_WRITABLE));
+            assert!(!request(&padded, ffi::PyBUF_ND));
+            assert!(!request(&padded, ffi::PyBUF_ANY_CONTIGUOUS));
+        });
+    }
+
+    #[test]
+    fn python_lu_solve() {
+        Python::initialize();
+        Python::attach(|py| {
+            let a = Bound::new(py, PyMatrix(Matrix::from_vec(2, 2, vec![2.0, 1.0, 1.0, 3.0]))).unwrap();
+            let (lu, piv) = py_lu(a.borrow()).unwrap();
+            let lu = Bound::new(py, lu).unwrap();
+            let x = py_lu_solve(lu.borrow(), piv, vec![4.0, 7.0]).unwrap();
+            assert!(crate::approx(x[0], 1.0) && crate::approx(x[1], 2.0));
+
+            let singular = Bound::new(py, PyMatrix::new(2, 2)).unwrap();
+            assert!(py_lu(singular.borrow()).is_err());
+        });
+    }
+}
@@ -0,0 +1,185 @@
+//! Low-discrepancy (quasi-random) sequences.
+//!
+//! These fill the unit cube more evenly than pseudo-random points, which
+//! speeds up the convergence of quasi-Monte-Carlo estimates such as
+//! randomized trace estimation.
+use crate::{Matrix, MatrixIndex};
+use alloc::vec::Vec;
+
+/// Sequence of points in [0, 1)^dim.
+pub trait Sequence {
+    /// Return the dimension of each point.
+    fn dim(&self) -> usize;
+
+    /// Write the next point into `x`.
+    fn next_point(&mut self, x: &mut [f64]);
+}
+
+/// First primes, used as the Halton bases.
+const PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Halton sequence of up to 32 dimensions.
+///
+/// Quality degrades in high dimensions, where Sobol is preferable.
+#[derive(Clone, Debug)]
+pub struct Halton {
+    dim: usize,
+    index: u64,
+}
+
+impl Halton {
+    /// Create a sequence of the given dimension, starting after the origin.
+    pub fn new(dim: usize) -> Halton {
+        assert!(dim <= PRIMES.len());
+        Halton { dim, index: 1 }
+    }
+}
+
+impl Sequence for Halton {
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn next_point(&mut self, x: &mut [f64]) {
+        assert_eq!(x.len(), self.dim);
+        for (x, &base) in x.iter_mut().zip(&PRIMES) {
+            // Radical inverse of the index in this base.
+            let (mut i, mut f, mut res) = (self.index, 1.0, 0.0);
+            while i > 0 {
+                f /= base as f64;
+                res += f * (i % base) as f64;
+                i /= base;
+            }
+            *x = res;
+        }
+        self.index += 1;
+    }
+}
+
+/// Primitive polynomial degree, coefficients and initial direction numbers
+/// for dimensions 2 and up (Joe and Kuo, new-joe-kuo-6.21201).
+const SOBOL_PARAMS: [(usize, u32, [u32; 5]); 9] = [
+    (1, 0, [1, 0, 0, 0, 0]),
+    (2, 1, [1, 3, 0, 0, 0]),
+    (3, 1, [1, 3, 1, 0, 0]),
+    (3, 2, [1, 1, 1, 0, 0]),
+    (4, 1, [1, 1, 3, 3, 0]),
+    (4, 4, [1, 3, 5, 13, 0]),
+    (5, 2, [1, 1, 5, 5, 17]),
+    (5, 4, [1, 1, 5, 5, 5]),
+    (5, 7, [1, 1, 7, 11, 19]),
+];
+
+/// Number of bits in each coordinate.
+const SOBOL_BITS: usize = 32;
+
+/// Sobol sequence of up to 10 dimensions, generated in Gray-code order.
+#[derive(Clone, Debug)]
+pub struct Sobol {
+    /// Direction numbers, SOBOL_BITS per dimension.
+    v: Vec<[u32; SOBOL_BITS]>,
+    x: Vec<u32>,
+    index: u32,
+}
+
+impl Sobol {
+    /// Create a sequence of the given dimension, starting after the origin.
+    pub fn new(dim: usize) -> Sobol {
+        assert!(dim <= SOBOL_PARAMS.len() + 1);
+        let mut v = Vec::with_capacity(dim);
+        // The first dimension is the van der Corput sequence in base 2.
+        v.push(core::array::from_fn(|k| 1 << (SOBOL_BITS - 1 - k)));
+        for &(s, a, init) in SOBOL_PARAMS.iter().take(dim.saturating_sub(1)) {
+            let mut m = [0u32; SOBOL_BITS];
+            m[..s].copy_from_slice(&init[..s]);
+            for k in s..SOBOL_BITS {
+                let mut value = m[k - s] ^ (m[k - s] << s);
+                for i in 1..s {
+                    if (a >> (s - 1 - i)) & 1 == 1 {
+                        value ^= m[k - i] << i;
+                    }
+                }
+                m[k] = value;
+            }
+            v.push(core::array::from_fn(|k| m[k] << (SOBOL_BITS - 1 - k)));
+        }
+        Sobol {
+            v,
+            x: alloc::vec![0; dim],
+            index: 0,
+        }
+    }
+}
+
+impl Sequence for Sobol {
+    fn dim(&self) -> usize {
+        self.v.len()
+    }
+
+    fn next_point(&mut self, x: &mut [f64]) {
+        assert_eq!(x.len(), self.v.len());
+        // Flip the direction number of the lowest zero bit of the index.
+        let c = self.index.trailing_ones() as usize;
+        assert!(c < SOBOL_BITS, "Sobol sequence exhausted");
+        for ((xi, state), v) in x.iter_mut().zip(self.x.iter_mut()).zip(&self.v) {
+            *state ^= v[c];
+            *xi = *state as f64 / (1u64 << SOBOL_BITS) as f64;
+        }
+        self.index += 1;
+    }
+}
+
+impl Matrix {
+    /// Return an m x dim matrix whose rows are the next m points of `seq`.
+    pub fn from_sequence(m: usize, seq: &mut impl Sequence) -> Matrix {
+        let n = seq.dim();
+        let mut res = Matrix::zero(m, n);
+        let mut x = alloc::vec![0.0; n];
+        for i in 0..m {
+            seq.next_point(&mut x);
+            for j in 0..n {
+                res.set(i, j, x[j]);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn halton_radical_inverse() {
+        let a = Matrix::from_sequence(4, &mut Halton::new(2));
+        assert_eq!(a.to_vec(), alloc::vec![1.0 / 2.0, 1.0 / 3.0,
+                                           1.0 / 4.0, 2.0 / 3.0,
+                                           3.0 / 4.0, 1.0 / 9.0,
+                                           1.0 / 8.0, 4.0 / 9.0]);
+    }
+
+    #[test]
+    fn sobol_points_and_uniformity() {
+        let a = Matrix::from_sequence(4, &mut Sobol::new(2));
+        assert_eq!(a.to_vec(), alloc::vec![0.5, 0.5,
+                                           0.75, 0.25,
+                                           0.25, 0.75,
+                                           0.375, 0.375]);
+
+        // The first 2^k - 1 points plus the origin hit each of the 2^k slabs
+        // of every coordinate exactly once.
+        let a = Matrix::from_sequence(255, &mut Sobol::new(10));
+        for j in 0..10 {
+            let mut seen = [false; 256];
+            seen[0] = true;
+            for i in 0..255 {
+                let slab = (a.get(i, j) * 256.0) as usize;
+                assert!(!seen[slab]);
+                seen[slab] = true;
+            }
+        }
+    }
+}
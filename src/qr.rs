@@ -0,0 +1,594 @@
+//! QR factorization and its updates for adding and removing rows.
+//!
+//! The row updates keep only the triangular factor R: for least squares
+//! the products with Q can be carried along with the right-hand side, and
+//! dropping Q makes each update O(n^2) however many rows the matrix has.
+use crate::cholesky::CholFactor;
+use crate::factor::{self, Factor};
+use crate::givens::Givens;
+use crate::householder::{apply_house_left, house, BlockReflector};
+use crate::math;
+use crate::operations::dot;
+#[cfg(feature = "parallel")]
+use crate::parallel;
+use crate::triangular::solve_lower;
+use crate::{Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Block size used by qr_r() for matrices with many columns.
+const QR_BLOCK: usize = 32;
+
+/// Return the n x n upper triangular factor R of the m x n matrix A = QR.
+///
+/// If m < n the trailing rows of R are zero.
+pub fn qr_r(a: &Matrix) -> Matrix {
+    let mut w = a.clone();
+    if a.n > QR_BLOCK {
+        qr_blocked(&mut w, QR_BLOCK);
+    } else {
+        triangularize(&mut w, a.n);
+    }
+    upper(&w, a.n)
+}
+
+/// Compute A = QR in place with panels of `nb` columns.
+///
+/// On return the upper triangle of `a` holds R and the rest is zero. Q is
+/// the product of the returned block reflectors, one per panel, the i-th
+/// acting on the last `dim()` rows; qr_apply_q() applies it. Each panel is
+/// factored with single reflectors and applied to the trailing columns as
+/// a block, which turns most of the work into matrix products.
+pub fn qr_blocked(a: &mut Matrix, nb: usize) -> Vec<BlockReflector> {
+    assert!(nb > 0);
+    let (m, n) = (a.m, a.n);
+    let mut blocks = Vec::new();
+    for k in (0..n.min(m)).step_by(nb) {
+        let end = (k + nb).min(n).min(m);
+        let mut block = BlockReflector::new(m - k);
+        for j in k..end {
+            let x: Vec<f64> = (j..m).map(|i| a.get(i, j)).collect();
+            let (v, beta) = house(&x);
+            apply_house_left(&v, beta, &mut a.submatrix_mut(j..m, j..end));
+            for i in j + 1..m {
+                a.set(i, j, 0.0);
+            }
+            block.push(&v, beta);
+        }
+        if end < n {
+            block.apply_left(&mut a.submatrix_mut(k..m, end..n), true);
+        }
+        blocks.push(block);
+    }
+    blocks
+}
+
+/// Replace C with Q C, or Q^T C if `transpose` is set, for the Q from
+/// qr_blocked().
+pub fn qr_apply_q(blocks: &[BlockReflector], c: &mut Matrix, transpose: bool) {
+    let m = c.m;
+    let n = c.n;
+    let mut apply = |block: &BlockReflector| {
+        block.apply_left(&mut c.submatrix_mut(m - block.dim()..m, 0..n), transpose);
+    };
+    // Q = Q_1 Q_2 ... so Q^T applies the first block first.
+    if transpose {
+        blocks.iter().for_each(&mut apply);
+    } else {
+        blocks.iter().rev().for_each(&mut apply);
+    }
+}
+
+/// Return the triangular factor R of a tall matrix by TSQR.
+///
+/// The rows are split into blocks of `block_rows` (at least the number of
+/// columns), each block is factored independently, and the block factors
+/// are combined pairwise in a binary tree by factoring [R_1; R_2]. Unlike
+/// Householder QR of the whole matrix the blocks have no dependencies, and
+/// with the `parallel` feature the blocks and each level of the tree run
+/// on rayon's pool. The result agrees with qr_r() up to the signs of the
+/// rows.
+pub fn tsqr(a: &Matrix, block_rows: usize) -> Matrix {
+    assert!(block_rows >= a.n.max(1));
+    let ranges: Vec<Range<usize>> = (0..a.m).step_by(block_rows).map(|i| i..(i + block_rows).min(a.m)).collect();
+    let leaf = |rows: &Range<usize>| {
+        let start = rows.start * a.ld();
+        let mut block = Matrix::zero(rows.len(), a.n);
+        for (i, row) in a.as_slice()[start..].chunks(a.ld()).take(rows.len()).enumerate() {
+            block.as_mut_slice()[i * a.n..(i + 1) * a.n].copy_from_slice(&row[..a.n]);
+        }
+        qr_r(&block)
+    };
+    #[cfg(feature = "parallel")]
+    let rs = parallel::run(|| ranges.par_iter().map(leaf).collect());
+    #[cfg(not(feature = "parallel"))]
+    let rs = ranges.iter().map(leaf).collect();
+    tsqr_reduce(rs, a.n)
+}
+
+/// Combine the n x n triangular factors of stacked row blocks into the
+/// factor of the whole, pairwise in a binary tree.
+pub(crate) fn tsqr_reduce(mut rs: Vec<Matrix>, n: usize) -> Matrix {
+    let combine = |pair: &[Matrix]| {
+        let mut data = pair[0].as_slice().to_vec();
+        if let Some(second) = pair.get(1) {
+            data.extend_from_slice(second.as_slice());
+        }
+        qr_r(&Matrix::from_vec(n * pair.len(), n, data))
+    };
+    if rs.is_empty() {
+        return Matrix::zero(n, n);
+    }
+    while rs.len() > 1 {
+        #[cfg(feature = "parallel")]
+        {
+            rs = parallel::run(|| rs.par_chunks(2).map(combine).collect());
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            rs = rs.chunks(2).map(combine).collect();
+        }
+    }
+    rs.pop().unwrap()
+}
+
+/// Update R so that it factors A with the row x appended.
+pub fn qr_add_row(r: &mut Matrix, x: &[f64]) {
+    add_row(r, &mut [], x, 0.0);
+}
+
+/// Update R so that it factors A with the row x removed.
+///
+/// R must be nonsingular. Returns false, leaving R unchanged, if removing
+/// x would leave A rank deficient (in particular if x is not a row of A).
+pub fn qr_remove_row(r: &mut Matrix, x: &[f64]) -> bool {
+    remove_row(r, &mut [], 0.0, x, 0.0).is_some()
+}
+
+/// Recursive least squares: min ||A x - b|| as rows of A and b are added
+/// and removed.
+pub struct LeastSquares {
+    /// Triangular factor of A.
+    r: Matrix,
+    /// The first n entries of Q^T b.
+    z: Vec<f64>,
+    /// Squared residual norm.
+    rss: f64,
+}
+
+impl LeastSquares {
+    /// Start with no observations of n unknowns.
+    pub fn new(n: usize) -> LeastSquares {
+        LeastSquares {
+            r: Matrix::zero(n, n),
+            z: vec![0.0; n],
+            rss: 0.0,
+        }
+    }
+
+    /// Start from the observations A x = b.
+    pub fn from_rows(a: &Matrix, b: &[f64]) -> LeastSquares {
+        assert_eq!(a.m, b.len());
+        let n = a.n;
+        let mut w = Matrix::zero(a.m, n + 1);
+        for i in 0..a.m {
+            let row = &mut w.as_mut_slice()[i * (n + 1)..(i + 1) * (n + 1)];
+            row[..n].copy_from_slice(&a.as_slice()[i * a.ld()..i * a.ld() + n]);
+            row[n] = b[i];
+        }
+        triangularize(&mut w, n);
+        let column: Vec<f64> = (0..a.m).map(|i| w.as_slice()[i * (n + 1) + n]).collect();
+        let k = n.min(a.m);
+        let mut z = vec![0.0; n];
+        z[..k].copy_from_slice(&column[..k]);
+        LeastSquares {
+            r: upper(&w, n),
+            z,
+            rss: dot(&column[k..], &column[k..]),
+        }
+    }
+
+    /// Return the triangular factor R.
+    pub fn r(&self) -> &Matrix {
+        &self.r
+    }
+
+    /// Add the observation x^T u = y.
+    pub fn add_row(&mut self, x: &[f64], y: f64) {
+        self.rss += add_row(&mut self.r, &mut self.z, x, y);
+    }
+
+    /// Remove the observation x^T u = y, which must have been added.
+    ///
+    /// Returns false, leaving the problem unchanged, if the remaining
+    /// observations would not determine the solution.
+    pub fn remove_row(&mut self, x: &[f64], y: f64) -> bool {
+        match remove_row(&mut self.r, &mut self.z, self.rss, x, y) {
+            Some(rss) => {
+                self.rss = rss;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return the least squares solution.
+    pub fn solve(&self) -> Vec<f64> {
+        let n = self.z.len();
+        let (ld, r) = (self.r.ld(), self.r.as_slice());
+        let mut u = self.z.clone();
+        for i in (0..n).rev() {
+            assert!(r[i * ld + i] != 0.0, "least squares problem is rank deficient");
+            u[i] = (u[i] - dot(&r[i * ld + i + 1..i * ld + n], &u[i + 1..])) / r[i * ld + i];
+        }
+        u
+    }
+
+    /// Return the residual norm ||A x - b|| at the solution.
+    pub fn residual_norm(&self) -> f64 {
+        math::sqrt(self.rss)
+    }
+}
+
+/// Return the weighted least squares solution of min sum_i w_i (A x - b)_i^2.
+///
+/// Each row is scaled by sqrt(w_i), as for observations with variances
+/// 1 / w_i. Weights must be nonnegative; a zero weight drops the row.
+pub fn solve_wls(a: &Matrix, b: &[f64], weights: &[f64]) -> Vec<f64> {
+    assert!(a.m == b.len() && a.m == weights.len());
+    assert!(weights.iter().all(|&w| w >= 0.0), "weights must be nonnegative");
+    let n = a.n;
+    let s: Vec<f64> = weights.iter().map(|&w| math::sqrt(w)).collect();
+    let wa = Matrix::from_vec(a.m, n, (0..a.m * n).map(|t| s[t / n] * a.get(t / n, t % n)).collect());
+    let wb: Vec<f64> = b.iter().zip(&s).map(|(b, s)| b * s).collect();
+    LeastSquares::from_rows(&wa, &wb).solve()
+}
+
+/// Return the generalized least squares solution of
+/// min (A x - b)^T C^-1 (A x - b) for the SPD covariance c of the errors in
+/// b, or None if c is not positive definite.
+///
+/// With C = L L^T, this whitens the problem to min ||L^-1 (A x - b)|| and
+/// solves that by QR, never forming C^-1.
+pub fn solve_gls(a: &Matrix, b: &[f64], c: &Matrix) -> Option<Vec<f64>> {
+    let (m, n) = (a.m, a.n);
+    assert!(b.len() == m && (c.m, c.n) == (m, m));
+    let chol = CholFactor::new(c)?;
+    let l = chol.l();
+    let mut wa = Matrix::zero(m, n);
+    let mut col = vec![0.0; m];
+    for j in 0..n {
+        (0..m).for_each(|i| col[i] = a.get(i, j));
+        solve_lower(l, &mut col);
+        (0..m).for_each(|i| wa.set(i, j, col[i]));
+    }
+    let mut wb = b.to_vec();
+    solve_lower(l, &mut wb);
+    Some(LeastSquares::from_rows(&wa, &wb).solve())
+}
+
+/// Reduce the first k columns of w to upper triangular form with
+/// Householder reflections, applying them to all columns.
+fn triangularize(w: &mut Matrix, k: usize) {
+    let (m, n) = (w.m, w.n);
+    for j in 0..k.min(m) {
+        let x: Vec<f64> = (j..m).map(|i| w.get(i, j)).collect();
+        let (v, beta) = house(&x);
+        apply_house_left(&v, beta, &mut w.submatrix_mut(j..m, j..n));
+        for i in j + 1..m {
+            w.set(i, j, 0.0);
+        }
+    }
+}
+
+/// Return the leading n x n upper triangle of w.
+fn upper(w: &Matrix, n: usize) -> Matrix {
+    let mut r = Matrix::zero(n, n);
+    for i in 0..n.min(w.m) {
+        let src = &w.as_slice()[i * w.ld() + i..i * w.ld() + n];
+        r.as_mut_slice()[i * n + i..(i + 1) * n].copy_from_slice(src);
+    }
+    r
+}
+
+/// Rotate the row (x, y) into [R z] and return the square of the part of
+/// y left over, which adds to the residual.
+fn add_row(r: &mut Matrix, z: &mut [f64], x: &[f64], y: f64) -> f64 {
+    let n = r.n;
+    assert_eq!(r.m, n);
+    assert_eq!(x.len(), n);
+    let ld = r.ld();
+    let a = r.as_mut_slice();
+    let mut x = x.to_vec();
+    let mut y = y;
+    for j in 0..n {
+        if x[j] == 0.0 {
+            continue;
+        }
+        let (rot, _) = Givens::new(a[j * ld + j], x[j]);
+        for k in j..n {
+            (a[j * ld + k], x[k]) = rot.rotate(a[j * ld + k], x[k]);
+        }
+        if !z.is_empty() {
+            (z[j], y) = rot.rotate(z[j], y);
+        }
+    }
+    y * y
+}
+
+/// Remove the row (x, y) from [R z] with squared residual rss, returning
+/// the new squared residual, or None if R would become singular.
+fn remove_row(r: &mut Matrix, z: &mut [f64], rss: f64, x: &[f64], y: f64) -> Option<f64> {
+    let n = r.n;
+    assert_eq!(r.m, n);
+    assert_eq!(x.len(), n);
+    let ld = r.ld();
+    // Since R^T p = x, the unit vector [p; alpha] applied to [R; 0] gives
+    // x^T. Rotating it onto the last axis turns [R; 0] into [R'; x^T] with
+    // R'^T R' = R^T R - x x^T.
+    let mut p = x.to_vec();
+    {
+        let a = r.as_slice();
+        for i in 0..n {
+            if a[i * ld + i] == 0.0 {
+                return None;
+            }
+            p[i] = (p[i] - (0..i).map(|k| a[k * ld + i] * p[k]).sum::<f64>()) / a[i * ld + i];
+        }
+    }
+    let pp = dot(&p, &p);
+    if pp >= 1.0 {
+        return None;
+    }
+    let mut alpha = math::sqrt(1.0 - pp);
+    // The component of b along the removed direction; rounding can take
+    // the residual slightly below zero when the rest fit exactly.
+    let mut zeta = if z.is_empty() { 0.0 } else { (y - dot(&p, z)) / alpha };
+    let rss = (rss - zeta * zeta).max(0.0);
+    let a = r.as_mut_slice();
+    let mut v = vec![0.0; n];
+    for i in (0..n).rev() {
+        // Rotating against the last entry keeps it positive.
+        let (rot, h) = Givens::new(alpha, p[i]);
+        alpha = h;
+        for k in i..n {
+            (v[k], a[i * ld + k]) = rot.rotate(v[k], a[i * ld + k]);
+        }
+        if !z.is_empty() {
+            (zeta, z[i]) = rot.rotate(zeta, z[i]);
+        }
+    }
+    Some(rss)
+}
+
+/// Householder QR factorization of a square matrix, kept for repeated
+/// solves.
+///
+/// It costs about twice as much as LuFactor but needs no pivoting. For
+/// overdetermined problems use LeastSquares.
+pub struct QrFactor {
+    /// R in the upper triangle.
+    r: Matrix,
+    blocks: Vec<BlockReflector>,
+    norm1: f64,
+}
+
+impl QrFactor {
+    /// Factor a copy of the square matrix `a`, or return None if it is
+    /// singular.
+    pub fn new(a: &Matrix) -> Option<QrFactor> {
+        QrFactor::from_matrix(a.clone())
+    }
+
+    /// Factor the square matrix `a` in place, or return None if it is
+    /// singular.
+    pub fn from_matrix(mut a: Matrix) -> Option<QrFactor> {
+        assert_eq!(a.m, a.n);
+        let norm1 = factor::norm1(&a);
+        let blocks = qr_blocked(&mut a, QR_BLOCK);
+        let nonsingular = (0..a.n).all(|i| a.get(i, i) != 0.0);
+        nonsingular.then_some(QrFactor { r: a, blocks, norm1 })
+    }
+
+    /// Return the triangular factor R.
+    pub fn r(&self) -> &Matrix {
+        &self.r
+    }
+
+    /// Return the block reflectors whose product is Q, as from qr_blocked().
+    pub fn blocks(&self) -> &[BlockReflector] {
+        &self.blocks
+    }
+
+    /// Replace b with Q b, or Q^T b if `transpose` is set.
+    fn apply_q(&self, b: &mut [f64], transpose: bool) {
+        let mut c = Matrix::from_vec(b.len(), 1, b.to_vec());
+        qr_apply_q(&self.blocks, &mut c, transpose);
+        b.copy_from_slice(c.as_slice());
+    }
+}
+
+impl Factor for QrFactor {
+    fn dim(&self) -> usize {
+        self.r.n
+    }
+
+    fn solve_in_place(&self, b: &mut [f64]) {
+        // R x = Q^T b.
+        let n = self.r.n;
+        self.apply_q(b, true);
+        for i in (0..n).rev() {
+            let s: f64 = (i + 1..n).map(|j| self.r.get(i, j) * b[j]).sum();
+            b[i] = (b[i] - s) / self.r.get(i, i);
+        }
+    }
+
+    fn solve_transpose_in_place(&self, b: &mut [f64]) {
+        // A^T = R^T Q^T, so x = Q R^-T b.
+        let n = self.r.n;
+        for i in 0..n {
+            let s: f64 = (0..i).map(|j| self.r.get(j, i) * b[j]).sum();
+            b[i] = (b[i] - s) / self.r.get(i, i);
+        }
+        self.apply_q(b, false);
+    }
+
+    fn det(&self) -> f64 {
+        // Each nontrivial reflector has determinant -1.
+        let reflections: usize = self.blocks.iter().map(|b| (0..b.len()).filter(|&i| b.t().get(i, i) != 0.0).count()).sum();
+        let d: f64 = (0..self.r.n).map(|i| self.r.get(i, i)).product();
+        if reflections % 2 == 1 {
+            -d
+        } else {
+            d
+        }
+    }
+
+    fn norm1(&self) -> f64 {
+        self.norm1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MatrixIndex;
+
+    #[test]
+    fn qr_row_updates_match_refactoring() {
+        let a = Matrix::rand_seeded(12, 4, 1);
+        let r = qr_r(&a);
+        // R^T R = A^T A.
+        for i in 0..4 {
+            for j in 0..4 {
+                let rtr: f64 = (0..4).map(|k| r.get(k, i) * r.get(k, j)).sum();
+                let ata: f64 = (0..12).map(|k| a.get(k, i) * a.get(k, j)).sum();
+                assert!((rtr - ata).abs() < 1e-12);
+            }
+        }
+
+        // Build R from the first 8 rows, append the rest, then remove the
+        // first 3: the result matches R of the last 9 rows up to signs.
+        let first: Vec<f64> = (0..8 * 4).map(|k| a.get(k / 4, k % 4)).collect();
+        let mut r = qr_r(&Matrix::from_vec(8, 4, first));
+        for i in 8..12 {
+            qr_add_row(&mut r, &(0..4).map(|j| a.get(i, j)).collect::<Vec<_>>());
+        }
+        for i in 0..3 {
+            assert!(qr_remove_row(&mut r, &(0..4).map(|j| a.get(i, j)).collect::<Vec<_>>()));
+        }
+        let last: Vec<f64> = (3 * 4..12 * 4).map(|k| a.get(k / 4, k % 4)).collect();
+        let expected = qr_r(&Matrix::from_vec(9, 4, last));
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((r.get(i, j).abs() - expected.get(i, j).abs()).abs() < 1e-10);
+            }
+        }
+        // A row that was never added cannot be removed.
+        assert!(!qr_remove_row(&mut r, &[10.0, 10.0, 10.0, 10.0]));
+    }
+
+    #[test]
+    fn blocked_qr_reconstructs() {
+        let (m, n) = (40, 23);
+        let a = Matrix::rand_seeded(m, n, 5);
+        let mut r = Matrix::from_vec(m, n, a.as_slice().to_vec());
+        let blocks = qr_blocked(&mut r, 8);
+        assert_eq!(blocks.len(), 3);
+        assert!((0..m).all(|i| (0..i.min(n)).all(|j| r.get(i, j) == 0.0)));
+        let unblocked = qr_r(&a);
+        assert!((0..n * n).all(|k| (r.get(k / n, k % n) - unblocked.get(k / n, k % n)).abs() < 1e-12));
+
+        // Q R = A, and Q^T undoes Q.
+        let mut qr = Matrix::from_vec(m, n, r.as_slice().to_vec());
+        qr_apply_q(&blocks, &mut qr, false);
+        assert!((0..m * n).all(|k| (qr.get(k / n, k % n) - a.get(k / n, k % n)).abs() < 1e-12));
+        qr_apply_q(&blocks, &mut qr, true);
+        assert!((0..m * n).all(|k| (qr.get(k / n, k % n) - r.get(k / n, k % n)).abs() < 1e-12));
+    }
+
+    #[test]
+    fn tsqr_matches_householder() {
+        let (m, n) = (1000, 6);
+        let a = Matrix::rand_seeded(m, n, 6);
+        let expected = qr_r(&a);
+        // Blocks of 64 give 16 leaves with a short last one; blocks of 100
+        // give 10 leaves, so some levels have an odd factor left over.
+        for block_rows in [64, 100, 1000] {
+            let r = tsqr(&a, block_rows);
+            assert!((0..n * n).all(|k| (r.get(k / n, k % n).abs() - expected.get(k / n, k % n).abs()).abs() < 1e-10));
+        }
+        // Fewer rows than columns in the last block.
+        let r = tsqr(&Matrix::rand_seeded(13, 4, 7), 4);
+        let expected = qr_r(&Matrix::rand_seeded(13, 4, 7));
+        assert!((0..16).all(|k| (r.get(k / 4, k % 4).abs() - expected.get(k / 4, k % 4).abs()).abs() < 1e-12));
+    }
+
+    #[test]
+    fn recursive_least_squares() {
+        let a = Matrix::rand_seeded(20, 3, 2);
+        let b: Vec<f64> = (0..20).map(|i| (i as f64 * 0.37).sin()).collect();
+        let row = |i: usize| -> Vec<f64> { (0..3).map(|j| a.get(i, j)).collect() };
+
+        let mut ls = LeastSquares::new(3);
+        for i in 0..20 {
+            ls.add_row(&row(i), b[i]);
+        }
+        let direct = LeastSquares::from_rows(&a, &b);
+        let (u, v) = (ls.solve(), direct.solve());
+        assert!(u.iter().zip(&v).all(|(x, y)| (x - y).abs() < 1e-12));
+        assert!((ls.residual_norm() - direct.residual_norm()).abs() < 1e-12);
+        // The residual norm is that of A u - b.
+        let rss: f64 = (0..20).map(|i| (dot(&row(i), &u) - b[i]).powi(2)).sum();
+        assert!((ls.residual_norm() - rss.sqrt()).abs() < 1e-12);
+
+        // A sliding window: drop the oldest 5 observations.
+        for i in 0..5 {
+            assert!(ls.remove_row(&row(i), b[i]));
+        }
+        let tail = Matrix::from_vec(15, 3, (5 * 3..20 * 3).map(|k| a.get(k / 3, k % 3)).collect());
+        let window = LeastSquares::from_rows(&tail, &b[5..]);
+        let (u, v) = (ls.solve(), window.solve());
+        assert!(u.iter().zip(&v).all(|(x, y)| (x - y).abs() < 1e-10));
+        assert!((ls.residual_norm() - window.residual_norm()).abs() < 1e-10);
+    }
+
+    #[test]
+    fn weighted_and_generalized_least_squares() {
+        let (m, n) = (12, 3);
+        let a = Matrix::rand_seeded(m, n, 4);
+        let b: Vec<f64> = (0..m).map(|i| (i as f64 * 0.7).cos()).collect();
+        // Normal equations A^T W A x = A^T W b for a dense weight W.
+        let normal = |w: &Matrix| {
+            let mut aw = Matrix::zero(n, m);
+            for i in 0..n {
+                for j in 0..m {
+                    aw.set(i, j, (0..m).map(|k| a.get(k, i) * w.get(k, j)).sum());
+                }
+            }
+            let lhs = Matrix::from_vec(n, n, (0..n * n).map(|t| (0..m).map(|k| aw.get(t / n, k) * a.get(k, t % n)).sum()).collect());
+            let rhs: Vec<f64> = (0..n).map(|i| (0..m).map(|k| aw.get(i, k) * b[k]).sum()).collect();
+            crate::lu::LuFactor::new(&lhs).unwrap().solve(&rhs)
+        };
+
+        let weights: Vec<f64> = (0..m).map(|i| 1.0 / (1.0 + i as f64)).collect();
+        let x = solve_wls(&a, &b, &weights);
+        let w = Matrix::from_vec(m, m, (0..m * m).map(|t| if t / m == t % m { weights[t / m] } else { 0.0 }).collect());
+        assert!(x.iter().zip(normal(&w)).all(|(p, q)| (p - q).abs() < 1e-12));
+        // GLS with a diagonal covariance is WLS with the inverse variances.
+        let c = Matrix::from_vec(m, m, (0..m * m).map(|t| if t / m == t % m { 1.0 / weights[t / m] } else { 0.0 }).collect());
+        assert!(solve_gls(&a, &b, &c).unwrap().iter().zip(&x).all(|(p, q)| (p - q).abs() < 1e-12));
+
+        // AR(1)-correlated errors, C_ij = rho^|i-j|.
+        let rho: f64 = 0.6;
+        let c = Matrix::from_vec(m, m, (0..m * m).map(|t| rho.powi((t / m).abs_diff(t % m) as i32)).collect());
+        let x = solve_gls(&a, &b, &c).unwrap();
+        let cinv = crate::lu::LuFactor::new(&c).unwrap().inverse();
+        assert!(x.iter().zip(normal(&cinv)).all(|(p, q)| (p - q).abs() < 1e-10));
+        let indefinite = Matrix::from_vec(m, m, (0..m * m).map(|t| if t / m == t % m { -1.0 } else { 0.0 }).collect());
+        assert!(solve_gls(&a, &b, &indefinite).is_none());
+    }
+}
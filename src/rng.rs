@@ -0,0 +1,328 @@
+//! Random number generation.
+//!
+//! Matrix constructors take any `Rng`, so the external `rand` crate is only
+//! needed (behind the `rand` feature) to plug in its generators.
+use crate::math;
+use alloc::vec::Vec;
+
+/// Source of uniformly distributed random bits.
+pub trait Rng {
+    /// Return the next 64 random bits.
+    fn next_u64(&mut self) -> u64;
+
+    /// Return the next 32 random bits.
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Return a uniform value in [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        // Use the top 53 bits so every value is exactly representable.
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Return a uniform index in [0, n).
+    fn next_index(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+        // Lemire's multiply-shift with rejection, so there is no modulo bias.
+        let n = n as u64;
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let x = self.next_u64() as u128 * n as u128;
+            if x as u64 >= threshold {
+                return (x >> 64) as usize;
+            }
+        }
+    }
+}
+
+/// Shuffle `data` in place with the Fisher-Yates algorithm.
+pub fn shuffle<T>(rng: &mut impl Rng, data: &mut [T]) {
+    for i in (1..data.len()).rev() {
+        data.swap(i, rng.next_index(i + 1));
+    }
+}
+
+/// Return k distinct indices sampled uniformly from [0, n), in random order.
+pub fn sample_indices(rng: &mut impl Rng, n: usize, k: usize) -> Vec<usize> {
+    assert!(k <= n);
+    // Partial Fisher-Yates: only the first k positions are shuffled.
+    let mut idx: Vec<usize> = (0..n).collect();
+    for i in 0..k {
+        idx.swap(i, i + rng.next_index(n - i));
+    }
+    idx.truncate(k);
+    idx
+}
+
+/// Distribution of the entries of a random matrix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    /// Uniform on [a, b).
+    Uniform(f64, f64),
+    /// Standard normal, sampled with the Box-Muller transform.
+    Normal,
+    /// Uniform over the integers in [lo, hi].
+    Integer(i64, i64),
+}
+
+impl Distribution {
+    /// Fill `out` with independent samples.
+    pub fn fill(&self, rng: &mut impl Rng, out: &mut [f64]) {
+        match *self {
+            Distribution::Uniform(a, b) => {
+                for x in out.iter_mut() {
+                    *x = a + (b - a) * rng.next_f64();
+                }
+            }
+            Distribution::Normal => {
+                // Each transform gives two samples.
+                for pair in out.chunks_mut(2) {
+                    let (z0, z1) = box_muller(rng);
+                    pair[0] = z0;
+                    if let Some(x) = pair.get_mut(1) {
+                        *x = z1;
+                    }
+                }
+            }
+            Distribution::Integer(lo, hi) => {
+                assert!(lo <= hi);
+                // hi - lo overflows i64 for ranges wider than i64::MAX.
+                let range = (hi as i128 - lo as i128) as f64 + 1.0;
+                for x in out.iter_mut() {
+                    *x = (lo as f64 + math::floor(range * rng.next_f64())).min(hi as f64);
+                }
+            }
+        }
+    }
+}
+
+/// Return two independent standard normal samples.
+fn box_muller(rng: &mut impl Rng) -> (f64, f64) {
+    // 1 - u is in (0, 1], so the logarithm is finite.
+    let r = math::sqrt(-2.0 * math::ln(1.0 - rng.next_f64()));
+    let (s, c) = math::sin_cos(2.0 * core::f64::consts::PI * rng.next_f64());
+    (r * c, r * s)
+}
+
+#[cfg(feature = "rand")]
+impl<R: rand::RngCore> Rng for R {
+    fn next_u64(&mut self) -> u64 {
+        rand::RngCore::next_u64(self)
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        rand::RngCore::next_u32(self)
+    }
+}
+
+/// 64-bit linear congruential generator.
+///
+/// The modulus is 2^64, so the state update is plain wrapping arithmetic.
+/// The low bits of a power-of-two LCG have short periods, so only the high
+/// 32 bits of each state are returned.
+#[derive(Clone, Debug)]
+pub struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    /// Multiplier and increment from Knuth's MMIX.
+    const A: u64 = 6364136223846793005;
+    const C: u64 = 1442695040888963407;
+
+    /// Create a generator from a seed.
+    pub fn new(seed: u64) -> Lcg {
+        // Scramble the seed so nearby seeds give unrelated streams.
+        Lcg { state: splitmix64(seed) }
+    }
+
+    /// Create a generator seeded from the standard library's hash keys.
+    #[cfg(feature = "std")]
+    pub fn from_entropy() -> Lcg {
+        Lcg::new(entropy())
+    }
+
+    fn step(&mut self) -> u32 {
+        self.state = self.state.wrapping_mul(Lcg::A).wrapping_add(Lcg::C);
+        (self.state >> 32) as u32
+    }
+}
+
+impl Rng for Lcg {
+    fn next_u64(&mut self) -> u64 {
+        let hi = self.step() as u64;
+        (hi << 32) | self.step() as u64
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.step()
+    }
+}
+
+/// xoshiro256** generator.
+///
+/// Period 2^256 - 1 with good statistical quality in all output bits; use
+/// this over `Lcg` for randomized algorithms.
+#[derive(Clone, Debug)]
+pub struct Xoshiro256 {
+    s: [u64; 4],
+}
+
+impl Xoshiro256 {
+    /// Create a generator from a seed, expanded with SplitMix64.
+    pub fn new(seed: u64) -> Xoshiro256 {
+        let mut s = [0; 4];
+        for (k, x) in s.iter_mut().enumerate() {
+            *x = splitmix64(seed.wrapping_add((k as u64).wrapping_mul(0x9e3779b97f4a7c15)));
+        }
+        Xoshiro256 { s }
+    }
+
+    /// Create a generator from a raw state, which must not be all zero.
+    pub fn from_state(s: [u64; 4]) -> Xoshiro256 {
+        assert!(s != [0; 4]);
+        Xoshiro256 { s }
+    }
+
+    /// Create a generator seeded from the standard library's hash keys.
+    #[cfg(feature = "std")]
+    pub fn from_entropy() -> Xoshiro256 {
+        Xoshiro256::new(entropy())
+    }
+
+    /// Advance by 2^128 steps.
+    ///
+    /// Calling this k times on copies of one generator gives k
+    /// non-overlapping streams, e.g. one per thread.
+    pub fn jump(&mut self) {
+        const JUMP: [u64; 4] = [0x180ec6d33cfd0aba, 0xd5a61266f0c9392c, 0xa9582618e03fc9aa, 0x39abdc4529b1661c];
+        let mut t = [0; 4];
+        for jump in JUMP {
+            for b in 0..64 {
+                if jump & (1 << b) != 0 {
+                    for (t, s) in t.iter_mut().zip(&self.s) {
+                        *t ^= s;
+                    }
+                }
+                self.next_u64();
+            }
+        }
+        self.s = t;
+    }
+}
+
+impl Rng for Xoshiro256 {
+    fn next_u64(&mut self) -> u64 {
+        let res = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+        res
+    }
+}
+
+/// Return a seed from the standard library's hash keys.
+#[cfg(feature = "std")]
+fn entropy() -> u64 {
+    use std::hash::{BuildHasher, RandomState};
+
+    let seed = RandomState::new().hash_one(0u64);
+    // The hash keys are fixed on wasm32-unknown-unknown.
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    let seed = seed ^ js_sys::Math::random().to_bits();
+    seed
+}
+
+/// SplitMix64 finalizer.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lcg_is_deterministic() {
+        let mut a = Lcg::new(42);
+        let mut b = Lcg::new(42);
+        let mut c = Lcg::new(43);
+        for _ in 0..100 {
+            let x = a.next_u64();
+            assert_eq!(x, b.next_u64());
+            assert_ne!(x, c.next_u64());
+        }
+    }
+
+    #[test]
+    fn lcg_f64_range_and_low_bits() {
+        let mut rng = Lcg::new(0);
+        let mut ones = 0;
+        for _ in 0..10000 {
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            ones += rng.next_u32() & 1;
+        }
+        // The low output bit should not alternate or stick.
+        assert!((4500..5500).contains(&ones));
+    }
+
+    #[test]
+    fn xoshiro_reference_and_jump() {
+        // Reference output for the state [1, 2, 3, 4].
+        let mut rng = Xoshiro256::from_state([1, 2, 3, 4]);
+        assert_eq!(rng.next_u64(), 11520);
+        assert_eq!(rng.next_u64(), 0);
+        assert_eq!(rng.next_u64(), 1509978240);
+
+        let mut a = Xoshiro256::new(9);
+        let mut b = a.clone();
+        b.jump();
+        assert!((0..100).all(|_| a.next_u64() != b.next_u64()));
+    }
+
+    #[test]
+    fn shuffle_and_sample() {
+        let mut rng = Lcg::new(2);
+        let mut x: Vec<usize> = (0..20).collect();
+        shuffle(&mut rng, &mut x);
+        assert_ne!(x, (0..20).collect::<Vec<_>>());
+        x.sort();
+        assert_eq!(x, (0..20).collect::<Vec<_>>());
+
+        let mut s = sample_indices(&mut rng, 100, 10);
+        s.sort();
+        s.dedup();
+        assert_eq!(s.len(), 10);
+        assert!(s.iter().all(|i| *i < 100));
+        assert_eq!(sample_indices(&mut rng, 5, 0), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn distributions() {
+        let mut rng = Lcg::new(1);
+        let mut x = [0.0; 10001];
+
+        Distribution::Normal.fill(&mut rng, &mut x);
+        let mean = x.iter().sum::<f64>() / x.len() as f64;
+        let var = x.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / x.len() as f64;
+        assert!(mean.abs() < 0.05 && (var - 1.0).abs() < 0.05);
+
+        Distribution::Uniform(-2.0, 3.0).fill(&mut rng, &mut x);
+        assert!(x.iter().all(|v| (-2.0..3.0).contains(v)));
+
+        Distribution::Integer(-1, 1).fill(&mut rng, &mut x);
+        assert!(x.iter().all(|v| [-1.0, 0.0, 1.0].contains(v)));
+        assert!([-1.0, 0.0, 1.0].iter().all(|v| x.contains(v)));
+        Distribution::Integer(i64::MIN, i64::MAX).fill(&mut rng, &mut x);
+        assert!(x.iter().any(|&v| v < 0.0) && x.iter().any(|&v| v > 0.0));
+    }
+}
@@ -0,0 +1,267 @@
+//! Solvers for saddle point (KKT) systems
+//!
+//! ```text
+//! [ A  B^T ] [ x ]   [ f ]
+//! [ B  -C  ] [ y ] = [ g ]
+//! ```
+//!
+//! with A SPD, B of full row rank and C symmetric positive semidefinite
+//! (often zero). Both methods work on the Schur complement
+//! S = B A^-1 B^T + C, applying A^-1 through an inner solver.
+use crate::iterative::{cg, Identity, LinearOperator, Preconditioner, TransposeOperator};
+use crate::math;
+use crate::operations::{axpy, dot, norm2};
+use crate::solver::{tolerance, Monitor, SolveResult, StoppingCriterion};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Inner solver applying A^-1 with CG to a fixed relative tolerance.
+///
+/// The outer methods assume the inner solves are accurate, so the
+/// tolerance should be well below the outer one.
+pub struct InnerCg<'a, A, M> {
+    a: &'a A,
+    m: &'a M,
+    tol: f64,
+    max_iter: usize,
+}
+
+impl<'a, A: LinearOperator, M: Preconditioner> InnerCg<'a, A, M> {
+    /// Solve with A using CG preconditioned by m.
+    pub fn new(a: &'a A, m: &'a M, tol: f64, max_iter: usize) -> InnerCg<'a, A, M> {
+        InnerCg { a, m, tol, max_iter }
+    }
+}
+
+impl<A: LinearOperator, M: Preconditioner> Preconditioner for InnerCg<'_, A, M> {
+    fn apply(&self, r: &[f64], z: &mut [f64]) {
+        z.fill(0.0);
+        cg(self.a, r, z, self.m, tolerance(self.tol, self.max_iter), None);
+    }
+}
+
+/// Saddle point system with blocks A, B and optionally C.
+pub struct SaddlePoint<'a, A, B> {
+    a: &'a A,
+    b: &'a B,
+    c: Option<&'a dyn LinearOperator>,
+}
+
+impl<'a, A: LinearOperator, B: TransposeOperator> SaddlePoint<'a, A, B> {
+    /// Create the system with C = 0.
+    pub fn new(a: &'a A, b: &'a B) -> SaddlePoint<'a, A, B> {
+        assert_eq!(a.nrows(), a.ncols());
+        assert_eq!(b.ncols(), a.nrows());
+        SaddlePoint { a, b, c: None }
+    }
+
+    /// Set the stabilization block C.
+    pub fn stabilization(mut self, c: &'a dyn LinearOperator) -> SaddlePoint<'a, A, B> {
+        assert_eq!((c.nrows(), c.ncols()), (self.b.nrows(), self.b.nrows()));
+        self.c = Some(c);
+        self
+    }
+
+    /// Return the Schur complement B A^-1 B^T + C, applying A^-1 with
+    /// `inner`.
+    pub fn schur<'s, S: Preconditioner>(&'s self, inner: &'s S) -> SchurComplement<'s, A, B, S> {
+        SchurComplement { system: self, inner }
+    }
+
+    /// Return the 2-norm of the residual of the whole system.
+    pub fn residual(&self, f: &[f64], g: &[f64], x: &[f64], y: &[f64]) -> f64 {
+        let mut r1 = vec![0.0; f.len()];
+        let mut tmp = vec![0.0; f.len()];
+        self.a.apply(x, &mut r1);
+        self.b.apply_transpose(y, &mut tmp);
+        axpy(1.0, &tmp, &mut r1);
+        axpy(-1.0, f, &mut r1);
+        let mut r2 = vec![0.0; g.len()];
+        self.b.apply(x, &mut r2);
+        if let Some(c) = self.c {
+            let mut cy = vec![0.0; g.len()];
+            c.apply(y, &mut cy);
+            axpy(-1.0, &cy, &mut r2);
+        }
+        axpy(-1.0, g, &mut r2);
+        math::sqrt(dot(&r1, &r1) + dot(&r2, &r2))
+    }
+
+    /// Solve by CG on the Schur complement system
+    /// S y = B A^-1 f - g, then x = A^-1 (f - B^T y).
+    ///
+    /// `y` is the initial guess on entry; `stop` applies to the Schur
+    /// complement residual, and `monitor` sees it each iteration.
+    #[allow(clippy::too_many_arguments)]
+    pub fn schur_cg(
+        &self,
+        f: &[f64],
+        g: &[f64],
+        x: &mut [f64],
+        y: &mut [f64],
+        inner: &impl Preconditioner,
+        stop: impl StoppingCriterion,
+        monitor: Option<Monitor>,
+    ) -> SolveResult {
+        let rhs = self.schur_rhs(f, g, inner);
+        let res = cg(&self.schur(inner), &rhs, y, &Identity, stop, monitor);
+        self.recover_x(f, y, x, inner);
+        res
+    }
+
+    /// Solve by the Uzawa iteration y += omega (B x - C y - g) with
+    /// x = A^-1 (f - B^T y), which is Richardson iteration on S.
+    ///
+    /// Converges for 0 < omega < 2 / lambda_max(S); `spectrum` can estimate
+    /// the eigenvalues of `schur()`. Cheaper per step than schur_cg() but
+    /// usually needs many more steps. `monitor` sees the Schur complement
+    /// residual each iteration.
+    #[allow(clippy::too_many_arguments)]
+    pub fn uzawa(
+        &self,
+        f: &[f64],
+        g: &[f64],
+        x: &mut [f64],
+        y: &mut [f64],
+        inner: &impl Preconditioner,
+        omega: f64,
+        mut stop: impl StoppingCriterion,
+        mut monitor: Option<Monitor>,
+    ) -> SolveResult {
+        let schur = self.schur(inner);
+        let rhs = self.schur_rhs(f, g, inner);
+        let mut r = vec![0.0; g.len()];
+        let residual = |y: &[f64], r: &mut [f64]| {
+            schur.apply(y, r);
+            r.iter_mut().zip(&rhs).for_each(|(ri, bi)| *ri = bi - *ri);
+            norm2(r)
+        };
+        let rhs_norm = norm2(&rhs);
+        let mut r_norm = residual(y, &mut r);
+        let mut res = SolveResult::new(r_norm);
+        while res.check(&mut stop, r_norm, rhs_norm, None) {
+            axpy(omega, &r, y);
+            r_norm = residual(y, &mut r);
+            if !res.record(r_norm, &mut monitor) {
+                break;
+            }
+        }
+        self.recover_x(f, y, x, inner);
+        res
+    }
+
+    /// Return B A^-1 f - g.
+    fn schur_rhs(&self, f: &[f64], g: &[f64], inner: &impl Preconditioner) -> Vec<f64> {
+        let mut af = vec![0.0; f.len()];
+        inner.apply(f, &mut af);
+        let mut rhs = vec![0.0; g.len()];
+        self.b.apply(&af, &mut rhs);
+        axpy(-1.0, g, &mut rhs);
+        rhs
+    }
+
+    /// Compute x = A^-1 (f - B^T y).
+    fn recover_x(&self, f: &[f64], y: &[f64], x: &mut [f64], inner: &impl Preconditioner) {
+        let mut rhs = vec![0.0; f.len()];
+        self.b.apply_transpose(y, &mut rhs);
+        rhs.iter_mut().zip(f).for_each(|(ri, fi)| *ri = fi - *ri);
+        inner.apply(&rhs, x);
+    }
+}
+
+/// Schur complement B A^-1 B^T + C of a saddle point system, as an
+/// operator.
+pub struct SchurComplement<'s, A, B, S> {
+    system: &'s SaddlePoint<'s, A, B>,
+    inner: &'s S,
+}
+
+impl<A: LinearOperator, B: TransposeOperator, S: Preconditioner> LinearOperator for SchurComplement<'_, A, B, S> {
+    fn nrows(&self) -> usize {
+        self.system.b.nrows()
+    }
+
+    fn ncols(&self) -> usize {
+        self.system.b.nrows()
+    }
+
+    fn apply(&self, y: &[f64], out: &mut [f64]) {
+        let n = self.system.a.nrows();
+        let mut bty = vec![0.0; n];
+        self.system.b.apply_transpose(y, &mut bty);
+        let mut z = vec![0.0; n];
+        self.inner.apply(&bty, &mut z);
+        self.system.b.apply(&z, out);
+        if let Some(c) = self.system.c {
+            let mut cy = vec![0.0; y.len()];
+            c.apply(y, &mut cy);
+            axpy(1.0, &cy, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::amg::Amg;
+    use crate::rng::Lcg;
+    use crate::spectrum::lanczos_extremes;
+    use crate::{Matrix, SparseMatrix};
+
+    /// Return a 10 x 36 constraint block of full row rank.
+    fn constraints() -> SparseMatrix {
+        let mut rng = Lcg::new(4);
+        let noise = SparseMatrix::rand(10, 36, 0.2, &mut rng);
+        let mut triplets = Vec::new();
+        for i in 0..10 {
+            triplets.push((i, 3 * i, 1.0));
+            for k in noise.row_ptr()[i]..noise.row_ptr()[i + 1] {
+                triplets.push((i, noise.col_idx()[k], noise.values()[k]));
+            }
+        }
+        SparseMatrix::from_triplets(10, 36, &triplets)
+    }
+
+    #[test]
+    fn schur_cg_solves_kkt() {
+        let a = SparseMatrix::laplacian(&[6, 6]);
+        let b = constraints();
+        let f: Vec<f64> = (0..36).map(|i| (i % 5) as f64).collect();
+        let g: Vec<f64> = (0..10).map(|i| i as f64 - 4.0).collect();
+        let amg = Amg::new(&a);
+        let inner = InnerCg::new(&a, &amg, 1e-13, 200);
+
+        let kkt = SaddlePoint::new(&a, &b);
+        let (mut x, mut y) = (vec![0.0; 36], vec![0.0; 10]);
+        let mut calls = 0;
+        let res = kkt.schur_cg(&f, &g, &mut x, &mut y, &inner, tolerance(1e-11, 100), Some(&mut |_, _| calls += 1));
+        assert!(res.converged);
+        assert_eq!(calls, res.iterations);
+        assert!(kkt.residual(&f, &g, &x, &y) < 1e-9);
+
+        // With a stabilization block, and the dense B.
+        let c = Matrix::from_vec(10, 10, (0..100).map(|k| if k % 11 == 0 { 0.5 } else { 0.0 }).collect());
+        let dense_b = b.to_dense();
+        let kkt = SaddlePoint::new(&a, &dense_b).stabilization(&c);
+        let (mut x, mut y) = (vec![0.0; 36], vec![0.0; 10]);
+        assert!(kkt.schur_cg(&f, &g, &mut x, &mut y, &inner, tolerance(1e-11, 100), None).converged);
+        assert!(kkt.residual(&f, &g, &x, &y) < 1e-9);
+    }
+
+    #[test]
+    fn uzawa_with_estimated_weight() {
+        let a = SparseMatrix::laplacian(&[6, 6]);
+        let b = constraints();
+        let f = vec![1.0; 36];
+        let g = vec![0.5; 10];
+        let inner = InnerCg::new(&a, &Identity, 1e-13, 200);
+        let kkt = SaddlePoint::new(&a, &b);
+        let (lmin, lmax) = lanczos_extremes(&kkt.schur(&inner), 10, &mut Lcg::new(5));
+        let omega = 2.0 / (lmin + 1.1 * lmax);
+
+        let (mut x, mut y) = (vec![0.0; 36], vec![0.0; 10]);
+        let res = kkt.uzawa(&f, &g, &mut x, &mut y, &inner, omega, tolerance(1e-10, 5000), None);
+        assert!(res.converged);
+        assert!(kkt.residual(&f, &g, &x, &y) < 1e-8);
+    }
+}
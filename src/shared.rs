@@ -0,0 +1,130 @@
+//! Shared, copy-on-write matrices.
+//!
+//! An ArcMatrix is a reference-counted Matrix: cloning it copies a pointer,
+//! so one large operator can be handed to many threads or tasks, and the
+//! entries are copied only when a clone that is still shared is mutated.
+use crate::iterative::LinearOperator;
+use crate::Matrix;
+use alloc::sync::Arc;
+use core::ops::Deref;
+
+/// Reference-counted matrix with copy-on-write mutation.
+///
+/// It dereferences to Matrix for reading, so views, products and solvers
+/// take it like any matrix.
+#[derive(Clone)]
+pub struct ArcMatrix {
+    inner: Arc<Matrix>,
+}
+
+impl ArcMatrix {
+    /// Take ownership of a.
+    pub fn new(a: Matrix) -> ArcMatrix {
+        ArcMatrix { inner: Arc::new(a) }
+    }
+
+    /// Return a mutable reference to the matrix, first copying the
+    /// entries if other clones share them.
+    pub fn make_mut(&mut self) -> &mut Matrix {
+        Arc::make_mut(&mut self.inner)
+    }
+
+    /// Return the matrix, copying it only if it is still shared.
+    pub fn into_matrix(self) -> Matrix {
+        Arc::unwrap_or_clone(self.inner)
+    }
+
+    /// Return whether other clones share the entries.
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.inner) > 1
+    }
+
+    /// Return whether a and b share the same entries.
+    pub fn ptr_eq(a: &ArcMatrix, b: &ArcMatrix) -> bool {
+        Arc::ptr_eq(&a.inner, &b.inner)
+    }
+}
+
+impl From<Matrix> for ArcMatrix {
+    fn from(a: Matrix) -> ArcMatrix {
+        ArcMatrix::new(a)
+    }
+}
+
+impl Deref for ArcMatrix {
+    type Target = Matrix;
+
+    fn deref(&self) -> &Matrix {
+        &self.inner
+    }
+}
+
+impl LinearOperator for ArcMatrix {
+    fn nrows(&self) -> usize {
+        self.inner.nrows()
+    }
+
+    fn ncols(&self) -> usize {
+        self.inner.ncols()
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        self.inner.apply(x, y);
+    }
+
+    fn apply_block(&self, x: &Matrix, y: &mut Matrix) {
+        self.inner.apply_block(x, y);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MatrixIndex;
+
+    #[test]
+    fn clones_share_until_written() {
+        let a = ArcMatrix::new(Matrix::rand_seeded(4, 4, 1));
+        let mut b = a.clone();
+        assert!(ArcMatrix::ptr_eq(&a, &b) && a.is_shared());
+        let before = a.get(1, 2);
+        b.make_mut().set(1, 2, 7.0);
+        assert!(!ArcMatrix::ptr_eq(&a, &b) && !a.is_shared());
+        assert_eq!((a.get(1, 2), b.get(1, 2)), (before, 7.0));
+        assert_eq!(a.get(3, 3), b.get(3, 3));
+
+        // An unshared matrix is written and returned without copying.
+        let ptr = b.as_slice().as_ptr();
+        b.make_mut().set(0, 0, 1.0);
+        assert_eq!(b.as_slice().as_ptr(), ptr);
+        let m = b.into_matrix();
+        assert_eq!(m.as_slice().as_ptr(), ptr);
+        let c = a.clone();
+        assert_eq!(c.into_matrix().get(1, 2), before);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn shared_across_threads() {
+        let a = ArcMatrix::new(Matrix::rand_seeded(50, 50, 2));
+        let x = vec![1.0; 50];
+        let mut expected = vec![0.0; 50];
+        a.apply(&x, &mut expected);
+        let results: Vec<Vec<f64>> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    let a = a.clone();
+                    let x = &x;
+                    s.spawn(move || {
+                        let mut y = vec![0.0; 50];
+                        a.apply(x, &mut y);
+                        y
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert!(results.iter().all(|y| *y == expected));
+        assert!(!a.is_shared());
+    }
+}
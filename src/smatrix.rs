@@ -0,0 +1,172 @@
+//! Stack-allocated fixed-size matrices.
+use crate::{Matrix, MatrixIndex};
+use core::ops::Mul;
+
+/// Fixed-size M x N matrix stored inline in row-major order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SMatrix<const M: usize, const N: usize> {
+    data: [[f64; N]; M],
+}
+
+impl<const M: usize, const N: usize> SMatrix<M, N> {
+    /// Create from an array of rows.
+    pub const fn new(data: [[f64; N]; M]) -> SMatrix<M, N> {
+        SMatrix { data }
+    }
+
+    /// Return a zeroed matrix.
+    pub const fn zero() -> SMatrix<M, N> {
+        SMatrix { data: [[0.0; N]; M] }
+    }
+
+    /// Return a matrix with ones on the diagonal.
+    pub fn identity() -> SMatrix<M, N> {
+        let mut res = SMatrix::zero();
+        for i in 0..M.min(N) {
+            res.data[i][i] = 1.0;
+        }
+        res
+    }
+
+    /// Copy out of a dynamic matrix of the same size.
+    pub fn from_matrix(a: &Matrix) -> SMatrix<M, N> {
+        assert_eq!(a.m, M);
+        assert_eq!(a.n, N);
+        let mut res = SMatrix::zero();
+        for i in 0..M {
+            for j in 0..N {
+                res.data[i][j] = a.get(i, j);
+            }
+        }
+        res
+    }
+
+    /// Copy into a dynamic matrix.
+    pub fn to_matrix(&self) -> Matrix {
+        Matrix::from_vec(M, N, self.data.iter().flatten().copied().collect())
+    }
+
+    /// Return the rows as an array.
+    pub fn rows(&self) -> &[[f64; N]; M] {
+        &self.data
+    }
+
+    /// Return the transpose.
+    pub fn transpose(&self) -> SMatrix<N, M> {
+        let mut res = SMatrix::zero();
+        for i in 0..M {
+            for j in 0..N {
+                res.data[j][i] = self.data[i][j];
+            }
+        }
+        res
+    }
+
+    /// Multiply by another fixed-size matrix.
+    ///
+    /// All loop bounds are constants, so the compiler fully unrolls this for
+    /// small sizes.
+    #[inline]
+    pub fn matmul<const P: usize>(&self, b: &SMatrix<N, P>) -> SMatrix<M, P> {
+        let mut res = SMatrix::zero();
+        for i in 0..M {
+            for k in 0..N {
+                let aik = self.data[i][k];
+                for j in 0..P {
+                    res.data[i][j] += aik * b.data[k][j];
+                }
+            }
+        }
+        res
+    }
+
+    /// Multiply by a vector.
+    #[inline]
+    pub fn matvec(&self, x: &[f64; N]) -> [f64; M] {
+        let mut res = [0.0; M];
+        for i in 0..M {
+            for j in 0..N {
+                res[i] += self.data[i][j] * x[j];
+            }
+        }
+        res
+    }
+}
+
+impl<const M: usize, const N: usize> From<SMatrix<M, N>> for Matrix {
+    fn from(a: SMatrix<M, N>) -> Matrix {
+        a.to_matrix()
+    }
+}
+
+impl<const M: usize, const N: usize, const P: usize> Mul<SMatrix<N, P>> for SMatrix<M, N> {
+    type Output = SMatrix<M, P>;
+
+    #[inline]
+    fn mul(self, rhs: SMatrix<N, P>) -> SMatrix<M, P> {
+        self.matmul(&rhs)
+    }
+}
+
+impl<const M: usize, const N: usize> MatrixIndex for SMatrix<M, N> {
+    #[inline]
+    fn get(&self, i: usize, j: usize) -> f64 {
+        self.data[i][j]
+    }
+
+    #[inline]
+    fn set(&mut self, i: usize, j: usize, value: f64) {
+        self.data[i][j] = value;
+    }
+
+    #[inline]
+    unsafe fn get_unchecked(&self, i: usize, j: usize) -> f64 {
+        *self.data
+            .get_unchecked(i)
+            .get_unchecked(j)
+    }
+
+    #[inline]
+    unsafe fn set_unchecked(&mut self, i: usize, j: usize, value: f64) {
+        *self.data
+            .get_unchecked_mut(i)
+            .get_unchecked_mut(j) = value;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{approx, matmul};
+
+    #[test]
+    fn smatrix_matmul_matches_dynamic() {
+        let x = Matrix::rand_seeded(3, 3, 1);
+        let y = Matrix::rand_seeded(3, 3, 2);
+        let mut z = Matrix::zero(3, 3);
+        matmul(&x.view(), &y.view(), &mut z.view_mut());
+
+        let sx = SMatrix::<3, 3>::from_matrix(&x);
+        let sy = SMatrix::<3, 3>::from_matrix(&y);
+        let sz = sx * sy;
+
+        for i in 0..3 {
+            for j in 0..3 {
+                assert!(approx(sz.get(i, j), z.get(i, j)));
+            }
+        }
+    }
+
+    #[test]
+    fn smatrix_roundtrip() {
+        let a = SMatrix::new([[1.0, 2.0, 3.0],
+                              [4.0, 5.0, 6.0]]);
+        let b: Matrix = a.into();
+        assert_eq!(b.m, 2);
+        assert_eq!(b.n, 3);
+        assert_eq!(SMatrix::<2, 3>::from_matrix(&b), a);
+        assert_eq!(a.transpose().transpose(), a);
+        assert_eq!(SMatrix::<4, 4>::identity().matvec(&[1.0, 2.0, 3.0, 4.0]),
+                   [1.0, 2.0, 3.0, 4.0]);
+    }
+}
@@ -0,0 +1,287 @@
+//! Results and diagnostics shared by the iterative solvers.
+use alloc::vec::Vec;
+
+/// Per-iteration callback, called with the iteration number (starting at 1)
+/// and the current residual norm.
+pub type Monitor<'a> = &'a mut dyn FnMut(usize, f64);
+
+/// Reason an iterative solver stopped early without converging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Breakdown {
+    /// A search direction had p^T A p <= 0, so A (or the preconditioner) is
+    /// not positive definite.
+    NotPositiveDefinite,
+    /// The residual became NaN or infinite.
+    NonFinite,
+}
+
+/// Outcome and convergence history of an iterative solve.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolveResult {
+    /// Number of iterations run.
+    pub iterations: usize,
+    /// Residual 2-norm before the first iteration and after each iteration.
+    pub residuals: Vec<f64>,
+    /// True if the stopping criterion reported convergence.
+    pub converged: bool,
+    /// Set if the solver broke down.
+    pub breakdown: Option<Breakdown>,
+}
+
+impl SolveResult {
+    /// Start a history with the initial residual norm.
+    pub fn new(r0: f64) -> SolveResult {
+        SolveResult {
+            iterations: 0,
+            residuals: alloc::vec![r0],
+            converged: false,
+            breakdown: None,
+        }
+    }
+
+    /// Record the residual norm after an iteration and call the monitor.
+    ///
+    /// Returns false, recording the breakdown, if the residual is not finite.
+    pub fn record(&mut self, residual: f64, monitor: &mut Option<Monitor>) -> bool {
+        self.iterations += 1;
+        self.residuals.push(residual);
+        if let Some(f) = monitor {
+            f(self.iterations, residual);
+        }
+        if !residual.is_finite() {
+            self.breakdown = Some(Breakdown::NonFinite);
+            return false;
+        }
+        true
+    }
+
+    /// Check `stop` at the given residual norm, recording whether it
+    /// converged. Returns true if the solver should continue.
+    pub fn check(
+        &mut self,
+        stop: &mut impl StoppingCriterion,
+        residual: f64,
+        rhs_norm: f64,
+        preconditioned: Option<(f64, f64)>,
+    ) -> bool {
+        let state = IterState {
+            iteration: self.iterations,
+            residual,
+            initial_residual: self.residuals[0],
+            rhs_norm,
+            preconditioned,
+        };
+        let status = stop.check(&state);
+        self.converged = status == Status::Converged;
+        status == Status::Continue
+    }
+
+    /// Return the last residual norm.
+    pub fn final_residual(&self) -> f64 {
+        *self.residuals.last().unwrap()
+    }
+
+    /// Return the residual norm relative to the initial one.
+    pub fn relative_residual(&self) -> f64 {
+        self.final_residual() / self.residuals[0]
+    }
+
+    /// Return true if the residual fell by less than the fraction `factor`
+    /// over the last `window` iterations, e.g. (10, 0.01) for less than 1%
+    /// progress in 10 iterations.
+    pub fn stagnated(&self, window: usize, factor: f64) -> bool {
+        let n = self.residuals.len();
+        if n <= window {
+            return false;
+        }
+        self.residuals[n - 1] > (1.0 - factor) * self.residuals[n - 1 - window]
+    }
+}
+
+/// State of a solver when a stopping criterion is checked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IterState {
+    /// Iterations completed so far.
+    pub iteration: usize,
+    /// Current residual 2-norm (or the solver's estimate of it).
+    pub residual: f64,
+    /// Residual 2-norm of the initial guess.
+    pub initial_residual: f64,
+    /// 2-norm of the right-hand side.
+    pub rhs_norm: f64,
+    /// Current and initial norms of the preconditioned residual, if the
+    /// solver is preconditioned.
+    pub preconditioned: Option<(f64, f64)>,
+}
+
+/// Decision of a stopping criterion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Continue,
+    /// Stop, the solution is accurate enough.
+    Converged,
+    /// Stop without converging, e.g. at an iteration limit.
+    Stopped,
+}
+
+/// Rule deciding when an iterative solver stops.
+///
+/// A solver checks it before every iteration. Combine criteria with or();
+/// make sure one of them eventually stops, e.g. `MaxIterations`.
+pub trait StoppingCriterion {
+    fn check(&mut self, state: &IterState) -> Status;
+
+    /// Stop as soon as either criterion does.
+    fn or<B: StoppingCriterion>(self, other: B) -> Or<Self, B>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+}
+
+/// Converged once the residual is below tol times the norm of b.
+#[derive(Clone, Copy, Debug)]
+pub struct RelativeResidual(pub f64);
+
+impl StoppingCriterion for RelativeResidual {
+    fn check(&mut self, state: &IterState) -> Status {
+        converged_if(state.residual <= self.0 * state.rhs_norm)
+    }
+}
+
+/// Converged once the residual is below tol.
+#[derive(Clone, Copy, Debug)]
+pub struct AbsoluteResidual(pub f64);
+
+impl StoppingCriterion for AbsoluteResidual {
+    fn check(&mut self, state: &IterState) -> Status {
+        converged_if(state.residual <= self.0)
+    }
+}
+
+/// Converged once the preconditioned residual fell by the factor tol.
+///
+/// Unpreconditioned solvers compare the residual to the initial residual
+/// instead.
+#[derive(Clone, Copy, Debug)]
+pub struct PreconditionedResidual(pub f64);
+
+impl StoppingCriterion for PreconditionedResidual {
+    fn check(&mut self, state: &IterState) -> Status {
+        let (z, z0) = state.preconditioned.unwrap_or((state.residual, state.initial_residual));
+        converged_if(z <= self.0 * z0)
+    }
+}
+
+/// Stop after the given number of iterations.
+#[derive(Clone, Copy, Debug)]
+pub struct MaxIterations(pub usize);
+
+impl StoppingCriterion for MaxIterations {
+    fn check(&mut self, state: &IterState) -> Status {
+        if state.iteration >= self.0 {
+            Status::Stopped
+        } else {
+            Status::Continue
+        }
+    }
+}
+
+/// User-defined criterion.
+pub struct Callback<F>(pub F);
+
+impl<F: FnMut(&IterState) -> Status> StoppingCriterion for Callback<F> {
+    fn check(&mut self, state: &IterState) -> Status {
+        (self.0)(state)
+    }
+}
+
+/// Criterion that stops when either of two criteria does.
+#[derive(Clone, Copy, Debug)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: StoppingCriterion, B: StoppingCriterion> StoppingCriterion for Or<A, B> {
+    fn check(&mut self, state: &IterState) -> Status {
+        match self.0.check(state) {
+            Status::Continue => self.1.check(state),
+            status => status,
+        }
+    }
+}
+
+impl<S: StoppingCriterion + ?Sized> StoppingCriterion for &mut S {
+    fn check(&mut self, state: &IterState) -> Status {
+        (**self).check(state)
+    }
+}
+
+/// Return the usual criterion: relative residual below tol, or max_iter
+/// iterations.
+pub fn tolerance(tol: f64, max_iter: usize) -> Or<RelativeResidual, MaxIterations> {
+    RelativeResidual(tol).or(MaxIterations(max_iter))
+}
+
+fn converged_if(cond: bool) -> Status {
+    if cond {
+        Status::Converged
+    } else {
+        Status::Continue
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn solve_result_history_and_monitor() {
+        let mut seen = Vec::new();
+        let mut f = |it, r| seen.push((it, r));
+        let mut monitor: Option<Monitor> = Some(&mut f);
+        let mut res = SolveResult::new(1.0);
+        assert!(res.record(0.5, &mut monitor));
+        assert!(res.record(0.25, &mut monitor));
+        assert!(!res.record(f64::NAN, &mut monitor));
+        assert_eq!(res.iterations, 3);
+        assert_eq!(res.breakdown, Some(Breakdown::NonFinite));
+        assert_eq!(seen[..2], [(1, 0.5), (2, 0.25)]);
+    }
+
+    #[test]
+    fn stopping_criteria() {
+        let mut state = IterState {
+            iteration: 3,
+            residual: 0.5,
+            initial_residual: 10.0,
+            rhs_norm: 100.0,
+            preconditioned: None,
+        };
+        assert_eq!(RelativeResidual(1e-2).check(&state), Status::Converged);
+        assert_eq!(AbsoluteResidual(0.1).check(&state), Status::Continue);
+        assert_eq!(PreconditionedResidual(0.1).check(&state), Status::Converged);
+        state.preconditioned = Some((2.0, 10.0));
+        assert_eq!(PreconditionedResidual(0.1).check(&state), Status::Continue);
+
+        let mut stop = AbsoluteResidual(0.1).or(MaxIterations(3));
+        assert_eq!(stop.check(&state), Status::Stopped);
+        let mut stop = tolerance(1e-3, 10).or(Callback(|s: &IterState| {
+            if s.residual < 1.0 { Status::Stopped } else { Status::Continue }
+        }));
+        assert_eq!(stop.check(&state), Status::Stopped);
+    }
+
+    #[test]
+    fn solve_result_stagnation() {
+        let res = SolveResult {
+            iterations: 4,
+            residuals: alloc::vec![1.0, 0.1, 0.0999, 0.0998, 0.0997],
+            converged: false,
+            breakdown: None,
+        };
+        assert!(res.stagnated(3, 0.01));
+        assert!(!res.stagnated(4, 0.01));
+        assert!(!res.stagnated(5, 0.01));
+        assert_eq!(res.relative_residual(), 0.0997);
+    }
+}
@@ -0,0 +1,472 @@
+//! Compressed sparse row matrices.
+use crate::math;
+#[cfg(feature = "parallel")]
+use crate::operations::PARALLEL_MIN_LEN;
+#[cfg(feature = "parallel")]
+use crate::parallel;
+use crate::rng::Rng;
+use crate::{Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Sparse matrix in compressed sparse row (CSR) format.
+///
+/// The column indices within each row are sorted and unique.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparseMatrix {
+    pub m: usize,
+    pub n: usize,
+    row_ptr: Vec<usize>,
+    col_idx: Vec<usize>,
+    values: Vec<f64>,
+}
+
+impl SparseMatrix {
+    /// Build from (row, column, value) triplets, summing duplicates.
+    pub fn from_triplets(m: usize, n: usize, triplets: &[(usize, usize, f64)]) -> SparseMatrix {
+        let mut sorted = triplets.to_vec();
+        sorted.sort_by_key(|&(i, j, _)| (i, j));
+        let mut row_ptr = vec![0; m + 1];
+        let mut col_idx: Vec<usize> = Vec::with_capacity(sorted.len());
+        let mut values: Vec<f64> = Vec::with_capacity(sorted.len());
+        let mut last = None;
+        for (i, j, value) in sorted {
+            assert!(i < m && j < n);
+            if last == Some((i, j)) {
+                *values.last_mut().unwrap() += value;
+                continue;
+            }
+            last = Some((i, j));
+            row_ptr[i + 1] += 1;
+            col_idx.push(j);
+            values.push(value);
+        }
+        for i in 0..m {
+            row_ptr[i + 1] += row_ptr[i];
+        }
+        SparseMatrix {
+            m,
+            n,
+            row_ptr,
+            col_idx,
+            values,
+        }
+    }
+
+    /// Copy the nonzero entries of a dense matrix.
+    pub fn from_dense(a: &Matrix) -> SparseMatrix {
+        let mut triplets = Vec::new();
+        for i in 0..a.m {
+            for j in 0..a.n {
+                let value = a.get(i, j);
+                if value != 0.0 {
+                    triplets.push((i, j, value));
+                }
+            }
+        }
+        SparseMatrix::from_triplets(a.m, a.n, &triplets)
+    }
+
+    /// Return a dense copy.
+    pub fn to_dense(&self) -> Matrix {
+        let mut res = Matrix::zero(self.m, self.n);
+        for i in 0..self.m {
+            for k in self.row_ptr[i]..self.row_ptr[i + 1] {
+                res.set(i, self.col_idx[k], self.values[k]);
+            }
+        }
+        res
+    }
+
+    /// Return the number of stored entries.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Return the row pointer array (length m + 1).
+    pub fn row_ptr(&self) -> &[usize] {
+        &self.row_ptr
+    }
+
+    /// Return the column index of each stored entry.
+    pub fn col_idx(&self) -> &[usize] {
+        &self.col_idx
+    }
+
+    /// Return the stored values.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Return the stored values mutably.
+    pub fn values_mut(&mut self) -> &mut [f64] {
+        &mut self.values
+    }
+
+    /// Compute y = A x.
+    ///
+    /// With the `parallel` feature, matrices with at least
+    /// `PARALLEL_MIN_LEN` nonzeros are split into one block of rows per
+    /// thread by nnz_partition(), so that rows of very different lengths,
+    /// as in power-law graphs, still give every thread the same work.
+    pub fn matvec(&self, x: &[f64], y: &mut [f64]) {
+        assert_eq!(x.len(), self.n);
+        assert_eq!(y.len(), self.m);
+        #[cfg(feature = "parallel")]
+        if self.nnz() >= PARALLEL_MIN_LEN {
+            let bounds = self.nnz_partition(parallel::num_threads());
+            let mut blocks = Vec::with_capacity(bounds.len() - 1);
+            let mut rest = y;
+            for w in bounds.windows(2) {
+                let (block, tail) = rest.split_at_mut(w[1] - w[0]);
+                blocks.push((w[0], block));
+                rest = tail;
+            }
+            parallel::run(|| blocks.into_par_iter().for_each(|(start, block)| self.matvec_rows(start, x, block)));
+            return;
+        }
+        self.matvec_rows(0, x, y);
+    }
+
+    /// Compute the rows start.. start + y.len() of A x.
+    fn matvec_rows(&self, start: usize, x: &[f64], y: &mut [f64]) {
+        for (i, yi) in (start..).zip(y.iter_mut()) {
+            let mut sum = 0.0;
+            for k in self.row_ptr[i]..self.row_ptr[i + 1] {
+                sum += self.values[k] * x[self.col_idx[k]];
+            }
+            *yi = sum;
+        }
+    }
+
+    /// Return parts + 1 row boundaries 0 = r_0 <= ... <= r_parts = m that
+    /// split the rows into parts contiguous blocks of about equal work.
+    ///
+    /// The work of a row is its number of nonzeros plus one, so that empty
+    /// rows still count. A single row is never split, so one row holding
+    /// more than a part's share of the nonzeros leads to imbalance.
+    pub fn nnz_partition(&self, parts: usize) -> Vec<usize> {
+        assert!(parts > 0);
+        let total = self.nnz() + self.m;
+        // Work before row r is row_ptr[r] + r, which increases with r.
+        let mut bounds: Vec<usize> = (0..parts).map(|p| {
+            let target = p * total / parts;
+            let (mut lo, mut hi) = (0, self.m);
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if self.row_ptr[mid] + mid < target {
+                    lo = mid + 1;
+                } else {
+                    hi = mid;
+                }
+            }
+            lo
+        }).collect();
+        bounds.push(self.m);
+        bounds
+    }
+
+    /// Compute Y = A X for a dense block X.
+    ///
+    /// Each stored entry is loaded once for all columns of X.
+    pub fn matmat(&self, x: &Matrix, y: &mut Matrix) {
+        assert_eq!(x.m, self.n);
+        assert_eq!((y.m, y.n), (self.m, x.n));
+        let (s, xld, yld) = (x.n, x.ld(), y.ld());
+        let (xs, ys) = (x.as_slice(), y.as_mut_slice());
+        for i in 0..self.m {
+            let yrow = &mut ys[i * yld..i * yld + s];
+            yrow.fill(0.0);
+            for k in self.row_ptr[i]..self.row_ptr[i + 1] {
+                let (value, j) = (self.values[k], self.col_idx[k]);
+                for (yv, xv) in yrow.iter_mut().zip(&xs[j * xld..j * xld + s]) {
+                    *yv += value * xv;
+                }
+            }
+        }
+    }
+
+    /// Return the diagonal entries, with zeros where none are stored.
+    pub fn diagonal(&self) -> Vec<f64> {
+        let mut res = vec![0.0; self.m.min(self.n)];
+        for (i, d) in res.iter_mut().enumerate() {
+            for k in self.row_ptr[i]..self.row_ptr[i + 1] {
+                if self.col_idx[k] == i {
+                    *d = self.values[k];
+                }
+            }
+        }
+        res
+    }
+
+    /// Return the transpose.
+    pub fn transpose(&self) -> SparseMatrix {
+        let mut row_ptr = vec![0; self.n + 1];
+        for &j in &self.col_idx {
+            row_ptr[j + 1] += 1;
+        }
+        for j in 0..self.n {
+            row_ptr[j + 1] += row_ptr[j];
+        }
+        // Visiting the rows in order keeps each new row sorted.
+        let mut next = row_ptr.clone();
+        let mut col_idx = vec![0; self.nnz()];
+        let mut values = vec![0.0; self.nnz()];
+        for i in 0..self.m {
+            for k in self.row_ptr[i]..self.row_ptr[i + 1] {
+                let pos = &mut next[self.col_idx[k]];
+                col_idx[*pos] = i;
+                values[*pos] = self.values[k];
+                *pos += 1;
+            }
+        }
+        SparseMatrix {
+            m: self.n,
+            n: self.m,
+            row_ptr,
+            col_idx,
+            values,
+        }
+    }
+
+    /// Return the sparse product A B.
+    pub fn matmul(&self, b: &SparseMatrix) -> SparseMatrix {
+        assert_eq!(self.n, b.m);
+        let mut row_ptr = vec![0; self.m + 1];
+        let mut col_idx = Vec::new();
+        let mut values = Vec::new();
+        // Dense accumulator for one row, with the columns it touched.
+        let mut acc = vec![0.0; b.n];
+        let mut marker = vec![usize::MAX; b.n];
+        let mut cols = Vec::new();
+        for i in 0..self.m {
+            cols.clear();
+            for k in self.row_ptr[i]..self.row_ptr[i + 1] {
+                let (j, value) = (self.col_idx[k], self.values[k]);
+                for l in b.row_ptr[j]..b.row_ptr[j + 1] {
+                    let c = b.col_idx[l];
+                    if marker[c] != i {
+                        marker[c] = i;
+                        acc[c] = 0.0;
+                        cols.push(c);
+                    }
+                    acc[c] += value * b.values[l];
+                }
+            }
+            cols.sort_unstable();
+            for &c in &cols {
+                col_idx.push(c);
+                values.push(acc[c]);
+            }
+            row_ptr[i + 1] = col_idx.len();
+        }
+        SparseMatrix {
+            m: self.m,
+            n: b.n,
+            row_ptr,
+            col_idx,
+            values,
+        }
+    }
+
+    /// Return the standard finite difference Laplacian on a grid with the
+    /// given number of interior points per dimension.
+    ///
+    /// The stencil is multiplied by h^2 (2d on the diagonal and -1 for each
+    /// neighbour) with zero Dirichlet boundaries. Points are numbered in
+    /// row-major order, the last dimension varying fastest.
+    pub fn laplacian(dims: &[usize]) -> SparseMatrix {
+        let n: usize = dims.iter().product();
+        let mut triplets = Vec::with_capacity(n * (2 * dims.len() + 1));
+        for row in 0..n {
+            triplets.push((row, row, 2.0 * dims.len() as f64));
+            let (mut rest, mut stride) = (row, 1);
+            for &len in dims.iter().rev() {
+                let i = rest % len;
+                if i > 0 {
+                    triplets.push((row, row - stride, -1.0));
+                }
+                if i + 1 < len {
+                    triplets.push((row, row + stride, -1.0));
+                }
+                rest /= len;
+                stride *= len;
+            }
+        }
+        SparseMatrix::from_triplets(n, n, &triplets)
+    }
+
+    /// Return a random m x n matrix where each entry is nonzero with
+    /// probability `density`, with values uniform in [0, 1).
+    ///
+    /// Runs in time proportional to the number of nonzeros.
+    pub fn rand(m: usize, n: usize, density: f64, rng: &mut impl Rng) -> SparseMatrix {
+        assert!((0.0..=1.0).contains(&density));
+        let mut row_ptr = vec![0; m + 1];
+        let mut col_idx = Vec::new();
+        let mut values = Vec::new();
+        if density > 0.0 {
+            let log_q = math::ln(1.0 - density);
+            for i in 0..m {
+                let mut j = 0;
+                loop {
+                    // Skip a geometrically distributed number of zeros.
+                    if density < 1.0 {
+                        let skip = math::floor(math::ln(1.0 - rng.next_f64()) / log_q);
+                        if skip >= (n - j) as f64 {
+                            break;
+                        }
+                        j += skip as usize;
+                    }
+                    if j >= n {
+                        break;
+                    }
+                    col_idx.push(j);
+                    values.push(rng.next_f64());
+                    j += 1;
+                }
+                row_ptr[i + 1] = col_idx.len();
+            }
+        }
+        SparseMatrix {
+            m,
+            n,
+            row_ptr,
+            col_idx,
+            values,
+        }
+    }
+
+    /// Return a random n x n band matrix with `lower` subdiagonals and
+    /// `upper` superdiagonals, with values uniform in [0, 1).
+    pub fn rand_banded(n: usize, lower: usize, upper: usize, rng: &mut impl Rng) -> SparseMatrix {
+        let mut triplets = Vec::new();
+        for i in 0..n {
+            for j in i.saturating_sub(lower)..(i + upper + 1).min(n) {
+                triplets.push((i, j, rng.next_f64()));
+            }
+        }
+        SparseMatrix::from_triplets(n, n, &triplets)
+    }
+
+    /// Return a random strictly diagonally dominant n x n matrix.
+    ///
+    /// The off-diagonal pattern is drawn as in rand() with values uniform in
+    /// [-1, 1); each diagonal entry exceeds the absolute sum of its row.
+    pub fn rand_diag_dominant(n: usize, density: f64, rng: &mut impl Rng) -> SparseMatrix {
+        let pattern = SparseMatrix::rand(n, n, density, rng);
+        let mut triplets = Vec::with_capacity(pattern.nnz() + n);
+        for i in 0..n {
+            let mut sum = 0.0;
+            for k in pattern.row_ptr[i]..pattern.row_ptr[i + 1] {
+                let j = pattern.col_idx[k];
+                if j != i {
+                    let value = 2.0 * pattern.values[k] - 1.0;
+                    sum += value.abs();
+                    triplets.push((i, j, value));
+                }
+            }
+            triplets.push((i, i, sum + 1.0));
+        }
+        SparseMatrix::from_triplets(n, n, &triplets)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Lcg;
+
+    #[test]
+    fn sparse_triplets_matvec() {
+        let a = SparseMatrix::from_triplets(2, 3, &[(1, 2, 4.0), (0, 0, 1.0), (1, 2, 1.0), (0, 1, 2.0)]);
+        assert_eq!(a.nnz(), 3);
+        assert_eq!(a.row_ptr(), &[0, 2, 3]);
+        let mut y = [0.0; 2];
+        a.matvec(&[1.0, 2.0, 3.0], &mut y);
+        assert_eq!(y, [5.0, 15.0]);
+        assert_eq!(SparseMatrix::from_dense(&a.to_dense()), a);
+
+        let x = Matrix::from_vec(3, 2, vec![1.0, 0.0, 2.0, 1.0, 3.0, -1.0]);
+        let mut y = Matrix::zero_aligned(2, 2, 32, true);
+        a.matmat(&x, &mut y);
+        assert_eq!(y.to_vec(), vec![5.0, 2.0, 15.0, -5.0]);
+    }
+
+    #[test]
+    fn nnz_balanced_partition() {
+        // A power-law-like matrix: row i has about 5000 / (i + 1) entries,
+        // enough nonzeros for the parallel product.
+        let m = 1000;
+        let triplets: Vec<(usize, usize, f64)> = (0..m).flat_map(|i| (0..(5000 / (i + 1)).min(m)).map(move |j| (i, j, (i + j) as f64))).collect();
+        let a = SparseMatrix::from_triplets(m, m, &triplets);
+        let bounds = a.nnz_partition(4);
+        assert_eq!((bounds.len(), bounds[0], bounds[4]), (5, 0, m));
+        let work: Vec<usize> = bounds.windows(2).map(|w| a.row_ptr()[w[1]] - a.row_ptr()[w[0]] + w[1] - w[0]).collect();
+        let share = (a.nnz() + m) / 4;
+        // Within one row (at most m + 1) of an equal share.
+        assert!(work.iter().all(|&w| w.abs_diff(share) <= m + 1), "{work:?}");
+        // Row counts alone would put most nonzeros in the first block.
+        assert!(bounds[1] < m / 8);
+        assert_eq!(SparseMatrix::from_triplets(0, 3, &[]).nnz_partition(3), [0, 0, 0, 0]);
+
+        let x: Vec<f64> = (0..m).map(|j| (j as f64).sin()).collect();
+        let mut y = vec![0.0; m];
+        a.matvec(&x, &mut y);
+        let d = a.to_dense();
+        assert!((0..m).all(|i| (y[i] - (0..m).map(|j| d.get(i, j) * x[j]).sum::<f64>()).abs() < 1e-9));
+    }
+
+    #[test]
+    fn sparse_transpose_product() {
+        let mut rng = Lcg::new(9);
+        let a = SparseMatrix::rand(6, 4, 0.4, &mut rng);
+        let b = SparseMatrix::rand(4, 5, 0.4, &mut rng);
+        let at = a.transpose();
+        assert_eq!((at.m, at.n), (4, 6));
+        assert_eq!(at.transpose(), a);
+
+        let c = a.matmul(&b).to_dense();
+        let (ad, bd) = (a.to_dense(), b.to_dense());
+        for i in 0..6 {
+            for j in 0..5 {
+                let value: f64 = (0..4).map(|k| ad.get(i, k) * bd.get(k, j)).sum();
+                assert!((c.get(i, j) - value).abs() < 1e-12);
+            }
+        }
+        assert_eq!(SparseMatrix::from_dense(&ad).diagonal(), (0..4).map(|i| ad.get(i, i)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn sparse_rand_generators() {
+        let mut rng = Lcg::new(5);
+        let a = SparseMatrix::rand(200, 300, 0.05, &mut rng);
+        let density = a.nnz() as f64 / (200.0 * 300.0);
+        assert!((density - 0.05).abs() < 0.005);
+        assert!(a.row_ptr().windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(SparseMatrix::rand(3, 4, 1.0, &mut rng).nnz(), 12);
+
+        let l = SparseMatrix::laplacian(&[3, 4]);
+        assert_eq!(l.nnz(), 12 + 2 * (2 * 4 + 3 * 3));
+        assert_eq!(l.to_dense().get(5, 5), 4.0);
+        assert_eq!(l.to_dense().get(5, 1), -1.0);
+
+        let b = SparseMatrix::rand_banded(10, 1, 2, &mut rng);
+        assert_eq!(b.nnz(), 10 + 9 + 9 + 8);
+
+        let c = SparseMatrix::rand_diag_dominant(50, 0.1, &mut rng);
+        for i in 0..50 {
+            let (mut diag, mut off) = (0.0, 0.0);
+            for k in c.row_ptr()[i]..c.row_ptr()[i + 1] {
+                if c.col_idx()[k] == i {
+                    diag = c.values()[k];
+                } else {
+                    off += c.values()[k].abs();
+                }
+            }
+            assert!(diag > off);
+        }
+    }
+}
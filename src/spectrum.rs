@@ -0,0 +1,212 @@
+//! Eigenvalue bounds for choosing solver parameters.
+//!
+//! Chebyshev iteration needs an interval containing the spectrum and
+//! weighted Jacobi a weight depending on it. Gershgorin discs give cheap
+//! guaranteed bounds; a few Lanczos steps give much tighter estimates.
+use crate::iterative::LinearOperator;
+use crate::math;
+use crate::operations::{axpy, dot, norm2, scale};
+use crate::rng::{Distribution, Rng};
+use crate::stationary::RowOperator;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Number of Lanczos steps used by the automatic parameter choices.
+const LANCZOS_STEPS: usize = 20;
+
+/// Factor by which Lanczos estimates of the largest eigenvalue are
+/// enlarged, since Ritz values approach it from below.
+const LMAX_SAFETY: f64 = 1.1;
+
+/// Return an interval containing the eigenvalues of a symmetric matrix,
+/// from the union of its Gershgorin discs.
+pub fn gershgorin(a: &impl RowOperator) -> (f64, f64) {
+    let diag = a.diagonal();
+    let mut lo = f64::INFINITY;
+    let mut hi = f64::NEG_INFINITY;
+    for (i, &d) in diag.iter().enumerate() {
+        let radius = a.row_abs_sum(i) - d.abs();
+        lo = lo.min(d - radius);
+        hi = hi.max(d + radius);
+    }
+    (lo, hi)
+}
+
+/// Estimate the smallest and largest eigenvalues of a symmetric operator
+/// with `steps` Lanczos steps from a random start.
+///
+/// These are Ritz values, so they lie inside the true spectrum; the
+/// largest one usually converges within a few percent in 10 to 20 steps,
+/// the smallest more slowly. An empty operator gives the empty interval
+/// (inf, -inf), as for gershgorin().
+pub fn lanczos_extremes(a: &impl LinearOperator, steps: usize, rng: &mut impl Rng) -> (f64, f64) {
+    assert_eq!(a.nrows(), a.ncols());
+    let n = a.nrows();
+    if n == 0 {
+        return (f64::INFINITY, f64::NEG_INFINITY);
+    }
+    let steps = steps.clamp(1, n);
+    let mut v = vec![0.0; n];
+    Distribution::Normal.fill(rng, &mut v);
+    scale(1.0 / norm2(&v), &mut v);
+    let mut v_prev = vec![0.0; n];
+    let mut w = vec![0.0; n];
+    let mut alpha = Vec::with_capacity(steps);
+    let mut beta: Vec<f64> = Vec::with_capacity(steps);
+    for j in 0..steps {
+        a.apply(&v, &mut w);
+        let aj = dot(&w, &v);
+        alpha.push(aj);
+        axpy(-aj, &v, &mut w);
+        if let Some(&b) = beta.last() {
+            axpy(-b, &v_prev, &mut w);
+        }
+        let b = norm2(&w);
+        // Stop early once an invariant subspace is found.
+        if j + 1 == steps || b <= 1e-12 * aj.abs() {
+            break;
+        }
+        beta.push(b);
+        core::mem::swap(&mut v_prev, &mut v);
+        v.copy_from_slice(&w);
+        scale(1.0 / b, &mut v);
+    }
+    (tridiagonal_eigenvalue(&alpha, &beta, 0), tridiagonal_eigenvalue(&alpha, &beta, alpha.len() - 1))
+}
+
+/// Return an interval for Chebyshev iteration on an SPD operator.
+///
+/// The lower end is the Lanczos estimate and the upper end the estimate
+/// enlarged by 10%: underestimating the top of the spectrum makes the
+/// iteration diverge, while overestimating the bottom only slows it.
+pub fn chebyshev_interval(a: &impl LinearOperator, rng: &mut impl Rng) -> (f64, f64) {
+    let (lo, hi) = lanczos_extremes(a, LANCZOS_STEPS, rng);
+    assert!(lo > 0.0, "operator is not positive definite");
+    (lo, LMAX_SAFETY * hi)
+}
+
+/// Return the weighted Jacobi weight 2 / (lmin + lmax) for SPD A, from the
+/// estimated spectrum of D^-1 A.
+pub fn jacobi_weight(a: &impl RowOperator, rng: &mut impl Rng) -> f64 {
+    let scaled = DiagonalScaled {
+        a,
+        s: a.diagonal().iter().map(|&d| 1.0 / math::sqrt(d)).collect(),
+    };
+    let (lo, hi) = lanczos_extremes(&scaled, LANCZOS_STEPS, rng);
+    2.0 / (lo + LMAX_SAFETY * hi)
+}
+
+/// The symmetric operator D^-1/2 A D^-1/2, similar to D^-1 A.
+struct DiagonalScaled<'a, A> {
+    a: &'a A,
+    s: Vec<f64>,
+}
+
+impl<A: LinearOperator> LinearOperator for DiagonalScaled<'_, A> {
+    fn nrows(&self) -> usize {
+        self.a.nrows()
+    }
+
+    fn ncols(&self) -> usize {
+        self.a.ncols()
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        let sx: Vec<f64> = x.iter().zip(&self.s).map(|(x, s)| x * s).collect();
+        self.a.apply(&sx, y);
+        y.iter_mut().zip(&self.s).for_each(|(y, s)| *y *= s);
+    }
+}
+
+/// Return the k-th smallest eigenvalue of the symmetric tridiagonal matrix
+/// with diagonal `alpha` and off-diagonal `beta`, by bisection.
+fn tridiagonal_eigenvalue(alpha: &[f64], beta: &[f64], k: usize) -> f64 {
+    let n = alpha.len();
+    let radius = |i: usize| {
+        (if i > 0 { beta[i - 1].abs() } else { 0.0 }) + (if i + 1 < n { beta[i].abs() } else { 0.0 })
+    };
+    let mut lo = (0..n).map(|i| alpha[i] - radius(i)).fold(f64::INFINITY, f64::min);
+    let mut hi = (0..n).map(|i| alpha[i] + radius(i)).fold(f64::NEG_INFINITY, f64::max);
+    for _ in 0..200 {
+        let mid = 0.5 * (lo + hi);
+        if mid <= lo || mid >= hi {
+            break;
+        }
+        if sturm_count(alpha, beta, mid) > k {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+/// Return the number of eigenvalues of the tridiagonal matrix below x.
+fn sturm_count(alpha: &[f64], beta: &[f64], x: f64) -> usize {
+    let mut count = 0;
+    let mut q = 1.0;
+    for i in 0..alpha.len() {
+        let off = if i > 0 { beta[i - 1] * beta[i - 1] / q } else { 0.0 };
+        q = alpha[i] - x - off;
+        if q == 0.0 {
+            q = -f64::EPSILON;
+        }
+        if q < 0.0 {
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iterative::chebyshev;
+    use crate::rng::Lcg;
+    use crate::solver::tolerance;
+    use crate::stationary::jacobi;
+    use crate::SparseMatrix;
+
+    #[test]
+    fn gershgorin_and_lanczos_bounds() {
+        let a = SparseMatrix::laplacian(&[50]);
+        let (lo, hi) = gershgorin(&a);
+        assert_eq!((lo, hi), (0.0, 4.0));
+        // The eigenvalues are 2 - 2 cos(k pi / 51).
+        let true_min = 2.0 - 2.0 * (core::f64::consts::PI / 51.0).cos();
+        let true_max = 2.0 + 2.0 * (core::f64::consts::PI / 51.0).cos();
+
+        let (lmin, lmax) = lanczos_extremes(&a, 20, &mut Lcg::new(1));
+        assert!(lmin >= true_min - 1e-12 && lmax <= true_max + 1e-12);
+        assert!(lmax > 0.97 * true_max);
+        // Enough steps recover the spectrum exactly.
+        let (lmin, lmax) = lanczos_extremes(&a.to_dense(), 50, &mut Lcg::new(2));
+        assert!((lmin - true_min).abs() < 1e-6 && (lmax - true_max).abs() < 1e-6);
+        let empty = SparseMatrix::from_triplets(0, 0, &[]);
+        assert_eq!(lanczos_extremes(&empty, 20, &mut Lcg::new(1)), gershgorin(&empty));
+
+        let (t0, t1) = (tridiagonal_eigenvalue(&[2.0, 2.0], &[1.0], 0), tridiagonal_eigenvalue(&[2.0, 2.0], &[1.0], 1));
+        assert!((t0 - 1.0).abs() < 1e-12 && (t1 - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn automatic_solver_parameters() {
+        let a = SparseMatrix::laplacian(&[20, 20]);
+        let b = vec![1.0; a.m];
+        let mut rng = Lcg::new(3);
+
+        let (lmin, lmax) = chebyshev_interval(&a, &mut rng);
+        assert!(lmax > 8.0 * (core::f64::consts::PI * 20.0 / 21.0 / 2.0).sin().powi(2));
+        let mut x = vec![0.0; a.m];
+        let res = chebyshev(&a, &b, &mut x, lmin, lmax, tolerance(1e-8, 2000), None);
+        assert!(res.converged);
+
+        // D^-1 A has eigenvalues in (0, 2), so the weight is close to 1.
+        let omega = jacobi_weight(&a, &mut rng);
+        assert!(omega > 0.8 && omega < 1.0);
+        let mut x = vec![0.0; 25];
+        let small = SparseMatrix::laplacian(&[5, 5]);
+        let res = jacobi(&small, &[1.0; 25], &mut x, jacobi_weight(&small, &mut rng), tolerance(1e-8, 1000), None);
+        assert!(res.converged);
+    }
+}
@@ -0,0 +1,175 @@
+//! Sparse triangular solves with level scheduling.
+//!
+//! Forward substitution with a sparse L computes x_i from the x_j with
+//! l_ij != 0, which forms a dependency graph. Its level sets (Anderson and
+//! Saad) group the rows whose dependencies all lie in earlier levels, so
+//! the rows of one level can be solved in parallel, with a barrier between
+//! levels. This is how ILU and incomplete Cholesky preconditioners are
+//! applied on many cores; their factors from a grid typically have about
+//! sqrt(n) levels in 2-D.
+#[cfg(feature = "parallel")]
+use crate::parallel;
+use crate::SparseMatrix;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Sparse triangular matrix with its level schedule.
+pub struct SparseTriangular {
+    n: usize,
+    lower: bool,
+    /// The off-diagonal entries of each row in CSR form.
+    row_ptr: Vec<usize>,
+    col_idx: Vec<usize>,
+    values: Vec<f64>,
+    /// The diagonal, or None for a unit diagonal.
+    diag: Option<Vec<f64>>,
+    /// The rows by level, level l holding order[level_ptr[l]..level_ptr[l + 1]].
+    order: Vec<usize>,
+    level_ptr: Vec<usize>,
+}
+
+impl SparseTriangular {
+    /// Take the lower triangle of a, or None if a diagonal entry is missing
+    /// or zero. With `unit` the diagonal is taken as one and the stored one
+    /// ignored, as for the L of LU factors stored together.
+    pub fn lower(a: &SparseMatrix, unit: bool) -> Option<SparseTriangular> {
+        SparseTriangular::new(a, true, unit)
+    }
+
+    /// Take the upper triangle of a, as lower().
+    pub fn upper(a: &SparseMatrix, unit: bool) -> Option<SparseTriangular> {
+        SparseTriangular::new(a, false, unit)
+    }
+
+    fn new(a: &SparseMatrix, lower: bool, unit: bool) -> Option<SparseTriangular> {
+        assert_eq!(a.m, a.n);
+        let n = a.n;
+        let mut row_ptr = vec![0; n + 1];
+        let (mut col_idx, mut values) = (Vec::new(), Vec::new());
+        let mut diag = vec![0.0; n];
+        for i in 0..n {
+            for k in a.row_ptr()[i]..a.row_ptr()[i + 1] {
+                let j = a.col_idx()[k];
+                if j == i {
+                    diag[i] = a.values()[k];
+                } else if (j < i) == lower {
+                    col_idx.push(j);
+                    values.push(a.values()[k]);
+                }
+            }
+            row_ptr[i + 1] = col_idx.len();
+        }
+        if !unit && diag.contains(&0.0) {
+            return None;
+        }
+
+        // A row's level is one more than the highest of its dependencies.
+        let mut level = vec![0; n];
+        let rows: Vec<usize> = if lower { (0..n).collect() } else { (0..n).rev().collect() };
+        for &i in &rows {
+            level[i] = col_idx[row_ptr[i]..row_ptr[i + 1]].iter().map(|&j| level[j] + 1).max().unwrap_or(0);
+        }
+        let levels = level.iter().max().map_or(0, |l| l + 1);
+        let mut level_ptr = vec![0; levels + 1];
+        level.iter().for_each(|&l| level_ptr[l + 1] += 1);
+        (0..levels).for_each(|l| level_ptr[l + 1] += level_ptr[l]);
+        let mut next = level_ptr.clone();
+        let mut order = vec![0; n];
+        for &i in &rows {
+            order[next[level[i]]] = i;
+            next[level[i]] += 1;
+        }
+        Some(SparseTriangular { n, lower, row_ptr, col_idx, values, diag: if unit { None } else { Some(diag) }, order, level_ptr })
+    }
+
+    /// Return the number of levels, the length of the critical path.
+    pub fn num_levels(&self) -> usize {
+        self.level_ptr.len() - 1
+    }
+
+    /// Return whether this is the lower triangle.
+    pub fn is_lower(&self) -> bool {
+        self.lower
+    }
+
+    /// Return x_i given the solved x_j it depends on.
+    fn row(&self, i: usize, x: &[f64]) -> f64 {
+        let mut sum = x[i];
+        for k in self.row_ptr[i]..self.row_ptr[i + 1] {
+            sum -= self.values[k] * x[self.col_idx[k]];
+        }
+        match &self.diag {
+            Some(d) => sum / d[i],
+            None => sum,
+        }
+    }
+
+    /// Solve T x = b in place, level by level.
+    ///
+    /// With the `parallel` feature, levels of at least PARALLEL_MIN_ROWS
+    /// rows are solved in parallel; the result is the same either way.
+    pub fn solve_in_place(&self, b: &mut [f64]) {
+        assert_eq!(b.len(), self.n);
+        for l in 0..self.num_levels() {
+            let rows = &self.order[self.level_ptr[l]..self.level_ptr[l + 1]];
+            #[cfg(feature = "parallel")]
+            if rows.len() >= parallel::PARALLEL_MIN_ROWS {
+                let xs: Vec<f64> = parallel::run(|| rows.par_iter().map(|&i| self.row(i, b)).collect());
+                rows.iter().zip(xs).for_each(|(&i, x)| b[i] = x);
+                continue;
+            }
+            for &i in rows {
+                b[i] = self.row(i, b);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MatrixIndex;
+
+    #[test]
+    fn level_schedule_of_a_grid() {
+        // The lower triangle of the 2-D Laplacian: row (i, j) depends on
+        // (i - 1, j) and (i, j - 1), so the levels are the antidiagonals.
+        let a = SparseMatrix::laplacian(&[5, 7]);
+        let l = SparseTriangular::lower(&a, false).unwrap();
+        assert_eq!(l.num_levels(), 5 + 7 - 1);
+        let u = SparseTriangular::upper(&a, false).unwrap();
+        assert!(u.num_levels() == 11 && !u.is_lower());
+        // A diagonal matrix is one level, wide enough to run in parallel.
+        let triplets: Vec<(usize, usize, f64)> = (0..1000).map(|i| (i, i, 1.0 + i as f64)).collect();
+        let d = SparseTriangular::lower(&SparseMatrix::from_triplets(1000, 1000, &triplets), false).unwrap();
+        assert_eq!(d.num_levels(), 1);
+        let mut x: Vec<f64> = (0..1000).map(|i| 2.0 + 2.0 * i as f64).collect();
+        d.solve_in_place(&mut x);
+        assert!(x.iter().all(|&v| v == 2.0));
+        let missing = SparseMatrix::from_triplets(2, 2, &[(0, 0, 1.0), (1, 0, 1.0)]);
+        assert!(SparseTriangular::lower(&missing, false).is_none());
+        assert!(SparseTriangular::lower(&missing, true).is_some());
+    }
+
+    #[test]
+    fn solves_match_dense_substitution() {
+        let a = SparseMatrix::laplacian(&[30, 40]);
+        let n = a.n;
+        let dense = a.to_dense();
+        let b: Vec<f64> = (0..n).map(|i| (i as f64 * 0.13).sin()).collect();
+        for (lower, unit) in [(true, false), (false, false), (true, true), (false, true)] {
+            let t = if lower { SparseTriangular::lower(&a, unit) } else { SparseTriangular::upper(&a, unit) }.unwrap();
+            let mut x = b.clone();
+            t.solve_in_place(&mut x);
+            // The unit triangles are badly conditioned and x grows large.
+            let scale = x.iter().fold(1.0, |m: f64, v| m.max(v.abs()));
+            for i in 0..n {
+                let diag = if unit { 1.0 } else { dense.get(i, i) };
+                let off: f64 = (0..n).filter(|&j| (j < i) == lower && j != i).map(|j| dense.get(i, j) * x[j]).sum();
+                assert!((diag * x[i] + off - b[i]).abs() < 1e-12 * scale, "{lower} {unit}");
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Stationary iterative methods: weighted Jacobi, Gauss-Seidel and SOR.
+//!
+//! The sweep functions perform a single relaxation step and serve as
+//! multigrid smoothers; jacobi(), gauss_seidel() and sor() repeat them as
+//! standalone solvers. The multicolor sweep visits the rows color by
+//! color, so that each color can be relaxed in parallel.
+use crate::coloring::Coloring;
+use crate::iterative::LinearOperator;
+use crate::operations::norm2;
+#[cfg(feature = "parallel")]
+use crate::parallel;
+use crate::solver::{Monitor, SolveResult, StoppingCriterion};
+use crate::{Matrix, MatrixIndex, SparseMatrix};
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Operator whose rows can be applied one at a time.
+pub trait RowOperator: LinearOperator {
+    /// Return the dot product of row i with x.
+    fn row_dot(&self, i: usize, x: &[f64]) -> f64;
+
+    /// Return the sum of the absolute values in row i.
+    fn row_abs_sum(&self, i: usize) -> f64;
+
+    /// Return the diagonal entries.
+    fn diagonal(&self) -> Vec<f64>;
+}
+
+impl RowOperator for Matrix {
+    fn row_dot(&self, i: usize, x: &[f64]) -> f64 {
+        let row = &self.as_slice()[i * self.ld()..i * self.ld() + self.n];
+        row.iter().zip(x).map(|(a, b)| a * b).sum()
+    }
+
+    fn row_abs_sum(&self, i: usize) -> f64 {
+        self.as_slice()[i * self.ld()..i * self.ld() + self.n].iter().map(|v| v.abs()).sum()
+    }
+
+    fn diagonal(&self) -> Vec<f64> {
+        (0..self.m.min(self.n)).map(|i| self.get(i, i)).collect()
+    }
+}
+
+impl RowOperator for SparseMatrix {
+    fn row_dot(&self, i: usize, x: &[f64]) -> f64 {
+        let row = self.row_ptr()[i]..self.row_ptr()[i + 1];
+        self.values()[row.clone()].iter().zip(&self.col_idx()[row]).map(|(v, &j)| v * x[j]).sum()
+    }
+
+    fn row_abs_sum(&self, i: usize) -> f64 {
+        self.values()[self.row_ptr()[i]..self.row_ptr()[i + 1]].iter().map(|v| v.abs()).sum()
+    }
+
+    fn diagonal(&self) -> Vec<f64> {
+        SparseMatrix::diagonal(self)
+    }
+}
+
+/// Return the reciprocals of the diagonal, as taken by the sweeps.
+pub fn inverse_diagonal(a: &impl RowOperator) -> Vec<f64> {
+    a.diagonal()
+        .iter()
+        .map(|&d| {
+            assert!(d != 0.0, "zero on the diagonal");
+            1.0 / d
+        })
+        .collect()
+}
+
+/// Apply one weighted Jacobi sweep x += omega D^-1 (b - A x).
+pub fn jacobi_sweep(a: &impl RowOperator, inv_diag: &[f64], b: &[f64], x: &mut [f64], omega: f64) {
+    let mut ax = vec![0.0; x.len()];
+    a.apply(x, &mut ax);
+    for i in 0..x.len() {
+        x[i] += omega * inv_diag[i] * (b[i] - ax[i]);
+    }
+}
+
+/// Apply one SOR sweep, visiting the rows forward or backward.
+///
+/// With omega = 1 this is a Gauss-Seidel sweep. A forward sweep followed
+/// by a backward one is symmetric (SSOR).
+pub fn sor_sweep(a: &impl RowOperator, inv_diag: &[f64], b: &[f64], x: &mut [f64], omega: f64, forward: bool) {
+    let mut relax = |i: usize| {
+        let r = b[i] - a.row_dot(i, x);
+        x[i] += omega * inv_diag[i] * r;
+    };
+    if forward {
+        (0..b.len()).for_each(&mut relax);
+    } else {
+        (0..b.len()).rev().for_each(&mut relax);
+    }
+}
+
+/// Apply one SOR sweep in the multicolor ordering of `coloring`, visiting
+/// the colors forward or backward.
+///
+/// The rows of a color do not depend on each other, so with the `parallel`
+/// feature each color of at least PARALLEL_MIN_ROWS rows is relaxed in
+/// parallel, with the same result as the serial sweep. Forward and
+/// backward sweeps again combine into a symmetric one.
+pub fn multicolor_sor_sweep(
+    a: &(impl RowOperator + Sync),
+    inv_diag: &[f64],
+    coloring: &Coloring,
+    b: &[f64],
+    x: &mut [f64],
+    omega: f64,
+    forward: bool,
+) {
+    assert_eq!(coloring.colors().len(), x.len());
+    let k = coloring.num_colors();
+    for c in 0..k {
+        let rows = &coloring.classes()[if forward { c } else { k - 1 - c }];
+        #[cfg(feature = "parallel")]
+        if rows.len() >= parallel::PARALLEL_MIN_ROWS {
+            let xs: Vec<f64> = parallel::run(|| rows.par_iter().map(|&i| x[i] + omega * inv_diag[i] * (b[i] - a.row_dot(i, x))).collect());
+            rows.iter().zip(xs).for_each(|(&i, v)| x[i] = v);
+            continue;
+        }
+        for &i in rows {
+            x[i] += omega * inv_diag[i] * (b[i] - a.row_dot(i, x));
+        }
+    }
+}
+
+/// Solve Ax = b with weighted Jacobi.
+///
+/// Converges for strictly diagonally dominant A, and for SPD A when omega
+/// is small enough.
+pub fn jacobi(
+    a: &impl RowOperator,
+    b: &[f64],
+    x: &mut [f64],
+    omega: f64,
+    stop: impl StoppingCriterion,
+    monitor: Option<Monitor>,
+) -> SolveResult {
+    let inv_diag = inverse_diagonal(a);
+    iterate(a, b, x, stop, monitor, |x| jacobi_sweep(a, &inv_diag, b, x, omega))
+}
+
+/// Solve Ax = b with forward Gauss-Seidel.
+pub fn gauss_seidel(
+    a: &impl RowOperator,
+    b: &[f64],
+    x: &mut [f64],
+    stop: impl StoppingCriterion,
+    monitor: Option<Monitor>,
+) -> SolveResult {
+    sor(a, b, x, 1.0, stop, monitor)
+}
+
+/// Solve Ax = b with successive over-relaxation.
+///
+/// Converges for SPD A with 0 < omega < 2.
+pub fn sor(
+    a: &impl RowOperator,
+    b: &[f64],
+    x: &mut [f64],
+    omega: f64,
+    stop: impl StoppingCriterion,
+    monitor: Option<Monitor>,
+) -> SolveResult {
+    let inv_diag = inverse_diagonal(a);
+    iterate(a, b, x, stop, monitor, |x| sor_sweep(a, &inv_diag, b, x, omega, true))
+}
+
+/// Repeat `sweep`, checking `stop` against the true residual before each.
+fn iterate(
+    a: &impl RowOperator,
+    b: &[f64],
+    x: &mut [f64],
+    mut stop: impl StoppingCriterion,
+    mut monitor: Option<Monitor>,
+    mut sweep: impl FnMut(&mut [f64]),
+) -> SolveResult {
+    assert_eq!(a.nrows(), a.ncols());
+    let mut r = vec![0.0; b.len()];
+    let mut residual = |x: &[f64]| {
+        a.apply(x, &mut r);
+        r.iter_mut().zip(b).for_each(|(ri, bi)| *ri = bi - *ri);
+        norm2(&r)
+    };
+    let b_norm = norm2(b);
+    let mut r_norm = residual(x);
+    let mut res = SolveResult::new(r_norm);
+    while res.check(&mut stop, r_norm, b_norm, None) {
+        sweep(x);
+        r_norm = residual(x);
+        if !res.record(r_norm, &mut monitor) {
+            break;
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Lcg;
+    use crate::solver::tolerance;
+
+    #[test]
+    fn stationary_methods_converge() {
+        let a = SparseMatrix::rand_diag_dominant(40, 0.2, &mut Lcg::new(8));
+        let dense = a.to_dense();
+        let b: Vec<f64> = (0..40).map(|i| i as f64).collect();
+
+        let mut x = vec![0.0; 40];
+        let jac = jacobi(&a, &b, &mut x, 1.0, tolerance(1e-10, 1000), None);
+        let mut y = vec![0.0; 40];
+        let gs = gauss_seidel(&dense, &b, &mut y, tolerance(1e-10, 1000), None);
+        assert!(jac.converged && gs.converged);
+        assert!(gs.iterations < jac.iterations);
+        assert!(x.iter().zip(&y).all(|(xi, yi)| (xi - yi).abs() < 1e-8));
+
+        // Dense and sparse sweeps agree.
+        let inv_diag = inverse_diagonal(&a);
+        let (mut u, mut v) = (vec![1.0; 40], vec![1.0; 40]);
+        sor_sweep(&a, &inv_diag, &b, &mut u, 1.3, false);
+        sor_sweep(&dense, &inv_diag, &b, &mut v, 1.3, false);
+        jacobi_sweep(&a, &inv_diag, &b, &mut u, 0.7);
+        jacobi_sweep(&dense, &inv_diag, &b, &mut v, 0.7);
+        assert!(u.iter().zip(&v).all(|(ui, vi)| (ui - vi).abs() < 1e-12));
+    }
+
+    #[test]
+    fn multicolor_gauss_seidel() {
+        // Red-black Gauss-Seidel on the 2-D Laplacian converges like the
+        // natural ordering.
+        let a = SparseMatrix::laplacian(&[10, 10]);
+        let n = a.m;
+        let coloring = Coloring::greedy(&a);
+        let inv_diag = inverse_diagonal(&a);
+        let b: Vec<f64> = (0..n).map(|i| (i % 7) as f64).collect();
+        let (mut x, mut y) = (vec![0.0; n], vec![0.0; n]);
+        for _ in 0..50 {
+            multicolor_sor_sweep(&a, &inv_diag, &coloring, &b, &mut x, 1.0, true);
+            sor_sweep(&a, &inv_diag, &b, &mut y, 1.0, true);
+        }
+        let residual = |x: &[f64]| {
+            let mut r = vec![0.0; n];
+            a.apply(x, &mut r);
+            norm2(&r.iter().zip(&b).map(|(p, q)| p - q).collect::<Vec<_>>())
+        };
+        let (rx, ry) = (residual(&x), residual(&y));
+        assert!(rx < 0.1 * norm2(&b) && rx < 2.0 * ry, "{rx} {ry}");
+
+        // Within a color the order does not matter: a sweep equals the
+        // natural-order sweep of the matrix permuted color by color.
+        let order: Vec<usize> = coloring.classes().iter().flatten().copied().collect();
+        let mut u = vec![0.5; n];
+        multicolor_sor_sweep(&a, &inv_diag, &coloring, &b, &mut u, 1.2, false);
+        let mut v = vec![0.5; n];
+        for &i in order.iter().rev() {
+            v[i] += 1.2 * inv_diag[i] * (b[i] - a.row_dot(i, &v));
+        }
+        assert_eq!(u, v);
+    }
+
+    #[test]
+    fn sor_over_relaxation_helps() {
+        // For the 1-D Laplacian the optimal weight is 2 / (1 + sin(pi h)).
+        let n = 50;
+        let a = SparseMatrix::laplacian(&[n]);
+        let b = vec![1.0; n];
+        let h = 1.0 / (n + 1) as f64;
+        let omega = 2.0 / (1.0 + (core::f64::consts::PI * h).sin());
+
+        let mut x = vec![0.0; n];
+        let gs = gauss_seidel(&a, &b, &mut x, tolerance(1e-8, 20000), None);
+        let mut x = vec![0.0; n];
+        let res = sor(&a, &b, &mut x, omega, tolerance(1e-8, 20000), None);
+        assert!(gs.converged && res.converged);
+        assert!(res.iterations * 10 < gs.iterations);
+    }
+}
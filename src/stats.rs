@@ -0,0 +1,170 @@
+//! Covariance and correlation matrices of data.
+//!
+//! The data are an m x d matrix with one sample per row, as for pca. The
+//! two-pass algorithm subtracts the mean before forming products. The
+//! single-pass one is Welford's update, which is as accurate and also
+//! works on a stream of samples through OnlineCovariance. Neither forms
+//! sums of raw products, whose cancellation ruins the result for data with
+//! a large mean.
+use crate::{math, Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// How cov_with() passes over the data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CovMethod {
+    /// Compute the mean first, then the products of deviations.
+    TwoPass,
+    /// Update the mean and products sample by sample (Welford).
+    SinglePass,
+}
+
+/// Running mean and covariance of a stream of samples.
+pub struct OnlineCovariance {
+    count: usize,
+    mean: Vec<f64>,
+    /// Sum of the products of deviations from the mean.
+    m2: Matrix,
+}
+
+impl OnlineCovariance {
+    /// Start with no samples of d features.
+    pub fn new(d: usize) -> OnlineCovariance {
+        OnlineCovariance { count: 0, mean: vec![0.0; d], m2: Matrix::zero(d, d) }
+    }
+
+    /// Add a sample.
+    pub fn push(&mut self, x: &[f64]) {
+        let d = self.mean.len();
+        assert_eq!(x.len(), d);
+        self.count += 1;
+        let before: Vec<f64> = x.iter().zip(&self.mean).map(|(x, m)| x - m).collect();
+        let inv = 1.0 / self.count as f64;
+        self.mean.iter_mut().zip(&before).for_each(|(m, b)| *m += b * inv);
+        for i in 0..d {
+            let after = x[i] - self.mean[i];
+            for j in 0..d {
+                self.m2.set(i, j, self.m2.get(i, j) + after * before[j]);
+            }
+        }
+    }
+
+    /// Return the number of samples.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Return the mean of the samples.
+    pub fn mean(&self) -> &[f64] {
+        &self.mean
+    }
+
+    /// Return the covariance, normalized by count - 1 with Bessel's
+    /// correction and by count without.
+    pub fn covariance(&self, bessel: bool) -> Matrix {
+        let d = self.mean.len();
+        let dof = if bessel { self.count as f64 - 1.0 } else { self.count as f64 };
+        assert!(dof > 0.0, "not enough samples");
+        // M2 is symmetric in exact arithmetic only; average its halves.
+        Matrix::from_vec(d, d, (0..d * d).map(|t| 0.5 * (self.m2.get(t / d, t % d) + self.m2.get(t % d, t / d)) / dof).collect())
+    }
+}
+
+/// Return the d x d sample covariance of the features, with Bessel's
+/// correction.
+pub fn cov(data: &Matrix) -> Matrix {
+    cov_with(data, CovMethod::TwoPass, true)
+}
+
+/// Return the d x d covariance of the features, normalized by m - 1 with
+/// Bessel's correction and by m without.
+pub fn cov_with(data: &Matrix, method: CovMethod, bessel: bool) -> Matrix {
+    let (m, d) = (data.m, data.n);
+    match method {
+        CovMethod::SinglePass => {
+            let mut acc = OnlineCovariance::new(d);
+            let mut row = vec![0.0; d];
+            for i in 0..m {
+                (0..d).for_each(|j| row[j] = data.get(i, j));
+                acc.push(&row);
+            }
+            acc.covariance(bessel)
+        }
+        CovMethod::TwoPass => {
+            let dof = if bessel { m as f64 - 1.0 } else { m as f64 };
+            assert!(dof > 0.0, "not enough samples");
+            let mean: Vec<f64> = (0..d).map(|j| (0..m).map(|i| data.get(i, j)).sum::<f64>() / m as f64).collect();
+            let mut c = Matrix::zero(d, d);
+            for i in 0..d {
+                for j in 0..=i {
+                    let sum: f64 = (0..m).map(|k| (data.get(k, i) - mean[i]) * (data.get(k, j) - mean[j])).sum();
+                    c.set(i, j, sum / dof);
+                    c.set(j, i, sum / dof);
+                }
+            }
+            c
+        }
+    }
+}
+
+/// Return the d x d matrix of Pearson correlation coefficients of the
+/// features.
+///
+/// The diagonal is exactly one. Entries for a constant feature are NaN,
+/// since its correlation is undefined.
+pub fn corrcoef(data: &Matrix) -> Matrix {
+    let mut c = cov(data);
+    let d = c.n;
+    let sd: Vec<f64> = (0..d).map(|i| math::sqrt(c.get(i, i))).collect();
+    for i in 0..d {
+        for j in 0..d {
+            // Rounding can push |r| slightly above one.
+            let r = if i == j && sd[i] > 0.0 { 1.0 } else { (c.get(i, j) / (sd[i] * sd[j])).clamp(-1.0, 1.0) };
+            c.set(i, j, r);
+        }
+    }
+    c
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn covariance_methods_agree() {
+        // Data with a huge offset: raw sums of products would lose every
+        // digit of the variance.
+        let r = Matrix::rand_seeded(50, 3, 7);
+        let data = Matrix::from_vec(50, 3, (0..150).map(|t| 1e9 + r.get(t / 3, t % 3) * (1 + t % 3) as f64).collect());
+        let two = cov(&data);
+        let one = cov_with(&data, CovMethod::SinglePass, true);
+        let centered = Matrix::from_vec(50, 3, (0..150).map(|t| r.get(t / 3, t % 3) * (1 + t % 3) as f64).collect());
+        let exact = cov(&centered);
+        assert!((0..9).all(|t| (two.get(t / 3, t % 3) - exact.get(t / 3, t % 3)).abs() < 1e-6));
+        assert!((0..9).all(|t| (one.get(t / 3, t % 3) - exact.get(t / 3, t % 3)).abs() < 1e-6));
+        // Without the correction the scale is (m - 1) / m.
+        let biased = cov_with(&data, CovMethod::SinglePass, false);
+        assert!((biased.get(1, 1) - one.get(1, 1) * 49.0 / 50.0).abs() < 1e-12);
+
+        let small = Matrix::from_vec(3, 2, vec![1.0, 2.0, 2.0, 4.0, 3.0, 9.0]);
+        let c = cov(&small);
+        assert_eq!((c.get(0, 0), c.get(0, 1), c.get(1, 1)), (1.0, 3.5, 13.0));
+    }
+
+    #[test]
+    fn correlation_coefficients() {
+        // Feature 1 is 3 x feature 0 + 1, feature 2 decreases with it.
+        let data = Matrix::from_vec(4, 4, vec![1.0, 4.0, 8.0, 5.0,
+                                               2.0, 7.0, 6.0, 5.0,
+                                               3.0, 10.0, 4.0, 5.0,
+                                               4.0, 13.0, 2.0, 5.0]);
+        let r = corrcoef(&data);
+        assert!((r.get(0, 1) - 1.0).abs() < 1e-15 && (r.get(0, 2) + 1.0).abs() < 1e-15);
+        assert!((0..3).all(|i| r.get(i, i) == 1.0));
+        assert!(r.get(3, 3).is_nan() && r.get(0, 3).is_nan());
+        let mut online = OnlineCovariance::new(4);
+        (0..4).for_each(|i| online.push(&[data.get(i, 0), data.get(i, 1), data.get(i, 2), data.get(i, 3)]));
+        assert_eq!(online.count(), 4);
+        assert_eq!(online.mean(), [2.5, 8.5, 5.0, 5.0]);
+    }
+}
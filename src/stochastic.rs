@@ -0,0 +1,176 @@
+//! Row-stochastic matrices and stationary distributions of Markov chains.
+//!
+//! A transition matrix P has P_ij the probability of moving from state i
+//! to state j, so its rows are nonnegative and sum to one. A stationary
+//! distribution pi is a probability vector with P^T pi = pi. It is unique
+//! when the chain is irreducible, and is then found either by power
+//! iteration, needing only products with P^T and so suited to large sparse
+//! chains such as PageRank, or by a dense linear solve.
+use crate::factor::Factor;
+use crate::iterative::TransposeOperator;
+use crate::lu::LuFactor;
+use crate::stationary::RowOperator;
+use crate::{Matrix, MatrixIndex, SparseMatrix};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl Matrix {
+    /// Scale each row to sum to one, returning the rows that sum to zero,
+    /// which are left unchanged (the dangling nodes of a graph).
+    pub fn normalize_rows(&mut self) -> Vec<usize> {
+        let mut zero = Vec::new();
+        for i in 0..self.m {
+            let sum: f64 = (0..self.n).map(|j| self.get(i, j)).sum();
+            if sum == 0.0 {
+                zero.push(i);
+                continue;
+            }
+            (0..self.n).for_each(|j| self.set(i, j, self.get(i, j) / sum));
+        }
+        zero
+    }
+}
+
+impl SparseMatrix {
+    /// Scale each row to sum to one, returning the rows that sum to zero,
+    /// which are left unchanged (the dangling nodes of a graph).
+    pub fn normalize_rows(&mut self) -> Vec<usize> {
+        let row_ptr = self.row_ptr().to_vec();
+        let values = self.values_mut();
+        let mut zero = Vec::new();
+        for i in 0..row_ptr.len() - 1 {
+            let row = &mut values[row_ptr[i]..row_ptr[i + 1]];
+            let sum: f64 = row.iter().sum();
+            if sum == 0.0 {
+                zero.push(i);
+                continue;
+            }
+            row.iter_mut().for_each(|v| *v /= sum);
+        }
+        zero
+    }
+}
+
+/// Return true if p is square and row stochastic: every entry is
+/// nonnegative and every row sums to one, to within tol.
+pub fn is_stochastic(p: &impl RowOperator, tol: f64) -> bool {
+    let n = p.nrows();
+    if p.ncols() != n {
+        return false;
+    }
+    let ones = vec![1.0; n];
+    // A row is nonnegative exactly when its sum equals its absolute sum.
+    (0..n).all(|i| {
+        let sum = p.row_dot(i, &ones);
+        (sum - 1.0).abs() <= tol && p.row_abs_sum(i) - sum <= tol
+    })
+}
+
+/// Return the stationary distribution of the row-stochastic p by power
+/// iteration, or None if it has not converged to within tol in the 1-norm
+/// after max_iter products with P^T.
+///
+/// The iteration runs on the lazy chain (I + P) / 2, which has the same
+/// stationary distributions but converges for periodic chains too. For an
+/// irreducible chain the error shrinks by the second largest eigenvalue
+/// modulus of (I + P) / 2 per step; a reducible one converges to a
+/// distribution that depends on the uniform start.
+pub fn stationary_distribution(p: &impl TransposeOperator, tol: f64, max_iter: usize) -> Option<Vec<f64>> {
+    let n = p.nrows();
+    assert_eq!(p.ncols(), n);
+    let mut pi = vec![1.0 / n as f64; n];
+    let mut next = vec![0.0; n];
+    for _ in 0..max_iter {
+        p.apply_transpose(&pi, &mut next);
+        let sum: f64 = next.iter().sum();
+        let mut change = 0.0;
+        for (x, y) in pi.iter_mut().zip(&next) {
+            // Renormalizing corrects the drift from rounding.
+            let new = 0.5 * (*x + y / sum);
+            change += (new - *x).abs();
+            *x = new;
+        }
+        // The lazy step halves the change, so double it for the true one.
+        if 2.0 * change <= tol {
+            return Some(pi);
+        }
+    }
+    None
+}
+
+/// Return the stationary distribution of the dense row-stochastic p by
+/// solving (I - P^T) pi = 0 with sum(pi) = 1, or None if that is singular
+/// because the chain has several closed classes, or if p is empty.
+///
+/// The normalization replaces the last equation, which is redundant.
+pub fn stationary_distribution_direct(p: &Matrix) -> Option<Vec<f64>> {
+    assert_eq!(p.m, p.n);
+    let n = p.n;
+    if n == 0 {
+        return None;
+    }
+    let mut a = Matrix::zero(n, n);
+    for i in 0..n {
+        for j in 0..n {
+            let value = if i == n - 1 { 1.0 } else { (i == j) as u8 as f64 - p.get(j, i) };
+            a.set(i, j, value);
+        }
+    }
+    let mut b = vec![0.0; n];
+    b[n - 1] = 1.0;
+    Some(LuFactor::new(&a)?.solve(&b))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stationary_distribution_of_a_chain() {
+        // A random walk on a 4-cycle with a self-loop at state 3, which
+        // makes it aperiodic and the distribution not uniform.
+        let mut p = Matrix::from_vec(4, 4, vec![0.0, 2.0, 0.0, 2.0,
+                                                1.0, 0.0, 1.0, 0.0,
+                                                0.0, 1.0, 0.0, 1.0,
+                                                1.0, 0.0, 1.0, 2.0]);
+        assert!(p.normalize_rows().is_empty());
+        assert!(is_stochastic(&p, 1e-15));
+        let direct = stationary_distribution_direct(&p).unwrap();
+        let power = stationary_distribution(&p, 1e-12, 1000).unwrap();
+        assert!((direct.iter().sum::<f64>() - 1.0).abs() < 1e-14);
+        assert!(direct.iter().zip(&power).all(|(a, b)| (a - b).abs() < 1e-11));
+        let mut back = vec![0.0; 4];
+        p.apply_transpose(&direct, &mut back);
+        assert!(back.iter().zip(&direct).all(|(a, b)| (a - b).abs() < 1e-15));
+
+        p.set(0, 1, -0.1);
+        assert!(!is_stochastic(&p, 1e-3));
+        assert!(stationary_distribution_direct(&Matrix::zero(0, 0)).is_none());
+    }
+
+    #[test]
+    fn pagerank_on_a_sparse_graph() {
+        // Links i -> j; node 3 has none and is dangling.
+        let links = [(0, 1), (0, 2), (1, 2), (2, 0), (4, 3), (4, 2)];
+        let n = 5;
+        let mut p = SparseMatrix::from_triplets(n, n, &links.map(|(i, j)| (i, j, 1.0)));
+        assert_eq!(p.normalize_rows(), [3]);
+        assert!(!is_stochastic(&p, 1e-15));
+
+        // Google matrix d P + (1 - d) / n, with dangling rows uniform.
+        let d = 0.85;
+        let mut g = p.to_dense();
+        for i in 0..n {
+            let dangling = i == 3;
+            (0..n).for_each(|j| g.set(i, j, if dangling { 1.0 / n as f64 } else { d * g.get(i, j) + (1.0 - d) / n as f64 }));
+        }
+        assert!(is_stochastic(&g, 1e-15));
+        let rank = stationary_distribution(&g, 1e-12, 1000).unwrap();
+        // Node 2 has the most links in, and node 4 none.
+        let best = (0..n).max_by(|&i, &j| rank[i].total_cmp(&rank[j])).unwrap();
+        assert_eq!(best, 2);
+        assert!((0..n).all(|i| rank[4] <= rank[i]));
+        let direct = stationary_distribution_direct(&g).unwrap();
+        assert!(direct.iter().zip(&rank).all(|(a, b)| (a - b).abs() < 1e-10));
+    }
+}
@@ -0,0 +1,424 @@
+//! Wrappers declaring the structure of a square matrix.
+//!
+//! Symmetric, Spd and LowerTriangular wrap a matrix, owned or borrowed,
+//! whose structure is known statically. They read only the lower triangle,
+//! so the strict upper triangle may hold anything, and products touch half
+//! the entries. Symmetric and Spd may instead wrap a PackedLower, which
+//! stores only the n (n + 1) / 2 entries of the lower triangle, halving
+//! the storage too.
+//!
+//! The Structured trait picks the factorization the structure allows:
+//! Cholesky for SPD matrices, O(n^2) substitution for triangular ones and
+//! LU for the rest, so generic code such as solve() gets the cheapest one
+//! from the type alone.
+//!
+//! Nothing is checked when wrapping, since checks cost as much as a
+//! product or a factorization. The checked() constructors and the
+//! check_symmetric(), symmetrize() and is_positive_definite() methods on
+//! Matrix validate first, against asymmetry from assembly round-off, say.
+use crate::cholesky::CholFactor;
+use crate::eig::{sym_eig, SymEig};
+use crate::factor::{self, Factor};
+use crate::iterative::LinearOperator;
+use crate::lu::LuFactor;
+use crate::triangular::solve_lower;
+use crate::{Matrix, MatrixIndex};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+
+/// Storage holding the lower triangle of a square matrix.
+pub trait LowerTriangle {
+    /// Return the dimension n.
+    fn dim(&self) -> usize;
+
+    /// Return entry (i, j) for j <= i.
+    fn lower(&self, i: usize, j: usize) -> f64;
+}
+
+/// The lower triangle of an n x n matrix, packed row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackedLower {
+    n: usize,
+    data: Vec<f64>,
+}
+
+/// A symmetric matrix, given by its lower triangle.
+pub struct Symmetric<M = Matrix>(pub M);
+
+/// A symmetric positive definite matrix, given by its lower triangle.
+pub struct Spd<M = Matrix>(pub M);
+
+/// A lower triangular matrix.
+pub struct LowerTriangular<M = Matrix>(pub M);
+
+/// A square operator with a factorization suited to its structure.
+pub trait Structured: LinearOperator {
+    type Factor: Factor;
+
+    /// Factor the matrix, or return None if it is singular, or for Spd,
+    /// not positive definite.
+    fn factor(&self) -> Option<Self::Factor>;
+}
+
+/// Return the solution of A x = b using the factorization for the
+/// structure of a, or None if it fails.
+pub fn solve<S: Structured>(a: &S, b: &[f64]) -> Option<Vec<f64>> {
+    Some(a.factor()?.solve(b))
+}
+
+impl Matrix {
+    /// Return max |a_ij - a_ji| relative to max |a_ij|, zero for a
+    /// symmetric matrix.
+    pub fn asymmetry(&self) -> f64 {
+        assert_eq!(self.m, self.n);
+        let n = self.n;
+        let (mut diff, mut size): (f64, f64) = (0.0, 0.0);
+        for i in 0..n {
+            for j in 0..n {
+                diff = diff.max((self.get(i, j) - self.get(j, i)).abs());
+                size = size.max(self.get(i, j).abs());
+            }
+        }
+        if size > 0.0 { diff / size } else { diff }
+    }
+
+    /// Return true if the matrix is square and symmetric to the relative
+    /// tolerance tol, as measured by asymmetry().
+    pub fn check_symmetric(&self, tol: f64) -> bool {
+        self.m == self.n && self.asymmetry() <= tol
+    }
+
+    /// Replace the matrix with its symmetric part (A + A^T) / 2.
+    pub fn symmetrize(&mut self) {
+        assert_eq!(self.m, self.n);
+        for i in 0..self.n {
+            for j in 0..i {
+                let v = 0.5 * (self.get(i, j) + self.get(j, i));
+                self.set(i, j, v);
+                self.set(j, i, v);
+            }
+        }
+    }
+
+    /// Return true if the Cholesky factorization of the lower triangle
+    /// succeeds; combine with check_symmetric() for a full matrix.
+    pub fn is_positive_definite(&self) -> bool {
+        self.m == self.n && CholFactor::new(self).is_some()
+    }
+}
+
+impl<M: Borrow<Matrix>> LowerTriangle for M {
+    fn dim(&self) -> usize {
+        let a = self.borrow();
+        assert_eq!(a.m, a.n);
+        a.n
+    }
+
+    fn lower(&self, i: usize, j: usize) -> f64 {
+        self.borrow().get(i, j)
+    }
+}
+
+impl PackedLower {
+    /// Pack the lower triangle of the square matrix a.
+    pub fn from_lower(a: &Matrix) -> PackedLower {
+        assert_eq!(a.m, a.n);
+        let data = (0..a.n).flat_map(|i| (0..=i).map(move |j| a.get(i, j))).collect();
+        PackedLower { n: a.n, data }
+    }
+
+    /// Return the packed entries, row i holding a_i0, ..., a_ii.
+    pub fn as_slice(&self) -> &[f64] {
+        &self.data
+    }
+}
+
+impl LowerTriangle for PackedLower {
+    fn dim(&self) -> usize {
+        self.n
+    }
+
+    fn lower(&self, i: usize, j: usize) -> f64 {
+        assert!(j <= i && i < self.n);
+        self.data[i * (i + 1) / 2 + j]
+    }
+}
+
+impl Structured for Matrix {
+    type Factor = LuFactor;
+
+    fn factor(&self) -> Option<LuFactor> {
+        LuFactor::new(self)
+    }
+}
+
+impl<M: Borrow<Matrix>> Symmetric<M> {
+    /// Wrap a, or return None if it is not symmetric to the relative
+    /// tolerance tol.
+    pub fn checked(a: M, tol: f64) -> Option<Symmetric<M>> {
+        a.borrow().check_symmetric(tol).then_some(Symmetric(a))
+    }
+}
+
+impl<M: LowerTriangle> Symmetric<M> {
+    /// Return the full matrix, with the upper triangle mirrored from the
+    /// lower.
+    pub fn full(&self) -> Matrix {
+        full_symmetric(&self.0)
+    }
+
+    /// Return the eigendecomposition.
+    pub fn eig(&self) -> SymEig {
+        sym_eig(&self.full())
+    }
+}
+
+impl<M: LowerTriangle> LinearOperator for Symmetric<M> {
+    fn nrows(&self) -> usize {
+        self.0.dim()
+    }
+
+    fn ncols(&self) -> usize {
+        self.0.dim()
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        symmetric_matvec(&self.0, x, y);
+    }
+}
+
+impl<M: LowerTriangle> Structured for Symmetric<M> {
+    // Without a symmetric indefinite factorization, LU on the full matrix.
+    type Factor = LuFactor;
+
+    fn factor(&self) -> Option<LuFactor> {
+        LuFactor::from_matrix(self.full())
+    }
+}
+
+impl<M: Borrow<Matrix>> Spd<M> {
+    /// Wrap a, or return None if it is not symmetric to the relative
+    /// tolerance tol or not positive definite.
+    pub fn checked(a: M, tol: f64) -> Option<Spd<M>> {
+        let m = a.borrow();
+        (m.check_symmetric(tol) && m.is_positive_definite()).then_some(Spd(a))
+    }
+}
+
+impl<M: LowerTriangle> Spd<M> {
+    /// Return the full matrix, with the upper triangle mirrored from the
+    /// lower.
+    pub fn full(&self) -> Matrix {
+        full_symmetric(&self.0)
+    }
+
+    /// Return the eigendecomposition.
+    pub fn eig(&self) -> SymEig {
+        sym_eig(&self.full())
+    }
+}
+
+impl<M: LowerTriangle> LinearOperator for Spd<M> {
+    fn nrows(&self) -> usize {
+        self.0.dim()
+    }
+
+    fn ncols(&self) -> usize {
+        self.0.dim()
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        symmetric_matvec(&self.0, x, y);
+    }
+}
+
+impl<M: LowerTriangle> Structured for Spd<M> {
+    type Factor = CholFactor;
+
+    fn factor(&self) -> Option<CholFactor> {
+        CholFactor::from_matrix(self.full())
+    }
+}
+
+impl<M: Borrow<Matrix>> LinearOperator for LowerTriangular<M> {
+    fn nrows(&self) -> usize {
+        self.0.borrow().m
+    }
+
+    fn ncols(&self) -> usize {
+        self.0.borrow().n
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        let a = self.0.borrow();
+        assert!(a.m == a.n && x.len() == a.n && y.len() == a.n);
+        for i in 0..a.n {
+            y[i] = (0..=i).map(|j| a.get(i, j) * x[j]).sum();
+        }
+    }
+}
+
+impl<M: Borrow<Matrix>> Structured for LowerTriangular<M> {
+    type Factor = TriangularFactor;
+
+    fn factor(&self) -> Option<TriangularFactor> {
+        let a = self.0.borrow();
+        assert_eq!(a.m, a.n);
+        let n = a.n;
+        let l = Matrix::from_vec(n, n, (0..n * n).map(|t| if t % n <= t / n { a.get(t / n, t % n) } else { 0.0 }).collect());
+        if (0..n).any(|i| l.get(i, i) == 0.0) {
+            return None;
+        }
+        Some(TriangularFactor { norm1: factor::norm1(&l), l })
+    }
+}
+
+/// A nonsingular lower triangular matrix, which is its own factorization.
+pub struct TriangularFactor {
+    l: Matrix,
+    norm1: f64,
+}
+
+impl Factor for TriangularFactor {
+    fn dim(&self) -> usize {
+        self.l.n
+    }
+
+    fn solve_in_place(&self, b: &mut [f64]) {
+        solve_lower(&self.l, b);
+    }
+
+    fn solve_transpose_in_place(&self, b: &mut [f64]) {
+        let (n, l) = (self.l.n, &self.l);
+        assert_eq!(b.len(), n);
+        for i in (0..n).rev() {
+            let sum: f64 = (i + 1..n).map(|k| l.get(k, i) * b[k]).sum();
+            b[i] = (b[i] - sum) / l.get(i, i);
+        }
+    }
+
+    fn det(&self) -> f64 {
+        (0..self.l.n).map(|i| self.l.get(i, i)).product()
+    }
+
+    fn norm1(&self) -> f64 {
+        self.norm1
+    }
+}
+
+fn full_symmetric(a: &impl LowerTriangle) -> Matrix {
+    let n = a.dim();
+    Matrix::from_vec(n, n, (0..n * n).map(|t| {
+        let (i, j) = (t / n, t % n);
+        if j <= i { a.lower(i, j) } else { a.lower(j, i) }
+    }).collect())
+}
+
+/// Compute y = A x from the lower triangle of a.
+fn symmetric_matvec(a: &impl LowerTriangle, x: &[f64], y: &mut [f64]) {
+    let n = a.dim();
+    assert!(x.len() == n && y.len() == n);
+    y.fill(0.0);
+    for i in 0..n {
+        let mut sum = a.lower(i, i) * x[i];
+        for j in 0..i {
+            let aij = a.lower(i, j);
+            sum += aij * x[j];
+            y[j] += aij * x[i];
+        }
+        y[i] += sum;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+
+    /// Return B B^T + n I in the lower triangle, with garbage above it.
+    fn spd_lower(n: usize) -> Matrix {
+        let b = Matrix::rand_seeded(n, n, 6);
+        Matrix::from_vec(n, n, (0..n * n).map(|t| {
+            let (i, j) = (t / n, t % n);
+            if j > i {
+                return f64::NAN;
+            }
+            (0..n).map(|k| b.get(i, k) * b.get(j, k)).sum::<f64>() + if i == j { n as f64 } else { 0.0 }
+        }).collect())
+    }
+
+    #[test]
+    fn spd_and_symmetric_read_the_lower_triangle() {
+        let n = 6;
+        let a = spd_lower(n);
+        let full = Spd(&a).full();
+        let x: Vec<f64> = (0..n).map(|i| 1.0 - i as f64 / 3.0).collect();
+        let (mut y, mut z) = (vec![0.0; n], vec![0.0; n]);
+        Spd(&a).apply(&x, &mut y);
+        full.apply(&x, &mut z);
+        assert!(y.iter().zip(&z).all(|(p, q)| (p - q).abs() < 1e-13));
+
+        let b = z;
+        let chol = solve(&Spd(&a), &b).unwrap();
+        let lu = solve(&Symmetric(&a), &b).unwrap();
+        assert!(chol.iter().zip(&x).all(|(p, q)| (p - q).abs() < 1e-13));
+        assert!(lu.iter().zip(&x).all(|(p, q)| (p - q).abs() < 1e-13));
+
+        // Packed storage holds half the entries and gives the same results.
+        let packed = PackedLower::from_lower(&a);
+        assert_eq!(packed.as_slice().len(), n * (n + 1) / 2);
+        let mut w = vec![0.0; n];
+        Symmetric(packed.clone()).apply(&x, &mut w);
+        assert!(w.iter().zip(&y).all(|(p, q)| (p - q).abs() < 1e-13));
+        let packed_chol = solve(&Spd(packed.clone()), &b).unwrap();
+        assert!(packed_chol.iter().zip(&x).all(|(p, q)| (p - q).abs() < 1e-13));
+        assert!((Spd(packed).eig().values[0] - Spd(&a).eig().values[0]).abs() < 1e-13);
+        assert!((Symmetric(&a).eig().values[0] - Spd(a).eig().values[0]).abs() < 1e-13);
+
+        // An indefinite matrix is still symmetric, but Cholesky fails.
+        let m = Matrix::from_vec(2, 2, vec![1.0, 9.0, 3.0, 1.0]);
+        assert!(Spd(&m).factor().is_none());
+        assert!(Symmetric(m).factor().is_some());
+    }
+
+    #[test]
+    fn triangular_factor_is_substitution() {
+        let l = Matrix::from_vec(3, 3, vec![2.0, 7.0, 7.0,
+                                            1.0, -1.0, 7.0,
+                                            3.0, 4.0, 0.5]);
+        let tri = LowerTriangular(&l);
+        let f = tri.factor().unwrap();
+        assert_eq!(f.det(), -1.0);
+        let x = [1.0, 2.0, -4.0];
+        let mut b = vec![0.0; 3];
+        tri.apply(&x, &mut b);
+        assert_eq!(b, [2.0, -1.0, 9.0]);
+        assert_eq!(f.solve(&b), x);
+        // L^T x = [2 + 1 * 2 - 12, -2 - 16, -2].
+        let mut c = vec![-8.0, -18.0, -2.0];
+        f.solve_transpose_in_place(&mut c);
+        assert_eq!(c, x);
+        let singular = Matrix::from_vec(2, 2, vec![1.0, 0.0, 5.0, 0.0]);
+        assert!(LowerTriangular(singular).factor().is_none());
+    }
+
+    #[test]
+    fn checked_wrappers() {
+        let n = 5;
+        let mut a = Spd(spd_lower(n)).full();
+        assert!(a.check_symmetric(0.0) && a.is_positive_definite());
+        // Round-off-sized asymmetry, as from assembly in a different order.
+        a.set(3, 1, a.get(3, 1) * (1.0 + 1e-15));
+        assert!(a.asymmetry() > 0.0 && !a.check_symmetric(0.0) && a.check_symmetric(1e-14));
+        assert!(Spd::checked(&a, 0.0).is_none() && Spd::checked(&a, 1e-14).is_some());
+        a.symmetrize();
+        assert!(a.check_symmetric(0.0));
+
+        // Symmetric but indefinite.
+        let mut b = Matrix::from_vec(2, 2, vec![1.0, 2.0, 2.0, 1.0]);
+        assert!(!b.is_positive_definite());
+        assert!(Symmetric::checked(&b, 0.0).is_some() && Spd::checked(&b, 0.0).is_none());
+        b.set(0, 1, 0.0);
+        assert!(Symmetric::checked(b, 0.5).is_none());
+    }
+}
@@ -0,0 +1,142 @@
+//! Singular value decomposition by one-sided Jacobi rotations.
+//!
+//! Hestenes' method rotates pairs of columns of A until they are mutually
+//! orthogonal; the column norms are then the singular values. It is slower
+//! than bidiagonalization for large matrices but simple, and accurate for
+//! the small singular values too.
+use crate::givens::Givens;
+use crate::math;
+use crate::{Matrix, MatrixIndex};
+use alloc::vec::Vec;
+
+/// Thin SVD A = U diag(s) V^T of an m x n matrix, with k = min(m, n).
+pub struct Svd {
+    /// m x k, orthonormal columns.
+    pub u: Matrix,
+    /// The k singular values, in decreasing order.
+    pub s: Vec<f64>,
+    /// k x n, orthonormal rows.
+    pub vt: Matrix,
+}
+
+impl Svd {
+    /// Return the numerical rank: the number of singular values above
+    /// tol times the largest.
+    pub fn rank(&self, tol: f64) -> usize {
+        let smax = self.s.first().copied().unwrap_or(0.0);
+        self.s.iter().filter(|&&s| s > tol * smax).count()
+    }
+}
+
+/// Return the thin SVD of a.
+///
+/// Columns of U for zero singular values are zero rather than completing
+/// an orthonormal basis.
+pub fn svd(a: &Matrix) -> Svd {
+    if a.m >= a.n {
+        let (ut, s, vt) = jacobi_svd(&a.transpose());
+        Svd { u: ut.transpose(), s, vt }
+    } else {
+        // Factor A^T = U S V^T, so A = V S U^T.
+        let (ut, s, vt) = jacobi_svd(a);
+        Svd { u: vt.transpose(), s, vt: ut }
+    }
+}
+
+/// One-sided Jacobi on the rows of w (n x m, n <= m), which are the
+/// columns of the matrix being factored. Returns (U^T, s, V^T).
+fn jacobi_svd(w: &Matrix) -> (Matrix, Vec<f64>, Matrix) {
+    let (n, m) = (w.m, w.n);
+    let mut w = Matrix::from_vec(n, m, (0..n * m).map(|k| w.get(k / m, k % m)).collect());
+    let mut v = Matrix::zero(n, n);
+    (0..n).for_each(|i| v.set(i, i, 1.0));
+    for _ in 0..60 {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in p + 1..n {
+                let (wd, ld) = (w.as_slice(), w.ld());
+                let (rp, rq) = (&wd[p * ld..p * ld + m], &wd[q * ld..q * ld + m]);
+                let alpha: f64 = rp.iter().map(|x| x * x).sum();
+                let beta: f64 = rq.iter().map(|x| x * x).sum();
+                let gamma: f64 = rp.iter().zip(rq).map(|(x, y)| x * y).sum();
+                if gamma == 0.0 || gamma.abs() <= f64::EPSILON * math::sqrt(alpha * beta) {
+                    continue;
+                }
+                rotated = true;
+                // Diagonalize the Gram matrix of the two columns.
+                let rot = Givens::jacobi(alpha, gamma, beta);
+                rot.apply_rows(&mut w, p, q);
+                rot.apply_rows(&mut v, p, q);
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+
+    let norms: Vec<f64> = (0..n).map(|i| math::sqrt((0..m).map(|j| w.get(i, j) * w.get(i, j)).sum())).collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| norms[j].total_cmp(&norms[i]));
+    let mut ut = Matrix::zero(n, m);
+    let mut vt = Matrix::zero(n, n);
+    for (k, &i) in order.iter().enumerate() {
+        for j in 0..m {
+            ut.set(k, j, if norms[i] > 0.0 { w.get(i, j) / norms[i] } else { 0.0 });
+        }
+        for j in 0..n {
+            vt.set(k, j, v.get(i, j));
+        }
+    }
+    (ut, order.iter().map(|&i| norms[i]).collect(), vt)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matmul;
+    use alloc::vec;
+
+    fn check(a: &Matrix) {
+        let f = svd(a);
+        let k = a.m.min(a.n);
+        assert_eq!((f.u.m, f.u.n, f.s.len(), f.vt.m, f.vt.n), (a.m, k, k, k, a.n));
+        assert!(f.s.windows(2).all(|w| w[0] >= w[1]));
+        // U S V^T = A.
+        let mut us = Matrix::zero(a.m, k);
+        for i in 0..a.m {
+            for j in 0..k {
+                us.set(i, j, f.u.get(i, j) * f.s[j]);
+            }
+        }
+        let mut usv = Matrix::zero(a.m, a.n);
+        matmul(&us.view(), &f.vt.view(), &mut usv.view_mut());
+        assert!((0..a.m).all(|i| (0..a.n).all(|j| (usv.get(i, j) - a.get(i, j)).abs() < 1e-13)));
+        // U^T U = V V^T = I.
+        for q in [&f.u.transpose(), &f.vt] {
+            let mut g = Matrix::zero(k, k);
+            matmul(&q.view(), &q.transpose().view(), &mut g.view_mut());
+            assert!((0..k).all(|i| (0..k).all(|j| (g.get(i, j) - if i == j { 1.0 } else { 0.0 }).abs() < 1e-13)));
+        }
+    }
+
+    #[test]
+    fn svd_reconstructs() {
+        check(&Matrix::rand_seeded(7, 4, 1));
+        check(&Matrix::rand_seeded(3, 6, 2));
+        check(&Matrix::rand_seeded(5, 5, 3));
+    }
+
+    #[test]
+    fn singular_values_of_known_matrix() {
+        // Rank 2 with singular values 3 and 2.
+        let a = Matrix::from_vec(3, 2, vec![3.0, 0.0,
+                                            0.0, -2.0,
+                                            0.0, 0.0]);
+        let f = svd(&a);
+        assert!((f.s[0] - 3.0).abs() < 1e-15 && (f.s[1] - 2.0).abs() < 1e-15);
+        let b = Matrix::from_vec(2, 3, vec![1.0, 2.0, 3.0, 2.0, 4.0, 6.0]);
+        let g = svd(&b);
+        assert_eq!(g.rank(1e-12), 1);
+        assert!((g.s[0] - 70f64.sqrt()).abs() < 1e-13 && g.s[1] < 1e-14);
+    }
+}
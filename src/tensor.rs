@@ -0,0 +1,335 @@
+//! Dense three-way tensors with Tucker and CP decompositions.
+//!
+//! Unfoldings follow Kolda and Bader: the mode-n unfolding X_(n) has a row
+//! for each index i_n, and its columns run over the other two indices with
+//! the lower mode varying fastest. With that ordering
+//! X_(1) = A (C kr B)^T for a CP tensor with factors A, B and C, where kr
+//! is the Khatri-Rao product.
+use crate::factor::Factor;
+use crate::lu::LuFactor;
+use crate::math;
+use crate::svd::svd;
+use crate::{matmul, Matrix, MatrixIndex};
+use alloc::vec::Vec;
+
+/// A dense I x J x K tensor, stored with the last index varying fastest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tensor3 {
+    dims: [usize; 3],
+    data: Vec<f64>,
+}
+
+impl Tensor3 {
+    /// Create from a vector, with entry (i, j, k) at (i J + j) K + k.
+    pub fn from_vec(dims: [usize; 3], data: Vec<f64>) -> Tensor3 {
+        assert_eq!(dims.iter().product::<usize>(), data.len());
+        Tensor3 { dims, data }
+    }
+
+    /// Return the tensor of zeros.
+    pub fn zero(dims: [usize; 3]) -> Tensor3 {
+        Tensor3::from_vec(dims, alloc::vec![0.0; dims.iter().product()])
+    }
+
+    /// Return the dimensions [I, J, K].
+    pub fn dims(&self) -> [usize; 3] {
+        self.dims
+    }
+
+    /// Return the entries in storage order.
+    pub fn as_slice(&self) -> &[f64] {
+        &self.data
+    }
+
+    /// Return entry (i, j, k).
+    pub fn get(&self, i: usize, j: usize, k: usize) -> f64 {
+        self.data[(i * self.dims[1] + j) * self.dims[2] + k]
+    }
+
+    /// Set entry (i, j, k).
+    pub fn set(&mut self, i: usize, j: usize, k: usize, value: f64) {
+        self.data[(i * self.dims[1] + j) * self.dims[2] + k] = value;
+    }
+
+    /// Return the Frobenius norm.
+    pub fn norm(&self) -> f64 {
+        math::sqrt(self.data.iter().map(|x| x * x).sum())
+    }
+
+    /// Return the mode-n unfolding X_(n), with n in 0..3.
+    pub fn unfold(&self, mode: usize) -> Matrix {
+        let (rows, cols) = unfolded_shape(self.dims, mode);
+        let mut res = Matrix::zero(rows, cols);
+        self.for_each_index(|idx, v| {
+            let (r, c) = unfolded_index(self.dims, mode, idx);
+            res.set(r, c, v);
+        });
+        res
+    }
+
+    /// Return the tensor of dimensions `dims` whose mode-n unfolding is a.
+    pub fn fold(a: &Matrix, mode: usize, dims: [usize; 3]) -> Tensor3 {
+        assert_eq!((a.m, a.n), unfolded_shape(dims, mode));
+        let mut res = Tensor3::zero(dims);
+        for i in 0..dims[0] {
+            for j in 0..dims[1] {
+                for k in 0..dims[2] {
+                    let (r, c) = unfolded_index(dims, mode, [i, j, k]);
+                    res.set(i, j, k, a.get(r, c));
+                }
+            }
+        }
+        res
+    }
+
+    /// Return the mode-n product X x_n U for a J x I_n matrix U, which
+    /// replaces dimension n by J.
+    pub fn mode_product(&self, mode: usize, u: &Matrix) -> Tensor3 {
+        assert_eq!(u.n, self.dims[mode]);
+        let x = self.unfold(mode);
+        let mut y = Matrix::zero(u.m, x.n);
+        matmul(&u.view(), &x.view(), &mut y.view_mut());
+        let mut dims = self.dims;
+        dims[mode] = u.m;
+        Tensor3::fold(&y, mode, dims)
+    }
+
+    fn for_each_index(&self, mut f: impl FnMut([usize; 3], f64)) {
+        for i in 0..self.dims[0] {
+            for j in 0..self.dims[1] {
+                for k in 0..self.dims[2] {
+                    f([i, j, k], self.get(i, j, k));
+                }
+            }
+        }
+    }
+}
+
+fn unfolded_shape(dims: [usize; 3], mode: usize) -> (usize, usize) {
+    assert!(mode < 3);
+    (dims[mode], dims.iter().product::<usize>() / dims[mode].max(1))
+}
+
+/// Return the position of entry idx in the mode-n unfolding.
+fn unfolded_index(dims: [usize; 3], mode: usize, idx: [usize; 3]) -> (usize, usize) {
+    let (mut col, mut stride) = (0, 1);
+    for m in (0..3).filter(|&m| m != mode) {
+        col += idx[m] * stride;
+        stride *= dims[m];
+    }
+    (idx[mode], col)
+}
+
+/// Tucker decomposition X = G x_1 U_1 x_2 U_2 x_3 U_3.
+pub struct Tucker {
+    /// The r_1 x r_2 x r_3 core G.
+    pub core: Tensor3,
+    /// The I_n x r_n factors, with orthonormal columns.
+    pub factors: [Matrix; 3],
+}
+
+impl Tucker {
+    /// Return the full tensor.
+    pub fn reconstruct(&self) -> Tensor3 {
+        (0..3).fold(self.core.clone(), |t, n| t.mode_product(n, &self.factors[n]))
+    }
+}
+
+/// Return the truncated higher-order SVD of x with multilinear ranks
+/// `ranks`.
+///
+/// Factor n holds the leading left singular vectors of X_(n) and the core
+/// is X projected onto them. This is quasi-optimal: the error is within a
+/// factor sqrt(3) of the best Tucker approximation of those ranks. Rank n
+/// is at most the smaller dimension of X_(n).
+pub fn hosvd(x: &Tensor3, ranks: [usize; 3]) -> Tucker {
+    let factors: [Matrix; 3] = core::array::from_fn(|n| {
+        let others: usize = (0..3).filter(|&k| k != n).map(|k| x.dims[k]).product();
+        assert!(ranks[n] <= x.dims[n].min(others));
+        let f = svd(&x.unfold(n));
+        let mut u = Matrix::zero(x.dims[n], ranks[n]);
+        for i in 0..x.dims[n] {
+            for j in 0..ranks[n] {
+                u.set(i, j, f.u.get(i, j));
+            }
+        }
+        u
+    });
+    let core = (0..3).fold(x.clone(), |t, n| t.mode_product(n, &factors[n].transpose()));
+    Tucker { core, factors }
+}
+
+/// CP (CANDECOMP/PARAFAC) decomposition X = sum_r w_r a_r o b_r o c_r.
+pub struct Cp {
+    /// The R weights.
+    pub weights: Vec<f64>,
+    /// The I_n x R factors, with unit columns.
+    pub factors: [Matrix; 3],
+}
+
+impl Cp {
+    /// Return the full tensor.
+    pub fn reconstruct(&self) -> Tensor3 {
+        let [a, b, c] = &self.factors;
+        let mut res = Tensor3::zero([a.m, b.m, c.m]);
+        for (r, w) in self.weights.iter().enumerate() {
+            for i in 0..a.m {
+                for j in 0..b.m {
+                    for k in 0..c.m {
+                        let v = res.get(i, j, k) + w * a.get(i, r) * b.get(j, r) * c.get(k, r);
+                        res.set(i, j, k, v);
+                    }
+                }
+            }
+        }
+        res
+    }
+}
+
+/// Return the Khatri-Rao (column-wise Kronecker) product of a and b, whose
+/// column r is a_r kron b_r.
+pub fn khatri_rao(a: &Matrix, b: &Matrix) -> Matrix {
+    assert_eq!(a.n, b.n);
+    let mut res = Matrix::zero(a.m * b.m, a.n);
+    for i in 0..a.m {
+        for j in 0..b.m {
+            for r in 0..a.n {
+                res.set(i * b.m + j, r, a.get(i, r) * b.get(j, r));
+            }
+        }
+    }
+    res
+}
+
+/// Return a rank-R CP decomposition of x by alternating least squares,
+/// started from the HOSVD factors.
+///
+/// Stops after `max_iter` sweeps or when the relative fit improves by less
+/// than `tol`. ALS converges to a local minimum, which for a tensor of
+/// exact rank R is normally the tensor itself. Panics if rank exceeds the
+/// smallest dimension.
+pub fn cp_als(x: &Tensor3, rank: usize, max_iter: usize, tol: f64) -> Cp {
+    assert!(rank > 0 && rank <= *x.dims.iter().min().unwrap());
+    let init = hosvd(x, [rank; 3]);
+    let mut cp = Cp {
+        weights: alloc::vec![1.0; rank],
+        factors: init.factors,
+    };
+    let unfoldings: [Matrix; 3] = core::array::from_fn(|n| x.unfold(n));
+    let xnorm = x.norm();
+    let mut fit = 0.0;
+    for _ in 0..max_iter {
+        for n in 0..3 {
+            // X_(n) = F_n (F_hi kr F_lo)^T over the other two modes.
+            let (lo, hi) = match n {
+                0 => (1, 2),
+                1 => (0, 2),
+                _ => (0, 1),
+            };
+            let kr = khatri_rao(&cp.factors[hi], &cp.factors[lo]);
+            let mut m = Matrix::zero(x.dims[n], rank);
+            matmul(&unfoldings[n].view(), &kr.view(), &mut m.view_mut());
+            // F_n = M (F_lo^T F_lo * F_hi^T F_hi)^-1, entrywise product.
+            let (glo, ghi) = (gram(&cp.factors[lo]), gram(&cp.factors[hi]));
+            let g = Matrix::from_vec(rank, rank, glo.as_slice().iter().zip(ghi.as_slice()).map(|(p, q)| p * q).collect());
+            let Some(lu) = LuFactor::new(&g) else {
+                return cp;
+            };
+            let mut f = Matrix::zero(x.dims[n], rank);
+            let mut row = alloc::vec![0.0; rank];
+            for i in 0..x.dims[n] {
+                (0..rank).for_each(|r| row[r] = m.get(i, r));
+                // G is symmetric, so solving G y = m_i^T gives the row.
+                lu.solve_in_place(&mut row);
+                (0..rank).for_each(|r| f.set(i, r, row[r]));
+            }
+            for r in 0..rank {
+                let norm = math::sqrt((0..f.m).map(|i| f.get(i, r) * f.get(i, r)).sum());
+                cp.weights[r] = norm;
+                if norm > 0.0 {
+                    (0..f.m).for_each(|i| f.set(i, r, f.get(i, r) / norm));
+                }
+            }
+            cp.factors[n] = f;
+        }
+        let err = residual_norm(x, &cp);
+        let new_fit = 1.0 - err / xnorm;
+        if (new_fit - fit).abs() < tol {
+            break;
+        }
+        fit = new_fit;
+    }
+    cp
+}
+
+/// Return A^T A.
+fn gram(a: &Matrix) -> Matrix {
+    let mut g = Matrix::zero(a.n, a.n);
+    matmul(&a.transpose().view(), &a.view(), &mut g.view_mut());
+    g
+}
+
+fn residual_norm(x: &Tensor3, cp: &Cp) -> f64 {
+    let y = cp.reconstruct();
+    math::sqrt(x.data.iter().zip(&y.data).map(|(p, q)| (p - q) * (p - q)).sum())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unfold_fold_and_mode_products() {
+        let dims = [2, 3, 4];
+        let x = Tensor3::from_vec(dims, (0..24).map(|v| v as f64).collect());
+        assert_eq!(x.get(1, 2, 3), 23.0);
+        let x1 = x.unfold(1);
+        assert_eq!((x1.m, x1.n), (3, 8));
+        // Column index i + 2 k for mode 1: entry (1, 2, 3) sits at (2, 7).
+        assert_eq!(x1.get(2, 7), 23.0);
+        for n in 0..3 {
+            assert_eq!(Tensor3::fold(&x.unfold(n), n, dims), x);
+        }
+
+        // Mode products along different modes commute, and the identity
+        // does nothing.
+        let u = Matrix::rand_seeded(5, 2, 1);
+        let v = Matrix::rand_seeded(3, 4, 2);
+        let a = x.mode_product(0, &u).mode_product(2, &v);
+        let b = x.mode_product(2, &v).mode_product(0, &u);
+        assert_eq!(a.dims(), [5, 3, 3]);
+        assert!(a.as_slice().iter().zip(b.as_slice()).all(|(p, q)| (p - q).abs() < 1e-12));
+        let mut id = Matrix::zero(3, 3);
+        (0..3).for_each(|i| id.set(i, i, 1.0));
+        assert_eq!(x.mode_product(1, &id), x);
+    }
+
+    #[test]
+    fn tucker_and_cp_recover_low_rank_tensors() {
+        // A tensor of CP rank 2, hence multilinear rank (2, 2, 2).
+        let centered = |m, seed| {
+            let a = Matrix::rand_seeded(m, 2, seed);
+            Matrix::from_vec(m, 2, a.as_slice().iter().map(|v| v - 0.5).collect())
+        };
+        let cp = Cp {
+            weights: alloc::vec![3.0, 1.5],
+            factors: [centered(4, 1), centered(5, 2), centered(3, 3)],
+        };
+        let x = cp.reconstruct();
+        let t = hosvd(&x, [2, 2, 2]);
+        assert_eq!(t.core.dims(), [2, 2, 2]);
+        let y = t.reconstruct();
+        assert!(x.as_slice().iter().zip(y.as_slice()).all(|(p, q)| (p - q).abs() < 1e-12));
+        // Truncating further loses the second component.
+        let lossy = hosvd(&x, [1, 1, 1]).reconstruct();
+        assert!(lossy.as_slice().iter().zip(x.as_slice()).any(|(p, q)| (p - q).abs() > 1e-3));
+        // X_(0) of a 7 x 2 x 2 tensor has only 4 singular vectors.
+        let tall = Tensor3::from_vec([7, 2, 2], Matrix::rand_seeded(7, 4, 4).as_slice().to_vec());
+        let y = hosvd(&tall, [4, 2, 2]).reconstruct();
+        assert!(tall.as_slice().iter().zip(y.as_slice()).all(|(p, q)| (p - q).abs() < 1e-12));
+
+        let fit = cp_als(&x, 2, 200, 1e-14);
+        assert!(residual_norm(&x, &fit) < 1e-10 * x.norm());
+        assert!(fit.factors.iter().all(|f| (0..2).all(|r| ((0..f.m).map(|i| f.get(i, r) * f.get(i, r)).sum::<f64>() - 1.0).abs() < 1e-12)));
+    }
+}
@@ -0,0 +1,95 @@
+//! Total least squares for errors in both A and b.
+//!
+//! Ordinary least squares puts all the error in b. When A is measured too,
+//! as in calibration, total least squares finds the smallest perturbation
+//! [dA db] in the Frobenius norm making (A + dA) x = b + db consistent. By
+//! Eckart-Young it removes the smallest singular value of [A b], and x
+//! comes from the matching right singular vector (Golub and Van Loan).
+use crate::svd::svd;
+use crate::{Matrix, MatrixIndex};
+use alloc::vec::Vec;
+
+/// Solution of a total least squares problem.
+pub struct Tls {
+    /// The solution x of (A + dA) x = b + db.
+    pub x: Vec<f64>,
+    /// The m x n correction dA to A.
+    pub delta_a: Matrix,
+    /// The correction db to b.
+    pub delta_b: Vec<f64>,
+    /// ||[dA db]||_F, the smallest singular value of [A b].
+    pub norm: f64,
+}
+
+/// Return the total least squares solution of A x ~ b for m x n a with
+/// m > n, or None if the problem has no solution because the last entry
+/// of the singular vector vanishes.
+///
+/// The solution is unique when the smallest singular value of [A b] is
+/// below that of A; with equal ones it is sensitive to the data, as TLS
+/// is more ill-conditioned than least squares in general.
+pub fn total_least_squares(a: &Matrix, b: &[f64]) -> Option<Tls> {
+    let (m, n) = (a.m, a.n);
+    assert!(b.len() == m && m > n);
+    let c = Matrix::from_vec(m, n + 1, (0..m * (n + 1)).map(|t| {
+        let (i, j) = (t / (n + 1), t % (n + 1));
+        if j < n { a.get(i, j) } else { b[i] }
+    }).collect());
+    let f = svd(&c);
+    let v: Vec<f64> = (0..=n).map(|j| f.vt.get(n, j)).collect();
+    let norm = f.s[n];
+    if v[n].abs() <= f64::EPSILON * (n + 1) as f64 {
+        return None;
+    }
+    let x: Vec<f64> = v[..n].iter().map(|vj| -vj / v[n]).collect();
+    // [dA db] = -sigma u v^T, so that [A b] + [dA db] has [x; -1] in its
+    // null space. When sigma = 0, u may be zero, which is still right.
+    let u: Vec<f64> = (0..m).map(|i| f.u.get(i, n)).collect();
+    let delta_a = Matrix::from_vec(m, n, (0..m * n).map(|t| -norm * u[t / n] * v[t % n]).collect());
+    let delta_b = (0..m).map(|i| -norm * u[i] * v[n]).collect();
+    Some(Tls { x, delta_a, delta_b, norm })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::qr::LeastSquares;
+    use alloc::vec;
+
+    #[test]
+    fn corrected_system_is_consistent() {
+        let (m, n) = (15, 2);
+        let a = Matrix::rand_seeded(m, n, 8);
+        let b: Vec<f64> = (0..m).map(|i| a.get(i, 0) - 2.0 * a.get(i, 1) + 0.05 * (i as f64 * 1.3).sin()).collect();
+        let t = total_least_squares(&a, &b).unwrap();
+        for i in 0..m {
+            let lhs: f64 = (0..n).map(|j| (a.get(i, j) + t.delta_a.get(i, j)) * t.x[j]).sum();
+            assert!((lhs - b[i] - t.delta_b[i]).abs() < 1e-13);
+        }
+        let fro: f64 = t.delta_a.as_slice().iter().chain(&t.delta_b).map(|v| v * v).sum();
+        assert!((fro.sqrt() - t.norm).abs() < 1e-14);
+        // Close to, but not the same as, ordinary least squares.
+        let ls = LeastSquares::from_rows(&a, &b).solve();
+        assert!(ls.iter().zip(&t.x).all(|(p, q)| (p - q).abs() < 0.05 && p != q));
+    }
+
+    #[test]
+    fn line_fit_with_errors_in_both_coordinates() {
+        // Points near y = 2 x scattered perpendicular to the line, where
+        // TLS recovers the slope exactly and least squares is biased. The
+        // offsets are uncorrelated with the position along the line.
+        let offsets = [0.2, -0.1, -0.1, -0.1, -0.1, 0.2];
+        let (xs, ys): (Vec<f64>, Vec<f64>) = offsets.iter().enumerate().map(|(i, e)| {
+            let t = i as f64 - 2.5;
+            (t + 2.0 * e, 2.0 * t - e)
+        }).unzip();
+        let a = Matrix::from_vec(6, 1, xs);
+        let t = total_least_squares(&a, &ys).unwrap();
+        assert!((t.x[0] - 2.0).abs() < 1e-13, "{}", t.x[0]);
+        assert!((LeastSquares::from_rows(&a, &ys).solve()[0] - 2.0).abs() > 1e-3);
+
+        // b orthogonal to a column of zeros: no x fits.
+        let z = Matrix::from_vec(3, 1, vec![0.0, 0.0, 0.0]);
+        assert!(total_least_squares(&z, &[1.0, 2.0, 2.0]).is_none());
+    }
+}
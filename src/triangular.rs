@@ -0,0 +1,229 @@
+//! O(n^2) solvers for triangular, quasi-triangular and Hessenberg systems.
+//!
+//! These are the systems left by the QR-type reductions: R from QR, the
+//! real Schur form T, and the Hessenberg matrices from Arnoldi and the
+//! Hessenberg reduction. Each is solved in O(n^2), against O(n^3) for
+//! general LU. Only the nonzero part of a is read, so the rest may hold
+//! anything.
+use crate::factor::Factor;
+use crate::{Matrix, MatrixIndex};
+use alloc::vec::Vec;
+
+/// Solve L x = b in place for the lower triangle of l.
+pub fn solve_lower(l: &Matrix, b: &mut [f64]) {
+    assert!(l.m == l.n && b.len() == l.n);
+    for i in 0..l.n {
+        let sum: f64 = (0..i).map(|k| l.get(i, k) * b[k]).sum();
+        b[i] = (b[i] - sum) / l.get(i, i);
+    }
+}
+
+/// Solve U x = b in place for the upper triangle of u.
+pub fn solve_upper(u: &Matrix, b: &mut [f64]) {
+    assert!(u.m == u.n && b.len() == u.n);
+    let n = u.n;
+    for i in (0..n).rev() {
+        let sum: f64 = (i + 1..n).map(|k| u.get(i, k) * b[k]).sum();
+        b[i] = (b[i] - sum) / u.get(i, i);
+    }
+}
+
+/// Solve T x = b in place for t upper quasi-triangular, as in the real
+/// Schur form: upper triangular except for 2 x 2 diagonal blocks, marked
+/// by a nonzero subdiagonal entry.
+///
+/// The 2 x 2 blocks are solved by Gaussian elimination with partial
+/// pivoting.
+pub fn solve_quasi_upper(t: &Matrix, b: &mut [f64]) {
+    assert!(t.m == t.n && b.len() == t.n);
+    let n = t.n;
+    let mut end = n;
+    while end > 0 {
+        let start = if end >= 2 && t.get(end - 1, end - 2) != 0.0 { end - 2 } else { end - 1 };
+        for i in start..end {
+            b[i] -= (end..n).map(|k| t.get(i, k) * b[k]).sum::<f64>();
+        }
+        if start + 1 == end {
+            b[start] /= t.get(start, start);
+        } else {
+            let (i, j) = (start, start + 1);
+            let (mut a, mut c, mut r) = ([t.get(i, i), t.get(i, j)], [t.get(j, i), t.get(j, j)], [b[i], b[j]]);
+            let swapped = c[0].abs() > a[0].abs();
+            if swapped {
+                (a, c) = (c, a);
+                r.swap(0, 1);
+            }
+            let l = c[0] / a[0];
+            let y = (r[1] - l * r[0]) / (c[1] - l * a[1]);
+            b[i] = (r[0] - a[1] * y) / a[0];
+            b[j] = y;
+        }
+        end = start;
+    }
+}
+
+/// LU factorization P H = L U of an upper Hessenberg matrix with partial
+/// pivoting, in O(n^2).
+///
+/// Each step eliminates the single subdiagonal entry, choosing the larger
+/// of two adjacent rows as pivot, so L is unit lower bidiagonal and U
+/// upper triangular.
+pub struct HessenbergLu {
+    n: usize,
+    /// U above the diagonal, the multipliers on the subdiagonal.
+    f: Matrix,
+    /// Whether rows k and k + 1 were swapped at step k.
+    swapped: Vec<bool>,
+    norm1: f64,
+}
+
+impl HessenbergLu {
+    /// Factor the upper Hessenberg part of h, or return None if it is
+    /// singular.
+    pub fn new(h: &Matrix) -> Option<HessenbergLu> {
+        assert_eq!(h.m, h.n);
+        let n = h.n;
+        let mut f = Matrix::zero(n, n);
+        for i in 0..n {
+            for j in i.saturating_sub(1)..n {
+                f.set(i, j, h.get(i, j));
+            }
+        }
+        let norm1 = (0..n).map(|j| (0..(j + 2).min(n)).map(|i| f.get(i, j).abs()).sum::<f64>()).fold(0.0, f64::max);
+        let mut swapped = Vec::with_capacity(n.saturating_sub(1));
+        for k in 0..n.saturating_sub(1) {
+            let swap = f.get(k + 1, k).abs() > f.get(k, k).abs();
+            if swap {
+                for j in k..n {
+                    let t = f.get(k, j);
+                    f.set(k, j, f.get(k + 1, j));
+                    f.set(k + 1, j, t);
+                }
+            }
+            swapped.push(swap);
+            let pivot = f.get(k, k);
+            if pivot == 0.0 {
+                return None;
+            }
+            let l = f.get(k + 1, k) / pivot;
+            f.set(k + 1, k, l);
+            for j in k + 1..n {
+                f.set(k + 1, j, f.get(k + 1, j) - l * f.get(k, j));
+            }
+        }
+        if n > 0 && f.get(n - 1, n - 1) == 0.0 {
+            return None;
+        }
+        Some(HessenbergLu { n, f, swapped, norm1 })
+    }
+}
+
+impl Factor for HessenbergLu {
+    fn dim(&self) -> usize {
+        self.n
+    }
+
+    fn solve_in_place(&self, b: &mut [f64]) {
+        assert_eq!(b.len(), self.n);
+        for (k, &swap) in self.swapped.iter().enumerate() {
+            if swap {
+                b.swap(k, k + 1);
+            }
+            b[k + 1] -= self.f.get(k + 1, k) * b[k];
+        }
+        solve_upper(&self.f, b);
+    }
+
+    fn solve_transpose_in_place(&self, b: &mut [f64]) {
+        assert_eq!(b.len(), self.n);
+        let (n, f) = (self.n, &self.f);
+        for i in 0..n {
+            let sum: f64 = (0..i).map(|k| f.get(k, i) * b[k]).sum();
+            b[i] = (b[i] - sum) / f.get(i, i);
+        }
+        for (k, &swap) in self.swapped.iter().enumerate().rev() {
+            b[k] -= f.get(k + 1, k) * b[k + 1];
+            if swap {
+                b.swap(k, k + 1);
+            }
+        }
+    }
+
+    fn det(&self) -> f64 {
+        let det: f64 = (0..self.n).map(|i| self.f.get(i, i)).product();
+        if self.swapped.iter().filter(|&&s| s).count() % 2 == 0 { det } else { -det }
+    }
+
+    fn norm1(&self) -> f64 {
+        self.norm1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lu::LuFactor;
+    use alloc::vec;
+
+    fn residual(a: &Matrix, x: &[f64], b: &[f64], transpose: bool) -> f64 {
+        (0..a.n)
+            .map(|i| {
+                let ax: f64 = (0..a.n).map(|j| if transpose { a.get(j, i) } else { a.get(i, j) } * x[j]).sum();
+                (ax - b[i]).abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn hessenberg_lu_matches_dense_lu() {
+        let n = 9;
+        let r = Matrix::rand_seeded(n, n, 5);
+        // Large subdiagonal entries force row swaps.
+        let h = Matrix::from_vec(n, n, (0..n * n).map(|t| {
+            let (i, j) = (t / n, t % n);
+            if i > j + 1 { 0.0 } else if i == j + 1 { 3.0 + r.get(i, j) } else { r.get(i, j) - 0.5 }
+        }).collect());
+        let f = HessenbergLu::new(&h).unwrap();
+        assert!(f.swapped.iter().any(|&s| s));
+        let b: Vec<f64> = (0..n).map(|i| (i as f64).sin()).collect();
+        let x = f.solve(&b);
+        assert!(residual(&h, &x, &b, false) < 1e-13);
+        let mut y = b.clone();
+        f.solve_transpose_in_place(&mut y);
+        assert!(residual(&h, &y, &b, true) < 1e-13);
+        let lu = LuFactor::new(&h).unwrap();
+        assert!((f.det() - lu.det()).abs() < 1e-12 * lu.det().abs());
+        assert!((f.norm1() - lu.norm1()).abs() < 1e-14);
+
+        // The leading 2 x 2 block is singular.
+        let s = Matrix::from_vec(3, 3, vec![1.0, 2.0, 3.0,
+                                            2.0, 4.0, 5.0,
+                                            0.0, 0.0, 6.0]);
+        assert!(HessenbergLu::new(&s).is_none());
+    }
+
+    #[test]
+    fn triangular_and_quasi_triangular() {
+        // A real Schur form with a complex pair 1 +- 2i in the middle; the
+        // entries below the structure are garbage that must be ignored.
+        let t = Matrix::from_vec(4, 4, vec![2.0, 1.0, -1.0, 3.0,
+                                            9.0, 1.0, 4.0, 0.5,
+                                            9.0, -1.0, 1.0, 2.0,
+                                            9.0, 9.0, 0.0, -3.0]);
+        let mut clean = Matrix::from_vec(4, 4, t.as_slice().to_vec());
+        (0..4).for_each(|i| (0..i).for_each(|j| if (i, j) != (2, 1) { clean.set(i, j, 0.0) }));
+        let b = vec![1.0, -2.0, 0.5, 4.0];
+        let mut x = b.clone();
+        solve_quasi_upper(&t, &mut x);
+        assert!(residual(&clean, &x, &b, false) < 1e-14);
+
+        let mut x = b.clone();
+        solve_upper(&t, &mut x);
+        (0..4).for_each(|i| (0..i).for_each(|j| clean.set(i, j, 0.0)));
+        assert!(residual(&clean, &x, &b, false) < 1e-14);
+        let mut x = b.clone();
+        solve_lower(&t, &mut x);
+        let lower = Matrix::from_vec(4, 4, (0..16).map(|k| if k % 4 <= k / 4 { t.get(k / 4, k % 4) } else { 0.0 }).collect());
+        assert!(residual(&lower, &x, &b, false) < 1e-14);
+    }
+}
@@ -0,0 +1,49 @@
+//! JavaScript typed-array interop for wasm32 builds.
+//!
+//! Build with `cargo build --target wasm32-unknown-unknown --features wasm`
+//! and export wrappers with `wasm-bindgen` in the application crate. The
+//! benchmark timers use `std::time::Instant`, which is unavailable in the
+//! browser.
+use crate::Matrix;
+use js_sys::Float64Array;
+
+impl Matrix {
+    /// Copy an m x n matrix out of a row-major `Float64Array`.
+    pub fn from_float64_array(m: usize, n: usize, a: &Float64Array) -> Matrix {
+        assert_eq!(a.length() as usize, m * n);
+        Matrix::from_vec(m, n, a.to_vec())
+    }
+
+    /// Overwrite the entries from a row-major `Float64Array`.
+    pub fn copy_from_float64_array(&mut self, a: &Float64Array) {
+        assert_eq!(a.length() as usize, self.m * self.n);
+        let (n, ld) = (self.n, self.ld());
+        for (i, row) in self.as_mut_slice().chunks_mut(ld).enumerate() {
+            let start = (i * n) as u32;
+            a.subarray(start, start + n as u32).copy_to(&mut row[..n]);
+        }
+    }
+
+    /// Copy the entries into a new row-major `Float64Array`.
+    pub fn to_float64_array(&self) -> Float64Array {
+        let res = Float64Array::new_with_length((self.m * self.n) as u32);
+        for (i, row) in self.as_slice().chunks(self.ld()).enumerate() {
+            let start = (i * self.n) as u32;
+            res.subarray(start, start + self.n as u32).copy_from(&row[..self.n]);
+        }
+        res
+    }
+
+    /// Return a `Float64Array` viewing the storage without copying.
+    ///
+    /// Padded rows are included, so the view holds `m * ld()` entries.
+    ///
+    /// # Safety
+    ///
+    /// The view must not be used after the matrix is dropped or modified, or
+    /// after the wasm memory grows (any allocation may do this).
+    pub unsafe fn float64_view(&self) -> Float64Array {
+        Float64Array::view(self.as_slice())
+    }
+}
+
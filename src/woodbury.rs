@@ -0,0 +1,197 @@
+//! Inverses of low-rank perturbations by the Sherman-Morrison-Woodbury
+//! formula.
+//!
+//! ```text
+//! (A + U C V^T)^-1 = A^-1 - A^-1 U (I + C V^T A^-1 U)^-1 C V^T A^-1
+//! ```
+//!
+//! This form does not need C to be invertible, so a rank-k downdate or a
+//! change of only some entries can be written with a singular C.
+use crate::iterative::{LinearOperator, Preconditioner};
+use crate::lu::{lu, lu_solve};
+use crate::operations::{axpy, gemv};
+use crate::{matmul, Matrix, MatrixIndex};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Applies (A + U C V^T)^-1 given a way to solve with A.
+///
+/// Setup takes k solves with A for a rank-k perturbation; each application
+/// then costs one solve with A plus O(nk).
+pub struct WoodburyOperator<S> {
+    solve: S,
+    /// A^-1 U, n x k.
+    z: Matrix,
+    /// C V^T, k x n.
+    cvt: Matrix,
+    /// LU factors of the capacitance matrix I + C V^T A^-1 U.
+    cap: Matrix,
+    cap_piv: Vec<usize>,
+}
+
+impl<S: Preconditioner> WoodburyOperator<S> {
+    /// Build the operator for the n x n matrix A, with U and V n x k and C
+    /// k x k.
+    ///
+    /// `solve` applies A^-1, for instance a closure around lu_solve() or
+    /// cholesky_solve() with a factorization of A. Returns None if the
+    /// capacitance matrix, and so the perturbed matrix, is singular.
+    pub fn new(solve: S, u: &Matrix, c: &Matrix, v: &Matrix) -> Option<WoodburyOperator<S>> {
+        let (n, k) = (u.m, u.n);
+        assert_eq!((v.m, v.n), (n, k));
+        assert_eq!((c.m, c.n), (k, k));
+        let mut z = Matrix::zero(n, k);
+        let mut col = vec![0.0; n];
+        let mut zc = vec![0.0; n];
+        for j in 0..k {
+            (0..n).for_each(|i| col[i] = u.get(i, j));
+            solve.apply(&col, &mut zc);
+            (0..n).for_each(|i| z.set(i, j, zc[i]));
+        }
+        let mut cvt = Matrix::zero(k, n);
+        matmul(&c.view(), &v.transpose().view(), &mut cvt.view_mut());
+        let mut cap = Matrix::zero(k, k);
+        matmul(&cvt.view(), &z.view(), &mut cap.view_mut());
+        for i in 0..k {
+            cap.set(i, i, cap.get(i, i) + 1.0);
+        }
+        let cap_piv = lu(&mut cap)?;
+        Some(WoodburyOperator {
+            solve,
+            z,
+            cvt,
+            cap,
+            cap_piv,
+        })
+    }
+
+    /// Return the rank k of the perturbation.
+    pub fn rank(&self) -> usize {
+        self.z.n
+    }
+}
+
+impl<S: Preconditioner> LinearOperator for WoodburyOperator<S> {
+    fn nrows(&self) -> usize {
+        self.z.m
+    }
+
+    fn ncols(&self) -> usize {
+        self.z.m
+    }
+
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        self.solve.apply(x, y);
+        let mut t = vec![0.0; self.rank()];
+        gemv(1.0, &self.cvt.view(), y, 0.0, &mut t);
+        lu_solve(&self.cap, &self.cap_piv, &mut t);
+        let mut zt = vec![0.0; y.len()];
+        gemv(1.0, &self.z.view(), &t, 0.0, &mut zt);
+        axpy(-1.0, &zt, y);
+    }
+}
+
+/// As a preconditioner it applies the exact inverse, so it can
+/// precondition solves with matrices close to A + U C V^T.
+impl<S: Preconditioner> Preconditioner for WoodburyOperator<S> {
+    fn apply(&self, r: &[f64], z: &mut [f64]) {
+        LinearOperator::apply(self, r, z);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cholesky::{cholesky, cholesky_solve};
+    use crate::iterative::cg;
+    use crate::solver::tolerance;
+    use crate::SparseMatrix;
+
+    #[test]
+    fn woodbury_matches_explicit_inverse() {
+        let n = 8;
+        let mut a = Matrix::rand_seeded(n, n, 1);
+        for i in 0..n {
+            a.set(i, i, a.get(i, i) + n as f64);
+        }
+        let u = Matrix::rand_seeded(n, 2, 2);
+        let v = Matrix::rand_seeded(n, 2, 3);
+        let c = Matrix::from_vec(2, 2, vec![1.0, 0.5, 0.0, 2.0]);
+
+        let mut f = Matrix::from_vec(n, n, a.as_slice().to_vec());
+        let piv = lu(&mut f).unwrap();
+        let solve = |r: &[f64], z: &mut [f64]| {
+            z.copy_from_slice(r);
+            lu_solve(&f, &piv, z);
+        };
+        let w = WoodburyOperator::new(solve, &u, &c, &v).unwrap();
+        assert_eq!(w.rank(), 2);
+
+        // Form A + U C V^T and solve directly.
+        let mut full = Matrix::from_vec(n, n, a.as_slice().to_vec());
+        for i in 0..n {
+            for j in 0..n {
+                let mut ucv = 0.0;
+                for p in 0..2 {
+                    for q in 0..2 {
+                        ucv += u.get(i, p) * c.get(p, q) * v.get(j, q);
+                    }
+                }
+                full.set(i, j, full.get(i, j) + ucv);
+            }
+        }
+        let fpiv = lu(&mut full).unwrap();
+        let b: Vec<f64> = (0..n).map(|i| i as f64 - 3.0).collect();
+        let mut expected = b.clone();
+        lu_solve(&full, &fpiv, &mut expected);
+        let mut x = vec![0.0; n];
+        LinearOperator::apply(&w, &b, &mut x);
+        assert!(x.iter().zip(&expected).all(|(p, q)| (p - q).abs() < 1e-12));
+
+        // I - e_0 e_0^T is singular.
+        let identity = |r: &[f64], z: &mut [f64]| z.copy_from_slice(r);
+        let mut e0 = Matrix::zero(n, 1);
+        e0.set(0, 0, 1.0);
+        assert!(WoodburyOperator::new(identity, &e0, &Matrix::from_vec(1, 1, vec![-1.0]), &e0).is_none());
+    }
+
+    #[test]
+    fn low_rank_update_of_laplacian() {
+        // Couple the two ends of a 1-D Laplacian, a rank-2 change, and solve
+        // through the Cholesky factor of the base.
+        let n = 30;
+        let base = SparseMatrix::laplacian(&[n]);
+        let mut l = base.to_dense();
+        assert!(cholesky(&mut l));
+        let solve = |r: &[f64], z: &mut [f64]| {
+            z.copy_from_slice(r);
+            cholesky_solve(&l, z);
+        };
+        let mut u = Matrix::zero(n, 2);
+        u.set(0, 0, 1.0);
+        u.set(n - 1, 1, 1.0);
+        let c = Matrix::from_vec(2, 2, vec![0.0, -0.5, -0.5, 0.0]);
+        let w = WoodburyOperator::new(solve, &u, &c, &u).unwrap();
+
+        let mut triplets = Vec::new();
+        for i in 0..n {
+            for k in base.row_ptr()[i]..base.row_ptr()[i + 1] {
+                triplets.push((i, base.col_idx()[k], base.values()[k]));
+            }
+        }
+        triplets.push((0, n - 1, -0.5));
+        triplets.push((n - 1, 0, -0.5));
+        let a = SparseMatrix::from_triplets(n, n, &triplets);
+        let b = vec![1.0; n];
+        let mut x = vec![0.0; n];
+        LinearOperator::apply(&w, &b, &mut x);
+        let mut ax = vec![0.0; n];
+        a.matvec(&x, &mut ax);
+        assert!(ax.iter().all(|v| (v - 1.0).abs() < 1e-10));
+
+        // As an exact preconditioner CG converges at once.
+        let mut y = vec![0.0; n];
+        let res = cg(&a, &b, &mut y, &w, tolerance(1e-10, 10), None);
+        assert!(res.converged && res.iterations <= 2);
+    }
+}